@@ -0,0 +1,890 @@
+//! Named in-memory collections managed together
+//!
+//! Every collection is a `MemANNStore` with its own `IndexConfiguration`, saved under
+//! `{root}/{name}/index`, and the configurations are listed in `{root}/collections.json`
+//! in the form of the index metadata.
+//! Collections are loaded on first use and saved then unloaded once idle, or when loading
+//! another one would go over the scratch budget. All of them run on one thread pool.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use diskann::common::{ANNError, ANNResult};
+use diskann::model::IndexConfiguration;
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+pub use crate::mem_ann_store::SearchResult;
+use crate::mem_ann_store::{MemANNStore, check_dimension};
+
+/// File listing the collections in the root directory
+const MANIFEST_FILE: &str = "collections.json";
+
+/// Query scratch spaces an index allocates besides the one per thread
+const BASE_SCRATCH_SPACES: usize = 5;
+
+/// Configuration of the manager
+#[derive(Debug, Clone)]
+pub struct CollectionManagerConfig {
+    /// Number of threads of the pool shared by the collections, 0 uses all the cores
+    pub num_threads: usize,
+
+    /// Query scratch spaces the loaded collections can hold together,
+    /// a collection holds 5 plus one per thread of its configuration
+    pub scratch_budget: usize,
+
+    /// Time since its last use after which a collection is saved and unloaded
+    pub idle_timeout: Duration,
+}
+
+impl Default for CollectionManagerConfig {
+    fn default() -> Self {
+        Self {
+            num_threads: 0,
+            scratch_budget: 1024,
+            idle_timeout: Duration::from_secs(300),
+        }
+    }
+}
+
+/// Collection as listed by the manager
+#[derive(Debug, Clone)]
+pub struct CollectionInfo {
+    pub name: String,
+
+    pub config: IndexConfiguration,
+
+    /// Whether the index is in memory
+    pub loaded: bool,
+}
+
+struct Collection {
+    config: IndexConfiguration,
+
+    /// Path prefix of the saved index
+    index_path: String,
+
+    /// Index when it is loaded
+    store: RwLock<Option<MemANNStore<f32>>>,
+
+    last_used: Mutex<Instant>,
+
+    /// Changed since it was last saved
+    dirty: AtomicBool,
+
+    /// Set once the collection is dropped, so that it is not loaded again
+    dropped: AtomicBool,
+}
+
+impl Collection {
+    fn new(root: &Path, name: &str, config: IndexConfiguration) -> Self {
+        Self {
+            config,
+            index_path: root.join(name).join("index").to_string_lossy().into_owned(),
+            store: RwLock::new(None),
+            last_used: Mutex::new(Instant::now()),
+            dirty: AtomicBool::new(false),
+            dropped: AtomicBool::new(false),
+        }
+    }
+
+    fn scratch_spaces(&self) -> usize {
+        BASE_SCRATCH_SPACES + self.config.index_write_parameter.num_threads as usize
+    }
+
+    fn touch(&self) {
+        if let Ok(mut last_used) = self.last_used.lock() {
+            *last_used = Instant::now();
+        }
+    }
+
+    fn idle_time(&self) -> Duration {
+        self.last_used
+            .lock()
+            .map(|last_used| last_used.elapsed())
+            .unwrap_or_default()
+    }
+
+    fn read(&self) -> ANNResult<RwLockReadGuard<'_, Option<MemANNStore<f32>>>> {
+        self.store.read().map_err(|_| {
+            ANNError::log_lock_poison_error(
+                "failed to acquire the lock for the collection".to_string(),
+            )
+        })
+    }
+
+    fn write(&self) -> ANNResult<RwLockWriteGuard<'_, Option<MemANNStore<f32>>>> {
+        self.store.write().map_err(|_| {
+            ANNError::log_lock_poison_error(
+                "failed to acquire the lock for the collection".to_string(),
+            )
+        })
+    }
+
+    fn is_loaded(&self) -> bool {
+        self.store
+            .try_read()
+            .map(|store| store.is_some())
+            .unwrap_or(true)
+    }
+}
+
+/// Named collections of one root directory, sharing a thread pool and a scratch budget
+///
+/// Lock order: the scratch lock is never taken while holding the lock of the collections
+/// or of a collection, since reserving scratch spaces can evict other collections.
+pub struct CollectionManager {
+    root: PathBuf,
+
+    config: CollectionManagerConfig,
+
+    pool: ThreadPool,
+
+    collections: RwLock<BTreeMap<String, Arc<Collection>>>,
+
+    /// Scratch spaces held by the loaded collections
+    scratch_in_use: Mutex<usize>,
+}
+
+impl CollectionManager {
+    /// Open the collections of root, creating the directory if needed, none is loaded yet
+    pub fn open(root: impl AsRef<Path>, config: CollectionManagerConfig) -> ANNResult<Self> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root)?;
+
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(config.num_threads)
+            .build()
+            .map_err(|err| {
+                ANNError::log_index_error(format!("failed to build the thread pool: {}", err))
+            })?;
+
+        let manifest_path = root.join(MANIFEST_FILE);
+        let configs: BTreeMap<String, IndexConfiguration> = if manifest_path.exists() {
+            serde_json::from_str(&fs::read_to_string(&manifest_path)?).map_err(|err| {
+                ANNError::log_index_error(format!(
+                    "failed to read {}: {}",
+                    manifest_path.display(),
+                    err
+                ))
+            })?
+        } else {
+            BTreeMap::new()
+        };
+
+        let collections = configs
+            .into_iter()
+            .map(|(name, config)| {
+                let collection = Collection::new(&root, &name, config);
+                (name, Arc::new(collection))
+            })
+            .collect();
+
+        Ok(Self {
+            root,
+            config,
+            pool,
+            collections: RwLock::new(collections),
+            scratch_in_use: Mutex::new(0),
+        })
+    }
+
+    /// Create an empty collection and save it, it stays loaded
+    ///
+    /// The thread count of the configuration is capped to the size of the shared pool,
+    /// 0 uses the whole pool.
+    pub fn create_collection(&self, name: &str, mut config: IndexConfiguration) -> ANNResult<()> {
+        validate_name(name)?;
+        if config.use_pq_dist {
+            return Err(ANNError::log_index_config_error(
+                "use_pq_dist".to_string(),
+                "PQ distances are not supported by in-memory collections".to_string(),
+            ));
+        }
+
+        let pool_threads = self.pool.current_num_threads() as u32;
+        let num_threads = &mut config.index_write_parameter.num_threads;
+        if *num_threads == 0 || *num_threads > pool_threads {
+            *num_threads = pool_threads;
+        }
+
+        if self.collections_read()?.contains_key(name) {
+            return Err(ANNError::log_index_error(format!(
+                "collection {} already exists",
+                name
+            )));
+        }
+
+        let collection = Arc::new(Collection::new(&self.root, name, config));
+        let scratch_spaces = collection.scratch_spaces();
+        self.reserve_scratch(scratch_spaces, &collection)?;
+
+        let result = self.create_loaded(name, &collection);
+        if result.is_err() {
+            self.release_scratch(scratch_spaces);
+        }
+        result
+    }
+
+    fn create_loaded(&self, name: &str, collection: &Arc<Collection>) -> ANNResult<()> {
+        let mut store = self
+            .pool
+            .install(|| MemANNStore::with_configuration(collection.config.clone()))?;
+
+        let mut collections = self.collections_write()?;
+        if collections.contains_key(name) {
+            return Err(ANNError::log_index_error(format!(
+                "collection {} already exists",
+                name
+            )));
+        }
+
+        fs::create_dir_all(self.root.join(name))?;
+        self.pool
+            .install(|| store.save_to_file(&collection.index_path))?;
+        *collection.write()? = Some(store);
+
+        collections.insert(name.to_string(), collection.clone());
+        if let Err(err) = self.write_manifest(&collections) {
+            collections.remove(name);
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// Remove a collection and its files
+    pub fn drop_collection(&self, name: &str) -> ANNResult<()> {
+        let collection = {
+            let mut collections = self.collections_write()?;
+            let collection = collections.remove(name).ok_or_else(|| not_found(name))?;
+            if let Err(err) = self.write_manifest(&collections) {
+                collections.insert(name.to_string(), collection);
+                return Err(err);
+            }
+            collection
+        };
+
+        let was_loaded = {
+            let mut store = collection.write()?;
+            collection.dropped.store(true, Ordering::SeqCst);
+            store.take().is_some()
+        };
+        if was_loaded {
+            self.release_scratch(collection.scratch_spaces());
+        }
+
+        fs::remove_dir_all(self.root.join(name))?;
+
+        Ok(())
+    }
+
+    /// Collections sorted by name
+    pub fn list_collections(&self) -> ANNResult<Vec<CollectionInfo>> {
+        Ok(self
+            .collections_read()?
+            .iter()
+            .map(|(name, collection)| CollectionInfo {
+                name: name.clone(),
+                config: collection.config.clone(),
+                loaded: collection.is_loaded(),
+            })
+            .collect())
+    }
+
+    /// Load a collection ahead of its first use
+    pub fn load_collection(&self, name: &str) -> ANNResult<()> {
+        let collection = self.collection(name)?;
+        collection.touch();
+
+        if collection.read()?.is_some() {
+            return Ok(());
+        }
+        self.load(&collection)
+    }
+
+    /// Save a collection if it changed since it was last saved
+    pub fn save_collection(&self, name: &str) -> ANNResult<()> {
+        let collection = self.collection(name)?;
+        self.save(&collection)
+    }
+
+    /// Save all the collections that changed since they were last saved
+    pub fn save_all(&self) -> ANNResult<()> {
+        for collection in self.collections_snapshot()? {
+            self.save(&collection)?;
+        }
+
+        Ok(())
+    }
+
+    /// Save a collection if needed and unload it
+    pub fn evict_collection(&self, name: &str) -> ANNResult<()> {
+        let collection = self.collection(name)?;
+        if self.unload(&collection)? {
+            self.release_scratch(collection.scratch_spaces());
+        }
+
+        Ok(())
+    }
+
+    /// Evict the collections unused for the idle timeout, returns their names
+    pub fn evict_idle(&self) -> ANNResult<Vec<String>> {
+        let idle: Vec<(String, Arc<Collection>)> = self
+            .collections_read()?
+            .iter()
+            .filter(|(_, collection)| collection.idle_time() >= self.config.idle_timeout)
+            .map(|(name, collection)| (name.clone(), collection.clone()))
+            .collect();
+
+        let mut evicted = Vec::new();
+        for (name, collection) in idle {
+            if self.unload(&collection)? {
+                self.release_scratch(collection.scratch_spaces());
+                evicted.push(name);
+            }
+        }
+
+        Ok(evicted)
+    }
+
+    /// Run evict_idle every interval on a background thread, until the handle is dropped
+    pub fn start_idle_eviction(self: &Arc<Self>, interval: Duration) -> IdleEvictor {
+        let manager = Arc::downgrade(self);
+        let (stop, stopped) = mpsc::channel::<()>();
+
+        let thread = thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                match manager.upgrade() {
+                    // Failures are logged by ANNError, the next round tries again
+                    Some(manager) => {
+                        let _ = manager.evict_idle();
+                    }
+                    None => return,
+                }
+            }
+        });
+
+        IdleEvictor {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+
+    /// Insert points of the dimension of the collection, returns (id_start, id_len)
+    pub fn insert(&self, name: &str, points: &[Vec<f32>]) -> ANNResult<(usize, usize)> {
+        self.with_store_mut(name, |collection, store| {
+            check_dimension(collection.config.dim, points)?;
+            store.insert_data(points)
+        })
+    }
+
+    /// Delete points by the ids returned from insert and search
+    pub fn soft_delete(&self, name: &str, ids: Vec<u32>) -> ANNResult<()> {
+        self.with_store_mut(name, |_, store| store.soft_delete(ids))
+    }
+
+    /// Search the k nearest neighbors of a query, l defaults to the search list size
+    /// of the collection
+    pub fn search(
+        &self,
+        name: &str,
+        query: &[f32],
+        k: usize,
+        l: Option<u32>,
+    ) -> ANNResult<SearchResult> {
+        let mut results = self.batch_search(name, &[query.to_vec()], k, l)?;
+        Ok(results.remove(0))
+    }
+
+    /// Search the k nearest neighbors of every query in parallel on the shared pool
+    pub fn batch_search(
+        &self,
+        name: &str,
+        queries: &[Vec<f32>],
+        k: usize,
+        l: Option<u32>,
+    ) -> ANNResult<Vec<SearchResult>> {
+        if k == 0 {
+            return Err(ANNError::log_index_error(
+                "k must be greater than 0".to_string(),
+            ));
+        }
+
+        self.with_store(name, |collection, store| {
+            check_dimension(collection.config.dim, queries)?;
+            let l_value = l.unwrap_or(store.search_list_size()).max(k as u32);
+
+            queries
+                .par_iter()
+                .map(|query| store.search(query, k, l_value))
+                .collect()
+        })
+    }
+
+    /// Run body against the loaded index of a collection on the shared pool
+    fn with_store<R, F>(&self, name: &str, body: F) -> ANNResult<R>
+    where
+        F: FnOnce(&Collection, &MemANNStore<f32>) -> ANNResult<R> + Send,
+        R: Send,
+    {
+        let collection = self.collection(name)?;
+        collection.touch();
+
+        loop {
+            {
+                let store = collection.read()?;
+                if let Some(store) = store.as_ref() {
+                    return self.pool.install(|| body(&collection, store));
+                }
+            }
+
+            // Another thread can evict it again before the read lock is taken
+            self.load(&collection)?;
+        }
+    }
+
+    fn with_store_mut<R, F>(&self, name: &str, body: F) -> ANNResult<R>
+    where
+        F: FnOnce(&Collection, &mut MemANNStore<f32>) -> ANNResult<R> + Send,
+        R: Send,
+    {
+        let collection = self.collection(name)?;
+        collection.touch();
+
+        loop {
+            {
+                let mut store = collection.write()?;
+                if let Some(store) = store.as_mut() {
+                    collection.dirty.store(true, Ordering::SeqCst);
+                    return self.pool.install(|| body(&collection, store));
+                }
+            }
+
+            self.load(&collection)?;
+        }
+    }
+
+    fn load(&self, collection: &Arc<Collection>) -> ANNResult<()> {
+        let scratch_spaces = collection.scratch_spaces();
+        self.reserve_scratch(scratch_spaces, collection)?;
+
+        let loaded = (|| {
+            let mut store = collection.write()?;
+            if collection.dropped.load(Ordering::SeqCst) {
+                return Err(ANNError::log_index_error(
+                    "collection was dropped".to_string(),
+                ));
+            }
+            if store.is_some() {
+                // Loaded by another thread meanwhile
+                return Ok(false);
+            }
+
+            *store = Some(self.pool.install(|| {
+                MemANNStore::open_with_configuration(
+                    collection.config.clone(),
+                    &collection.index_path,
+                )
+            })?);
+            Ok(true)
+        })();
+
+        if !matches!(loaded, Ok(true)) {
+            self.release_scratch(scratch_spaces);
+        }
+        loaded.map(|_| ())
+    }
+
+    /// Save the collection if needed and unload it, returns whether it was loaded
+    fn unload(&self, collection: &Collection) -> ANNResult<bool> {
+        let mut store = collection.write()?;
+        match store.as_mut() {
+            Some(loaded) => {
+                if collection.dirty.load(Ordering::SeqCst) {
+                    self.pool
+                        .install(|| loaded.save_to_file(&collection.index_path))?;
+                    collection.dirty.store(false, Ordering::SeqCst);
+                }
+                *store = None;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn save(&self, collection: &Collection) -> ANNResult<()> {
+        let mut store = collection.write()?;
+        if let Some(loaded) = store.as_mut()
+            && collection.dirty.load(Ordering::SeqCst)
+        {
+            self.pool
+                .install(|| loaded.save_to_file(&collection.index_path))?;
+            collection.dirty.store(false, Ordering::SeqCst);
+        }
+
+        Ok(())
+    }
+
+    /// Take scratch spaces from the budget, evicting the least recently used collections
+    /// other than loading until they fit
+    fn reserve_scratch(&self, scratch_spaces: usize, loading: &Arc<Collection>) -> ANNResult<()> {
+        if scratch_spaces > self.config.scratch_budget {
+            return Err(ANNError::log_index_config_error(
+                "num_threads".to_string(),
+                format!(
+                    "the collection needs {} scratch spaces, the budget is {}",
+                    scratch_spaces, self.config.scratch_budget
+                ),
+            ));
+        }
+
+        let mut in_use = self.scratch_lock()?;
+        while *in_use + scratch_spaces > self.config.scratch_budget {
+            let victim = self
+                .collections_snapshot()?
+                .into_iter()
+                .filter(|collection| !Arc::ptr_eq(collection, loading) && collection.is_loaded())
+                .max_by_key(|collection| collection.idle_time())
+                .ok_or_else(|| {
+                    ANNError::log_index_error(
+                        "the scratch budget is taken by collections in use".to_string(),
+                    )
+                })?;
+
+            if self.unload(&victim)? {
+                *in_use -= victim.scratch_spaces();
+            }
+        }
+        *in_use += scratch_spaces;
+
+        Ok(())
+    }
+
+    fn release_scratch(&self, scratch_spaces: usize) {
+        if let Ok(mut in_use) = self.scratch_lock() {
+            *in_use = in_use.saturating_sub(scratch_spaces);
+        }
+    }
+
+    fn write_manifest(&self, collections: &BTreeMap<String, Arc<Collection>>) -> ANNResult<()> {
+        let configs: BTreeMap<&String, &IndexConfiguration> = collections
+            .iter()
+            .map(|(name, collection)| (name, &collection.config))
+            .collect();
+        let json = serde_json::to_string_pretty(&configs).map_err(|err| {
+            ANNError::log_index_error(format!("failed to write the manifest: {}", err))
+        })?;
+
+        // Written aside first, so that a failed write keeps the previous manifest
+        let manifest_path = self.root.join(MANIFEST_FILE);
+        let temp_path = self.root.join(format!("{}.tmp", MANIFEST_FILE));
+        fs::write(&temp_path, json)?;
+        fs::rename(&temp_path, &manifest_path)?;
+
+        Ok(())
+    }
+
+    fn collection(&self, name: &str) -> ANNResult<Arc<Collection>> {
+        self.collections_read()?
+            .get(name)
+            .cloned()
+            .ok_or_else(|| not_found(name))
+    }
+
+    fn collections_snapshot(&self) -> ANNResult<Vec<Arc<Collection>>> {
+        Ok(self.collections_read()?.values().cloned().collect())
+    }
+
+    fn collections_read(
+        &self,
+    ) -> ANNResult<RwLockReadGuard<'_, BTreeMap<String, Arc<Collection>>>> {
+        self.collections.read().map_err(|_| {
+            ANNError::log_lock_poison_error(
+                "failed to acquire the lock for the collections".to_string(),
+            )
+        })
+    }
+
+    fn collections_write(
+        &self,
+    ) -> ANNResult<RwLockWriteGuard<'_, BTreeMap<String, Arc<Collection>>>> {
+        self.collections.write().map_err(|_| {
+            ANNError::log_lock_poison_error(
+                "failed to acquire the lock for the collections".to_string(),
+            )
+        })
+    }
+
+    fn scratch_lock(&self) -> ANNResult<MutexGuard<'_, usize>> {
+        self.scratch_in_use.lock().map_err(|_| {
+            ANNError::log_lock_poison_error(
+                "failed to acquire the lock for the scratch budget".to_string(),
+            )
+        })
+    }
+}
+
+/// Background idle eviction, stopped when dropped
+pub struct IdleEvictor {
+    stop: Option<Sender<()>>,
+
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for IdleEvictor {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Names are used as directory names
+fn validate_name(name: &str) -> ANNResult<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(ANNError::log_index_config_error(
+            "name".to_string(),
+            format!(
+                "{:?} is not a valid collection name, use ASCII letters, digits, '-' and '_'",
+                name
+            ),
+        ))
+    }
+}
+
+fn not_found(name: &str) -> ANNError {
+    ANNError::log_index_error(format!("collection {} does not exist", name))
+}
+
+#[cfg(test)]
+mod collection_manager_test {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use vector::Metric;
+
+    use super::*;
+
+    const DIM: usize = 128;
+
+    /// Deterministic points in [0, 1)
+    fn random_points(num_points: usize, dim: usize, seed: u64) -> Vec<Vec<f32>> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..num_points)
+            .map(|_| (0..dim).map(|_| rng.gen_range(0f32..1f32)).collect())
+            .collect()
+    }
+
+    fn test_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!(
+            "collection_manager_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&root);
+        root
+    }
+
+    fn test_manager(root: &Path, scratch_budget: usize) -> CollectionManager {
+        let config = CollectionManagerConfig {
+            num_threads: 2,
+            scratch_budget,
+            idle_timeout: Duration::from_secs(300),
+        };
+        CollectionManager::open(root, config).unwrap()
+    }
+
+    fn index_config(metric: Metric, dim: usize) -> IndexConfiguration {
+        MemANNStore::<f32>::configuration(metric, dim, 32, 50, 1.2, 1, 64)
+    }
+
+    fn loaded(manager: &CollectionManager) -> Vec<String> {
+        manager
+            .list_collections()
+            .unwrap()
+            .into_iter()
+            .filter(|info| info.loaded)
+            .map(|info| info.name)
+            .collect()
+    }
+
+    #[test]
+    fn create_list_drop_test() {
+        let root = test_root("create_list_drop");
+        let manager = test_manager(&root, 1024);
+
+        manager
+            .create_collection("customer-b", index_config(Metric::L2, DIM))
+            .unwrap();
+        manager
+            .create_collection("customer_a", index_config(Metric::Cosine, 100))
+            .unwrap();
+
+        assert!(
+            manager
+                .create_collection("customer_a", index_config(Metric::L2, DIM))
+                .is_err()
+        );
+        assert!(
+            manager
+                .create_collection("../escape", index_config(Metric::L2, DIM))
+                .is_err()
+        );
+        assert!(
+            manager
+                .create_collection("", index_config(Metric::L2, DIM))
+                .is_err()
+        );
+
+        let collections = manager.list_collections().unwrap();
+        let names: Vec<&str> = collections.iter().map(|info| info.name.as_str()).collect();
+        assert_eq!(names, vec!["customer-b", "customer_a"]);
+        assert_eq!(collections[1].config.dist_metric, Metric::Cosine);
+        assert_eq!(collections[1].config.dim, 100);
+        assert_eq!(collections[1].config.aligned_dim, 104);
+        let saved_parameters = collections[1].config.index_write_parameter;
+
+        manager.drop_collection("customer-b").unwrap();
+        assert!(!root.join("customer-b").exists());
+        assert!(manager.drop_collection("customer-b").is_err());
+        assert!(manager.search("customer-b", &[0f32; DIM], 1, None).is_err());
+
+        // The manifest only lists the remaining collection
+        let reopened = test_manager(&root, 1024);
+        let collections = reopened.list_collections().unwrap();
+        assert_eq!(collections.len(), 1);
+        assert_eq!(collections[0].name, "customer_a");
+        assert_eq!(collections[0].config.dist_metric, Metric::Cosine);
+        assert_eq!(
+            collections[0].config.index_write_parameter,
+            saved_parameters
+        );
+        assert!(!collections[0].loaded);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn lazy_load_and_evict_test() {
+        let root = test_root("lazy_load_and_evict");
+        let points = random_points(50, DIM, 7);
+        {
+            let manager = test_manager(&root, 1024);
+            manager
+                .create_collection("vectors", index_config(Metric::L2, DIM))
+                .unwrap();
+            assert_eq!(manager.insert("vectors", &points).unwrap(), (0, 50));
+
+            manager.evict_collection("vectors").unwrap();
+            assert!(loaded(&manager).is_empty());
+
+            // The next search loads it again from disk
+            let result = manager.search("vectors", &points[10], 5, None).unwrap();
+            assert_eq!(result.ids[0], 10);
+            assert_eq!(loaded(&manager), vec!["vectors"]);
+
+            manager.soft_delete("vectors", vec![10]).unwrap();
+            manager.save_all().unwrap();
+        }
+
+        let manager = test_manager(&root, 1024);
+        assert!(loaded(&manager).is_empty());
+        assert!(manager.insert("vectors", &random_points(1, 64, 1)).is_err());
+
+        let results = manager
+            .batch_search(
+                "vectors",
+                &[points[10].clone(), points[20].clone()],
+                5,
+                None,
+            )
+            .unwrap();
+        assert!(!results[0].ids.contains(&10));
+        assert_eq!(results[1].ids[0], 20);
+        assert_eq!(results[1].ids.len(), results[1].distances.len());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn scratch_budget_evicts_least_recently_used_test() {
+        let root = test_root("scratch_budget");
+        // A collection with one thread takes 6 scratch spaces, the budget fits two
+        let manager = test_manager(&root, 12);
+        let points = random_points(20, DIM, 3);
+
+        for name in ["a", "b"] {
+            manager
+                .create_collection(name, index_config(Metric::L2, DIM))
+                .unwrap();
+            manager.insert(name, &points).unwrap();
+        }
+        manager.search("a", &points[0], 1, None).unwrap();
+
+        manager
+            .create_collection("c", index_config(Metric::L2, DIM))
+            .unwrap();
+        assert_eq!(loaded(&manager), vec!["a", "c"]);
+
+        // b was saved when it was evicted
+        let result = manager.search("b", &points[3], 1, None).unwrap();
+        assert_eq!(result.ids, vec![3]);
+        assert_eq!(loaded(&manager), vec!["b", "c"]);
+
+        let mut too_large = index_config(Metric::L2, DIM);
+        too_large.index_write_parameter.num_threads = 2;
+        let small_manager = test_manager(&test_root("scratch_budget_small"), 6);
+        assert!(small_manager.create_collection("d", too_large).is_err());
+
+        let _ = fs::remove_dir_all(&root);
+        let _ = fs::remove_dir_all(test_root("scratch_budget_small"));
+    }
+
+    #[test]
+    fn evict_idle_test() {
+        let root = test_root("evict_idle");
+        let mut config = CollectionManagerConfig {
+            num_threads: 2,
+            ..Default::default()
+        };
+        let manager = CollectionManager::open(&root, config.clone()).unwrap();
+        for name in ["a", "b"] {
+            manager
+                .create_collection(name, index_config(Metric::L2, DIM))
+                .unwrap();
+        }
+        assert!(manager.evict_idle().unwrap().is_empty());
+        drop(manager);
+
+        config.idle_timeout = Duration::ZERO;
+        let manager = Arc::new(CollectionManager::open(&root, config).unwrap());
+        manager.load_collection("a").unwrap();
+        manager.load_collection("b").unwrap();
+        assert_eq!(manager.evict_idle().unwrap(), vec!["a", "b"]);
+
+        manager.load_collection("a").unwrap();
+        let evictor = manager.start_idle_eviction(Duration::from_millis(10));
+        let start = Instant::now();
+        while !loaded(&manager).is_empty() && start.elapsed() < Duration::from_secs(10) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        drop(evictor);
+        assert!(loaded(&manager).is_empty());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
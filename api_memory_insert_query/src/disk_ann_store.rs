@@ -1,108 +1,259 @@
-/*
+use std::marker::PhantomData;
+
 use diskann::{
-    common::ANNResult,
-    index::ann_disk_index::{create_disk_index, ANNDiskIndex},
+    common::{ANNError, ANNResult},
+    index::ann_disk_index::{ANNDiskIndex, create_disk_index},
     model::{
-        vertex::{DIM_104, DIM_128, DIM_256, DIM_512}, DiskIndexBuildParameters, IndexConfiguration, IndexWriteParametersBuilder
+        DiskIndexBuildParameters, IndexConfiguration, IndexMetadata, IndexWriteParametersBuilder,
+        vertex::{DIM_104, DIM_128, DIM_256, DIM_512},
     },
     storage::DiskIndexStorage,
-    utils::{load_metadata_from_file, round_up, Timer},
+    utils::{VectorElement, file_exists, load_metadata_from_file, round_up},
 };
 
 use vector::{FullPrecisionDistance, Metric};
 
-/// The main function to build a disk index
-#[allow(clippy::too_many_arguments)]
-fn build_disk_index<T>(
+/// Search list size used to replay the warmup sample when generating the cache list
+const CACHE_SAMPLE_SEARCH_LIST: u32 = 15;
+
+/// Beamwidth used to replay the warmup sample when generating the cache list
+const CACHE_SAMPLE_BEAMWIDTH: u32 = 6;
+
+/// Disk (SSD) resident counterpart of `MemANNStore`.
+/// Building writes `{prefix}_disk.index`, the PQ pivot/compressed tables and the
+/// `{prefix}_sample_data.bin` warmup queries next to the given index path prefix.
+pub struct DiskANNStore<T>
+where
+    T: VectorElement + Sync + Send + 'static,
+    [T; DIM_104]: FullPrecisionDistance<T, DIM_104>,
+    [T; DIM_128]: FullPrecisionDistance<T, DIM_128>,
+    [T; DIM_256]: FullPrecisionDistance<T, DIM_256>,
+    [T; DIM_512]: FullPrecisionDistance<T, DIM_512>,
+{
     metric: Metric,
-    data_path: &str,
-    r: u32,
-    l: u32,
-    index_path_prefix: &str,
+
+    max_degree: u32,
+
+    search_list_size: u32,
+
+    alpha: f32,
+
     num_threads: u32,
-    search_ram_limit_gb: f64,
-    index_build_ram_limit_gb: f64,
-    num_pq_chunks: usize,
-    use_opq: bool,
-) -> ANNResult<()>
+
+    /// Number of Vamana build passes, None for the default
+    num_rounds: Option<u32>,
+
+    disk_build_param: DiskIndexBuildParameters,
+
+    index: Option<Box<dyn ANNDiskIndex<T>>>,
+
+    _phantom_data: PhantomData<T>,
+}
+
+impl<T> DiskANNStore<T>
 where
-    T: Default + Copy + Sync + Send + Into<f32>,
+    T: VectorElement + Sync + Send + 'static,
     [T; DIM_104]: FullPrecisionDistance<T, DIM_104>,
     [T; DIM_128]: FullPrecisionDistance<T, DIM_128>,
     [T; DIM_256]: FullPrecisionDistance<T, DIM_256>,
     [T; DIM_512]: FullPrecisionDistance<T, DIM_512>,
 {
-    let disk_index_build_parameters =
-        DiskIndexBuildParameters::new(search_ram_limit_gb, index_build_ram_limit_gb)?;
-
-    let index_write_parameters = IndexWriteParametersBuilder::new(l, r)
-        .with_saturate_graph(true)
-        .with_num_threads(num_threads)
-        .build();
-
-    let (data_num, data_dim) = load_metadata_from_file(data_path)?;
-
-    let config = IndexConfiguration::new(
-        metric,
-        data_dim,
-        round_up(data_dim as u64, 8_u64) as usize,
-        data_num,
-        num_pq_chunks > 0,
-        num_pq_chunks,
-        use_opq,
-        0,
-        1f32,
-        index_write_parameters,
-    );
-    let storage = DiskIndexStorage::new(data_path.to_string(), index_path_prefix.to_string())?;
-    let mut index = create_disk_index::<T>(Some(disk_index_build_parameters), config, storage)?;
-
-    let timer = Timer::new();
-
-    index.build("")?;
-
-    let diff = timer.elapsed();
-    println!("Indexing time: {}", diff.as_secs_f64());
-
-    Ok(())
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        metric: Metric,
+        max_degree: u32,
+        search_list_size: u32,
+        alpha: f32,
+        num_threads: u32,
+        search_ram_limit_gb: f64,
+        index_build_ram_limit_gb: f64,
+    ) -> ANNResult<Self> {
+        let disk_build_param =
+            DiskIndexBuildParameters::new(search_ram_limit_gb, index_build_ram_limit_gb)?;
+
+        Ok(Self {
+            metric,
+            max_degree,
+            search_list_size,
+            alpha,
+            num_threads,
+            num_rounds: None,
+            disk_build_param,
+            index: None,
+            _phantom_data: PhantomData,
+        })
+    }
+
+    /// Set the number of Vamana build passes
+    pub fn with_num_rounds(mut self, num_rounds: u32) -> Self {
+        self.num_rounds = Some(num_rounds);
+        self
+    }
+
+    /// Build the disk index for the vectors in `data_path` (bin format) and save it under `index_path_prefix`
+    pub fn build_from_file(&mut self, data_path: &str, index_path_prefix: &str) -> ANNResult<()> {
+        let mut builder = IndexWriteParametersBuilder::new(self.search_list_size, self.max_degree)
+            .with_alpha(self.alpha)
+            .with_saturate_graph(true)
+            .with_num_threads(self.num_threads);
+        if let Some(num_rounds) = self.num_rounds {
+            builder = builder.with_num_rounds(num_rounds);
+        }
+        let index_write_parameters = builder.build();
+
+        let (data_num, data_dim) = load_metadata_from_file(data_path)?;
+
+        let config = IndexConfiguration::new(
+            self.metric,
+            data_dim,
+            round_up(data_dim as u64, 8_u64) as usize,
+            data_num,
+            false,
+            0,
+            false,
+            0,
+            1f32,
+            index_write_parameters,
+        );
+        let storage = DiskIndexStorage::new(data_path.to_string(), index_path_prefix.to_string())?;
+        let mut index = create_disk_index::<T>(Some(self.disk_build_param), config, storage)?;
+
+        index.build("")?;
+        self.index = Some(index);
+
+        Ok(())
+    }
+
+    /// Load the disk index built under `index_path_prefix` for searching, with the configuration
+    /// saved in its metadata. Up to `num_nodes_to_cache` nodes are kept in memory: the ones the
+    /// warmup sample of the build expands most, or the ones closest to the medoid without it.
+    pub fn load(&mut self, index_path_prefix: &str, num_nodes_to_cache: usize) -> ANNResult<()> {
+        let storage = DiskIndexStorage::<T>::new_for_search(index_path_prefix.to_string())?;
+        let metadata = IndexMetadata::load(&storage.metadata_file())?;
+        metadata.check_data_type::<T>()?;
+        if metadata.configuration.dist_metric != self.metric {
+            return Err(ANNError::log_index_config_error(
+                "metric".to_string(),
+                format!(
+                    "Disk index {} was built with {:?}, not {:?}",
+                    index_path_prefix, metadata.configuration.dist_metric, self.metric
+                ),
+            ));
+        }
+
+        let mut index = create_disk_index::<T>(None, metadata.configuration, storage)?;
+        index.load(self.num_threads)?;
+
+        if num_nodes_to_cache > 0 {
+            let warmup_query_file = format!("{}_sample_data.bin", index_path_prefix);
+            let node_list = if file_exists(&warmup_query_file) {
+                index.generate_cache_list_from_sample_queries(
+                    &warmup_query_file,
+                    CACHE_SAMPLE_SEARCH_LIST,
+                    CACHE_SAMPLE_BEAMWIDTH,
+                    num_nodes_to_cache,
+                )?
+            } else {
+                index.cache_bfs_levels(num_nodes_to_cache)?
+            };
+            index.load_cache_list(&node_list)?;
+        }
+
+        self.index = Some(index);
+
+        Ok(())
+    }
+
+    /// Search the loaded index for the k_value nearest neighbors of query with search list size
+    /// l_value, reading up to beam_width nodes from disk per hop. Returns the number of distance
+    /// comparisons. A built index has to be loaded first.
+    pub fn search(
+        &self,
+        query: &[T],
+        k_value: usize,
+        l_value: u32,
+        beam_width: u32,
+        indices: &mut [u32],
+        distances: &mut [f32],
+    ) -> ANNResult<u32> {
+        let index = self.index.as_ref().ok_or_else(|| {
+            ANNError::log_index_error("No disk index, build or load one first".to_string())
+        })?;
+
+        index.beam_search(
+            query,
+            k_value,
+            l_value,
+            beam_width,
+            u32::MAX,
+            indices,
+            distances,
+            None,
+        )
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use diskann::common::ANNResult;
+    use std::fs;
+
+    use diskann::utils::file_exists;
     use vector::Metric;
-    use crate::disk_ann_store::build_disk_index;
 
-    const VECTOR_FILE: &str = "test/input/embeddings.json";
-    const INPUT_VECTOR_FILE: &str = "test/input/saved_vector_file.data";
-    //const INPUT_VECTOR_FILE: &str = "test/input/siftsmall_learn.bin";
-    const SAVED_VECTOR_FILE: &str = "test/output/saved_vector_file";
+    use super::DiskANNStore;
+
+    const TEST_DATA_FILE: &str = "../diskann/tests/data/siftsmall_learn_256pts.fbin";
+    const TRUTH_DISK_LAYOUT: &str =
+        "../diskann/tests/data/truth_disk_index_siftsmall_learn_256pts_R4_L50_A1.2_disk.index";
 
     #[test]
-    fn test_disk_output() -> ANNResult<()> {
-        let err = build_disk_index::<f32>(
-            Metric::L2,
-            INPUT_VECTOR_FILE,
-            11,
-            61,
-            SAVED_VECTOR_FILE,
-            1,
-            1.0,
-            1.0,
-            0,
-            false,
-        );
+    fn build_from_file_test() {
+        let output_dir = std::env::temp_dir().join("disk_ann_store_build_from_file_test");
+        fs::create_dir_all(&output_dir).unwrap();
+        let index_path_prefix = output_dir.join("siftsmall_learn_256pts");
+        let index_path_prefix = index_path_prefix.to_str().unwrap();
 
-        match err {
-            Ok(_) => {
-                println!("Index build completed successfully");
-                Ok(())
-            }
-            Err(err) => {
-                eprintln!("Error: {:?}", err);
-                Err(err)
-            }
+        // The truth layout was built in a single pass
+        let mut store = DiskANNStore::<f32>::new(Metric::L2, 4, 50, 1.2, 1, 1.0, 1.0)
+            .unwrap()
+            .with_num_rounds(1);
+        assert!(store.index.is_none());
+
+        store
+            .build_from_file(TEST_DATA_FILE, index_path_prefix)
+            .unwrap();
+        assert!(store.index.is_some());
+
+        for suffix in [
+            "_disk.index",
+            ".bin_pq_pivots.bin",
+            ".bin_pq_compressed.bin",
+            "_sample_data.bin",
+        ] {
+            assert!(file_exists(&format!("{index_path_prefix}{suffix}")));
         }
+
+        let disk_layout = fs::read(format!("{index_path_prefix}_disk.index")).unwrap();
+        let truth_disk_layout = fs::read(TRUTH_DISK_LAYOUT).unwrap();
+        assert!(disk_layout == truth_disk_layout);
+
+        fs::remove_dir_all(output_dir).unwrap();
+    }
+
+    #[test]
+    fn search_without_index_test() {
+        let store = DiskANNStore::<f32>::new(Metric::L2, 4, 50, 1.2, 1, 1.0, 1.0).unwrap();
+        let mut indices = [0u32; 1];
+        let mut distances = [0f32; 1];
+        assert!(
+            store
+                .search(&[0f32; 128], 1, 50, 4, &mut indices, &mut distances)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn new_rejects_empty_ram_budget() {
+        assert!(DiskANNStore::<f32>::new(Metric::L2, 4, 50, 1.2, 1, 0.0, 1.0).is_err());
     }
 }
-*/
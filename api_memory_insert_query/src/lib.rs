@@ -1,2 +1,4 @@
+pub mod collection_manager;
 pub mod disk_ann_store;
 pub mod mem_ann_store;
+pub mod server;
@@ -1,22 +1,52 @@
 #![allow(dead_code)]
+use std::borrow::Cow;
 use std::marker::PhantomData;
 
 use diskann::{
-    common::ANNResult,
-    index::{ANNInmemIndex, INIT_WARMUP_DATA_LEN, create_inmem_index},
+    common::{ANNError, ANNResult},
+    index::{
+        ANNInmemIndex, GraphStats, INIT_WARMUP_DATA_LEN, create_inmem_index, load_inmem_index,
+        tune_inmem_search,
+    },
     model::{
-        IndexConfiguration,
+        IndexConfiguration, IndexMetadata, Payload, PayloadSchema, TunedSearchParameters,
         configuration::index_write_parameters::IndexWriteParametersBuilder,
         vertex::{DIM_104, DIM_128, DIM_256, DIM_512},
     },
-    utils::round_up,
+    utils::{VectorElement, round_up},
 };
 
 use vector::{FullPrecisionDistance, Metric};
 
+/// Id the searches report for the result slots they could not fill
+pub const MISSING_ID: u32 = u32::MAX;
+
+/// Neighbors of a query, closest first
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    pub ids: Vec<u32>,
+
+    pub distances: Vec<f32>,
+}
+
+/// Fail unless every vector has the given dimension
+pub fn check_dimension<T, V: AsRef<[T]>>(dimension: usize, vectors: &[V]) -> ANNResult<()> {
+    match vectors
+        .iter()
+        .find(|vector| vector.as_ref().len() != dimension)
+    {
+        Some(vector) => Err(ANNError::log_index_error(format!(
+            "expected vectors of dimension {}, got {}",
+            dimension,
+            vector.as_ref().len()
+        ))),
+        None => Ok(()),
+    }
+}
+
 pub struct MemANNStore<T>
 where
-    T: Default + Copy + Sync + Send + Into<f32> + 'static,
+    T: VectorElement + Sync + Send + 'static,
     [T; DIM_104]: FullPrecisionDistance<T, DIM_104>,
     [T; DIM_128]: FullPrecisionDistance<T, DIM_128>,
     [T; DIM_256]: FullPrecisionDistance<T, DIM_256>,
@@ -41,7 +71,7 @@ where
 
 impl<T> MemANNStore<T>
 where
-    T: Default + Copy + Sync + Send + Into<f32> + From<f32> + 'static,
+    T: VectorElement + Sync + Send + From<f32> + 'static,
     [T; DIM_104]: FullPrecisionDistance<T, DIM_104>,
     [T; DIM_128]: FullPrecisionDistance<T, DIM_128>,
     [T; DIM_256]: FullPrecisionDistance<T, DIM_256>,
@@ -56,13 +86,111 @@ where
         num_threads: u32,
         max_point: usize,
     ) -> ANNResult<Self> {
+        Self::with_configuration(Self::configuration(
+            metric,
+            dimension,
+            max_degree,
+            search_list_size,
+            alpha,
+            num_threads,
+            max_point,
+        ))
+    }
+
+    /// Create a store with the warmup points from a full index configuration
+    pub fn with_configuration(config: IndexConfiguration) -> ANNResult<Self> {
+        let dimension = config.dim;
+        let mut slf = Self::create(config)?;
+
+        let mut create_points = vec![vec![T::default(); dimension]; INIT_WARMUP_DATA_LEN as usize];
+
+        for (i, point) in create_points.iter_mut().enumerate() {
+            for (j, value) in point.iter_mut().enumerate() {
+                *value = ((i + j) as f32).into();
+            }
+        }
+
+        Self::init_data(&mut slf, &create_points)?;
+
+        Ok(slf)
+    }
+
+    /// Open a store saved with save_to_file, it already holds the warmup points
+    pub fn open(
+        metric: Metric,
+        dimension: usize,
+        max_degree: u32,
+        search_list_size: u32,
+        alpha: f32,
+        num_threads: u32,
+        save_path: &str,
+    ) -> ANNResult<Self> {
+        Self::open_with_configuration(
+            Self::configuration(
+                metric,
+                dimension,
+                max_degree,
+                search_list_size,
+                alpha,
+                num_threads,
+                0,
+            ),
+            save_path,
+        )
+    }
+
+    /// Open a store saved with save_to_file, max_points of the configuration is ignored
+    pub fn open_with_configuration(
+        mut config: IndexConfiguration,
+        save_path: &str,
+    ) -> ANNResult<Self> {
+        config.max_points = 0;
+        let mut slf = Self::create(config)?;
+
+        slf.load_from_file(save_path)?;
+
+        Ok(slf)
+    }
+
+    /// Open a store saved with save_to_file with the configuration saved in its metadata
+    pub fn load(save_path: &str) -> ANNResult<Self> {
+        let mut config = IndexMetadata::load(&IndexMetadata::file_name(save_path))?.configuration;
+        config.max_points = 0;
+        let index = load_inmem_index::<T>(save_path)?;
+
+        Ok(Self::from_index(config, index))
+    }
+
+    /// Load a store like load, failing unless it was saved with the given metric and dimension
+    pub fn load_checked(save_path: &str, metric: Metric, dimension: usize) -> ANNResult<Self> {
+        let slf = Self::load(save_path)?;
+        if slf.config.dist_metric != metric || slf.config.dim != dimension {
+            return Err(ANNError::log_index_error(format!(
+                "index {} was saved with metric {:?} and dimension {}, not {:?} and {}",
+                save_path, slf.config.dist_metric, slf.config.dim, metric, dimension
+            )));
+        }
+
+        Ok(slf)
+    }
+
+    /// Index configuration the store parameters map to
+    pub fn configuration(
+        metric: Metric,
+        dimension: usize,
+        max_degree: u32,
+        search_list_size: u32,
+        alpha: f32,
+        num_threads: u32,
+        max_point: usize,
+    ) -> IndexConfiguration {
         let index_write_parameters = IndexWriteParametersBuilder::new(search_list_size, max_degree)
             .with_alpha(alpha)
             .with_saturate_graph(false)
             .with_num_threads(num_threads)
             .build();
 
-        let config = IndexConfiguration::new(
+        IndexConfiguration::new(
             metric,
             dimension,
             round_up(dimension as u64, 8_u64) as usize,
@@ -74,47 +202,130 @@ where
             0,
             2.0f32,
             index_write_parameters,
-        );
-        let index = create_inmem_index::<T>(config.clone())?;
+        )
+    }
 
-        let mut create_points = vec![vec![T::default(); dimension]; 5];
+    fn create(config: IndexConfiguration) -> ANNResult<Self> {
+        let index = create_inmem_index::<T>(config.clone())?;
 
-        for i in 0..INIT_WARMUP_DATA_LEN as usize {
-            for j in 0..dimension {
-                create_points[i][j] = ((i + j) as f32).into();
-            }
-        }
+        Ok(Self::from_index(config, index))
+    }
 
-        let mut slf = Self {
-            metric,
-            max_degree,
-            search_list_size,
-            alpha,
-            num_threads,
+    fn from_index(config: IndexConfiguration, index: Box<dyn ANNInmemIndex<T>>) -> Self {
+        Self {
+            metric: config.dist_metric,
+            max_degree: config.index_write_parameter.max_degree,
+            search_list_size: config.index_write_parameter.search_list_size,
+            alpha: config.index_write_parameter.alpha,
+            num_threads: config.index_write_parameter.num_threads,
             config,
             index,
             _phantom_data: PhantomData,
-        };
+        }
+    }
 
-        Self::init_data(&mut slf, &create_points)?;
+    /// Dimension of the points
+    pub fn dimension(&self) -> usize {
+        self.config.dim
+    }
 
-        Ok(slf)
+    /// Dimension a query has to be zero padded to
+    pub fn aligned_dimension(&self) -> usize {
+        self.config.aligned_dim
     }
 
-    pub fn init_data(&mut self, data: &Vec<Vec<T>>) -> ANNResult<()> {
+    /// L of the searches that give none: the tuned one, else the configured search list size
+    pub fn search_list_size(&self) -> u32 {
+        self.index.search_list_size()
+    }
+
+    /// Tune the L of the searches that give none for recall@k_value of target_recall percent
+    /// on a sample of queries, against their exact neighbors. It is saved with the index.
+    pub fn tune_search(
+        &mut self,
+        queries: &[Vec<T>],
+        k_value: usize,
+        target_recall: f32,
+        max_l_value: u32,
+    ) -> ANNResult<TunedSearchParameters> {
+        let aligned_dim = self.config.aligned_dim;
+        let mut flat_queries = Vec::with_capacity(queries.len() * aligned_dim);
+        for query in queries {
+            flat_queries.extend_from_slice(&self.padded_query(query)?);
+        }
+
+        let tuned_search = tune_inmem_search(
+            self.index.as_ref(),
+            &flat_queries,
+            aligned_dim,
+            None,
+            k_value,
+            target_recall,
+            max_l_value,
+        )?;
+        self.index.set_tuned_search_parameters(Some(tuned_search));
+
+        Ok(tuned_search)
+    }
+
+    pub fn init_data(&mut self, data: &[Vec<T>]) -> ANNResult<()> {
         self.index.build_vector(data)
     }
 
     /// Return (id_start, id_len)
-    pub fn insert_data(&mut self, data: &Vec<Vec<T>>) -> ANNResult<(usize, usize)> {
+    pub fn insert_data(&mut self, data: &[Vec<T>]) -> ANNResult<(usize, usize)> {
         self.index.insert_vector(data)
     }
 
+    /// Keep a payload per point, of bytes or of records of the given schema
+    pub fn enable_payloads(&mut self, schema: Option<PayloadSchema>) -> ANNResult<()> {
+        self.index.enable_payloads(schema)
+    }
+
+    /// Insert points with one payload each, return (id_start, id_len)
+    pub fn insert_data_with_payloads(
+        &mut self,
+        data: &[Vec<T>],
+        payloads: Vec<Payload>,
+    ) -> ANNResult<(usize, usize)> {
+        self.index.insert_vector_with_payloads(data, payloads)
+    }
+
+    /// Delete points by the ids returned from insert_data and query. They don't count the
+    /// warmup points, which come first in the index, so they are offset by INIT_WARMUP_DATA_LEN
     pub fn soft_delete(&mut self, vertex_ids_to_delete: Vec<u32>) -> ANNResult<()> {
+        let vertex_ids_to_delete: Vec<u32> = vertex_ids_to_delete
+            .into_iter()
+            .map(|id| id.saturating_add(INIT_WARMUP_DATA_LEN))
+            .collect();
         let len = vertex_ids_to_delete.len();
         self.index.soft_delete(vertex_ids_to_delete, len)
     }
 
+    /// Degree statistics of the graph, the warmup points included
+    pub fn graph_stats(&self) -> ANNResult<GraphStats> {
+        self.index.graph_stats()
+    }
+
+    /// Reconnect the points that searches can no longer reach after many inserts and deletes,
+    /// returns how many were fixed
+    pub fn repair_connectivity(&mut self) -> ANNResult<usize> {
+        self.index.repair_connectivity()
+    }
+
+    /// Drop the deleted points and renumber the others densely, returns the new id of each id
+    /// returned by insert_data, None for the deleted ones
+    pub fn compact(&mut self) -> ANNResult<Vec<Option<u32>>> {
+        // The warmup points come first in the graph and are never deleted
+        Ok(self
+            .index
+            .compact()?
+            .into_iter()
+            .skip(INIT_WARMUP_DATA_LEN as usize)
+            .map(|new_id| new_id.map(|id| id - INIT_WARMUP_DATA_LEN))
+            .collect())
+    }
+
     pub fn save_to_file(&mut self, save_path: &str) -> ANNResult<()> {
         self.index.save(save_path)
     }
@@ -123,6 +334,7 @@ where
         self.index.load_with_enhance(save_path, 0)
     }
 
+    /// Query of the dimension of the points, or already zero padded to the aligned dimension
     pub fn query(
         &self,
         query: &[T],
@@ -131,7 +343,158 @@ where
         indices: &mut [u32],
         distances: &mut [f32],
     ) -> ANNResult<u32> {
-        self.index
-            .search_with_distance(query, k_value, l_value, indices, distances)
+        self.index.search_with_distance(
+            &self.padded_query(query)?,
+            k_value,
+            l_value,
+            indices,
+            distances,
+            None,
+        )
+    }
+
+    /// The k_value nearest neighbors of query, fewer when the search could not fill them all
+    pub fn search(&self, query: &[T], k_value: usize, l_value: u32) -> ANNResult<SearchResult> {
+        let mut ids = vec![MISSING_ID; k_value];
+        let mut distances = vec![f32::INFINITY; k_value];
+        self.query(query, k_value, l_value, &mut ids, &mut distances)?;
+
+        let found = ids.iter().take_while(|&&id| id != MISSING_ID).count();
+        ids.truncate(found);
+        distances.truncate(found);
+
+        Ok(SearchResult { ids, distances })
+    }
+
+    /// Queries are zero padded to the aligned dimension like the points in the index
+    fn padded_query<'a>(&self, query: &'a [T]) -> ANNResult<Cow<'a, [T]>> {
+        if query.len() == self.config.aligned_dim {
+            return Ok(Cow::Borrowed(query));
+        }
+        check_dimension(self.config.dim, &[query])?;
+
+        let mut padded = query.to_vec();
+        padded.resize(self.config.aligned_dim, T::default());
+        Ok(Cow::Owned(padded))
+    }
+
+    /// Query among the points whose id passes predicate, see search_with_predicate for selectivity.
+    /// Returns the number of comparisons and of result slots filled.
+    #[allow(clippy::too_many_arguments)]
+    pub fn query_with_predicate(
+        &self,
+        query: &[T],
+        k_value: usize,
+        l_value: u32,
+        predicate: &dyn Fn(u32) -> bool,
+        selectivity: Option<f32>,
+        indices: &mut [u32],
+        distances: &mut [f32],
+    ) -> ANNResult<(u32, usize)> {
+        self.index.search_with_predicate(
+            &self.padded_query(query)?,
+            k_value,
+            l_value,
+            predicate,
+            selectivity,
+            indices,
+            distances,
+        )
+    }
+
+    /// Fraction of the points whose id passes predicate, to reuse across queries
+    pub fn predicate_selectivity(&self, predicate: &dyn Fn(u32) -> bool) -> f32 {
+        self.index.estimate_selectivity(predicate)
+    }
+
+    /// Query that also copies the payloads of the neighbors, None for those without one
+    pub fn query_with_payloads(
+        &self,
+        query: &[T],
+        k_value: usize,
+        l_value: u32,
+        indices: &mut [u32],
+        distances: &mut [f32],
+        payloads: &mut [Option<Payload>],
+    ) -> ANNResult<u32> {
+        self.index.search_with_distance(
+            &self.padded_query(query)?,
+            k_value,
+            l_value,
+            indices,
+            distances,
+            Some(payloads),
+        )
+    }
+}
+
+#[cfg(test)]
+mod mem_ann_store_test {
+    use super::*;
+
+    #[test]
+    fn soft_delete_inserted_id_test() {
+        let dimension = 128;
+        let mut store = MemANNStore::<f32>::new(
+            Metric::L2,
+            dimension,
+            16,
+            50,
+            1.2,
+            1,
+            INIT_WARMUP_DATA_LEN as usize,
+        )
+        .unwrap();
+        let points: Vec<Vec<f32>> = (0..20)
+            .map(|i| (0..dimension).map(|j| (100 * i + j) as f32).collect())
+            .collect();
+        let (id_start, _) = store.insert_data(&points).unwrap();
+
+        let mut indices = [0u32; 1];
+        let mut distances = [0f32; 1];
+        let id = id_start as u32 + 3;
+        store
+            .query(&points[3], 1, 50, &mut indices, &mut distances)
+            .unwrap();
+        assert_eq!(indices[0], id);
+
+        store.soft_delete(vec![id]).unwrap();
+        store
+            .query(&points[3], 1, 50, &mut indices, &mut distances)
+            .unwrap();
+        assert_ne!(indices[0], id);
+    }
+
+    #[test]
+    fn load_checked_test() {
+        let dimension = 128;
+        let root = std::env::temp_dir().join(format!("mem_ann_store_load_{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let save_path = root.join("index").to_string_lossy().into_owned();
+        let mut store = MemANNStore::<f32>::new(
+            Metric::L2,
+            dimension,
+            16,
+            50,
+            1.2,
+            1,
+            INIT_WARMUP_DATA_LEN as usize,
+        )
+        .unwrap();
+        let points: Vec<Vec<f32>> = (0..20)
+            .map(|i| (0..dimension).map(|j| (100 * i + j) as f32).collect())
+            .collect();
+        let (id_start, _) = store.insert_data(&points).unwrap();
+        store.save_to_file(&save_path).unwrap();
+
+        let loaded = MemANNStore::<f32>::load_checked(&save_path, Metric::L2, dimension).unwrap();
+        let result = loaded.search(&points[3], 1, 50).unwrap();
+        let other_metric = MemANNStore::<f32>::load_checked(&save_path, Metric::Cosine, dimension);
+        let other_dimension = MemANNStore::<f32>::load_checked(&save_path, Metric::L2, 64);
+
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(result.ids, vec![id_start as u32 + 3]);
+        assert!(other_metric.is_err());
+        assert!(other_dimension.is_err());
     }
 }
@@ -0,0 +1,194 @@
+//! REST server around `MemANNStore`
+//!
+//! Requests go through a bounded in-process queue served by a fixed number of worker
+//! threads. On shutdown the server stops accepting connections, runs the queued requests
+//! and saves the index to its index path.
+
+mod request_queue;
+mod routes;
+
+pub use request_queue::{QueueError, RequestQueue, SharedStore};
+pub use routes::*;
+
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+
+use diskann::common::{ANNError, ANNResult};
+use diskann::model::IndexMetadata;
+use diskann::utils::file_exists;
+use tokio::net::TcpListener;
+use vector::Metric;
+
+use crate::mem_ann_store::MemANNStore;
+
+/// Configuration of the server
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Address to listen on, port 0 picks a free port
+    pub address: SocketAddr,
+
+    /// Path prefix the index is loaded from if it exists and saved to on shutdown
+    pub index_path: String,
+
+    pub metric: Metric,
+
+    pub dimension: usize,
+
+    pub max_degree: u32,
+
+    pub search_list_size: u32,
+
+    pub alpha: f32,
+
+    /// Number of threads used to build the graph, 0 uses all the cores
+    pub num_threads: u32,
+
+    /// Number of worker threads serving requests
+    pub concurrency: usize,
+
+    /// Number of requests that can wait for a worker before new ones are rejected
+    pub queue_size: usize,
+}
+
+impl ServerConfig {
+    /// Default configuration for vectors of the given dimension
+    pub fn new(dimension: usize, index_path: &str) -> Self {
+        Self {
+            address: SocketAddr::from(([127, 0, 0, 1], 8080)),
+            index_path: index_path.to_string(),
+            metric: Metric::L2,
+            dimension,
+            max_degree: 64,
+            search_list_size: 100,
+            alpha: 1.2,
+            num_threads: 0,
+            concurrency: 4,
+            queue_size: 1024,
+        }
+    }
+}
+
+/// Server bound to its address, ready to run
+pub struct Server {
+    config: ServerConfig,
+
+    listener: TcpListener,
+
+    store: SharedStore,
+}
+
+impl Server {
+    /// Load the index if it was saved before, otherwise create an empty one, and bind the address
+    pub async fn bind(config: ServerConfig) -> ANNResult<Self> {
+        let store = if file_exists(&IndexMetadata::file_name(&config.index_path)) {
+            // The saved configuration takes precedence over the command line
+            log::info!("Loading index from {}", config.index_path);
+            MemANNStore::load(&config.index_path)?
+        } else if file_exists(&format!("{}.data", config.index_path)) {
+            log::info!("Loading index from {}", config.index_path);
+            MemANNStore::open(
+                config.metric,
+                config.dimension,
+                config.max_degree,
+                config.search_list_size,
+                config.alpha,
+                config.num_threads,
+                &config.index_path,
+            )?
+        } else {
+            log::info!("Creating index {}", config.index_path);
+            MemANNStore::new(
+                config.metric,
+                config.dimension,
+                config.max_degree,
+                config.search_list_size,
+                config.alpha,
+                config.num_threads,
+                1024,
+            )?
+        };
+
+        let listener = TcpListener::bind(config.address).await?;
+
+        Ok(Self {
+            config,
+            listener,
+            store: Arc::new(RwLock::new(store)),
+        })
+    }
+
+    /// Address the server listens on
+    pub fn local_addr(&self) -> ANNResult<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Serve requests until shutdown completes, then save the index
+    pub async fn run<F>(self, shutdown: F) -> ANNResult<()>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let state = Arc::new(AppState {
+            queue: RequestQueue::new(
+                self.store.clone(),
+                self.config.concurrency,
+                self.config.queue_size,
+            ),
+            dimension: self.store_read()?.dimension(),
+            search_list_size: self.store_read()?.search_list_size(),
+            index_path: self.config.index_path.clone(),
+        });
+
+        log::info!("Listening on {}", self.local_addr()?);
+        axum::serve(self.listener, routes::router(state.clone()))
+            .with_graceful_shutdown(shutdown)
+            .await?;
+
+        log::info!("Shutting down, saving index to {}", self.config.index_path);
+        let store = self.store.clone();
+        let index_path = self.config.index_path.clone();
+        tokio::task::spawn_blocking(move || {
+            state.queue.shutdown();
+
+            let mut store = store.write().map_err(|_| {
+                ANNError::log_lock_poison_error(
+                    "failed to acquire the lock for the store".to_string(),
+                )
+            })?;
+            store.save_to_file(&index_path)
+        })
+        .await
+        .map_err(|err| ANNError::log_index_error(format!("failed to save the index: {}", err)))?
+    }
+
+    fn store_read(&self) -> ANNResult<std::sync::RwLockReadGuard<'_, MemANNStore<f32>>> {
+        self.store.read().map_err(|_| {
+            ANNError::log_lock_poison_error("failed to acquire the lock for the store".to_string())
+        })
+    }
+}
+
+/// Resolve on Ctrl+C, or on SIGTERM on unix
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending().await,
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
@@ -0,0 +1,162 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{Receiver, SyncSender, TrySendError, sync_channel};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+
+use tokio::sync::oneshot;
+
+use crate::mem_ann_store::MemANNStore;
+
+/// Store shared by the workers, searches take the read lock and updates the write lock
+pub type SharedStore = Arc<RwLock<MemANNStore<f32>>>;
+
+type Job = Box<dyn FnOnce(&SharedStore) + Send>;
+
+/// Reason a request could not be queued
+#[derive(Debug, PartialEq, Eq)]
+pub enum QueueError {
+    /// All the slots of the queue are taken
+    Full,
+
+    /// The queue no longer accepts requests
+    ShuttingDown,
+
+    /// The job panicked before returning a result
+    Panicked,
+}
+
+/// Bounded queue of requests run against the store by a fixed number of worker threads
+pub struct RequestQueue {
+    sender: Mutex<Option<SyncSender<Job>>>,
+
+    workers: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl RequestQueue {
+    /// Start concurrency workers taking requests from a queue of queue_size slots
+    pub fn new(store: SharedStore, concurrency: usize, queue_size: usize) -> Self {
+        let (sender, receiver) = sync_channel::<Job>(queue_size);
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let workers = (0..concurrency.max(1))
+            .map(|_| {
+                let receiver = receiver.clone();
+                let store = store.clone();
+                thread::spawn(move || Self::work(&receiver, &store))
+            })
+            .collect();
+
+        Self {
+            sender: Mutex::new(Some(sender)),
+            workers: Mutex::new(workers),
+        }
+    }
+
+    fn work(receiver: &Mutex<Receiver<Job>>, store: &SharedStore) {
+        loop {
+            // The lock is released before the job runs, so that the other workers can take one
+            let job = match receiver.lock() {
+                Ok(receiver) => receiver.recv(),
+                Err(_) => return,
+            };
+
+            match job {
+                // A panicking job drops its result sender, which fails its request only
+                Ok(job) => {
+                    let _ = panic::catch_unwind(AssertUnwindSafe(|| job(store)));
+                }
+                Err(_) => return,
+            }
+        }
+    }
+
+    /// Queue a job and wait for its result
+    pub async fn execute<F, R>(&self, job: F) -> Result<R, QueueError>
+    where
+        F: FnOnce(&SharedStore) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (result_sender, result_receiver) = oneshot::channel();
+        let job: Job = Box::new(move |store| {
+            let _ = result_sender.send(job(store));
+        });
+
+        {
+            let sender = self.sender.lock().map_err(|_| QueueError::ShuttingDown)?;
+            let sender = sender.as_ref().ok_or(QueueError::ShuttingDown)?;
+            sender.try_send(job).map_err(|err| match err {
+                TrySendError::Full(_) => QueueError::Full,
+                TrySendError::Disconnected(_) => QueueError::ShuttingDown,
+            })?;
+        }
+
+        result_receiver.await.map_err(|_| QueueError::Panicked)
+    }
+
+    /// Stop accepting requests, run the queued ones and wait for the workers to exit
+    pub fn shutdown(&self) {
+        if let Ok(mut sender) = self.sender.lock() {
+            sender.take();
+        }
+
+        let workers = match self.workers.lock() {
+            Ok(mut workers) => std::mem::take(&mut *workers),
+            Err(_) => return,
+        };
+        for worker in workers {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod request_queue_test {
+    use std::sync::mpsc;
+
+    use vector::Metric;
+
+    use super::*;
+
+    fn test_store() -> SharedStore {
+        let store = MemANNStore::new(Metric::L2, 128, 32, 50, 1.2, 1, 16).unwrap();
+        Arc::new(RwLock::new(store))
+    }
+
+    #[tokio::test]
+    async fn full_queue_rejects_requests_test() {
+        let queue = Arc::new(RequestQueue::new(test_store(), 1, 1));
+
+        // Hold the only worker, so that the next request takes the only slot of the queue
+        let (release_sender, release_receiver) = mpsc::channel::<()>();
+        let (started_sender, started_receiver) = oneshot::channel::<()>();
+        let blocking_queue = queue.clone();
+        let blocked = tokio::spawn(async move {
+            blocking_queue
+                .execute(move |_| {
+                    let _ = started_sender.send(());
+                    let _ = release_receiver.recv();
+                })
+                .await
+        });
+        started_receiver.await.unwrap();
+
+        let (queued, rejected) = tokio::join!(queue.execute(|_| 1), async {
+            let rejected = queue.execute(|_| 2).await;
+            release_sender.send(()).unwrap();
+            rejected
+        });
+
+        assert_eq!(blocked.await.unwrap(), Ok(()));
+        assert_eq!(queued, Ok(1));
+        assert_eq!(rejected, Err(QueueError::Full));
+    }
+
+    #[tokio::test]
+    async fn shutdown_rejects_new_requests_test() {
+        let queue = RequestQueue::new(test_store(), 2, 4);
+        assert_eq!(queue.execute(|_| 1).await, Ok(1));
+
+        queue.shutdown();
+        assert_eq!(queue.execute(|_| 2).await, Err(QueueError::ShuttingDown));
+    }
+}
@@ -0,0 +1,311 @@
+use std::sync::Arc;
+
+use axum::Router;
+use axum::extract::{Json, State};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use diskann::common::ANNError;
+use diskann::instrumentation::metrics;
+use diskann::instrumentation::prometheus_exporter::{PROMETHEUS_CONTENT_TYPE, render_prometheus};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::request_queue::{QueueError, RequestQueue, SharedStore};
+use crate::mem_ann_store::{MemANNStore, SearchResult, check_dimension};
+
+/// State shared by the handlers
+pub struct AppState {
+    pub queue: RequestQueue,
+
+    pub dimension: usize,
+
+    pub search_list_size: u32,
+
+    pub index_path: String,
+}
+
+/// Routes of the server
+pub fn router(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/health", get(health))
+        .route("/metrics", get(prometheus_metrics))
+        .route("/insert", post(insert))
+        .route("/delete", post(delete))
+        .route("/search", post(search))
+        .route("/batch_search", post(batch_search))
+        .route("/snapshot", post(snapshot))
+        .with_state(state)
+}
+
+/// Error returned to the client as {"error": message}
+#[derive(Debug)]
+pub struct ServerError {
+    status: StatusCode,
+
+    message: String,
+}
+
+impl ServerError {
+    fn bad_request(message: String) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message,
+        }
+    }
+}
+
+impl From<ANNError> for ServerError {
+    fn from(err: ANNError) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: err.to_string(),
+        }
+    }
+}
+
+impl From<QueueError> for ServerError {
+    fn from(err: QueueError) -> Self {
+        let (status, message) = match err {
+            QueueError::Full => (StatusCode::SERVICE_UNAVAILABLE, "request queue is full"),
+            QueueError::ShuttingDown => {
+                (StatusCode::SERVICE_UNAVAILABLE, "server is shutting down")
+            }
+            QueueError::Panicked => (StatusCode::INTERNAL_SERVER_ERROR, "request failed"),
+        };
+
+        Self {
+            status,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl IntoResponse for ServerError {
+    fn into_response(self) -> Response {
+        let body = ErrorResponse {
+            error: self.message,
+        };
+        (self.status, Json(body)).into_response()
+    }
+}
+
+type ServerResult<T> = Result<Json<T>, ServerError>;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HealthResponse {
+    pub status: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InsertRequest {
+    pub vectors: Vec<Vec<f32>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InsertResponse {
+    /// Id of the first inserted vector, the others follow it
+    pub first_id: usize,
+
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeleteRequest {
+    pub ids: Vec<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeleteResponse {
+    pub deleted: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchRequest {
+    pub vector: Vec<f32>,
+
+    pub k: usize,
+
+    /// Search list size, the one of the index by default
+    #[serde(default)]
+    pub l: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchSearchRequest {
+    pub vectors: Vec<Vec<f32>>,
+
+    pub k: usize,
+
+    /// Search list size, the one of the index by default
+    #[serde(default)]
+    pub l: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct SearchResponse {
+    /// Ids of the neighbors, closest first
+    pub ids: Vec<u32>,
+
+    pub distances: Vec<f32>,
+}
+
+impl From<SearchResult> for SearchResponse {
+    fn from(result: SearchResult) -> Self {
+        Self {
+            ids: result.ids,
+            distances: result.distances,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchSearchResponse {
+    pub results: Vec<SearchResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapshotResponse {
+    pub path: String,
+}
+
+impl AppState {
+    fn check_dimension(&self, vectors: &[Vec<f32>]) -> Result<(), ServerError> {
+        check_dimension(self.dimension, vectors)
+            .map_err(|err| ServerError::bad_request(err.to_string()))
+    }
+
+    fn check_queries(&self, vectors: &[Vec<f32>], k: usize) -> Result<(), ServerError> {
+        if k == 0 {
+            return Err(ServerError::bad_request(
+                "k must be greater than 0".to_string(),
+            ));
+        }
+        self.check_dimension(vectors)
+    }
+
+    fn l_value(&self, l: Option<u32>, k: usize) -> u32 {
+        l.unwrap_or(self.search_list_size).max(k as u32)
+    }
+}
+
+fn read_store<R>(
+    store: &SharedStore,
+    body: impl FnOnce(&MemANNStore<f32>) -> Result<R, ANNError>,
+) -> Result<R, ANNError> {
+    let store = store.read().map_err(|_| {
+        ANNError::log_lock_poison_error("failed to acquire the lock for the store".to_string())
+    })?;
+    body(&store)
+}
+
+fn write_store<R>(
+    store: &SharedStore,
+    body: impl FnOnce(&mut MemANNStore<f32>) -> Result<R, ANNError>,
+) -> Result<R, ANNError> {
+    let mut store = store.write().map_err(|_| {
+        ANNError::log_lock_poison_error("failed to acquire the lock for the store".to_string())
+    })?;
+    body(&mut store)
+}
+
+async fn health() -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: "ok".to_string(),
+    })
+}
+
+/// Metrics of the index in the Prometheus text format
+async fn prometheus_metrics() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE)],
+        render_prometheus(metrics()),
+    )
+}
+
+async fn insert(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<InsertRequest>,
+) -> ServerResult<InsertResponse> {
+    state.check_dimension(&request.vectors)?;
+
+    let (first_id, count) = state
+        .queue
+        .execute(move |store| write_store(store, |store| store.insert_data(&request.vectors)))
+        .await??;
+
+    Ok(Json(InsertResponse { first_id, count }))
+}
+
+async fn delete(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<DeleteRequest>,
+) -> ServerResult<DeleteResponse> {
+    let deleted = request.ids.len();
+    state
+        .queue
+        .execute(move |store| write_store(store, |store| store.soft_delete(request.ids)))
+        .await??;
+
+    Ok(Json(DeleteResponse { deleted }))
+}
+
+async fn search(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<SearchRequest>,
+) -> ServerResult<SearchResponse> {
+    let k = request.k;
+    let l_value = state.l_value(request.l, k);
+    let query = request.vector;
+    state.check_queries(std::slice::from_ref(&query), k)?;
+
+    let result = state
+        .queue
+        .execute(move |store| read_store(store, |store| store.search(&query, k, l_value)))
+        .await??;
+
+    Ok(Json(result.into()))
+}
+
+async fn batch_search(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<BatchSearchRequest>,
+) -> ServerResult<BatchSearchResponse> {
+    let k = request.k;
+    let l_value = state.l_value(request.l, k);
+    let queries = request.vectors;
+    state.check_queries(&queries, k)?;
+
+    // A batch takes one slot of the queue and runs its queries in parallel
+    let results = state
+        .queue
+        .execute(move |store| {
+            read_store(store, |store| {
+                queries
+                    .par_iter()
+                    .map(|query| store.search(query, k, l_value).map(SearchResponse::from))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+        })
+        .await??;
+
+    Ok(Json(BatchSearchResponse { results }))
+}
+
+/// Save the index to the index path of the server, the only place snapshots go
+async fn snapshot(State(state): State<Arc<AppState>>) -> ServerResult<SnapshotResponse> {
+    let path = state.index_path.clone();
+
+    let saved_path = path.clone();
+    state
+        .queue
+        .execute(move |store| write_store(store, |store| store.save_to_file(&saved_path)))
+        .await??;
+
+    Ok(Json(SnapshotResponse { path }))
+}
@@ -0,0 +1,50 @@
+use std::path::Path;
+
+use api_memory_insert_query::disk_ann_store::DiskANNStore;
+use diskann::index::{compute_ground_truth, recall_at_k};
+use diskann::utils::load_bin;
+use vector::Metric;
+
+const BASE_FILE: &str = "../diskann/tests/data/siftsmall_learn_256pts.fbin";
+const QUERY_FILE: &str = "../diskann/tests/data/siftsmall_learn_256pts_2.fbin";
+const K: usize = 10;
+const L: u32 = 50;
+const BEAM_WIDTH: u32 = 4;
+const NUM_NODES_TO_CACHE: usize = 32;
+
+#[test]
+fn build_load_and_search_recall() {
+    let output_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("disk_ann_store_recall_test");
+    std::fs::create_dir_all(&output_dir).unwrap();
+    let index_path_prefix = output_dir.join("siftsmall_learn_256pts");
+    let index_path_prefix = index_path_prefix.to_str().unwrap();
+
+    let mut store = DiskANNStore::<f32>::new(Metric::L2, 16, L, 1.2, 1, 1.0, 1.0).unwrap();
+    store.build_from_file(BASE_FILE, index_path_prefix).unwrap();
+    store.load(index_path_prefix, NUM_NODES_TO_CACHE).unwrap();
+
+    let (base, _, dim) = load_bin::<f32>(BASE_FILE, 0).unwrap();
+    let (queries, num_queries, _) = load_bin::<f32>(QUERY_FILE, 0).unwrap();
+    let ground_truth = compute_ground_truth(&base, &queries, dim, K, Metric::L2);
+
+    let mut results = Vec::with_capacity(num_queries);
+    for query in queries.chunks(dim) {
+        let mut indices = [0u32; K];
+        let mut distances = [0f32; K];
+        store
+            .search(query, K, L, BEAM_WIDTH, &mut indices, &mut distances)
+            .unwrap();
+        assert!(distances.windows(2).all(|pair| pair[0] <= pair[1]));
+        results.push(indices.to_vec());
+    }
+
+    let recall = recall_at_k(&ground_truth, &results, K);
+    assert!(recall >= 90.0, "recall@{} {}", K, recall);
+
+    // The store only loads indexes of its metric
+    let mut cosine_store =
+        DiskANNStore::<f32>::new(Metric::Cosine, 16, L, 1.2, 1, 1.0, 1.0).unwrap();
+    assert!(cosine_store.load(index_path_prefix, 0).is_err());
+
+    std::fs::remove_dir_all(output_dir).unwrap();
+}
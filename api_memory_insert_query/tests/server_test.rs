@@ -0,0 +1,182 @@
+use std::net::SocketAddr;
+use std::path::Path;
+
+use api_memory_insert_query::server::{
+    BatchSearchResponse, DeleteResponse, ErrorResponse, InsertResponse, SearchResponse, Server,
+    ServerConfig, SnapshotResponse,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+const DIM: usize = 128;
+
+/// Deterministic points in [0, 1)
+fn random_points(num_points: usize, seed: u64) -> Vec<Vec<f32>> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..num_points)
+        .map(|_| (0..DIM).map(|_| rng.gen_range(0f32..1f32)).collect())
+        .collect()
+}
+
+fn test_config(name: &str) -> ServerConfig {
+    let index_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    for extension in ["", ".data", ".delete", ".metadata"] {
+        let _ = std::fs::remove_file(format!("{}{}", index_path.display(), extension));
+    }
+
+    let mut config = ServerConfig::new(DIM, index_path.to_str().unwrap());
+    config.address = SocketAddr::from(([127, 0, 0, 1], 0));
+    config.max_degree = 32;
+    config.search_list_size = 50;
+    config.concurrency = 2;
+    config
+}
+
+/// Start the server, returns its address, the shutdown trigger and the task to await
+async fn start(config: ServerConfig) -> (SocketAddr, oneshot::Sender<()>, JoinHandle<()>) {
+    let server = Server::bind(config).await.unwrap();
+    let address = server.local_addr().unwrap();
+    let (shutdown_sender, shutdown_receiver) = oneshot::channel::<()>();
+
+    let task = tokio::spawn(async move {
+        server
+            .run(async {
+                let _ = shutdown_receiver.await;
+            })
+            .await
+            .unwrap();
+    });
+
+    (address, shutdown_sender, task)
+}
+
+/// Send a request and return the status code and the JSON body
+async fn request(
+    address: SocketAddr,
+    method: &str,
+    path: &str,
+    body: Option<Value>,
+) -> (u16, Value) {
+    let body = body.map(|body| body.to_string()).unwrap_or_default();
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    let request = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        address,
+        body.len(),
+        body
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+
+    (status, serde_json::from_str(body).unwrap_or(Value::Null))
+}
+
+async fn post<T: DeserializeOwned>(address: SocketAddr, path: &str, body: Value) -> T {
+    let (status, body) = request(address, "POST", path, Some(body)).await;
+    assert_eq!(status, 200, "{} failed: {}", path, body);
+    serde_json::from_value(body).unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn insert_search_delete_and_restart_test() {
+    let config = test_config("server_test_index");
+    let points = random_points(300, 7);
+    let (address, shutdown, task) = start(config.clone()).await;
+
+    let (status, _) = request(address, "GET", "/health", None).await;
+    assert_eq!(status, 200);
+
+    let inserted: InsertResponse =
+        post(address, "/insert", json!({ "vectors": points[..200] })).await;
+    assert_eq!((inserted.first_id, inserted.count), (0, 200));
+    let inserted: InsertResponse =
+        post(address, "/insert", json!({ "vectors": points[200..] })).await;
+    assert_eq!((inserted.first_id, inserted.count), (200, 100));
+
+    let found: SearchResponse =
+        post(address, "/search", json!({ "vector": points[250], "k": 5 })).await;
+    assert_eq!(found.ids.len(), 5);
+    assert_eq!(found.ids[0], 250);
+
+    let batch: BatchSearchResponse = post(
+        address,
+        "/batch_search",
+        json!({ "vectors": points[10..20], "k": 3, "l": 80 }),
+    )
+    .await;
+    assert_eq!(batch.results.len(), 10);
+    for (i, result) in batch.results.iter().enumerate() {
+        assert_eq!(result.ids[0], 10 + i as u32);
+    }
+
+    let deleted: DeleteResponse = post(address, "/delete", json!({ "ids": [42] })).await;
+    assert_eq!(deleted.deleted, 1);
+    let found: SearchResponse =
+        post(address, "/search", json!({ "vector": points[42], "k": 5 })).await;
+    assert!(!found.ids.contains(&42));
+
+    let (status, _) = request(address, "GET", "/metrics", None).await;
+    assert_eq!(status, 200);
+
+    let snapshot: SnapshotResponse = post(address, "/snapshot", json!({})).await;
+    assert_eq!(snapshot.path, config.index_path);
+
+    // Shutdown saves the index, which the next server loads
+    shutdown.send(()).unwrap();
+    task.await.unwrap();
+    assert!(Path::new(&format!("{}.data", config.index_path)).exists());
+
+    let (address, shutdown, task) = start(config).await;
+    let found: SearchResponse =
+        post(address, "/search", json!({ "vector": points[250], "k": 5 })).await;
+    assert_eq!(found.ids[0], 250);
+    let found: SearchResponse =
+        post(address, "/search", json!({ "vector": points[42], "k": 5 })).await;
+    assert!(!found.ids.contains(&42));
+
+    shutdown.send(()).unwrap();
+    task.await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn invalid_requests_test() {
+    let (address, shutdown, task) = start(test_config("server_invalid_test_index")).await;
+
+    let (status, body) = request(
+        address,
+        "POST",
+        "/insert",
+        Some(json!({ "vectors": [vec![0f32; DIM - 1]] })),
+    )
+    .await;
+    assert_eq!(status, 400);
+    let error: ErrorResponse = serde_json::from_value(body).unwrap();
+    assert!(error.error.contains("dimension"));
+
+    let (status, _) = request(
+        address,
+        "POST",
+        "/search",
+        Some(json!({ "vector": vec![0f32; DIM], "k": 0 })),
+    )
+    .await;
+    assert_eq!(status, 400);
+
+    let (status, _) = request(address, "POST", "/search", Some(json!({ "k": 1 }))).await;
+    assert_eq!(status, 422);
+
+    shutdown.send(()).unwrap();
+    task.await.unwrap();
+}
@@ -0,0 +1,177 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+
+#ifndef DISKANN_H
+#define DISKANN_H
+
+/* Generated by cbindgen from src/lib.rs, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Id reported for the result slots a search could not fill
+#define DISKANN_MISSING_ID UINT32_MAX
+
+// Distance metric
+typedef enum DiskannMetric {
+  // Squared Euclidean (L2-Squared)
+  DISKANN_METRIC_L2 = 0,
+  // Cosine similarity
+  DISKANN_METRIC_COSINE = 1,
+  // Inner product, negated so that a smaller distance means a closer vector
+  DISKANN_METRIC_INNER_PRODUCT = 2,
+} DiskannMetric;
+
+// Status returned by every call
+typedef enum DiskannStatus {
+  // Success
+  DISKANN_STATUS_OK = 0,
+  // A null pointer, a zero k or a path that is not UTF-8 was passed
+  DISKANN_STATUS_INVALID_ARGUMENT = 1,
+  // Index construction and search error
+  DISKANN_STATUS_INDEX_ERROR = 2,
+  // Index configuration error
+  DISKANN_STATUS_INDEX_CONFIG_ERROR = 3,
+  // File or disk IO error
+  DISKANN_STATUS_IO_ERROR = 4,
+  // A lock was poisoned by a failed thread
+  DISKANN_STATUS_LOCK_POISON_ERROR = 5,
+  // Memory allocation error
+  DISKANN_STATUS_MEMORY_ERROR = 6,
+  // Integer or slice conversion error
+  DISKANN_STATUS_CONVERSION_ERROR = 7,
+  // PQ construction error
+  DISKANN_STATUS_PQ_ERROR = 8,
+  // Logging error
+  DISKANN_STATUS_LOG_ERROR = 9,
+  // The call panicked, the index must not be used anymore
+  DISKANN_STATUS_PANIC = 10,
+  // The operation was cancelled by its progress observer
+  DISKANN_STATUS_CANCELLED = 11,
+} DiskannStatus;
+
+// Opaque in-memory index over float vectors
+//
+// Ids are assigned in insertion order starting from 0.
+typedef struct DiskannIndex DiskannIndex;
+
+// Parameters to create, build or load an index
+typedef struct DiskannIndexParameters {
+  // Distance metric
+  enum DiskannMetric metric;
+  // Dimension of the float vectors
+  size_t dim;
+  // Max degree of the graph
+  uint32_t max_degree;
+  // Search list size used to build the graph and by default to search it
+  uint32_t search_list_size;
+  // Pruning factor, usually between 1.0 and 1.4
+  float alpha;
+  // Number of threads used to build the graph, 0 uses all the cores
+  uint32_t num_threads;
+  // Number of points to reserve room for, the index grows past it
+  size_t max_points;
+} DiskannIndexParameters;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Return the message of the last failed call on this thread, or null if no call failed
+//
+// The string is owned by the library and stays valid until the next call fails on this
+// thread.
+const char *diskann_last_error_message(void);
+
+// Return the default parameters for vectors of the given dimension
+struct DiskannIndexParameters diskann_default_parameters(size_t dim);
+
+// Create an empty index
+//
+// # Safety
+// parameters must point to valid parameters and out to writable memory for a handle.
+// The handle written to out must be released with diskann_index_free.
+enum DiskannStatus diskann_index_create(const struct DiskannIndexParameters *parameters,
+                                        struct DiskannIndex **out);
+
+// Build an index from num_points row-major vectors of parameters->dim floats
+//
+// # Safety
+// parameters must point to valid parameters, data to num_points * parameters->dim floats
+// and out to writable memory for a handle. The handle written to out must be released with
+// diskann_index_free.
+enum DiskannStatus diskann_index_build(const struct DiskannIndexParameters *parameters,
+                                       const float *data,
+                                       size_t num_points,
+                                       struct DiskannIndex **out);
+
+// Load an index saved with diskann_index_save with its saved configuration
+//
+// The dimension and metric of the parameters have to match the saved ones, or the call fails
+// with DISKANN_STATUS_INDEX_ERROR. search_list_size is the default of the searches.
+//
+// # Safety
+// parameters must point to valid parameters, path to a nul terminated string and out to
+// writable memory for a handle. The handle written to out must be released with
+// diskann_index_free.
+enum DiskannStatus diskann_index_load(const struct DiskannIndexParameters *parameters,
+                                      const char *path,
+                                      struct DiskannIndex **out);
+
+// Release an index, null is ignored
+//
+// # Safety
+// index must be null or a handle returned by this library that was not released yet.
+void diskann_index_free(struct DiskannIndex *index);
+
+// Insert num_points row-major vectors, the id of the first one is written to first_id
+//
+// # Safety
+// index must be a valid handle, data must point to num_points * dim floats and first_id
+// must be null or writable.
+enum DiskannStatus diskann_index_insert(struct DiskannIndex *index,
+                                        const float *data,
+                                        size_t num_points,
+                                        size_t *first_id);
+
+// Mark the points with the given ids as deleted, they are no longer returned by search
+//
+// # Safety
+// index must be a valid handle and ids must point to num_ids ids.
+enum DiskannStatus diskann_index_delete(struct DiskannIndex *index,
+                                        const uint32_t *ids,
+                                        size_t num_ids);
+
+// Search the k nearest neighbors of num_queries row-major queries in parallel
+//
+// The k results of each query are written to ids and distances, slots the search could not
+// fill hold DISKANN_MISSING_ID. A search_list_size of 0 uses the one of the parameters.
+//
+// # Safety
+// index must be a valid handle, queries must point to num_queries * dim floats, ids to
+// num_queries * k writable ids and distances must be null or point to num_queries * k
+// writable floats.
+enum DiskannStatus diskann_index_search(const struct DiskannIndex *index,
+                                        const float *queries,
+                                        size_t num_queries,
+                                        size_t k,
+                                        uint32_t search_list_size,
+                                        uint32_t *ids,
+                                        float *distances);
+
+// Save the index to the given path
+//
+// # Safety
+// index must be a valid handle and path a nul terminated string.
+enum DiskannStatus diskann_index_save(struct DiskannIndex *index, const char *path);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* DISKANN_H */
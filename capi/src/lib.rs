@@ -0,0 +1,583 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+#![warn(missing_docs)]
+
+//! C API for the in-memory index
+//!
+//! The index lives behind an opaque DiskannIndex handle. Every call returns a DiskannStatus,
+//! on failure the error message is kept per thread and can be read with
+//! diskann_last_error_message. The C header include/diskann.h is generated from this file
+//! with cbindgen.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+use api_memory_insert_query::mem_ann_store::MemANNStore;
+use diskann::common::ANNError;
+use rayon::prelude::*;
+use vector::Metric;
+
+/// Id reported for the result slots a search could not fill
+pub const DISKANN_MISSING_ID: u32 = u32::MAX;
+
+/// Status returned by every call
+#[repr(C)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DiskannStatus {
+    /// Success
+    Ok = 0,
+
+    /// A null pointer, a zero k or a path that is not UTF-8 was passed
+    InvalidArgument = 1,
+
+    /// Index construction and search error
+    IndexError = 2,
+
+    /// Index configuration error
+    IndexConfigError = 3,
+
+    /// File or disk IO error
+    IoError = 4,
+
+    /// A lock was poisoned by a failed thread
+    LockPoisonError = 5,
+
+    /// Memory allocation error
+    MemoryError = 6,
+
+    /// Integer or slice conversion error
+    ConversionError = 7,
+
+    /// PQ construction error
+    PqError = 8,
+
+    /// Logging error
+    LogError = 9,
+
+    /// The call panicked, the index must not be used anymore
+    Panic = 10,
+
+    /// The operation was cancelled by its progress observer
+    Cancelled = 11,
+}
+
+impl From<&ANNError> for DiskannStatus {
+    fn from(err: &ANNError) -> Self {
+        match err {
+            ANNError::IndexError { .. } => DiskannStatus::IndexError,
+            ANNError::IndexConfigError { .. } | ANNError::DataTypeMismatch { .. } => {
+                DiskannStatus::IndexConfigError
+            }
+            ANNError::IOError { .. }
+            | ANNError::DiskIOAlignmentError { .. }
+            | ANNError::FileSizeMismatch { .. } => DiskannStatus::IoError,
+            ANNError::LockPoisonError { .. } => DiskannStatus::LockPoisonError,
+            ANNError::MemoryAllocLayoutError { .. } => DiskannStatus::MemoryError,
+            ANNError::TryFromIntError { .. } | ANNError::TryFromSliceError { .. } => {
+                DiskannStatus::ConversionError
+            }
+            ANNError::PQError { .. } => DiskannStatus::PqError,
+            ANNError::LogError { .. } => DiskannStatus::LogError,
+            ANNError::Cancelled { .. } => DiskannStatus::Cancelled,
+        }
+    }
+}
+
+/// Distance metric
+#[repr(C)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DiskannMetric {
+    /// Squared Euclidean (L2-Squared)
+    L2 = 0,
+
+    /// Cosine similarity
+    Cosine = 1,
+
+    /// Inner product, negated so that a smaller distance means a closer vector
+    InnerProduct = 2,
+}
+
+impl From<DiskannMetric> for Metric {
+    fn from(metric: DiskannMetric) -> Self {
+        match metric {
+            DiskannMetric::L2 => Metric::L2,
+            DiskannMetric::Cosine => Metric::Cosine,
+            DiskannMetric::InnerProduct => Metric::InnerProduct,
+        }
+    }
+}
+
+/// Parameters to create, build or load an index
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct DiskannIndexParameters {
+    /// Distance metric
+    pub metric: DiskannMetric,
+
+    /// Dimension of the float vectors
+    pub dim: usize,
+
+    /// Max degree of the graph
+    pub max_degree: u32,
+
+    /// Search list size used to build the graph and by default to search it
+    pub search_list_size: u32,
+
+    /// Pruning factor, usually between 1.0 and 1.4
+    pub alpha: f32,
+
+    /// Number of threads used to build the graph, 0 uses all the cores
+    pub num_threads: u32,
+
+    /// Number of points to reserve room for, the index grows past it
+    pub max_points: usize,
+}
+
+/// Opaque in-memory index over float vectors
+///
+/// Ids are assigned in insertion order starting from 0.
+pub struct DiskannIndex {
+    store: MemANNStore<f32>,
+
+    search_list_size: u32,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Error of a call, before it is turned into a status
+enum CallError {
+    InvalidArgument(String),
+
+    Index(ANNError),
+
+    Panic(String),
+}
+
+impl From<ANNError> for CallError {
+    fn from(err: ANNError) -> Self {
+        CallError::Index(err)
+    }
+}
+
+type CallResult<T> = Result<T, CallError>;
+
+/// Run the body of a call, keep its error message and map it to a status
+fn ffi_call<F>(body: F) -> DiskannStatus
+where
+    F: FnOnce() -> CallResult<()>,
+{
+    let result = catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|panic| {
+        let message = panic
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        Err(CallError::Panic(message))
+    });
+
+    let (status, message) = match result {
+        Ok(()) => return DiskannStatus::Ok,
+        Err(CallError::InvalidArgument(message)) => (DiskannStatus::InvalidArgument, message),
+        Err(CallError::Index(err)) => (DiskannStatus::from(&err), err.to_string()),
+        Err(CallError::Panic(message)) => (DiskannStatus::Panic, message),
+    };
+
+    // Messages never hold a nul byte, but do not fail the call over one
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+
+    status
+}
+
+fn invalid_argument(message: &str) -> CallError {
+    CallError::InvalidArgument(message.to_string())
+}
+
+/// Number of elements of num_rows rows of row_len, which must not overflow
+fn checked_len(num_rows: usize, row_len: usize, name: &str) -> CallResult<usize> {
+    num_rows
+        .checked_mul(row_len)
+        .ok_or_else(|| invalid_argument(&format!("{} is too large", name)))
+}
+
+/// Borrow len elements from ptr, which may be null when len is 0
+unsafe fn slice_from<'a, T>(ptr: *const T, len: usize, name: &str) -> CallResult<&'a [T]> {
+    if len == 0 {
+        return Ok(&[]);
+    }
+    if ptr.is_null() {
+        return Err(invalid_argument(&format!("{} is null", name)));
+    }
+
+    Ok(std::slice::from_raw_parts(ptr, len))
+}
+
+/// Mutably borrow len elements from ptr, which may be null when len is 0
+unsafe fn slice_from_mut<'a, T>(ptr: *mut T, len: usize, name: &str) -> CallResult<&'a mut [T]> {
+    if len == 0 {
+        return Ok(&mut []);
+    }
+    if ptr.is_null() {
+        return Err(invalid_argument(&format!("{} is null", name)));
+    }
+
+    Ok(std::slice::from_raw_parts_mut(ptr, len))
+}
+
+unsafe fn ref_from<'a, T>(ptr: *const T, name: &str) -> CallResult<&'a T> {
+    ptr.as_ref()
+        .ok_or_else(|| invalid_argument(&format!("{} is null", name)))
+}
+
+unsafe fn mut_from<'a, T>(ptr: *mut T, name: &str) -> CallResult<&'a mut T> {
+    ptr.as_mut()
+        .ok_or_else(|| invalid_argument(&format!("{} is null", name)))
+}
+
+unsafe fn str_from<'a>(ptr: *const c_char, name: &str) -> CallResult<&'a str> {
+    if ptr.is_null() {
+        return Err(invalid_argument(&format!("{} is null", name)));
+    }
+
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map_err(|_| invalid_argument(&format!("{} is not UTF-8", name)))
+}
+
+/// Split row-major data into points of dim floats
+fn to_points(data: &[f32], dim: usize) -> Vec<Vec<f32>> {
+    data.chunks_exact(dim).map(|row| row.to_vec()).collect()
+}
+
+/// Move the index to the heap and hand it out through out
+unsafe fn write_index(out: *mut *mut DiskannIndex, index: DiskannIndex) {
+    *out = Box::into_raw(Box::new(index));
+}
+
+fn create_index(parameters: &DiskannIndexParameters) -> CallResult<DiskannIndex> {
+    let store = MemANNStore::new(
+        parameters.metric.into(),
+        parameters.dim,
+        parameters.max_degree,
+        parameters.search_list_size,
+        parameters.alpha,
+        parameters.num_threads,
+        parameters.max_points,
+    )?;
+
+    Ok(DiskannIndex {
+        store,
+        search_list_size: parameters.search_list_size,
+    })
+}
+
+/// Return the message of the last failed call on this thread, or null if no call failed
+///
+/// The string is owned by the library and stays valid until the next call fails on this
+/// thread.
+#[no_mangle]
+pub extern "C" fn diskann_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Return the default parameters for vectors of the given dimension
+#[no_mangle]
+pub extern "C" fn diskann_default_parameters(dim: usize) -> DiskannIndexParameters {
+    DiskannIndexParameters {
+        metric: DiskannMetric::L2,
+        dim,
+        max_degree: 64,
+        search_list_size: 100,
+        alpha: 1.2,
+        num_threads: 0,
+        max_points: 1024,
+    }
+}
+
+/// Create an empty index
+///
+/// # Safety
+/// parameters must point to valid parameters and out to writable memory for a handle.
+/// The handle written to out must be released with diskann_index_free.
+#[no_mangle]
+pub unsafe extern "C" fn diskann_index_create(
+    parameters: *const DiskannIndexParameters,
+    out: *mut *mut DiskannIndex,
+) -> DiskannStatus {
+    ffi_call(|| {
+        let parameters = ref_from(parameters, "parameters")?;
+        if out.is_null() {
+            return Err(invalid_argument("out is null"));
+        }
+
+        write_index(out, create_index(parameters)?);
+        Ok(())
+    })
+}
+
+/// Build an index from num_points row-major vectors of parameters->dim floats
+///
+/// # Safety
+/// parameters must point to valid parameters, data to num_points * parameters->dim floats
+/// and out to writable memory for a handle. The handle written to out must be released with
+/// diskann_index_free.
+#[no_mangle]
+pub unsafe extern "C" fn diskann_index_build(
+    parameters: *const DiskannIndexParameters,
+    data: *const f32,
+    num_points: usize,
+    out: *mut *mut DiskannIndex,
+) -> DiskannStatus {
+    ffi_call(|| {
+        let parameters = ref_from(parameters, "parameters")?;
+        let data = slice_from(
+            data,
+            checked_len(num_points, parameters.dim, "data")?,
+            "data",
+        )?;
+        if out.is_null() {
+            return Err(invalid_argument("out is null"));
+        }
+
+        let mut index = create_index(&DiskannIndexParameters {
+            max_points: num_points,
+            ..*parameters
+        })?;
+        index.store.insert_data(&to_points(data, parameters.dim))?;
+
+        write_index(out, index);
+        Ok(())
+    })
+}
+
+/// Load an index saved with diskann_index_save with its saved configuration
+///
+/// The dimension and metric of the parameters have to match the saved ones, or the call fails
+/// with DISKANN_STATUS_INDEX_ERROR. search_list_size is the default of the searches.
+///
+/// # Safety
+/// parameters must point to valid parameters, path to a nul terminated string and out to
+/// writable memory for a handle. The handle written to out must be released with
+/// diskann_index_free.
+#[no_mangle]
+pub unsafe extern "C" fn diskann_index_load(
+    parameters: *const DiskannIndexParameters,
+    path: *const c_char,
+    out: *mut *mut DiskannIndex,
+) -> DiskannStatus {
+    ffi_call(|| {
+        let parameters = ref_from(parameters, "parameters")?;
+        let path = str_from(path, "path")?;
+        if out.is_null() {
+            return Err(invalid_argument("out is null"));
+        }
+
+        let store = MemANNStore::load_checked(path, parameters.metric.into(), parameters.dim)?;
+
+        write_index(
+            out,
+            DiskannIndex {
+                store,
+                search_list_size: parameters.search_list_size,
+            },
+        );
+        Ok(())
+    })
+}
+
+/// Release an index, null is ignored
+///
+/// # Safety
+/// index must be null or a handle returned by this library that was not released yet.
+#[no_mangle]
+pub unsafe extern "C" fn diskann_index_free(index: *mut DiskannIndex) {
+    if !index.is_null() {
+        drop(Box::from_raw(index));
+    }
+}
+
+/// Insert num_points row-major vectors, the id of the first one is written to first_id
+///
+/// # Safety
+/// index must be a valid handle, data must point to num_points * dim floats and first_id
+/// must be null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn diskann_index_insert(
+    index: *mut DiskannIndex,
+    data: *const f32,
+    num_points: usize,
+    first_id: *mut usize,
+) -> DiskannStatus {
+    ffi_call(|| {
+        let index = mut_from(index, "index")?;
+        let dim = index.store.dimension();
+        let data = slice_from(data, checked_len(num_points, dim, "data")?, "data")?;
+
+        let (start, _) = index.store.insert_data(&to_points(data, dim))?;
+        if let Some(first_id) = first_id.as_mut() {
+            *first_id = start;
+        }
+
+        Ok(())
+    })
+}
+
+/// Mark the points with the given ids as deleted, they are no longer returned by search
+///
+/// # Safety
+/// index must be a valid handle and ids must point to num_ids ids.
+#[no_mangle]
+pub unsafe extern "C" fn diskann_index_delete(
+    index: *mut DiskannIndex,
+    ids: *const u32,
+    num_ids: usize,
+) -> DiskannStatus {
+    ffi_call(|| {
+        let index = mut_from(index, "index")?;
+        let ids = slice_from(ids, num_ids, "ids")?;
+
+        index.store.soft_delete(ids.to_vec())?;
+        Ok(())
+    })
+}
+
+/// Search the k nearest neighbors of num_queries row-major queries in parallel
+///
+/// The k results of each query are written to ids and distances, slots the search could not
+/// fill hold DISKANN_MISSING_ID. A search_list_size of 0 uses the one of the parameters.
+///
+/// # Safety
+/// index must be a valid handle, queries must point to num_queries * dim floats, ids to
+/// num_queries * k writable ids and distances must be null or point to num_queries * k
+/// writable floats.
+#[no_mangle]
+pub unsafe extern "C" fn diskann_index_search(
+    index: *const DiskannIndex,
+    queries: *const f32,
+    num_queries: usize,
+    k: usize,
+    search_list_size: u32,
+    ids: *mut u32,
+    distances: *mut f32,
+) -> DiskannStatus {
+    ffi_call(|| {
+        let index = ref_from(index, "index")?;
+        if k == 0 {
+            return Err(invalid_argument("k must be greater than 0"));
+        }
+
+        let dim = index.store.dimension();
+        let queries = slice_from(
+            queries,
+            checked_len(num_queries, dim, "queries")?,
+            "queries",
+        )?;
+        let num_results = checked_len(num_queries, k, "ids")?;
+        let ids = slice_from_mut(ids, num_results, "ids")?;
+        let mut own_distances = Vec::new();
+        let distances = if distances.is_null() {
+            own_distances.resize(num_results, 0f32);
+            &mut own_distances[..]
+        } else {
+            slice_from_mut(distances, num_results, "distances")?
+        };
+
+        let l_value = match search_list_size {
+            0 => index.search_list_size,
+            l_value => l_value,
+        }
+        .max(k as u32);
+
+        ids.fill(DISKANN_MISSING_ID);
+        distances.fill(f32::INFINITY);
+
+        ids.par_chunks_mut(k)
+            .zip(distances.par_chunks_mut(k))
+            .zip(queries.par_chunks_exact(dim))
+            .try_for_each(|((ids, distances), query)| {
+                let result = index.store.search(query, k, l_value)?;
+                ids[..result.ids.len()].copy_from_slice(&result.ids);
+                distances[..result.distances.len()].copy_from_slice(&result.distances);
+                Ok::<_, ANNError>(())
+            })?;
+
+        Ok(())
+    })
+}
+
+/// Save the index to the given path
+///
+/// # Safety
+/// index must be a valid handle and path a nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn diskann_index_save(
+    index: *mut DiskannIndex,
+    path: *const c_char,
+) -> DiskannStatus {
+    ffi_call(|| {
+        let index = mut_from(index, "index")?;
+        let path = str_from(path, "path")?;
+
+        index.store.save_to_file(path)?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod capi_test {
+    use super::*;
+
+    #[test]
+    fn ann_error_maps_to_status_test() {
+        let err = ANNError::log_index_error("bad".to_string());
+        assert_eq!(DiskannStatus::from(&err), DiskannStatus::IndexError);
+
+        let err = ANNError::IOError {
+            err: std::io::Error::other("bad"),
+        };
+        assert_eq!(DiskannStatus::from(&err), DiskannStatus::IoError);
+    }
+
+    #[test]
+    fn failed_call_keeps_message_test() {
+        let status = unsafe { diskann_index_create(ptr::null(), ptr::null_mut()) };
+        assert_eq!(status, DiskannStatus::InvalidArgument);
+
+        let message = unsafe { CStr::from_ptr(diskann_last_error_message()) };
+        assert_eq!(message.to_str().unwrap(), "parameters is null");
+    }
+
+    #[test]
+    fn overflowing_data_length_test() {
+        let parameters = diskann_default_parameters(128);
+        let data = [0f32; 128];
+        let mut index = ptr::null_mut();
+        let status =
+            unsafe { diskann_index_build(&parameters, data.as_ptr(), usize::MAX, &mut index) };
+        assert_eq!(status, DiskannStatus::InvalidArgument);
+        assert!(index.is_null());
+
+        let message = unsafe { CStr::from_ptr(diskann_last_error_message()) };
+        assert_eq!(message.to_str().unwrap(), "data is too large");
+    }
+
+    #[test]
+    fn panic_maps_to_status_test() {
+        let status = ffi_call(|| panic!("boom"));
+        assert_eq!(status, DiskannStatus::Panic);
+
+        let message = unsafe { CStr::from_ptr(diskann_last_error_message()) };
+        assert_eq!(message.to_str().unwrap(), "boom");
+    }
+}
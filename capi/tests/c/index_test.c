@@ -0,0 +1,114 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+#include <stdio.h>
+#include <string.h>
+
+#include "diskann.h"
+
+#define DIM 128
+#define NUM_POINTS 300
+#define K 5
+
+#define CHECK(condition)                                                   \
+    do {                                                                   \
+        if (!(condition)) {                                                \
+            const char *message = diskann_last_error_message();            \
+            fprintf(stderr, "%s:%d: check failed: %s (last error: %s)\n",  \
+                    __FILE__, __LINE__, #condition,                        \
+                    message != NULL ? message : "none");                   \
+            return 1;                                                      \
+        }                                                                  \
+    } while (0)
+
+static float data[NUM_POINTS * DIM];
+
+/* Deterministic points in [0, 1) */
+static void fill_points(float *points, size_t count) {
+    uint32_t state = 12345;
+    for (size_t i = 0; i < count; i++) {
+        state = state * 1103515245u + 12345u;
+        points[i] = (float)(state >> 8) / (float)(1u << 24);
+    }
+}
+
+static int search_finds_itself(const DiskannIndex *index, size_t first, size_t count) {
+    uint32_t ids[16 * K];
+    float distances[16 * K];
+
+    CHECK(count <= 16);
+    CHECK(diskann_index_search(index, &data[first * DIM], count, K, 0, ids, distances) ==
+          DISKANN_STATUS_OK);
+    for (size_t i = 0; i < count; i++) {
+        CHECK(ids[i * K] == first + i);
+        CHECK(distances[i * K] < 1e-4f);
+    }
+
+    return 0;
+}
+
+int main(int argc, char **argv) {
+    const char *save_path = argc > 1 ? argv[1] : "c_api_test_index";
+    DiskannIndexParameters parameters = diskann_default_parameters(DIM);
+    DiskannIndex *index = NULL;
+    DiskannIndex *loaded = NULL;
+    uint32_t ids[K];
+    size_t first_id = 0;
+
+    parameters.max_degree = 32;
+    parameters.search_list_size = 50;
+    fill_points(data, NUM_POINTS * DIM);
+
+    /* Build from the first half and insert the second one */
+    CHECK(diskann_index_build(&parameters, data, NUM_POINTS / 2, &index) == DISKANN_STATUS_OK);
+    CHECK(index != NULL);
+    CHECK(diskann_index_insert(index, &data[NUM_POINTS / 2 * DIM], NUM_POINTS / 2, &first_id) ==
+          DISKANN_STATUS_OK);
+    CHECK(first_id == NUM_POINTS / 2);
+    CHECK(search_finds_itself(index, 0, 10) == 0);
+    CHECK(search_finds_itself(index, NUM_POINTS - 10, 10) == 0);
+
+    /* Deleted points are no longer returned */
+    uint32_t deleted = 7;
+    CHECK(diskann_index_delete(index, &deleted, 1) == DISKANN_STATUS_OK);
+    CHECK(diskann_index_search(index, &data[7 * DIM], 1, K, 100, ids, NULL) == DISKANN_STATUS_OK);
+    for (size_t i = 0; i < K; i++) {
+        CHECK(ids[i] != deleted);
+    }
+
+    /* Save and load keep the points and the deletes */
+    CHECK(diskann_index_save(index, save_path) == DISKANN_STATUS_OK);
+    CHECK(diskann_index_load(&parameters, save_path, &loaded) == DISKANN_STATUS_OK);
+    CHECK(search_finds_itself(loaded, 20, 10) == 0);
+    CHECK(diskann_index_search(loaded, &data[7 * DIM], 1, K, 100, ids, NULL) == DISKANN_STATUS_OK);
+    for (size_t i = 0; i < K; i++) {
+        CHECK(ids[i] != deleted);
+    }
+
+    /* Errors come back as a status and a message */
+    CHECK(diskann_index_search(index, data, 1, 0, 0, ids, NULL) ==
+          DISKANN_STATUS_INVALID_ARGUMENT);
+    CHECK(strstr(diskann_last_error_message(), "k must be") != NULL);
+    CHECK(diskann_index_insert(NULL, data, 1, NULL) == DISKANN_STATUS_INVALID_ARGUMENT);
+
+    /* Loading checks the metric against the saved one */
+    DiskannIndex *mismatched = NULL;
+    parameters.metric = DISKANN_METRIC_COSINE;
+    CHECK(diskann_index_load(&parameters, save_path, &mismatched) == DISKANN_STATUS_INDEX_ERROR);
+    CHECK(strstr(diskann_last_error_message(), "was saved with metric") != NULL);
+    CHECK(mismatched == NULL);
+    parameters.metric = DISKANN_METRIC_L2;
+
+    DiskannIndex *unsupported = NULL;
+    parameters.dim = 3;
+    CHECK(diskann_index_create(&parameters, &unsupported) == DISKANN_STATUS_INDEX_ERROR);
+    CHECK(unsupported == NULL);
+
+    diskann_index_free(loaded);
+    diskann_index_free(index);
+    diskann_index_free(NULL);
+
+    printf("C API test passed\n");
+    return 0;
+}
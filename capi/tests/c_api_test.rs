@@ -0,0 +1,48 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+#![cfg(unix)]
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Folder holding the cdylib built for this test run
+fn library_dir() -> PathBuf {
+    // Integration tests run from target/<profile>/deps, next to the cdylib
+    env::current_exe().unwrap().parent().unwrap().to_path_buf()
+}
+
+#[test]
+fn c_program_test() {
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let out_dir = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let library_dir = library_dir();
+    let program = out_dir.join("c_api_index_test");
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+
+    let status = Command::new(compiler)
+        .args(["-std=c99", "-Wall", "-Wextra", "-Werror", "-o"])
+        .arg(&program)
+        .arg(crate_dir.join("tests").join("c").join("index_test.c"))
+        .arg("-I")
+        .arg(crate_dir.join("include"))
+        .arg("-L")
+        .arg(&library_dir)
+        .arg(format!("-Wl,-rpath,{}", library_dir.display()))
+        .arg("-ldiskann_c")
+        .status()
+        .expect("failed to run the C compiler");
+    assert!(status.success(), "failed to compile the C test program");
+
+    let output = Command::new(&program)
+        .arg(out_dir.join("c_api_test_index"))
+        .output()
+        .expect("failed to run the C test program");
+    assert!(
+        output.status.success(),
+        "C test program failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,19 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+
+/// Header generated by build.rs from src/lib.rs
+const GENERATED_HEADER: &str = include_str!(concat!(env!("OUT_DIR"), "/diskann.h"));
+
+/// Header checked in for C and Go callers that do not run cargo
+const CHECKED_IN_HEADER: &str = include_str!("../include/diskann.h");
+
+#[test]
+fn checked_in_header_is_up_to_date_test() {
+    assert!(
+        GENERATED_HEADER == CHECKED_IN_HEADER,
+        "capi/include/diskann.h is out of date, regenerate it from the repository root with:\n\
+         cbindgen --config capi/cbindgen.toml --crate diskann_capi --output capi/include/diskann.h capi"
+    );
+}
@@ -13,10 +13,14 @@ use diskann::{
         IndexConfiguration,
     },
     utils::round_up,
-    utils::{file_exists, load_ids_to_delete_from_file, load_metadata_from_file, Timer},
+    utils::{
+        file_exists, load_ids_to_delete_from_file, load_metadata_from_file, load_vectors,
+        vectors_metadata, InputFormat, Timer, VectorElement,
+    },
 };
 
-use vector::{FullPrecisionDistance, Half, Metric};
+use logger::trace_logger::{init_trace_logger, LevelFilter};
+use vector::{BFloat16, FullPrecisionDistance, Half, Metric};
 
 // The main function to build an in-memory index
 #[allow(clippy::too_many_arguments)]
@@ -24,6 +28,7 @@ fn build_and_insert_delete_in_memory_index<T>(
     metric: Metric,
     data_path: &str,
     delta_path: &str,
+    input_format: InputFormat,
     r: u32,
     l: u32,
     alpha: f32,
@@ -35,7 +40,7 @@ fn build_and_insert_delete_in_memory_index<T>(
     delete_path: &str,
 ) -> ANNResult<()>
 where
-    T: Default + Copy + Sync + Send + Into<f32>,
+    T: VectorElement + Sync + Send,
     [T; DIM_104]: FullPrecisionDistance<T, DIM_104>,
     [T; DIM_128]: FullPrecisionDistance<T, DIM_128>,
     [T; DIM_256]: FullPrecisionDistance<T, DIM_256>,
@@ -47,7 +52,15 @@ where
         .with_num_threads(num_threads)
         .build();
 
-    let (data_num, data_dim) = load_metadata_from_file(data_path)?;
+    // bin files are read by the index, the other formats are loaded here
+    let vectors = match input_format {
+        InputFormat::Bin => None,
+        _ => Some(load_vectors::<T>(data_path, input_format)?),
+    };
+    let (data_num, data_dim) = match &vectors {
+        Some(vectors) => vectors_metadata(vectors),
+        None => load_metadata_from_file(data_path)?,
+    };
 
     let config = IndexConfiguration::new(
         metric,
@@ -65,15 +78,21 @@ where
 
     let timer = Timer::new();
 
-    index.build(data_path, data_num)?;
+    match &vectors {
+        Some(vectors) => index.build_vector(vectors)?,
+        None => index.build(data_path, data_num)?,
+    }
 
     let diff = timer.elapsed();
 
     println!("Initial indexing time: {}", diff.as_secs_f64());
 
-    let (delta_data_num, _) = load_metadata_from_file(delta_path)?;
-
-    index.insert(delta_path, delta_data_num)?;
+    if input_format == InputFormat::Bin {
+        let (delta_data_num, _) = load_metadata_from_file(delta_path)?;
+        index.insert(delta_path, delta_data_num)?;
+    } else {
+        index.insert_vector(&load_vectors::<T>(delta_path, input_format)?)?;
+    }
 
     if !delete_path.is_empty() {
         if !file_exists(delete_path) {
@@ -94,11 +113,14 @@ where
 }
 
 fn main() -> ANNResult<()> {
+    init_trace_logger(LevelFilter::Info).unwrap();
+
     let mut data_type = String::new();
     let mut dist_fn = String::new();
     let mut data_path = String::new();
     let mut insert_path = String::new();
     let mut index_path_prefix = String::new();
+    let mut input_format = InputFormat::Bin;
     let mut delete_path = String::new();
 
     let mut num_threads = 0u32;
@@ -163,6 +185,17 @@ fn main() -> ANNResult<()> {
                     })?
                     .to_owned();
             }
+            "--input_format" => {
+                input_format = iter
+                    .next()
+                    .ok_or_else(|| {
+                        ANNError::log_index_config_error(
+                            "input_format".to_string(),
+                            "Missing input format".to_string(),
+                        )
+                    })?
+                    .parse()?;
+            }
             "--index_path_prefix" => {
                 index_path_prefix = iter
                     .next()
@@ -330,6 +363,7 @@ fn main() -> ANNResult<()> {
                 metric,
                 &data_path,
                 &insert_path,
+                input_format,
                 r,
                 l,
                 alpha,
@@ -346,6 +380,7 @@ fn main() -> ANNResult<()> {
                 metric,
                 &data_path,
                 &insert_path,
+                input_format,
                 r,
                 l,
                 alpha,
@@ -362,6 +397,7 @@ fn main() -> ANNResult<()> {
                 metric,
                 &data_path,
                 &insert_path,
+                input_format,
                 r,
                 l,
                 alpha,
@@ -378,6 +414,24 @@ fn main() -> ANNResult<()> {
                 metric,
                 &data_path,
                 &insert_path,
+                input_format,
+                r,
+                l,
+                alpha,
+                &index_path_prefix,
+                num_threads,
+                _use_pq_build,
+                build_pq_bytes as usize,
+                use_opq,
+                &delete_path,
+            )?;
+        }
+        "bf16" => {
+            build_and_insert_delete_in_memory_index::<BFloat16>(
+                metric,
+                &data_path,
+                &insert_path,
+                input_format,
                 r,
                 l,
                 alpha,
@@ -390,7 +444,7 @@ fn main() -> ANNResult<()> {
             )?;
         }
         _ => {
-            println!("Unsupported type. Use one of int8, uint8 or float.");
+            println!("Unsupported type. Use one of int8, uint8, float, f16 or bf16.");
             return Err(ANNError::log_index_config_error(
                 "data_type".to_string(),
                 "Invalid data type".to_string(),
@@ -404,12 +458,13 @@ fn main() -> ANNResult<()> {
 fn print_help() {
     println!("Arguments");
     println!("--help, -h                Print information on arguments");
-    println!("--data_type               data type <int8/uint8/float> (required)");
-    println!("--dist_fn                 distance function <l2/cosine> (required)");
+    println!("--data_type               data type <int8/uint8/float/f16/bf16> (required)");
+    println!("--dist_fn                 distance function <l2/cosine/ip> (required)");
     println!(
         "--data_path               Input data file in bin format for initial build (required)"
     );
     println!("--insert_path             Input data file in bin format for insert (required)");
+    println!("--input_format            Format of the data and insert files <bin/npy/json/jsonl> (default: bin)");
     println!("--index_path_prefix       Path prefix for saving index file components (required)");
     println!("--max_degree, -R          Maximum graph degree (default: 64)");
     println!("--Lbuild, -L              Build complexity, higher value results in better graphs (default: 100)");
@@ -8,16 +8,17 @@ use diskann::{
     common::{ANNError, ANNResult},
     index::ann_disk_index::create_disk_index,
     model::{
-        default_param_vals::ALPHA,
+        default_param_vals::{ALPHA, NUM_ROUNDS},
         vertex::{DIM_104, DIM_128, DIM_256, DIM_512},
         DiskIndexBuildParameters, IndexConfiguration, IndexWriteParametersBuilder,
     },
     storage::DiskIndexStorage,
     utils::round_up,
-    utils::{load_metadata_from_file, Timer},
+    utils::{load_metadata_from_file, Timer, VectorElement},
 };
 
-use vector::{FullPrecisionDistance, Half, Metric};
+use logger::trace_logger::{init_trace_logger, LevelFilter};
+use vector::{BFloat16, FullPrecisionDistance, Half, Metric};
 
 /// The main function to build a disk index
 #[allow(clippy::too_many_arguments)]
@@ -28,13 +29,15 @@ fn build_disk_index<T>(
     l: u32,
     index_path_prefix: &str,
     num_threads: u32,
+    num_rounds: u32,
+    seed: Option<u64>,
     search_ram_limit_gb: f64,
     index_build_ram_limit_gb: f64,
     num_pq_chunks: usize,
     use_opq: bool,
 ) -> ANNResult<()>
 where
-    T: Default + Copy + Sync + Send + Into<f32>,
+    T: VectorElement + Sync + Send,
     [T; DIM_104]: FullPrecisionDistance<T, DIM_104>,
     [T; DIM_128]: FullPrecisionDistance<T, DIM_128>,
     [T; DIM_256]: FullPrecisionDistance<T, DIM_256>,
@@ -43,10 +46,14 @@ where
     let disk_index_build_parameters =
         DiskIndexBuildParameters::new(search_ram_limit_gb, index_build_ram_limit_gb)?;
 
-    let index_write_parameters = IndexWriteParametersBuilder::new(l, r)
+    let mut builder = IndexWriteParametersBuilder::new(l, r)
         .with_saturate_graph(true)
         .with_num_threads(num_threads)
-        .build();
+        .with_num_rounds(num_rounds);
+    if let Some(seed) = seed {
+        builder = builder.with_seed(seed);
+    }
+    let index_write_parameters = builder.build();
 
     let (data_num, data_dim) = load_metadata_from_file(data_path)?;
 
@@ -76,12 +83,16 @@ where
 }
 
 fn main() -> ANNResult<()> {
+    init_trace_logger(LevelFilter::Info).unwrap();
+
     let mut data_type = String::new();
     let mut dist_fn = String::new();
     let mut data_path = String::new();
     let mut index_path_prefix = String::new();
 
     let mut num_threads = 0u32;
+    let mut num_rounds = NUM_ROUNDS;
+    let mut seed = None;
     let mut r = 64u32;
     let mut l = 100u32;
     let mut search_ram_limit_gb = 0f64;
@@ -194,6 +205,41 @@ fn main() -> ANNResult<()> {
                         )
                     })?;
             }
+            "--num_rounds" => {
+                num_rounds = iter
+                    .next()
+                    .ok_or_else(|| {
+                        ANNError::log_index_config_error(
+                            "num_rounds".to_string(),
+                            "Missing number of build passes".to_string(),
+                        )
+                    })?
+                    .parse()
+                    .map_err(|err| {
+                        ANNError::log_index_config_error(
+                            "num_rounds".to_string(),
+                            format!("ParseIntError: {}", err),
+                        )
+                    })?;
+            }
+            "--seed" => {
+                seed = Some(
+                    iter.next()
+                        .ok_or_else(|| {
+                            ANNError::log_index_config_error(
+                                "seed".to_string(),
+                                "Missing seed".to_string(),
+                            )
+                        })?
+                        .parse()
+                        .map_err(|err| {
+                            ANNError::log_index_config_error(
+                                "seed".to_string(),
+                                format!("ParseIntError: {}", err),
+                            )
+                        })?,
+                );
+            }
             "--build_PQ_bytes" => {
                 build_pq_bytes = iter
                     .next()
@@ -287,8 +333,8 @@ fn main() -> ANNResult<()> {
         .map_err(|err| ANNError::log_index_config_error("dist_fn".to_string(), err.to_string()))?;
 
     println!(
-        "Starting index build with R: {}  Lbuild: {}  alpha: {}  #threads: {} search_DRAM_budget: {} build_DRAM_budget: {}",
-        r, l, ALPHA, num_threads, search_ram_limit_gb, index_build_ram_limit_gb
+        "Starting index build with R: {}  Lbuild: {}  alpha: {}  #threads: {}  #passes: {} search_DRAM_budget: {} build_DRAM_budget: {}",
+        r, l, ALPHA, num_threads, num_rounds, search_ram_limit_gb, index_build_ram_limit_gb
     );
 
     let err = match data_type.as_str() {
@@ -299,6 +345,8 @@ fn main() -> ANNResult<()> {
             l,
             &index_path_prefix,
             num_threads,
+            num_rounds,
+            seed,
             search_ram_limit_gb,
             index_build_ram_limit_gb,
             build_pq_bytes as usize,
@@ -311,6 +359,8 @@ fn main() -> ANNResult<()> {
             l,
             &index_path_prefix,
             num_threads,
+            num_rounds,
+            seed,
             search_ram_limit_gb,
             index_build_ram_limit_gb,
             build_pq_bytes as usize,
@@ -323,6 +373,8 @@ fn main() -> ANNResult<()> {
             l,
             &index_path_prefix,
             num_threads,
+            num_rounds,
+            seed,
             search_ram_limit_gb,
             index_build_ram_limit_gb,
             build_pq_bytes as usize,
@@ -335,13 +387,29 @@ fn main() -> ANNResult<()> {
             l,
             &index_path_prefix,
             num_threads,
+            num_rounds,
+            seed,
+            search_ram_limit_gb,
+            index_build_ram_limit_gb,
+            build_pq_bytes as usize,
+            use_opq,
+        ),
+        "bf16" => build_disk_index::<BFloat16>(
+            metric,
+            &data_path,
+            r,
+            l,
+            &index_path_prefix,
+            num_threads,
+            num_rounds,
+            seed,
             search_ram_limit_gb,
             index_build_ram_limit_gb,
             build_pq_bytes as usize,
             use_opq,
         ),
         _ => {
-            println!("Unsupported type. Use one of int8, uint8, float or f16.");
+            println!("Unsupported type. Use one of int8, uint8, float, f16 or bf16.");
             return Err(ANNError::log_index_config_error(
                 "data_type".to_string(),
                 "Invalid data type".to_string(),
@@ -364,8 +432,8 @@ fn main() -> ANNResult<()> {
 fn print_help() {
     println!("Arguments");
     println!("--help, -h                Print information on arguments");
-    println!("--data_type               data type <int8/uint8/float> (required)");
-    println!("--dist_fn                 distance function <l2/cosine> (required)");
+    println!("--data_type               data type <int8/uint8/float/f16/bf16> (required)");
+    println!("--dist_fn                 distance function <l2/cosine/ip> (required)");
     println!("--data_path               Input data file in bin format (required)");
     println!("--index_path_prefix       Path prefix for saving index file components (required)");
     println!("--max_degree, -R          Maximum graph degree (default: 64)");
@@ -373,6 +441,8 @@ fn print_help() {
     println!("--search_DRAM_budget      Bound on the memory footprint of the index at search time in GB. Once built, the index will use up only the specified RAM limit, the rest will reside on disk");
     println!("--build_DRAM_budget       Limit on the memory allowed for building the index in GB");
     println!("--num_threads, -T         Number of threads used for building index (defaults to num of CPU logic cores)");
+    println!("--num_rounds              Number of Vamana passes, the first ones use alpha=1 (default: 2)");
+    println!("--seed                    Seed of the random choices of the build, builds with one thread and the same seed are identical (default: random)");
     println!("--build_PQ_bytes          Number of PQ bytes to build the index; 0 for full precision build (default: 0)");
     println!("--use_opq                 Set true for OPQ compression while using PQ distance comparisons for building the index, and false for PQ compression (default: false)");
 }
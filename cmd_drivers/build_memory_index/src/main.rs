@@ -13,39 +13,57 @@ use diskann::{
         IndexConfiguration, IndexWriteParametersBuilder,
     },
     utils::round_up,
-    utils::{load_metadata_from_file, Timer},
+    utils::{
+        load_metadata_from_file, load_vectors, vectors_metadata, InputFormat, Timer, VectorElement,
+    },
 };
 
-use vector::{FullPrecisionDistance, Half, Metric};
+use logger::trace_logger::{init_trace_logger, LevelFilter};
+use vector::{BFloat16, FullPrecisionDistance, Half, Metric};
 
 /// The main function to build an in-memory index
 #[allow(clippy::too_many_arguments)]
 fn build_in_memory_index<T>(
     metric: Metric,
     data_path: &str,
+    input_format: InputFormat,
     r: u32,
     l: u32,
     alpha: f32,
     save_path: &str,
     num_threads: u32,
+    num_rounds: u32,
+    seed: Option<u64>,
     _use_pq_build: bool,
     _num_pq_bytes: usize,
     use_opq: bool,
 ) -> ANNResult<()>
 where
-    T: Default + Copy + Sync + Send + Into<f32>,
+    T: VectorElement + Sync + Send,
     [T; DIM_104]: FullPrecisionDistance<T, DIM_104>,
     [T; DIM_128]: FullPrecisionDistance<T, DIM_128>,
     [T; DIM_256]: FullPrecisionDistance<T, DIM_256>,
     [T; DIM_512]: FullPrecisionDistance<T, DIM_512>,
 {
-    let index_write_parameters = IndexWriteParametersBuilder::new(l, r)
+    let mut builder = IndexWriteParametersBuilder::new(l, r)
         .with_alpha(alpha)
         .with_saturate_graph(false)
         .with_num_threads(num_threads)
-        .build();
+        .with_num_rounds(num_rounds);
+    if let Some(seed) = seed {
+        builder = builder.with_seed(seed);
+    }
+    let index_write_parameters = builder.build();
 
-    let (data_num, data_dim) = load_metadata_from_file(data_path)?;
+    // bin files are read by the index, the other formats are loaded here
+    let vectors = match input_format {
+        InputFormat::Bin => None,
+        _ => Some(load_vectors::<T>(data_path, input_format)?),
+    };
+    let (data_num, data_dim) = match &vectors {
+        Some(vectors) => vectors_metadata(vectors),
+        None => load_metadata_from_file(data_path)?,
+    };
 
     let config = IndexConfiguration::new(
         metric,
@@ -63,7 +81,10 @@ where
 
     let timer = Timer::new();
 
-    index.build(data_path, data_num)?;
+    match &vectors {
+        Some(vectors) => index.build_vector(vectors)?,
+        None => index.build(data_path, data_num)?,
+    }
 
     let diff = timer.elapsed();
 
@@ -74,24 +95,29 @@ where
 }
 
 fn main() -> ANNResult<()> {
+    init_trace_logger(LevelFilter::Info).unwrap();
+
     let args = BuildMemoryIndexArgs::parse();
 
     let _use_pq_build = args.build_pq_bytes > 0;
 
     println!(
-        "Starting index build with R: {}  Lbuild: {}  alpha: {}  #threads: {}",
-        args.max_degree, args.l_build, args.alpha, args.num_threads
+        "Starting index build with R: {}  Lbuild: {}  alpha: {}  #threads: {}  #passes: {}",
+        args.max_degree, args.l_build, args.alpha, args.num_threads, args.num_rounds
     );
 
     let err = match args.data_type {
         DataType::Float => build_in_memory_index::<f32>(
             args.dist_fn,
             &args.data_path.to_string_lossy(),
+            args.input_format,
             args.max_degree,
             args.l_build,
             args.alpha,
             &args.index_path_prefix,
             args.num_threads,
+            args.num_rounds,
+            args.seed,
             _use_pq_build,
             args.build_pq_bytes,
             args.use_opq,
@@ -99,11 +125,29 @@ fn main() -> ANNResult<()> {
         DataType::FP16 => build_in_memory_index::<Half>(
             args.dist_fn,
             &args.data_path.to_string_lossy(),
+            args.input_format,
             args.max_degree,
             args.l_build,
             args.alpha,
             &args.index_path_prefix,
             args.num_threads,
+            args.num_rounds,
+            args.seed,
+            _use_pq_build,
+            args.build_pq_bytes,
+            args.use_opq,
+        ),
+        DataType::BF16 => build_in_memory_index::<BFloat16>(
+            args.dist_fn,
+            &args.data_path.to_string_lossy(),
+            args.input_format,
+            args.max_degree,
+            args.l_build,
+            args.alpha,
+            &args.index_path_prefix,
+            args.num_threads,
+            args.num_rounds,
+            args.seed,
             _use_pq_build,
             args.build_pq_bytes,
             args.use_opq,
@@ -129,11 +173,14 @@ enum DataType {
 
     /// Half data type.
     FP16,
+
+    /// Brain float data type.
+    BF16,
 }
 
 #[derive(Debug, Parser)]
 struct BuildMemoryIndexArgs {
-    /// data type <int8/uint8/float / fp16> (required)
+    /// data type <float / fp16 / bf16> (required)
     #[arg(long = "data_type", default_value = "float")]
     pub data_type: DataType,
 
@@ -145,6 +192,10 @@ struct BuildMemoryIndexArgs {
     #[arg(long = "data_path", short, required = true)]
     pub data_path: PathBuf,
 
+    /// Format of the data file <bin / npy / json / jsonl>, npy and json files are converted to the data type
+    #[arg(long = "input_format", default_value = "bin")]
+    pub input_format: InputFormat,
+
     /// Path to the index file. The index will be saved to this prefixed name.
     #[arg(long = "index_path_prefix", short, required = true)]
     pub index_path_prefix: String,
@@ -165,6 +216,14 @@ struct BuildMemoryIndexArgs {
     #[arg(long = "num_threads", short = 'T', default_value = "1")]
     pub num_threads: u32,
 
+    /// Number of Vamana passes over the data, the passes before the last one use alpha=1
+    #[arg(long = "num_rounds", default_value = "2")]
+    pub num_rounds: u32,
+
+    /// Seed of the random visit order of the passes, builds with one thread and the same seed are identical
+    #[arg(long = "seed")]
+    pub seed: Option<u64>,
+
     /// Number of PQ bytes to build the index; 0 for full precision build
     #[arg(long = "build_pq_bytes", short, default_value = "0")]
     pub build_pq_bytes: usize,
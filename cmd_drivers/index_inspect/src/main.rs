@@ -0,0 +1,288 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+use std::fs::File;
+use std::io::Read;
+use std::process::exit;
+
+use clap::{Parser, ValueEnum};
+use diskann::{
+    common::{ANNError, ANNResult},
+    index::{create_inmem_index, load_inmem_index, GraphDiagnostics, RebuildPolicy},
+    model::{
+        vertex::{DIM_104, DIM_128, DIM_256, DIM_512},
+        IndexConfiguration, IndexMetadata, IndexWriteParametersBuilder,
+    },
+    utils::{file_exists, load_metadata_from_file, round_up, VectorElement},
+};
+
+use logger::trace_logger::{init_trace_logger, LevelFilter};
+use vector::{BFloat16, FullPrecisionDistance, Half, Metric};
+
+/// Number of unreachable ids listed in the text report
+const MAX_LISTED_IDS: usize = 20;
+
+/// Max degree and frozen point count from the header of a saved graph
+fn read_graph_header(graph_file: &str) -> ANNResult<(u32, usize)> {
+    let mut header = [0u8; 24];
+    File::open(graph_file)?.read_exact(&mut header)?;
+
+    // expected_file_size: u64, max_observed_degree: u32, start: u32, num_frozen_pts: u64
+    let max_degree = u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+    let mut num_frozen_pts = [0u8; 8];
+    num_frozen_pts.copy_from_slice(&header[16..24]);
+
+    Ok((max_degree, u64::from_le_bytes(num_frozen_pts) as usize))
+}
+
+/// Load the saved in-memory index and check its graph
+fn inspect_index<T>(index_path_prefix: &str) -> ANNResult<GraphDiagnostics>
+where
+    T: VectorElement + Sync + Send,
+    [T; DIM_104]: FullPrecisionDistance<T, DIM_104>,
+    [T; DIM_128]: FullPrecisionDistance<T, DIM_128>,
+    [T; DIM_256]: FullPrecisionDistance<T, DIM_256>,
+    [T; DIM_512]: FullPrecisionDistance<T, DIM_512>,
+{
+    if file_exists(&IndexMetadata::file_name(index_path_prefix)) {
+        return load_inmem_index::<T>(index_path_prefix)?.diagnostics();
+    }
+
+    // Indexes saved without metadata: the graph header gives the rest of the configuration
+    let (data_num, data_dim) = load_metadata_from_file(&format!("{}.data", index_path_prefix))?;
+    let (max_degree, num_frozen_pts) = read_graph_header(index_path_prefix)?;
+
+    // The search parameters and the metric play no part in the checks
+    let index_write_parameters =
+        IndexWriteParametersBuilder::new(max_degree.max(1), max_degree.max(1)).build();
+    let config = IndexConfiguration::new(
+        Metric::L2,
+        data_dim,
+        round_up(data_dim as u64, 8_u64) as usize,
+        0,
+        false,
+        0,
+        false,
+        num_frozen_pts,
+        1.0f32,
+        index_write_parameters,
+    );
+    let mut index = create_inmem_index::<T>(config)?;
+    index.load_with_enhance(index_path_prefix, data_num)?;
+
+    index.diagnostics()
+}
+
+/// Min, average and max of the values counted in a histogram indexed by value
+fn histogram_summary(histogram: &[usize]) -> (usize, f32, usize) {
+    let count: usize = histogram.iter().sum();
+    let total: usize = histogram
+        .iter()
+        .enumerate()
+        .map(|(value, count)| value * count)
+        .sum();
+    let min = histogram.iter().position(|&count| count > 0).unwrap_or(0);
+    let max = histogram.iter().rposition(|&count| count > 0).unwrap_or(0);
+
+    (min, total as f32 / count.max(1) as f32, max)
+}
+
+fn print_histogram(name: &str, histogram: &[usize]) {
+    println!("{} histogram (degree: points):", name);
+    for (degree, count) in histogram.iter().enumerate() {
+        if *count > 0 {
+            println!("  {:>5}: {}", degree, count);
+        }
+    }
+}
+
+fn print_report(
+    index_path_prefix: &str,
+    diagnostics: &GraphDiagnostics,
+    rebuild_reasons: &[String],
+    histograms: bool,
+) {
+    let percent = |ratio: f32| 100.0 * ratio;
+
+    println!("Index: {}", index_path_prefix);
+    println!(
+        "Points: {} active, {} frozen, {} deleted ({:.2}%)",
+        diagnostics.num_points,
+        diagnostics.num_frozen_points,
+        diagnostics.num_deleted,
+        percent(diagnostics.deleted_ratio())
+    );
+    println!("Edges: {}", diagnostics.num_edges);
+
+    let (min, avg, max) = histogram_summary(&diagnostics.out_degree_histogram);
+    println!("Out-degree: min {} avg {:.2} max {}", min, avg, max);
+    let (min, avg, max) = histogram_summary(&diagnostics.in_degree_histogram);
+    println!(
+        "In-degree: min {} avg {:.2} max {}, {} points without in-edges",
+        min,
+        avg,
+        max,
+        diagnostics.in_degree_histogram.first().unwrap_or(&0)
+    );
+    if histograms {
+        print_histogram("Out-degree", &diagnostics.out_degree_histogram);
+        print_histogram("In-degree", &diagnostics.in_degree_histogram);
+    }
+
+    println!(
+        "Unreachable points: {} ({:.2}% of the live points)",
+        diagnostics.unreachable.len(),
+        percent(diagnostics.unreachable_ratio())
+    );
+    if !diagnostics.unreachable.is_empty() {
+        let listed: Vec<String> = diagnostics
+            .unreachable
+            .iter()
+            .take(MAX_LISTED_IDS)
+            .map(|id| id.to_string())
+            .collect();
+        let more = if diagnostics.unreachable.len() > MAX_LISTED_IDS {
+            ", ..."
+        } else {
+            ""
+        };
+        println!("  graph ids: {}{}", listed.join(", "), more);
+    }
+    println!(
+        "Dangling edges to deleted points: {}",
+        diagnostics.num_dangling_edges
+    );
+    println!("Edges to missing points: {}", diagnostics.num_invalid_edges);
+    println!("Self-loops: {}", diagnostics.num_self_loops);
+    println!("Duplicate edges: {}", diagnostics.num_duplicate_edges);
+
+    if rebuild_reasons.is_empty() {
+        println!("Verdict: healthy");
+    } else {
+        println!("Verdict: rebuild recommended");
+        for reason in rebuild_reasons {
+            println!("  - {}", reason);
+        }
+    }
+}
+
+/// Data type of the arguments, else the one saved in the metadata, else float
+fn data_type(args: &IndexInspectArgs) -> ANNResult<DataType> {
+    if let Some(data_type) = args.data_type {
+        return Ok(data_type);
+    }
+
+    let metadata_file = IndexMetadata::file_name(&args.index_path_prefix);
+    if !file_exists(&metadata_file) {
+        return Ok(DataType::Float);
+    }
+
+    match IndexMetadata::load(&metadata_file)?.data_type.as_str() {
+        "float" => Ok(DataType::Float),
+        "f16" => Ok(DataType::FP16),
+        "bf16" => Ok(DataType::BF16),
+        data_type => Err(ANNError::log_index_config_error(
+            "data_type".to_string(),
+            format!("{} vectors are not supported", data_type),
+        )),
+    }
+}
+
+fn main() -> ANNResult<()> {
+    init_trace_logger(LevelFilter::Warn).unwrap();
+
+    let args = IndexInspectArgs::parse();
+
+    let diagnostics = data_type(&args).and_then(|data_type| match data_type {
+        DataType::Float => inspect_index::<f32>(&args.index_path_prefix),
+        DataType::FP16 => inspect_index::<Half>(&args.index_path_prefix),
+        DataType::BF16 => inspect_index::<BFloat16>(&args.index_path_prefix),
+    });
+    let diagnostics = match diagnostics {
+        Ok(diagnostics) => diagnostics,
+        Err(err) => {
+            eprintln!("Error: {:?}", err);
+            return Err(err);
+        }
+    };
+
+    let policy = RebuildPolicy {
+        max_deleted_ratio: args.max_deleted_ratio,
+        max_unreachable_ratio: args.max_unreachable_ratio,
+        max_dangling_edge_ratio: args.max_dangling_edge_ratio,
+    };
+    let rebuild_reasons = diagnostics.rebuild_reasons(&policy);
+
+    if args.json {
+        let report = serde_json::json!({
+            "diagnostics": diagnostics,
+            "deleted_ratio": diagnostics.deleted_ratio(),
+            "unreachable_ratio": diagnostics.unreachable_ratio(),
+            "dangling_edge_ratio": diagnostics.dangling_edge_ratio(),
+            "rebuild_reasons": rebuild_reasons,
+        });
+        println!("{}", report);
+    } else {
+        print_report(
+            &args.index_path_prefix,
+            &diagnostics,
+            &rebuild_reasons,
+            args.histograms,
+        );
+    }
+
+    if args.check && !rebuild_reasons.is_empty() {
+        exit(2);
+    }
+
+    Ok(())
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+enum DataType {
+    /// Float data type.
+    Float,
+
+    /// Half data type.
+    FP16,
+
+    /// Brain float data type.
+    BF16,
+}
+
+/// Report the graph health of a saved in-memory index
+#[derive(Debug, Parser)]
+struct IndexInspectArgs {
+    /// data type <float / fp16 / bf16> of the saved vectors, read from the metadata by default
+    #[arg(long = "data_type")]
+    pub data_type: Option<DataType>,
+
+    /// Prefix the index was saved to, with the graph in the prefix file itself
+    #[arg(long = "index_path_prefix", short, required = true)]
+    pub index_path_prefix: String,
+
+    /// Print the full out-degree and in-degree histograms
+    #[arg(long = "histograms", default_value = "false")]
+    pub histograms: bool,
+
+    /// Print the report as JSON
+    #[arg(long = "json", default_value = "false")]
+    pub json: bool,
+
+    /// Exit with status 2 when a rebuild is recommended
+    #[arg(long = "check", default_value = "false")]
+    pub check: bool,
+
+    /// Recommend a rebuild past this fraction of deleted points
+    #[arg(long = "max_deleted_ratio", default_value = "0.2")]
+    pub max_deleted_ratio: f32,
+
+    /// Recommend a rebuild past this fraction of unreachable live points
+    #[arg(long = "max_unreachable_ratio", default_value = "0.01")]
+    pub max_unreachable_ratio: f32,
+
+    /// Recommend a rebuild past this fraction of edges to deleted or missing points
+    #[arg(long = "max_dangling_edge_ratio", default_value = "0.1")]
+    pub max_dangling_edge_ratio: f32,
+}
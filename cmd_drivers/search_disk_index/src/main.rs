@@ -0,0 +1,612 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+mod search_index_utils;
+use bytemuck::Pod;
+use diskann::{
+    common::{ANNError, ANNResult},
+    index::{
+        ann_disk_index::{create_disk_index, ANNDiskIndex},
+        get_mean_stats, get_percentile_stats, QueryStats, DEFAULT_BEAM_WIDTH,
+    },
+    model::{
+        configuration::index_write_parameters::{default_param_vals, IndexWriteParametersBuilder},
+        vertex::{DIM_104, DIM_128, DIM_256, DIM_512},
+        IndexConfiguration, IndexMetadata, TunedSearchParameters,
+    },
+    storage::DiskIndexStorage,
+    utils::{
+        file_exists, load_aligned_bin, load_metadata_from_file, save_bin_f32, save_bin_u32,
+        set_rayon_num_threads, VectorElement,
+    },
+};
+use logger::trace_logger::{init_trace_logger, LevelFilter};
+use std::{env, path::Path, process::exit, time::Instant};
+use vector::{BFloat16, FullPrecisionDistance, Half, Metric};
+
+use rayon::prelude::*;
+
+/// Largest beamwidth tried when tuning
+const MAX_TUNED_BEAMWIDTH: u32 = 64;
+
+/// Beamwidth tuning starts from this value
+const START_BEAMWIDTH: u32 = 2;
+
+/// Search list size used to replay the warmup sample when generating the cache list
+const CACHE_SAMPLE_SEARCH_LIST: u32 = 15;
+
+/// Beamwidth used to replay the warmup sample when generating the cache list
+const CACHE_SAMPLE_BEAMWIDTH: u32 = 6;
+
+#[allow(clippy::too_many_arguments)]
+fn search_disk_index<T>(
+    metric: Metric,
+    index_path_prefix: &str,
+    result_path_prefix: &str,
+    query_file: &str,
+    truthset_file: &str,
+    num_threads: u32,
+    recall_at: u32,
+    beamwidth: u32,
+    num_nodes_to_cache: u32,
+    search_io_limit: u32,
+    l_vec: &[u32],
+    fail_if_recall_below: f32,
+) -> ANNResult<i32>
+where
+    T: VectorElement + Pod + Sync + Send,
+    [T; DIM_104]: FullPrecisionDistance<T, DIM_104>,
+    [T; DIM_128]: FullPrecisionDistance<T, DIM_128>,
+    [T; DIM_256]: FullPrecisionDistance<T, DIM_256>,
+    [T; DIM_512]: FullPrecisionDistance<T, DIM_512>,
+{
+    // Load the query file
+    let (query, query_num, query_dim, query_aligned_dim) = load_aligned_bin::<T>(query_file)?;
+    let mut gt_dim: usize = 0;
+    let mut gt_ids: Option<Vec<u32>> = None;
+    let mut gt_dists: Option<Vec<f32>> = None;
+
+    // Check for ground truth
+    let mut calc_recall_flag = false;
+    if !truthset_file.is_empty() && Path::new(truthset_file).exists() {
+        let ret = search_index_utils::load_truthset(truthset_file)?;
+        gt_ids = Some(ret.0);
+        gt_dists = ret.1;
+        let gt_num = ret.2;
+        gt_dim = ret.3;
+
+        if gt_num != query_num {
+            println!("Error. Mismatch in number of queries and ground truth data");
+        }
+
+        calc_recall_flag = true;
+    } else {
+        println!(
+            "Truthset file {} not found. Not computing recall",
+            truthset_file
+        );
+    }
+
+    // The compressed PQ table has one row per point of the index
+    let (index_num_points, _) =
+        load_metadata_from_file(&format!("{}.bin_pq_compressed.bin", index_path_prefix))?;
+
+    // Max degree is read from the disk index itself, the write parameters only carry the search list size
+    let index_write_params = IndexWriteParametersBuilder::new(
+        *l_vec.iter().max().unwrap_or(&recall_at),
+        default_param_vals::MAX_DEGREE,
+    )
+    .with_num_threads(num_threads)
+    .build();
+
+    let index_config = IndexConfiguration::new(
+        metric,
+        query_dim,
+        query_aligned_dim,
+        index_num_points,
+        false,
+        0,
+        false,
+        0,
+        1f32,
+        index_write_params,
+    );
+    let storage = DiskIndexStorage::new_for_search(index_path_prefix.to_string())?;
+    let mut index = create_disk_index::<T>(None, index_config, storage)?;
+    index.load(num_threads)?;
+    set_rayon_num_threads(num_threads);
+
+    // Cache the nodes most visited by the warmup sample written by the index build,
+    // or the nodes closest to the medoid when there is no sample
+    let warmup_query_file = format!("{}_sample_data.bin", index_path_prefix);
+    if num_nodes_to_cache > 0 {
+        let node_list = if file_exists(&warmup_query_file) {
+            index.generate_cache_list_from_sample_queries(
+                &warmup_query_file,
+                CACHE_SAMPLE_SEARCH_LIST,
+                CACHE_SAMPLE_BEAMWIDTH,
+                num_nodes_to_cache as usize,
+            )?
+        } else {
+            index.cache_bfs_levels(num_nodes_to_cache as usize)?
+        };
+        index.load_cache_list(&node_list)?;
+    }
+
+    // Beamwidth is tuned on the warmup sample
+    let mut tuning_sample = None;
+    if beamwidth == 0 {
+        if file_exists(&warmup_query_file) {
+            let (warmup, _, warmup_dim, warmup_aligned_dim) =
+                load_aligned_bin::<T>(&warmup_query_file)?;
+            if warmup_dim != query_dim {
+                return Err(ANNError::log_index_error(format!(
+                    "Warmup sample dim {} doesn't match query dim {}",
+                    warmup_dim, query_dim
+                )));
+            }
+            tuning_sample = Some((warmup, warmup_aligned_dim));
+        } else {
+            println!(
+                "Warmup sample {} not found. Tuning beamwidth on the queries",
+                warmup_query_file
+            );
+        }
+    }
+
+    println!("Using {} threads to search", num_threads);
+    let mut table_width = 4 + 12 + 16 + 20 + 15 + 16 + 16;
+    let mut table_header_str = format!(
+        "{:>4}{:>12}{:>16}{:>20}{:>15}{:>16}{:>16}",
+        "L", "Beamwidth", "QPS", "Mean Latency (mus)", "99.9 Latency", "Mean IOs", "CPU (mus)"
+    );
+    if calc_recall_flag {
+        table_header_str.push_str(&format!("{:>12}", format!("Recall@{}", recall_at)));
+        table_width += 12;
+    }
+
+    println!("{}", table_header_str);
+    println!("{}", "=".repeat(table_width));
+
+    let mut query_result_ids: Vec<Vec<u32>> =
+        vec![vec![0; query_num * recall_at as usize]; l_vec.len()];
+    let mut query_result_dists: Vec<Vec<f32>> =
+        vec![vec![0.0; query_num * recall_at as usize]; l_vec.len()];
+    let mut stats: Vec<QueryStats> = vec![QueryStats::default(); query_num];
+    let mut optimized_beamwidth = START_BEAMWIDTH;
+    let mut best_recall = 0.0;
+
+    for test_id in 0..l_vec.len() {
+        let l_value = l_vec[test_id];
+
+        if l_value < recall_at {
+            println!(
+                "Ignoring search with L:{} since it's smaller than K:{}",
+                l_value, recall_at
+            );
+            continue;
+        }
+
+        if beamwidth == 0 {
+            let (sample, sample_aligned_dim) = match &tuning_sample {
+                Some((warmup, warmup_aligned_dim)) => (&warmup[..], *warmup_aligned_dim),
+                None => (&query[..], query_aligned_dim),
+            };
+            optimized_beamwidth = optimize_beamwidth(
+                index.as_ref(),
+                sample,
+                sample_aligned_dim,
+                l_value,
+                search_io_limit,
+                optimized_beamwidth,
+            )?;
+        } else {
+            optimized_beamwidth = beamwidth;
+        }
+
+        let zipped = query_result_ids[test_id]
+            .par_chunks_mut(recall_at as usize)
+            .zip(query_result_dists[test_id].par_chunks_mut(recall_at as usize))
+            .zip(stats.par_iter_mut())
+            .zip(query.par_chunks(query_aligned_dim));
+
+        let start = Instant::now();
+        zipped.try_for_each(|(((query_result, query_dists), stat), query_chunk)| {
+            *stat = QueryStats::default();
+            index
+                .beam_search(
+                    query_chunk,
+                    recall_at as usize,
+                    l_value,
+                    optimized_beamwidth,
+                    search_io_limit,
+                    query_result,
+                    query_dists,
+                    Some(stat),
+                )
+                .map(|_| ())
+        })?;
+        let diff = Instant::now().duration_since(start);
+
+        let qps: f32 = query_num as f32 / diff.as_secs_f32();
+        let mean_latency = get_mean_stats(&stats, |stat| stat.total_us);
+        let latency_999 = get_percentile_stats(&stats, 0.999, |stat| stat.total_us);
+        let mean_ios = get_mean_stats(&stats, |stat| stat.n_ios);
+        let mean_cpu_us = get_mean_stats(&stats, |stat| stat.cpu_us);
+
+        let mut stat_str = format!(
+            "{: >4}{: >12}{: >16.2}{: >20.2}{: >15.2}{: >16.2}{: >16.2}",
+            l_value, optimized_beamwidth, qps, mean_latency, latency_999, mean_ios, mean_cpu_us
+        );
+
+        if calc_recall_flag {
+            let recall = search_index_utils::calculate_recall(
+                query_num,
+                gt_ids.as_ref().unwrap(),
+                &gt_dists,
+                gt_dim,
+                &query_result_ids[test_id],
+                recall_at,
+                recall_at,
+            )? as f32;
+            stat_str.push_str(&format!("{: >12.2}", recall));
+            best_recall = f32::max(best_recall, recall);
+        }
+
+        println!("{}", stat_str);
+    }
+
+    println!("Done searching. Now saving results");
+    for (test_id, l_value) in l_vec.iter().enumerate() {
+        if *l_value < recall_at {
+            continue;
+        }
+
+        let cur_result_path = format!("{}_{}_idx_uint32.bin", result_path_prefix, l_value);
+        save_bin_u32(
+            &cur_result_path,
+            query_result_ids[test_id].as_slice(),
+            query_num,
+            recall_at as usize,
+            0,
+        )?;
+
+        let cur_result_path = format!("{}_{}_dists_float.bin", result_path_prefix, l_value);
+        save_bin_f32(
+            &cur_result_path,
+            query_result_dists[test_id].as_slice(),
+            query_num,
+            recall_at as usize,
+            0,
+        )?;
+    }
+
+    if best_recall >= fail_if_recall_below {
+        Ok(0)
+    } else {
+        Ok(-1)
+    }
+}
+
+/// Grow the beamwidth by 10% at a time from start_beamwidth while QPS on the tuning sample improves
+/// and the 99.9 latency stays within 15ms plus twice the mean latency. Returns the best beamwidth.
+fn optimize_beamwidth<T>(
+    index: &dyn ANNDiskIndex<T>,
+    tuning_sample: &[T],
+    tuning_sample_aligned_dim: usize,
+    l_value: u32,
+    search_io_limit: u32,
+    start_beamwidth: u32,
+) -> ANNResult<u32>
+where
+    T: VectorElement + Sync + Send,
+{
+    let tuning_sample_num = tuning_sample.len() / tuning_sample_aligned_dim;
+    let mut cur_beamwidth = start_beamwidth;
+    let mut best_beamwidth = start_beamwidth;
+    let mut max_qps = 0f32;
+
+    let mut result_ids = vec![0u32; tuning_sample_num];
+    let mut result_dists = vec![0f32; tuning_sample_num];
+    let mut stats = vec![QueryStats::default(); tuning_sample_num];
+
+    while cur_beamwidth <= MAX_TUNED_BEAMWIDTH {
+        let zipped = result_ids
+            .par_chunks_mut(1)
+            .zip(result_dists.par_chunks_mut(1))
+            .zip(stats.par_iter_mut())
+            .zip(tuning_sample.par_chunks(tuning_sample_aligned_dim));
+
+        let start = Instant::now();
+        zipped.try_for_each(|(((result_id, result_dist), stat), query_chunk)| {
+            *stat = QueryStats::default();
+            index
+                .beam_search(
+                    query_chunk,
+                    1,
+                    l_value,
+                    cur_beamwidth,
+                    search_io_limit,
+                    result_id,
+                    result_dist,
+                    Some(stat),
+                )
+                .map(|_| ())
+        })?;
+        let diff = Instant::now().duration_since(start);
+
+        let qps = tuning_sample_num as f32 / diff.as_secs_f32();
+        let latency_999 = get_percentile_stats(&stats, 0.999, |stat| stat.total_us) as f64;
+        let mean_latency = get_mean_stats(&stats, |stat| stat.total_us);
+
+        if qps > max_qps && latency_999 < 15000.0 + mean_latency * 2.0 {
+            max_qps = qps;
+            best_beamwidth = cur_beamwidth;
+            cur_beamwidth = (cur_beamwidth as f32 * 1.1).ceil() as u32;
+        } else {
+            break;
+        }
+    }
+
+    Ok(best_beamwidth)
+}
+
+fn main() -> ANNResult<()> {
+    init_trace_logger(LevelFilter::Info).unwrap();
+
+    let return_val: i32;
+    {
+        let mut data_type: String = String::new();
+        let mut metric: Option<Metric> = None;
+        let mut index_path_prefix: String = String::new();
+        let mut result_path_prefix: String = String::new();
+        let mut query_file: String = String::new();
+        let mut truthset_file: String = String::new();
+        let mut num_cpus: u32 = num_cpus::get() as u32;
+        let mut recall_at: Option<u32> = None;
+        let mut beamwidth: Option<u32> = None;
+        let mut num_nodes_to_cache: u32 = 0;
+        let mut search_io_limit: u32 = u32::MAX;
+        let mut l_vec: Vec<u32> = Vec::new();
+        let mut fail_if_recall_below: f32 = 0.0;
+
+        let args: Vec<String> = env::args().collect();
+        let mut iter = args.iter().skip(1).peekable();
+        while let Some(arg) = iter.next() {
+            let ann_error =
+                || ANNError::log_index_config_error(String::from(arg), format!("Missing {}", arg));
+            let parse_error = |err: std::num::ParseIntError| {
+                ANNError::log_index_config_error(String::from(arg), format!("ParseError: {}", err))
+            };
+            match arg.as_str() {
+                "--help" | "-h" => {
+                    print_help();
+                    return Ok(());
+                }
+                "--data_type" => {
+                    data_type = iter.next().ok_or_else(ann_error)?.to_owned();
+                }
+                "--dist_fn" => {
+                    metric = Some(iter.next().ok_or_else(ann_error)?.parse().map_err(|err| {
+                        ANNError::log_index_config_error(
+                            String::from(arg),
+                            format!("ParseError: {}", err),
+                        )
+                    })?);
+                }
+                "--index_path_prefix" => {
+                    index_path_prefix = iter.next().ok_or_else(ann_error)?.to_owned();
+                }
+                "--result_path" => {
+                    result_path_prefix = iter.next().ok_or_else(ann_error)?.to_owned();
+                }
+                "--query_file" => {
+                    query_file = iter.next().ok_or_else(ann_error)?.to_owned();
+                }
+                "--gt_file" => {
+                    truthset_file = iter.next().ok_or_else(ann_error)?.to_owned();
+                }
+                "--recall_at" | "-K" => {
+                    recall_at = Some(
+                        iter.next()
+                            .ok_or_else(ann_error)?
+                            .parse()
+                            .map_err(parse_error)?,
+                    );
+                }
+                "--search_list" | "-L" => {
+                    while iter.peek().is_some() && !iter.peek().unwrap().starts_with('-') {
+                        l_vec.push(
+                            iter.next()
+                                .ok_or_else(ann_error)?
+                                .parse()
+                                .map_err(parse_error)?,
+                        );
+                    }
+                }
+                "--beamwidth" | "-W" => {
+                    beamwidth = Some(
+                        iter.next()
+                            .ok_or_else(ann_error)?
+                            .parse()
+                            .map_err(parse_error)?,
+                    );
+                }
+                "--num_nodes_to_cache" => {
+                    num_nodes_to_cache = iter
+                        .next()
+                        .ok_or_else(ann_error)?
+                        .parse()
+                        .map_err(parse_error)?;
+                }
+                "--search_io_limit" => {
+                    search_io_limit = iter
+                        .next()
+                        .ok_or_else(ann_error)?
+                        .parse()
+                        .map_err(parse_error)?;
+                }
+                "--num_threads" | "-T" => {
+                    num_cpus = iter
+                        .next()
+                        .ok_or_else(ann_error)?
+                        .parse()
+                        .map_err(parse_error)?;
+                }
+                "--fail_if_recall_below" => {
+                    fail_if_recall_below =
+                        iter.next().ok_or_else(ann_error)?.parse().map_err(|err| {
+                            ANNError::log_index_config_error(
+                                String::from(arg),
+                                format!("ParseError: {}", err),
+                            )
+                        })?;
+                }
+                _ => {
+                    return Err(ANNError::log_index_error(format!(
+                        "Unknown argument: {}",
+                        arg
+                    )));
+                }
+            }
+        }
+
+        if metric.is_none() {
+            return Err(ANNError::log_index_error(String::from("No metric given!")));
+        } else if recall_at.is_none() {
+            return Err(ANNError::log_index_error(String::from(
+                "No recall_at given!",
+            )));
+        }
+
+        // Without a search list or beamwidth, search with the ones tune_search saved for the index
+        let tuned_search = tuned_search_parameters(&index_path_prefix)?;
+        if l_vec.is_empty() {
+            match tuned_search {
+                Some(tuned_search) => l_vec.push(tuned_search.l_value),
+                None => {
+                    return Err(ANNError::log_index_error(String::from(
+                        "No search_list given and no tuned L, run tune_search first!",
+                    )));
+                }
+            }
+        }
+        let beamwidth = beamwidth
+            .or(tuned_search.and_then(|tuned_search| tuned_search.beam_width))
+            .unwrap_or(DEFAULT_BEAM_WIDTH);
+
+        return_val = match data_type.as_str() {
+            "float" => search_disk_index::<f32>(
+                metric.unwrap(),
+                &index_path_prefix,
+                &result_path_prefix,
+                &query_file,
+                &truthset_file,
+                num_cpus,
+                recall_at.unwrap(),
+                beamwidth,
+                num_nodes_to_cache,
+                search_io_limit,
+                &l_vec,
+                fail_if_recall_below,
+            )?,
+            "int8" => search_disk_index::<i8>(
+                metric.unwrap(),
+                &index_path_prefix,
+                &result_path_prefix,
+                &query_file,
+                &truthset_file,
+                num_cpus,
+                recall_at.unwrap(),
+                beamwidth,
+                num_nodes_to_cache,
+                search_io_limit,
+                &l_vec,
+                fail_if_recall_below,
+            )?,
+            "uint8" => search_disk_index::<u8>(
+                metric.unwrap(),
+                &index_path_prefix,
+                &result_path_prefix,
+                &query_file,
+                &truthset_file,
+                num_cpus,
+                recall_at.unwrap(),
+                beamwidth,
+                num_nodes_to_cache,
+                search_io_limit,
+                &l_vec,
+                fail_if_recall_below,
+            )?,
+            "f16" => search_disk_index::<Half>(
+                metric.unwrap(),
+                &index_path_prefix,
+                &result_path_prefix,
+                &query_file,
+                &truthset_file,
+                num_cpus,
+                recall_at.unwrap(),
+                beamwidth,
+                num_nodes_to_cache,
+                search_io_limit,
+                &l_vec,
+                fail_if_recall_below,
+            )?,
+            "bf16" => search_disk_index::<BFloat16>(
+                metric.unwrap(),
+                &index_path_prefix,
+                &result_path_prefix,
+                &query_file,
+                &truthset_file,
+                num_cpus,
+                recall_at.unwrap(),
+                beamwidth,
+                num_nodes_to_cache,
+                search_io_limit,
+                &l_vec,
+                fail_if_recall_below,
+            )?,
+            _ => {
+                return Err(ANNError::log_index_error(format!(
+                    "Unknown data type: {}!",
+                    data_type
+                )));
+            }
+        };
+    }
+
+    // Rust only allows returning values with this method, but this will immediately terminate the program without running destructors on the
+    // stack. To get around this enclose main function logic in a block so that by the time we return here all destructors have been called.
+    exit(return_val);
+}
+
+/// Search parameters saved in the metadata of the disk index by tune_search
+fn tuned_search_parameters(index_path_prefix: &str) -> ANNResult<Option<TunedSearchParameters>> {
+    let metadata_file = IndexMetadata::file_name(&format!("{}_disk.index", index_path_prefix));
+    if !file_exists(&metadata_file) {
+        return Ok(None);
+    }
+
+    Ok(IndexMetadata::load(&metadata_file)?.tuned_search)
+}
+
+fn print_help() {
+    println!("Arguments");
+    println!("--help, -h                Print information on arguments");
+    println!("--data_type               data type <int8/uint8/float/f16/bf16> (required)");
+    println!("--dist_fn                 distance function <l2/cosine/ip> (required)");
+    println!("--index_path_prefix       Path prefix to the disk index (required)");
+    println!("--result_path             Path prefix for saving results of the queries (required)");
+    println!("--query_file              Query file in binary format");
+    println!("--gt_file                 Ground truth file for the queryset");
+    println!("--recall_at, -K           Number of neighbors to be returned");
+    println!("--search_list, -L         List of L values of search (defaults to the L tuned by tune_search)");
+    println!("--beamwidth, -W           Beamwidth for search. Set 0 to optimize internally (defaults to the tuned one, else 2)");
+    println!(
+        "--num_nodes_to_cache      Number of nodes to keep in memory, picked by replaying the warmup sample or by BFS from the medoid (defaults to 0)"
+    );
+    println!("--search_io_limit         Max number of nodes read from disk per query (defaults to unlimited)");
+    println!("--num_threads, -T         Number of threads used for searching (defaults to num_cpus::get())");
+    println!("--fail_if_recall_below    If set to a value >0 and <100%, program returns -1 if best recall found is below this threshold");
+}
@@ -10,12 +10,13 @@ use diskann::{
     model::{
         configuration::index_write_parameters::{default_param_vals, IndexWriteParametersBuilder},
         vertex::{DIM_104, DIM_128, DIM_256, DIM_512},
-        IndexConfiguration,
+        IndexConfiguration, IndexMetadata,
     },
-    utils::{load_metadata_from_file, save_bin_u32},
+    utils::{file_exists, load_aligned_bin, load_metadata_from_file, save_bin_u32, VectorElement},
 };
+use logger::trace_logger::{init_trace_logger, LevelFilter};
 use std::{env, path::Path, process::exit, time::Instant};
-use vector::{FullPrecisionDistance, Half, Metric};
+use vector::{BFloat16, FullPrecisionDistance, Half, Metric};
 
 use rayon::prelude::*;
 
@@ -29,20 +30,19 @@ fn search_memory_index<T>(
     num_threads: u32,
     recall_at: u32,
     print_all_recalls: bool,
-    l_vec: &Vec<u32>,
+    l_vec: &[u32],
     show_qps_per_thread: bool,
     fail_if_recall_below: f32,
 ) -> ANNResult<i32>
 where
-    T: Default + Copy + Sized + Pod + Sync + Send + Into<f32>,
+    T: VectorElement + Pod + Sync + Send,
     [T; DIM_104]: FullPrecisionDistance<T, DIM_104>,
     [T; DIM_128]: FullPrecisionDistance<T, DIM_128>,
     [T; DIM_256]: FullPrecisionDistance<T, DIM_256>,
     [T; DIM_512]: FullPrecisionDistance<T, DIM_512>,
 {
     // Load the query file
-    let (query, query_num, query_dim, query_aligned_dim) =
-        search_index_utils::load_aligned_bin::<T>(query_file)?;
+    let (query, query_num, query_dim, query_aligned_dim) = load_aligned_bin::<T>(query_file)?;
     let mut gt_dim: usize = 0;
     let mut gt_ids: Option<Vec<u32>> = None;
     let mut gt_dists: Option<Vec<f32>> = None;
@@ -229,6 +229,8 @@ where
 }
 
 fn main() -> ANNResult<()> {
+    init_trace_logger(LevelFilter::Info).unwrap();
+
     let return_val: i32;
     {
         let mut data_type: String = String::new();
@@ -336,6 +338,11 @@ fn main() -> ANNResult<()> {
             )));
         }
 
+        // Without a search list, search with the L tune_search saved for the index
+        if l_vec.is_empty() {
+            l_vec.push(tuned_l_value(&index_path)?);
+        }
+
         // Seems like float is the only supported data type for FullPrecisionDistance right now,
         // but keep the structure in place here for future data types
         match data_type.as_str() {
@@ -399,6 +406,21 @@ fn main() -> ANNResult<()> {
                     fail_if_recall_below,
                 )?;
             }
+            "bf16" => {
+                return_val = search_memory_index::<BFloat16>(
+                    metric.unwrap(),
+                    &index_path,
+                    &result_path_prefix,
+                    &query_file,
+                    &truthset_file,
+                    num_cpus,
+                    recall_at.unwrap(),
+                    print_all_recalls,
+                    &l_vec,
+                    show_qps_per_thread,
+                    fail_if_recall_below,
+                )?;
+            }
             _ => {
                 return Err(ANNError::log_index_error(format!(
                     "Unknown data type: {}!",
@@ -413,18 +435,37 @@ fn main() -> ANNResult<()> {
     exit(return_val);
 }
 
+/// L saved in the metadata of the index by tune_search
+fn tuned_l_value(index_path: &str) -> ANNResult<u32> {
+    let metadata_file = IndexMetadata::file_name(index_path);
+    let tuned_search = if file_exists(&metadata_file) {
+        IndexMetadata::load(&metadata_file)?.tuned_search
+    } else {
+        None
+    };
+
+    tuned_search
+        .map(|tuned_search| tuned_search.l_value)
+        .ok_or_else(|| {
+            ANNError::log_index_error(format!(
+                "No search_list given and no tuned L in {}, run tune_search first",
+                metadata_file
+            ))
+        })
+}
+
 fn print_help() {
     println!("Arguments");
     println!("--help, -h                Print information on arguments");
-    println!("--data_type               data type <int8/uint8/float> (required)");
-    println!("--dist_fn                 distance function <l2/cosine> (required)");
+    println!("--data_type               data type <int8/uint8/float/f16/bf16> (required)");
+    println!("--dist_fn                 distance function <l2/cosine/ip> (required)");
     println!("--index_path_prefix       Path prefix to the index (required)");
     println!("--result_path             Path prefix for saving results of the queries (required)");
     println!("--query_file              Query file in binary format");
     println!("--gt_file                 Ground truth file for the queryset");
     println!("--recall_at, -K           Number of neighbors to be returned");
     println!("--print_all_recalls       Print recalls at all positions, from 1 up to specified recall_at value");
-    println!("--search_list             List of L values of search");
+    println!("--search_list             List of L values of search (defaults to the L tuned by tune_search)");
     println!("----num_threads, -T       Number of threads used for building index (defaults to num_cpus::get())");
     println!("--qps_per_thread          Print overall QPS divided by the number of threads in the output table");
     println!("--fail_if_recall_below    If set to a value >0 and <100%, program returns -1 if best recall found is below this threshold");
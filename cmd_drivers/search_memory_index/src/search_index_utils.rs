@@ -2,12 +2,8 @@
  * Copyright (c) Microsoft Corporation. All rights reserved.
  * Licensed under the MIT license.
  */
-use bytemuck::{cast_slice, Pod};
-use diskann::{
-    common::{ANNError, ANNResult, AlignedBoxWithSlice},
-    model::data_store::DatasetDto,
-    utils::{copy_aligned_data_from_file, is_aligned, round_up},
-};
+use bytemuck::cast_slice;
+use diskann::common::{ANNError, ANNResult};
 use std::collections::HashSet;
 use std::fs::File;
 use std::io::Read;
@@ -79,6 +75,7 @@ pub(crate) fn get_graph_num_frozen_points(graph_file: &str) -> ANNResult<usize>
 }
 
 #[inline]
+#[allow(clippy::type_complexity)]
 pub(crate) fn load_truthset(
     bin_file: &str,
 ) -> ANNResult<(Vec<u32>, Option<Vec<f32>>, usize, usize)> {
@@ -126,61 +123,3 @@ pub(crate) fn load_truthset(
 
     Ok((ids, None, npts, dim))
 }
-
-#[inline]
-pub(crate) fn load_aligned_bin<T: Default + Copy + Sized + Pod>(
-    bin_file: &str,
-) -> ANNResult<(AlignedBoxWithSlice<T>, usize, usize, usize)> {
-    let t_size = size_of::<T>();
-    let (npts, dim, file_size): (usize, usize, usize);
-    {
-        println!("Reading (with alignment) bin file: {bin_file}");
-        let mut file = File::open(bin_file)?;
-        file_size = file.metadata()?.len() as usize;
-
-        let mut buffer = [0; size_of::<i32>()];
-        file.read_exact(&mut buffer)?;
-        npts = i32::from_le_bytes(buffer) as usize;
-
-        file.read_exact(&mut buffer)?;
-        dim = i32::from_le_bytes(buffer) as usize;
-    }
-
-    let rounded_dim = round_up(dim, 8);
-    let expected_actual_file_size = npts * dim * size_of::<T>() + 2 * size_of::<u32>();
-
-    if file_size != expected_actual_file_size {
-        return Err(ANNError::log_index_error(format!(
-            "ERROR: File size mismatch. Actual size is {} while expected size is {} 
-        npts = {}, #dims = {}, aligned_dim = {}",
-            file_size, expected_actual_file_size, npts, dim, rounded_dim
-        )));
-    }
-
-    println!("Metadata: #pts = {npts}, #dims = {dim}, aligned_dim = {rounded_dim}...");
-
-    let alloc_size = npts * rounded_dim;
-    let alignment = 8 * t_size;
-    println!(
-        "allocating aligned memory of {} bytes... ",
-        alloc_size * t_size
-    );
-    if !is_aligned(alloc_size * t_size, alignment) {
-        return Err(ANNError::log_index_error(format!(
-            "Requested memory size is not a multiple of {}. Can not be allocated.",
-            alignment
-        )));
-    }
-
-    let mut data = AlignedBoxWithSlice::<T>::new(alloc_size, alignment)?;
-    let dto = DatasetDto {
-        data: &mut data,
-        rounded_dim,
-    };
-
-    println!("done. Copying data to mem_aligned buffer...");
-
-    let (_, _) = copy_aligned_data_from_file(bin_file, dto, 0)?;
-
-    Ok((data, npts, dim, rounded_dim))
-}
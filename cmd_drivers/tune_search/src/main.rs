@@ -0,0 +1,305 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+use std::fs::File;
+use std::io::{BufReader, Read};
+
+use clap::{Parser, ValueEnum};
+use diskann::{
+    common::{ANNError, ANNResult},
+    index::{
+        ann_disk_index::create_disk_index, compute_ground_truth, load_inmem_index,
+        save_tuned_search_parameters, tune_disk_search, tune_inmem_search,
+    },
+    model::{
+        vertex::{DIM_104, DIM_128, DIM_256, DIM_512},
+        IndexConfiguration, IndexMetadata, TunedSearchParameters,
+    },
+    storage::DiskIndexStorage,
+    utils::{file_exists, load_aligned_bin, set_rayon_num_threads, VectorElement},
+};
+use logger::trace_logger::{init_trace_logger, LevelFilter};
+use vector::{BFloat16, FullPrecisionDistance, Half};
+
+/// Ids of the first num_queries rows of a truthset file: npts and dim, the ids, then
+/// optionally the distances
+fn load_truthset_ids(truthset_file: &str, num_queries: usize) -> ANNResult<Vec<Vec<u32>>> {
+    let mut reader = BufReader::new(File::open(truthset_file)?);
+    let mut buffer = [0u8; 4];
+    reader.read_exact(&mut buffer)?;
+    let npts = u32::from_le_bytes(buffer) as usize;
+    reader.read_exact(&mut buffer)?;
+    let dim = u32::from_le_bytes(buffer) as usize;
+
+    if npts < num_queries {
+        return Err(ANNError::log_index_error(format!(
+            "Truthset {} has {} rows for {} queries",
+            truthset_file, npts, num_queries
+        )));
+    }
+
+    let mut ids = Vec::with_capacity(num_queries);
+    let mut row = vec![0u8; dim * 4];
+    for _ in 0..num_queries {
+        reader.read_exact(&mut row)?;
+        ids.push(
+            row.chunks_exact(4)
+                .map(|id| u32::from_le_bytes([id[0], id[1], id[2], id[3]]))
+                .collect(),
+        );
+    }
+
+    Ok(ids)
+}
+
+/// First num_queries queries of the query file, zero padded to aligned_dim
+fn load_queries<T: Default + Copy>(
+    query_file: &str,
+    num_queries: Option<usize>,
+    config: &IndexConfiguration,
+) -> ANNResult<(Vec<T>, usize)> {
+    let (queries, query_num, query_dim, query_aligned_dim) = load_aligned_bin::<T>(query_file)?;
+    if query_dim != config.dim || query_aligned_dim != config.aligned_dim {
+        return Err(ANNError::log_index_error(format!(
+            "Query dim {} doesn't match the index dim {}",
+            query_dim, config.dim
+        )));
+    }
+
+    let num_queries = num_queries.unwrap_or(query_num).min(query_num);
+    Ok((
+        queries[..num_queries * query_aligned_dim].to_vec(),
+        num_queries,
+    ))
+}
+
+/// Tune L of the in-memory index saved to args.index_path_prefix
+fn tune_memory_index<T>(args: &TuneSearchArgs) -> ANNResult<(String, TunedSearchParameters)>
+where
+    T: VectorElement + Sync + Send,
+    [T; DIM_104]: FullPrecisionDistance<T, DIM_104>,
+    [T; DIM_128]: FullPrecisionDistance<T, DIM_128>,
+    [T; DIM_256]: FullPrecisionDistance<T, DIM_256>,
+    [T; DIM_512]: FullPrecisionDistance<T, DIM_512>,
+{
+    let metadata_file = IndexMetadata::file_name(&args.index_path_prefix);
+    let index = load_inmem_index::<T>(&args.index_path_prefix)?;
+    let config = IndexMetadata::load(&metadata_file)?.configuration;
+    let (queries, num_queries) = load_queries::<T>(&args.query_file, args.num_queries, &config)?;
+
+    // Without a truthset, the index computes the exact neighbors with the ids search returns
+    let ground_truth = match &args.gt_file {
+        Some(gt_file) => Some(load_truthset_ids(gt_file, num_queries)?),
+        None => None,
+    };
+
+    let tuned_search = tune_inmem_search(
+        index.as_ref(),
+        &queries,
+        config.aligned_dim,
+        ground_truth.as_deref(),
+        args.recall_at,
+        args.target_recall,
+        args.max_search_list,
+    )?;
+
+    Ok((metadata_file, tuned_search))
+}
+
+/// Tune L and beamwidth of the disk index built with args.index_path_prefix
+fn tune_disk_index<T>(args: &TuneSearchArgs) -> ANNResult<(String, TunedSearchParameters)>
+where
+    T: VectorElement + Sync + Send,
+    [T; DIM_104]: FullPrecisionDistance<T, DIM_104>,
+    [T; DIM_128]: FullPrecisionDistance<T, DIM_128>,
+    [T; DIM_256]: FullPrecisionDistance<T, DIM_256>,
+    [T; DIM_512]: FullPrecisionDistance<T, DIM_512>,
+{
+    let storage = DiskIndexStorage::<T>::new_for_search(args.index_path_prefix.clone())?;
+    let metadata_file = storage.metadata_file();
+    let metadata = IndexMetadata::load(&metadata_file)?;
+    metadata.check_data_type::<T>()?;
+    let config = metadata.configuration;
+    let (queries, num_queries) = load_queries::<T>(&args.query_file, args.num_queries, &config)?;
+
+    // Without a truthset, the exact neighbors are computed from the base points
+    let ground_truth = match (&args.gt_file, &args.base_file) {
+        (Some(gt_file), _) => load_truthset_ids(gt_file, num_queries)?,
+        (None, Some(base_file)) => {
+            let (base, _, base_dim, base_aligned_dim) = load_aligned_bin::<T>(base_file)?;
+            if base_dim != config.dim || base_aligned_dim != config.aligned_dim {
+                return Err(ANNError::log_index_error(format!(
+                    "Base dim {} doesn't match the index dim {}",
+                    base_dim, config.dim
+                )));
+            }
+            compute_ground_truth(
+                &base,
+                &queries,
+                config.aligned_dim,
+                args.recall_at,
+                config.dist_metric,
+            )
+        }
+        (None, None) => {
+            return Err(ANNError::log_index_config_error(
+                "gt_file".to_string(),
+                "A disk index needs --gt_file or --base_file for the ground truth".to_string(),
+            ));
+        }
+    };
+
+    let mut index = create_disk_index::<T>(None, config.clone(), storage)?;
+    index.load(args.num_threads)?;
+
+    let tuned_search = tune_disk_search(
+        index.as_ref(),
+        &queries,
+        config.aligned_dim,
+        &ground_truth,
+        args.recall_at,
+        args.target_recall,
+        args.max_search_list,
+        args.max_beamwidth,
+    )?;
+
+    Ok((metadata_file, tuned_search))
+}
+
+fn tune_search<T>(args: &TuneSearchArgs) -> ANNResult<(String, TunedSearchParameters)>
+where
+    T: VectorElement + Sync + Send,
+    [T; DIM_104]: FullPrecisionDistance<T, DIM_104>,
+    [T; DIM_128]: FullPrecisionDistance<T, DIM_128>,
+    [T; DIM_256]: FullPrecisionDistance<T, DIM_256>,
+    [T; DIM_512]: FullPrecisionDistance<T, DIM_512>,
+{
+    match args.index_type {
+        IndexType::Memory => tune_memory_index::<T>(args),
+        IndexType::Disk => tune_disk_index::<T>(args),
+    }
+}
+
+/// Data type saved in the metadata of the index
+fn data_type(args: &TuneSearchArgs) -> ANNResult<String> {
+    let metadata_file = match args.index_type {
+        IndexType::Memory => IndexMetadata::file_name(&args.index_path_prefix),
+        IndexType::Disk => {
+            IndexMetadata::file_name(&format!("{}_disk.index", args.index_path_prefix))
+        }
+    };
+    if !file_exists(&metadata_file) {
+        return Err(ANNError::log_index_error(format!(
+            "No metadata {}, save or build the index again to write it",
+            metadata_file
+        )));
+    }
+
+    Ok(IndexMetadata::load(&metadata_file)?.data_type)
+}
+
+fn main() -> ANNResult<()> {
+    init_trace_logger(LevelFilter::Info).unwrap();
+
+    let args = TuneSearchArgs::parse();
+    set_rayon_num_threads(args.num_threads);
+
+    let result = data_type(&args).and_then(|data_type| match data_type.as_str() {
+        "float" => tune_search::<f32>(&args),
+        "int8" => tune_search::<i8>(&args),
+        "uint8" => tune_search::<u8>(&args),
+        "f16" => tune_search::<Half>(&args),
+        "bf16" => tune_search::<BFloat16>(&args),
+        data_type => Err(ANNError::log_index_config_error(
+            "data_type".to_string(),
+            format!("{} vectors are not supported", data_type),
+        )),
+    });
+    let (metadata_file, tuned_search) = match result {
+        Ok(result) => result,
+        Err(err) => {
+            eprintln!("Error: {:?}", err);
+            return Err(err);
+        }
+    };
+
+    println!(
+        "Recall@{} {:.2} (target {:.2}) with L {}",
+        tuned_search.k_value, tuned_search.recall, tuned_search.target_recall, tuned_search.l_value
+    );
+    if let Some(beam_width) = tuned_search.beam_width {
+        println!("Beamwidth {}", beam_width);
+    }
+
+    if !args.dry_run {
+        save_tuned_search_parameters(&metadata_file, tuned_search)?;
+        println!("Saved to {}", metadata_file);
+    }
+
+    Ok(())
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+enum IndexType {
+    /// In-memory index saved by build_memory_index.
+    Memory,
+
+    /// Disk index built by build_disk_index.
+    Disk,
+}
+
+/// Find the smallest L, and beamwidth for a disk index, reaching a target recall on a sample
+/// of queries and save them in the metadata of the index, for the searches to default to
+#[derive(Debug, Parser)]
+struct TuneSearchArgs {
+    /// Type of the index
+    #[arg(long = "index_type", value_enum, default_value = "memory")]
+    pub index_type: IndexType,
+
+    /// Path prefix of the index, the data type and metric are read from its metadata
+    #[arg(long = "index_path_prefix", short, required = true)]
+    pub index_path_prefix: String,
+
+    /// Held-out queries in binary format
+    #[arg(long = "query_file", required = true)]
+    pub query_file: String,
+
+    /// Number of queries of the query file to tune on, all by default
+    #[arg(long = "num_queries")]
+    pub num_queries: Option<usize>,
+
+    /// Ground truth of the queries. By default an in-memory index compares the queries with all
+    /// its points, and a disk index with the points of --base_file.
+    /// The ids of an in-memory index are the ones its searches return.
+    #[arg(long = "gt_file")]
+    pub gt_file: Option<String>,
+
+    /// Points the disk index was built from, to compute the ground truth without --gt_file
+    #[arg(long = "base_file")]
+    pub base_file: Option<String>,
+
+    /// K of the recall@K to reach
+    #[arg(long = "recall_at", short = 'K', default_value = "10")]
+    pub recall_at: usize,
+
+    /// Recall@K to reach, in percent
+    #[arg(long = "target_recall", required = true)]
+    pub target_recall: f32,
+
+    /// Largest L to try
+    #[arg(long = "max_search_list", default_value = "1000")]
+    pub max_search_list: u32,
+
+    /// Largest beamwidth to try on a disk index
+    #[arg(long = "max_beamwidth", default_value = "16")]
+    pub max_beamwidth: u32,
+
+    /// Number of threads used for searching
+    #[arg(long = "num_threads", short = 'T', default_value_t = num_cpus::get() as u32)]
+    pub num_threads: u32,
+
+    /// Print the tuned parameters without saving them
+    #[arg(long = "dry_run", default_value = "false")]
+    pub dry_run: bool,
+}
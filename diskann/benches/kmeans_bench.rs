@@ -33,6 +33,7 @@ fn benchmark_kmeans_rust(c: &mut Criterion) {
                 &mut centers_copy,
                 NUM_CENTERS,
                 MAX_KMEANS_REPS,
+                &mut rng,
             )
         })
     });
@@ -59,6 +60,7 @@ fn benchmark_kmeans_c(c: &mut Criterion) {
                 centers_copy.as_mut_slice(),
                 NUM_CENTERS,
                 MAX_KMEANS_REPS,
+                &mut rng,
             );
         })
     });
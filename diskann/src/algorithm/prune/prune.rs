@@ -68,7 +68,7 @@ where
 
                 // Add the entry to the result if its not been deleted, and doesn't
                 // add a self loop
-                if delete_set_ptr.map_or(true, |delete_set| !delete_set.contains(&neighbor.id))
+                if delete_set_ptr.is_none_or(|delete_set| !delete_set.contains(&neighbor.id))
                     && neighbor.id != location
                 {
                     result.push(neighbor.id);
@@ -90,6 +90,14 @@ where
                                 occlude_factor[j].max(neighbor2.distance / djk)
                             };
                         }
+                        Metric::InnerProduct => {
+                            // Distances are negated inner products, flip them back for MIPS
+                            let x = -neighbor2.distance;
+                            let y = -djk;
+                            if y > cur_alpha * x {
+                                occlude_factor[j] = occlude_factor[j].max(cur_alpha + 0.01);
+                            }
+                        }
                     }
                 }
             }
@@ -106,6 +114,7 @@ where
     ///
     /// * `location` - The id of the data point whose neighbors are to be pruned.
     /// * `pool` - A vector of neighbors to be pruned, sorted by distance to the query point.
+    /// * `alpha` - The occlusion factor of this pass, see `robust_prune`.
     /// * `pruned_list` - A vector to store the ids of the pruned neighbors.
     /// * `scratch` - A mutable reference to a scratch space for in-memory queries.
     ///
@@ -116,6 +125,7 @@ where
         &self,
         location: u32,
         pool: &mut Vec<Neighbor>,
+        alpha: f32,
         pruned_list: &mut AdjacencyList,
         scratch: &mut InMemQueryScratch<T, N>,
     ) -> ANNResult<()> {
@@ -124,7 +134,7 @@ where
             pool,
             self.configuration.index_write_parameter.max_degree,
             self.configuration.index_write_parameter.max_occlusion_size,
-            self.configuration.index_write_parameter.alpha,
+            alpha,
             pruned_list,
             scratch,
         )
@@ -207,12 +217,14 @@ where
     /// * `n` - The index of the new point
     /// * `pruned_list` is a vector of the neighbors of n that have been pruned by a previous step
     /// * `range` is the target number of neighbors for each point
+    /// * `alpha` is the occlusion factor of the pruning
     /// * `scratch` is a mutable reference to a scratch space that can be reused for intermediate computations
     pub fn inter_insert(
         &self,
         n: u32,
         pruned_list: &Vec<u32>,
         range: u32,
+        alpha: f32,
         scratch: &mut InMemQueryScratch<T, N>,
     ) -> ANNResult<()> {
         // Borrow the pruned_list as a source pool of neighbors
@@ -245,7 +257,13 @@ where
                 let mut new_out_neighbors =
                     AdjacencyList::for_range(self.configuration.write_range());
                 // Prune the neighbors of vertex_id using a helper method
-                self.prune_neighbors(vertex_id, &mut dummy_pool, &mut new_out_neighbors, scratch)?;
+                self.prune_neighbors(
+                    vertex_id,
+                    &mut dummy_pool,
+                    alpha,
+                    &mut new_out_neighbors,
+                    scratch,
+                )?;
 
                 self.set_neighbors(vertex_id, new_out_neighbors)?;
             }
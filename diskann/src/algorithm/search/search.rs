@@ -42,6 +42,7 @@ where
     /// # Arguments
     /// * `query` - query vertex
     /// * `scratch` - in-memory query scratch
+    ///
     /// TODO: use_filter, filteredLindex
     pub fn search_for_point(
         &self,
@@ -132,6 +133,7 @@ where
     /// # Arguments
     /// * `query` - query vertex
     /// * `scratch` - in-memory query scratch
+    ///
     /// TODO: use_filter, filter_label, search_invocation
     fn greedy_search(
         &self,
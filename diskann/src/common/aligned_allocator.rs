@@ -59,10 +59,12 @@ where
         Ok(Self { layout, val })
     }
 
+    /// Double the capacity, keeping the existing values.
     pub fn double_capacity(&mut self) -> ANNResult<()> {
         self.ensure_capacity(self.capacity() * 2)
     }
 
+    /// Grow the capacity to a multiple of the current capacity that holds at least `capacity` values, keeping the existing values.
     pub fn ensure_capacity(&mut self, capacity: usize) -> ANNResult<()> {
         let orig_capacity = self.capacity();
         if capacity <= orig_capacity {
@@ -83,10 +85,12 @@ where
         Ok(())
     }
 
+    /// Returns the number of values the slice can hold.
     pub fn capacity(&self) -> usize {
         self.val.len()
     }
 
+    /// Returns the alignment of the slice in bytes.
     pub fn alignment(&self) -> usize {
         self.layout.align()
     }
@@ -137,7 +141,7 @@ where
         range: Range<usize>,
         slice_len: usize,
     ) -> ANNResult<Vec<&mut [T]>> {
-        if range.len() % slice_len != 0 || range.end > self.len() {
+        if !range.len().is_multiple_of(slice_len) || range.end > self.len() {
             return Err(ANNError::log_index_error(format!(
                 "Cannot split range ({:?}) of AlignedBoxWithSlice (len: {}) into nonoverlapping mutable slices with length {}", 
                 range,
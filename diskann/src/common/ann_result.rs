@@ -80,6 +80,14 @@ pub enum ANNError {
         actual_size: usize,
         expected_actual_file_size: usize,
     },
+
+    /// The vectors of a saved index are not of the requested element type
+    #[error("DataTypeMismatch: the index holds {found} vectors, not {expected}")]
+    DataTypeMismatch { expected: String, found: String },
+
+    /// A long running operation was stopped by its ProgressObserver
+    #[error("Cancelled: {operation} was cancelled")]
+    Cancelled { operation: String },
 }
 
 impl ANNError {
@@ -163,6 +171,24 @@ impl ANNError {
         }
     }
 
+    /// Create, log and return FileSizeMismatch
+    #[inline]
+    pub fn log_file_size_mismatch_error(
+        message: String,
+        actual_size: usize,
+        expected_actual_file_size: usize,
+    ) -> Self {
+        let ann_err = ANNError::FileSizeMismatch {
+            message,
+            actual_size,
+            expected_actual_file_size,
+        };
+        match log_error(ann_err.to_string()) {
+            Ok(()) => ann_err,
+            Err(log_err) => ANNError::LogError { err: log_err },
+        }
+    }
+
     /// Create, log and return TryFromSliceError
     #[inline]
     pub fn log_try_from_slice_error(err: TryFromSliceError) -> Self {
@@ -172,6 +198,25 @@ impl ANNError {
             Err(log_err) => ANNError::LogError { err: log_err },
         }
     }
+
+    /// Create, log and return DataTypeMismatch
+    #[inline]
+    pub fn log_data_type_mismatch_error(expected: String, found: String) -> Self {
+        let ann_err = ANNError::DataTypeMismatch { expected, found };
+        match log_error(ann_err.to_string()) {
+            Ok(()) => ann_err,
+            Err(log_err) => ANNError::LogError { err: log_err },
+        }
+    }
+
+    /// Create, log and return Cancelled
+    /// It is requested by the caller, so it is logged at info level rather than as an error
+    #[inline]
+    pub fn log_cancelled(operation: String) -> Self {
+        let ann_err = ANNError::Cancelled { operation };
+        log::info!("{}", ann_err);
+        ann_err
+    }
 }
 
 #[cfg(test)]
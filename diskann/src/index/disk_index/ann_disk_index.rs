@@ -6,24 +6,53 @@
 
 //! ANN disk index abstraction
 
+use std::sync::Arc;
+
 use vector::FullPrecisionDistance;
 
+use crate::instrumentation::ProgressObserver;
 use crate::model::vertex::{DIM_104, DIM_128, DIM_256, DIM_512};
-use crate::model::{DiskIndexBuildParameters, IndexConfiguration};
+use crate::model::{DiskIndexBuildParameters, IndexConfiguration, TunedSearchParameters};
 use crate::storage::DiskIndexStorage;
+use crate::utils::VectorElement;
 
 use crate::common::{ANNError, ANNResult};
 
-use super::DiskIndex;
+use super::{DiskIndex, QueryStats};
 
 /// ANN disk index abstraction for custom <T, N>
 pub trait ANNDiskIndex<T>: Sync + Send
 where
-    T: Default + Copy + Sync + Send + Into<f32>,
+    T: VectorElement + Sync + Send,
 {
     /// Build index
     fn build(&mut self, codebook_prefix: &str) -> ANNResult<()>;
 
+    /// Report the progress of build to observer, at the end of each stage as DiskBuild and
+    /// through the in-memory graph build as Build. Once it is cancelled build stops with
+    /// ANNError::Cancelled and removes the intermediate in-memory index, whichever stage it
+    /// stopped at.
+    fn set_progress_observer(&mut self, observer: Option<Arc<dyn ProgressObserver>>);
+
+    /// Load the disk index, PQ tables and query scratch for num_threads search threads
+    fn load(&mut self, num_threads: u32) -> ANNResult<()>;
+
+    /// Get up to num_nodes_to_cache nodes closest to the medoid in BFS order
+    fn cache_bfs_levels(&self, num_nodes_to_cache: usize) -> ANNResult<Vec<u32>>;
+
+    /// Replay the sample queries in sample_file and get up to num_nodes_to_cache nodes
+    /// ordered by how often they were expanded
+    fn generate_cache_list_from_sample_queries(
+        &self,
+        sample_file: &str,
+        l_value: u32,
+        beam_width: u32,
+        num_nodes_to_cache: usize,
+    ) -> ANNResult<Vec<u32>>;
+
+    /// Keep the fp vectors and neighbors of the nodes in node_list in memory for search
+    fn load_cache_list(&mut self, node_list: &[u32]) -> ANNResult<()>;
+
     /// Search the index for K nearest neighbors of query using given L value, for benchmarking purposes
     fn search(
         &self,
@@ -34,6 +63,7 @@ where
     ) -> ANNResult<u32>;
 
     /// Search the index for K nearest neighbors of query using given L value, for benchmarking purposes
+    /// The beamwidth is the tuned one if there is one, else DEFAULT_BEAM_WIDTH
     fn search_with_distance(
         &self,
         query: &[T],
@@ -42,6 +72,32 @@ where
         indices: &mut [u32],
         distances: &mut [f32],
     ) -> ANNResult<u32>;
+
+    /// Search list size searches default to: the tuned L if there is one, else the
+    /// search list size of the configuration
+    fn search_list_size(&self) -> u32;
+
+    /// Search parameters tuned for a target recall, read from the metadata by load
+    fn tuned_search_parameters(&self) -> Option<TunedSearchParameters>;
+
+    /// Set the search parameters tuned for a target recall, search_with_distance uses their
+    /// beamwidth. save_tuned_search_parameters persists them.
+    fn set_tuned_search_parameters(&mut self, tuned_search: Option<TunedSearchParameters>);
+
+    /// Beam search the index for K nearest neighbors of query, reading at most beam_width nodes
+    /// from disk per hop and io_limit nodes in total. Returns the number of distance comparisons.
+    #[allow(clippy::too_many_arguments)]
+    fn beam_search(
+        &self,
+        query: &[T],
+        k_value: usize,
+        l_value: u32,
+        beam_width: u32,
+        io_limit: u32,
+        indices: &mut [u32],
+        distances: &mut [f32],
+        stats: Option<&mut QueryStats>,
+    ) -> ANNResult<u32>;
 }
 
 /// Create Index<T, N> based on configuration
@@ -51,7 +107,7 @@ pub fn create_disk_index<'a, T>(
     storage: DiskIndexStorage<T>,
 ) -> ANNResult<Box<dyn ANNDiskIndex<T> + 'a>>
 where
-    T: Default + Copy + Sync + Send + Into<f32> + 'a,
+    T: VectorElement + Sync + Send + 'a,
     [T; DIM_104]: FullPrecisionDistance<T, DIM_104>,
     [T; DIM_128]: FullPrecisionDistance<T, DIM_128>,
     [T; DIM_256]: FullPrecisionDistance<T, DIM_256>,
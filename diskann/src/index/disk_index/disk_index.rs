@@ -1,64 +1,69 @@
-use std::io::Read;
 /*
  * Copyright (c) Microsoft Corporation. All rights reserved.
  * Licensed under the MIT license.
  */
 use std::mem;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use std::fs::File;
-
-cfg_if! {
-    if #[cfg(target_os = "windows")] {
-        use std::os::windows::fs::MetadataExt;
-    } else {
-        use std::os::linux::fs::MetadataExt;
-    }
-}
-
-use byteorder::{NativeEndian, ReadBytesExt};
-use cfg_if::cfg_if;
+use hashbrown::HashSet;
 use logger::logger::indexlog::DiskIndexConstructionCheckpoint;
-use rand::distributions::Uniform;
-use rand::rngs::StdRng;
-use rand::SeedableRng;
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use vector::{FullPrecisionDistance, Metric};
-
-use crate::common::{ANNError, ANNResult, AlignedBoxWithSlice};
-//use crate::index::percentile_stats::{get_mean_stats, get_percentile_stats, QueryStats};
-//use crate::index::utils::{calculate_recall, load_aligned_bin, load_truthset};
+use rayon::prelude::*;
+use vector::FullPrecisionDistance;
+
+use crate::common::{ANNError, ANNResult};
 use crate::index::{ANNInmemIndex, InmemIndex};
-use crate::instrumentation::DiskIndexBuildLogger;
+use crate::instrumentation::{metrics, DiskIndexBuildLogger, IndexOperation, ProgressObserver};
 use crate::model::configuration::DiskIndexBuildParameters;
+use crate::model::data_store::DiskScratchDataset;
+use crate::model::graph::DiskGraph;
 use crate::model::{
-    generate_quantized_data, IndexConfiguration, GRAPH_SLACK_FACTOR, MAX_PQ_CHUNKS,
-    MAX_PQ_TRAINING_SET_SIZE,
+    aggregate_coords, generate_quantized_data, pq_dist_lookup, AlignedFileReader,
+    ArcConcurrentBoxedQueue, FixedChunkPQTable, IndexConfiguration, IndexMetadata, Neighbor,
+    NeighborPriorityQueue, SSDQueryScratch, ScratchStoreManager, TunedSearchParameters, Vertex,
+    GRAPH_SLACK_FACTOR, MAX_GRAPH_DEGREE, MAX_N_SECTOR_READS, MAX_PQ_CHUNKS,
+    MAX_PQ_TRAINING_SET_SIZE, SECTOR_LEN,
 };
-use crate::storage::DiskIndexStorage;
-use crate::utils::{convert_types_u64_u32, file_exists, set_rayon_num_threads};
+use crate::storage::{DiskGraphStorage, DiskIndexStorage, DiskLayoutMeta};
+use crate::utils::{file_exists, load_bin, set_rayon_num_threads, VectorElement};
 
 use super::ann_disk_index::ANNDiskIndex;
+use super::{NodeCache, QueryStats};
 
 pub const OVERHEAD_FACTOR: f64 = 1.1f64;
 
 pub const MAX_SAMPLE_POINTS_FOR_WARMUP: usize = 100_000;
 
-const WARMUP: bool = false;
+/// Default beamwidth of search when none is given
+pub const DEFAULT_BEAM_WIDTH: u32 = 2;
 
-macro_rules! round_up {
-    ($x:expr, $y:expr) => {
-        (($x / $y) + (if $x % $y != 0 { 1 } else { 0 })) * $y
-    };
-}
+/// Number of visited nodes reserved in each query scratch
+const VISITED_RESERVE: usize = 4096;
+
+/// Disk index data loaded for the query path
+struct DiskIndexSearchData<T> {
+    /// Metadata in the first sector of the disk index
+    disk_layout_meta: DiskLayoutMeta,
+
+    /// PQ pivot table
+    pq_table: FixedChunkPQTable,
 
-macro_rules! div_round_up {
-    ($x:expr, $y:expr) => {
-        ($x / $y) + (if $x % $y != 0 { 1 } else { 0 })
-    };
+    /// PQ compressed vectors: num_pts * num_pq_chunks
+    pq_compressed_data: Vec<u8>,
+
+    /// Number of PQ chunks
+    num_pq_chunks: usize,
+
+    /// Disk index reader shared by search threads
+    reader: Arc<AlignedFileReader>,
+
+    /// Nodes kept in memory, None until a cache list is loaded
+    node_cache: Option<NodeCache<T>>,
 }
 
 pub struct DiskIndex<T, const N: usize>
 where
+    T: Default + Copy,
     [T; N]: FullPrecisionDistance<T, N>,
 {
     /// Parameters for index construction
@@ -68,11 +73,24 @@ where
     configuration: IndexConfiguration,
 
     pub storage: DiskIndexStorage<T>,
+
+    /// Data for the query path
+    /// None until the index is loaded
+    search_data: Option<DiskIndexSearchData<T>>,
+
+    /// Query scratch queue
+    query_scratch_queue: ArcConcurrentBoxedQueue<SSDQueryScratch<T, N>>,
+
+    /// Follows and cancels the build
+    progress_observer: Option<Arc<dyn ProgressObserver>>,
+
+    /// Search parameters tuned for a target recall, read from the metadata on load
+    tuned_search: Option<TunedSearchParameters>,
 }
 
 impl<T, const N: usize> DiskIndex<T, N>
 where
-    T: Default + Copy + Sync + Send + Into<f32>,
+    T: VectorElement + Sync + Send,
     [T; N]: FullPrecisionDistance<T, N>,
 {
     pub fn new(
@@ -84,6 +102,10 @@ where
             disk_build_param,
             configuration,
             storage,
+            search_data: None,
+            query_scratch_queue: ArcConcurrentBoxedQueue::new(),
+            progress_observer: None,
+            tuned_search: None,
         }
     }
 
@@ -116,6 +138,7 @@ where
         }
 
         let mut index = InmemIndex::<T, N>::new(self.configuration.clone())?;
+        index.set_progress_observer(self.progress_observer.clone());
         index.build(data_path, num_points)?;
         index.save(inmem_index_path)?;
 
@@ -142,233 +165,340 @@ where
             )
         })
     }
-    /* disk part
-    /////////////////////////////////////////////////////////////////////////////////////////////////////////////
-
-    // load_aligned_bin functions START
-
-    //template <typename T, typename LabelT = uint32_t>
-    fn search_disk_index(metric: Metric, index_path_prefix: &str,
-                        result_output_prefix: &str, query_file: &str, gt_file: &str,
-                        num_threads: u32, recall_at: u32, beamwidth: u32,
-                        num_nodes_to_cache: u32, search_io_limit: u32,
-                        Lvec: &Vec<u32>, fail_if_recall_below: f32,
-                        query_filters: &Vec<&str>, use_reorder_data: bool/*  = false */) -> ANNResult<i32>
-    {
-        let warmup_query_file = format!("{index_path_prefix}_sample_data.bin");
-
-        // load query bin
-        //T *query = nullptr;
-        //let mut query = vec![];
-        let mut query; // = AlignedBoxWithSlice::new(capacity, alignment);
-        let mut query_num: usize;
-        let mut query_dim: usize;
-        let mut query_aligned_dim: usize;
-        load_aligned_bin<T>(query_file, &mut query, &mut query_num, &mut query_dim, &mut query_aligned_dim)?;
-
-        let filtered_search = false;
-        if (!query_filters.is_empty()) {
-            filtered_search = true;
-            if (query_filters.len() != 1 && query_filters.len() != query_num) {
-                // "Error. Mismatch in number of queries and size of query filters file
-                return -1; // To return -1 or some other error handling?
-            }
-        }
-
-        let mut gt_ids;
-        let mut gt_dists;
-        let mut calc_recall_flag = false;
-        if (gt_file != "null" && gt_file != "NULL" && file_exists(gt_file)) {
-            let mut gt_num;
-            let mut gt_dim;
-            load_truthset(gt_file, &mut gt_ids, &mut gt_dists, &mut gt_num, &mut gt_dim)?;
-            if (gt_num != query_num) {
-                // Error. Mismatch in number of queries and ground truth data
-            }
-            calc_recall_flag = true;
-        }
 
-        //std::shared_ptr<AlignedFileReader> reader = nullptr;
-        cfg_if! {
-            let reader: AlignedFileReader = if #[cfg(target_os = "windows")] {
-                reader.reset(new WindowsAlignedFileReader())
-            } else {
-                reader.reset(new LinuxAlignedFileReader())
-            };
-        };
+    /// Beam search the disk index for K nearest neighbors of query.
+    /// Up to beam_width closest unexpanded candidates (by PQ distance) are expanded per hop:
+    /// cached nodes are read from the node cache and the others from disk.
+    /// The expanded nodes are re-ranked with their full precision vectors.
+    /// Returns the number of distance comparisons.
+    /// # Arguments
+    /// * `query` - query vector, at least dim long
+    /// * `k_value` - number of neighbors to return
+    /// * `l_value` - search list size
+    /// * `beam_width` - max number of nodes read from disk per hop
+    /// * `io_limit` - max number of nodes read from disk for this query
+    /// * `indices` - output ids, at least k_value long
+    /// * `distances` - output distances, at least k_value long
+    /// * `stats` - optional per query statistics
+    /// * `expanded_nodes` - optional list to record the ids of the expanded nodes
+    #[allow(clippy::too_many_arguments)]
+    fn cached_beam_search(
+        &self,
+        query: &[T],
+        k_value: usize,
+        l_value: u32,
+        beam_width: u32,
+        io_limit: u32,
+        indices: &mut [u32],
+        distances: &mut [f32],
+        stats: Option<&mut QueryStats>,
+        mut expanded_nodes: Option<&mut Vec<u32>>,
+    ) -> ANNResult<u32> {
+        let timer = Instant::now();
+        let search_data = self.fetch_search_data()?;
+        let meta = &search_data.disk_layout_meta;
+        let dim = meta.dims as usize;
 
-        //std::unique_ptr<diskann::PQFlashIndex<T, LabelT>> _pFlashIndex(new diskann::PQFlashIndex<T, LabelT>(reader, metric));
-        let _pFlashIndex: PQFlashIndex<T, LabelT> = PQFlashIndex::new();
+        if k_value > l_value as usize {
+            return Err(ANNError::log_index_error(format!(
+                "Set L: {} to a value of at least K: {}",
+                l_value, k_value
+            )));
+        }
 
-        let res = _pFlashIndex.load(num_threads, index_path_prefix.c_str());
+        if beam_width == 0 || beam_width as usize > MAX_N_SECTOR_READS {
+            return Err(ANNError::log_index_error(format!(
+                "Beamwidth: {} should be in range [1, {}]",
+                beam_width, MAX_N_SECTOR_READS
+            )));
+        }
 
-        if (res != 0) {
-            return res;
+        if query.len() < dim {
+            return Err(ANNError::log_index_error(format!(
+                "Query dim {} is smaller than index dim {}",
+                query.len(),
+                dim
+            )));
         }
 
-        std::vector<uint32_t> node_list;
-        diskann::cout << "Caching " << num_nodes_to_cache << " nodes around medoid(s)" << std::endl;
-        _pFlashIndex.cache_bfs_levels(num_nodes_to_cache, node_list);
-        // if (num_nodes_to_cache > 0)
-        //     _pFlashIndex->generate_cache_list_from_sample_queries(warmup_query_file, 15, 6, num_nodes_to_cache,
-        //     num_threads, node_list);
-        _pFlashIndex.load_cache_list(node_list);
-        node_list.clear();
-        node_list.shrink_to_fit();
+        let mut scratch_manager =
+            ScratchStoreManager::new(self.query_scratch_queue.clone(), Duration::from_millis(10))?;
+        let scratch = scratch_manager.scratch_space().ok_or_else(|| {
+            ANNError::log_index_error(
+                "ScratchStoreManager doesn't have SSDQueryScratch instance available".to_string(),
+            )
+        })?;
+
+        scratch.best_candidates.reserve(l_value as usize);
+        scratch.best_candidates.set_capacity(l_value as usize);
+
+        scratch.query.fill(T::default());
+        scratch.query[..dim].copy_from_slice(&query[..dim]);
+        let query_vertex =
+            Vertex::<T, N>::try_from((&scratch.query[..], u32::MAX)).map_err(|err| {
+                ANNError::log_index_error(format!(
+                    "TryFromSliceError: failed to get Vertex for query, err={}",
+                    err
+                ))
+            })?;
+
+        // Pre-calculate the distances between query and the PQ centroids of each chunk
+        let pq_scratch = scratch.pq_scratch.as_mut().ok_or_else(|| {
+            ANNError::log_index_error("SSDQueryScratch doesn't have PQScratch".to_string())
+        })?;
+        pq_scratch.set(dim, query, 1.0);
+        search_data
+            .pq_table
+            .preprocess_query(&mut pq_scratch.rotated_query[..dim]);
+        let pq_dists = search_data
+            .pq_table
+            .populate_chunk_distances(&pq_scratch.rotated_query);
+        let compute_pq_dists = |ids: &[u32]| -> Vec<f32> {
+            let pq_coords = aggregate_coords(
+                ids,
+                &search_data.pq_compressed_data,
+                search_data.num_pq_chunks,
+            );
+            pq_dist_lookup(&pq_coords, ids.len(), search_data.num_pq_chunks, &pq_dists)
+        };
 
-        let warmup_l = 20u64;
-        let warmup_num = 0usize;
-        let warmup_dim = 0usize;
-        let warmup_aligned_dim = 0usize;
-        // T *warmup = nullptr;
-        let mut warmup; // = AlignedBoxWithSlice::new(capacity, alignment);
+        let medoid: u32 = meta.medoid.try_into()?;
+        scratch.id_scratch.insert(medoid);
+        scratch
+            .best_candidates
+            .insert(Neighbor::new(medoid, compute_pq_dists(&[medoid])[0]));
+        let mut cmps: u32 = 1;
+
+        search_data.reader.register_thread()?;
+        let mut graph = DiskGraph::new(
+            dim,
+            meta.num_nodes_per_sector,
+            meta.max_node_len,
+            (dim * mem::size_of::<T>()) as u64,
+            beam_width as usize,
+            DiskGraphStorage::new(search_data.reader.clone())?,
+        )?;
 
-        if (WARMUP)
-        {
-            if (file_exists(&warmup_query_file)) {
-                load_aligned_bin<T>(&warmup_query_file, &mut warmup, &mut warmup_num, &mut warmup_dim, &mut warmup_aligned_dim);
-            }
-            else
+        let mut num_ios: u32 = 0;
+        let mut num_hops: u32 = 0;
+        let mut num_cache_hits: u32 = 0;
+        let mut io_time = Duration::ZERO;
+        let mut frontier: Vec<u32> = Vec::with_capacity(beam_width as usize);
+        let node_cache = search_data.node_cache.as_ref();
+        let mut cached_frontier: Vec<(u32, &[T], &[u32])> = Vec::with_capacity(beam_width as usize);
+        let mut unvisited_neighbors: Vec<u32> = Vec::with_capacity(MAX_GRAPH_DEGREE);
+
+        while scratch.best_candidates.has_notvisited_node() && num_ios < io_limit {
+            frontier.clear();
+            cached_frontier.clear();
+            while scratch.best_candidates.has_notvisited_node()
+                && frontier.len() + cached_frontier.len() < beam_width as usize
             {
-                warmup_num = 150000.min(15000 * num_threads);
-                warmup_dim = query_dim;
-                warmup_aligned_dim = query_aligned_dim;
-                warmup = AlignedBoxWithSlice::new(warmup_num * warmup_aligned_dim, 8 * mem::sizeof::<T>()).unwrap();
+                let id = scratch.best_candidates.closest_notvisited().id;
+                match node_cache.and_then(|node_cache| node_cache.get(id)) {
+                    Some((vector, neighbors)) => cached_frontier.push((id, vector, neighbors)),
+                    None => frontier.push(id),
+                }
+            }
+            num_hops += 1;
+
+            // Expand the cached nodes without reading from disk
+            for &(id, vector, neighbors) in cached_frontier.iter() {
+                let vertex = Vertex::<T, N>::try_from((vector, id)).map_err(|err| {
+                    ANNError::log_index_error(format!(
+                        "TryFromSliceError: failed to get Vertex for cached node, err={}",
+                        err
+                    ))
+                })?;
+                let distance = query_vertex.compare(&vertex, self.configuration.dist_metric);
+                scratch.full_return_set.push(Neighbor::new(id, distance));
+
+                cmps += 1 + Self::visit_neighbors(
+                    neighbors,
+                    meta.num_pts,
+                    &mut scratch.id_scratch,
+                    &mut scratch.best_candidates,
+                    &mut unvisited_neighbors,
+                    &compute_pq_dists,
+                );
+
+                if let Some(expanded_nodes) = expanded_nodes.as_mut() {
+                    expanded_nodes.push(id);
+                }
+            }
+            num_cache_hits += cached_frontier.len() as u32;
 
+            if frontier.is_empty() {
+                continue;
+            }
 
-                let mut rng = StdRng::from_entropy();
-                let range = Uniform::new_inclusive(-128, 127);
+            graph.reset();
+            for &id in frontier.iter() {
+                graph.add_vertex(id);
+            }
 
-                for i in 0..warmup_num {
-                    let index_base = i * warmup_aligned_dim;
-                    for d in 0..warmup_dim {
-                        warmup[index_base + d] = rng.sample(&range).into();
-                    }
+            let io_timer = Instant::now();
+            graph.fetch_nodes()?;
+            io_time += io_timer.elapsed();
+            num_ios += frontier.len() as u32;
+
+            for item in &graph {
+                let (node_index, vertex_and_neighbors) = item?;
+
+                // Re-rank the expanded node with its full precision vector
+                let vertex = graph.copy_fp_vector_to_disk_scratch_dataset(
+                    node_index,
+                    &mut scratch.scratch_dataset,
+                )?;
+                let distance = query_vertex.compare(&vertex, self.configuration.dist_metric);
+                scratch
+                    .full_return_set
+                    .push(Neighbor::new(vertex.vertex_id(), distance));
+
+                cmps += 1 + Self::visit_neighbors(
+                    vertex_and_neighbors.get_neighbors(),
+                    meta.num_pts,
+                    &mut scratch.id_scratch,
+                    &mut scratch.best_candidates,
+                    &mut unvisited_neighbors,
+                    &compute_pq_dists,
+                );
+
+                if let Some(expanded_nodes) = expanded_nodes.as_mut() {
+                    expanded_nodes.push(vertex_and_neighbors.vertex_id);
                 }
             }
-
-            // Warming up index...
-            let mut warmup_result_ids_64 = vec![0u64; warmup_num];
-            let mut warmup_result_dists = vec![0f32; warmup_num];
-
-            (0..warmup_num).into_par_iter().for_each(|i| {
-                _pFlashIndex.cached_beam_search(warmup + (i * warmup_aligned_dim), 1, warmup_l,
-                                                warmup_result_ids_64.data() + (i * 1),
-                                                warmup_result_dists.data() + (i * 1), 4);
-            });
         }
 
-        let mut query_result_ids: Vec<Vec<u32>> = vec![vec![]; Lvec.len()];
-        let mut query_result_dists: Vec<Vec<f32>> = vec![vec![]; Lvec.len()];
-
-        let mut optimized_beamwidth = 2;
-        let best_recall = 0.0;
+        scratch.full_return_set.sort_unstable();
 
-        for test_id in 0..Lvec.len() {
-            let l = Lvec[test_id];
-            if l < recall_at {
-                // Ignoring search with `L` since it's smaller than `K`
-                continue;
+        let mut pos = 0;
+        for neighbor in scratch.full_return_set.iter() {
+            if pos == k_value {
+                break;
             }
 
-            if beamwidth <= 0 {
-                // Tuning beamwidth..
-                optimized_beamwidth =
-                    optimize_beamwidth(_pFlashIndex, warmup, warmup_num, warmup_aligned_dim, l, optimized_beamwidth);
-            }
-            else {
-                optimized_beamwidth = beamwidth;
+            // Frozen points are never returned
+            if meta.num_frozen_pts > 0 && neighbor.id as u64 == meta.frozen_loc {
+                continue;
             }
 
-            query_result_ids[test_id].resize(recall_at * query_num);
-            query_result_dists[test_id].resize(recall_at * query_num);
-
-            let mut stats = vec![QueryStats::default(); query_num];
-            let mut query_result_ids_64 = vec![0u64; recall_at * query_num];
-
-            (0..query_num).into_par_iter().for_each(|i| {
-                if !filtered_search {
-                    _pFlashIndex.cached_beam_search(query + (i * query_aligned_dim), recall_at, l,
-                                                    query_result_ids_64.data() + (i * recall_at),
-                                                    query_result_dists[test_id].data() + (i * recall_at),
-                                                    optimized_beamwidth, use_reorder_data, stats + i);
-                }
-                else
-                {
-                    let label_for_search: LabelT;
-                    if (query_filters.size() == 1)
-                    { // one label for all queries
-                        label_for_search = _pFlashIndex.get_converted_label(query_filters[0]);
-                    }
-                    else
-                    { // one label for each query
-                        label_for_search = _pFlashIndex.get_converted_label(query_filters[i]);
-                    }
-                    _pFlashIndex.cached_beam_search(
-                        query + (i * query_aligned_dim), recall_at, l, query_result_ids_64.data() + (i * recall_at),
-                        query_result_dists[test_id].data() + (i * recall_at), optimized_beamwidth, true, label_for_search,
-                        use_reorder_data, stats + i);
-                }
+            indices[pos] = neighbor.id;
+            distances[pos] = neighbor.distance;
+            pos += 1;
+        }
 
-            });
+        if pos < k_value {
+            log::debug!(
+                "Found fewer than K elements for query! Found: {} but K: {}",
+                pos,
+                k_value
+            );
+        }
 
-            query_result_ids[test_id] = convert_types_u64_u32(&query_result_ids_64, query_num, recall_at);
+        if let Some(stats) = stats {
+            let total_time = timer.elapsed();
+            stats.total_us = total_time.as_micros() as f32;
+            stats.io_us = io_time.as_micros() as f32;
+            stats.cpu_us = total_time.saturating_sub(io_time).as_micros() as f32;
+            stats.n_4k = num_ios;
+            stats.n_ios = num_ios;
+            stats.read_size = num_ios * SECTOR_LEN as u32;
+            stats.n_cmps = cmps;
+            stats.n_hops = num_hops;
+            stats.n_cache_hits = num_cache_hits;
+        }
 
-            let mean_latency = get_mean_stats(&stats, |stat: &QueryStats| { stat.total_us });
-            let latency_999 = get_percentile_stats(&stats, 0.999, |stat: &QueryStats| { stat.total_us });
-            let mean_ios = get_mean_stats(&stats, |stat: &QueryStats| { stat.n_ios });
-            let mean_cpuus = get_mean_stats(&stats, |stat: &QueryStats| { stat.cpu_us });
-            let mean_io_us = get_mean_stats(&stats, |stat: &QueryStats| { stat.io_us });
+        Ok(cmps)
+    }
 
-            let mut recall = 0f64;
-            if (calc_recall_flag)
-            {
-                recall = calculate_recall(query_num, gt_ids, gt_dists, gt_dim,
-                                                &query_result_ids[test_id], recall_at, recall_at);
-                best_recall = recall.max(best_recall);
+    /// Insert the unvisited neighbors of an expanded node into best_candidates by PQ distance.
+    /// Returns the number of PQ distance comparisons.
+    fn visit_neighbors<F>(
+        neighbors: &[u32],
+        num_pts: u64,
+        id_scratch: &mut HashSet<u32>,
+        best_candidates: &mut NeighborPriorityQueue,
+        unvisited_neighbors: &mut Vec<u32>,
+        compute_pq_dists: &F,
+    ) -> u32
+    where
+        F: Fn(&[u32]) -> Vec<f32>,
+    {
+        unvisited_neighbors.clear();
+        for &neighbor_id in neighbors.iter() {
+            if (neighbor_id as u64) < num_pts && id_scratch.insert(neighbor_id) {
+                unvisited_neighbors.push(neighbor_id);
             }
         }
 
-        // Done searching. Now saving results
-        let mut test_id = 0u64;
-        for &l in Lvec {
-            if (l < recall_at) {
-                continue;
+        if !unvisited_neighbors.is_empty() {
+            let neighbor_dists = compute_pq_dists(unvisited_neighbors);
+            for (&id, &distance) in unvisited_neighbors.iter().zip(neighbor_dists.iter()) {
+                best_candidates.insert(Neighbor::new(id, distance));
             }
+        }
 
-            let cur_result_path = format!("{result_output_prefix}_{l}_idx_uint32.bin");
-            diskann::save_bin<uint32_t>(&cur_result_path, &query_result_ids[test_id], query_num, recall_at);
+        unvisited_neighbors.len() as u32
+    }
+
+    /// Read nodes from the disk index, at most MAX_N_SECTOR_READS at a time,
+    /// and pass each node's id, aligned fp vector and neighbors to visit
+    fn read_nodes<F>(&self, ids: &[u32], mut visit: F) -> ANNResult<()>
+    where
+        F: FnMut(u32, &[T], &[u32]) -> ANNResult<()>,
+    {
+        let search_data = self.fetch_search_data()?;
+        let meta = &search_data.disk_layout_meta;
+        let dim = meta.dims as usize;
 
-            let cur_result_path = format!("{result_output_prefix}_{l}_dists_float.bin");
-            diskann::save_bin<float>(cur_result_path, &query_result_dists[test_id], query_num, recall_at);
-            test_id += 1;
+        if let Some(&id) = ids.iter().find(|&&id| id as u64 >= meta.num_pts) {
+            return Err(ANNError::log_index_error(format!(
+                "Node id {} is out of range, the disk index has {} points",
+                id, meta.num_pts
+            )));
         }
 
-        Ok(if best_recall >= fail_if_recall_below {0} else{-1})
-    }
-    */
-}
+        search_data.reader.register_thread()?;
+        let mut graph = DiskGraph::new(
+            dim,
+            meta.num_nodes_per_sector,
+            meta.max_node_len,
+            (dim * mem::size_of::<T>()) as u64,
+            MAX_N_SECTOR_READS,
+            DiskGraphStorage::new(search_data.reader.clone())?,
+        )?;
+        let mut scratch_dataset = DiskScratchDataset::<T, N>::new()?;
 
-impl<T, const N: usize> ANNDiskIndex<T> for DiskIndex<T, N>
-where
-    T: Default + Copy + Sync + Send + Into<f32>,
-    [T; N]: FullPrecisionDistance<T, N>,
-{
-    fn build(&mut self, codebook_prefix: &str) -> ANNResult<()> {
-        if self.configuration.index_write_parameter.num_threads > 0 {
-            set_rayon_num_threads(self.configuration.index_write_parameter.num_threads);
+        for batch in ids.chunks(MAX_N_SECTOR_READS) {
+            graph.reset();
+            for &id in batch.iter() {
+                graph.add_vertex(id);
+            }
+            graph.fetch_nodes()?;
+
+            for item in &graph {
+                let (node_index, vertex_and_neighbors) = item?;
+                let vertex = graph
+                    .copy_fp_vector_to_disk_scratch_dataset(node_index, &mut scratch_dataset)?;
+                visit(
+                    vertex.vertex_id(),
+                    vertex.vector(),
+                    vertex_and_neighbors.get_neighbors(),
+                )?;
+            }
         }
 
-        println!(
-            "Starting index build: R={} L={} Query RAM budget={} Indexing RAM budget={} T={}",
-            self.configuration.index_write_parameter.max_degree,
-            self.configuration.index_write_parameter.search_list_size,
-            self.fetch_disk_build_param()?.search_ram_limit(),
-            self.fetch_disk_build_param()?.index_build_ram_limit(),
-            self.configuration.index_write_parameter.num_threads
-        );
+        Ok(())
+    }
 
-        let mut logger = DiskIndexBuildLogger::new(DiskIndexConstructionCheckpoint::PqConstruction);
+    /// Run the build stages that can be cancelled, up to the in-memory graph
+    fn build_cancellable_stages(
+        &mut self,
+        codebook_prefix: &str,
+        logger: &mut DiskIndexBuildLogger,
+    ) -> ANNResult<()> {
+        self.build_stage_completed(0.0)?;
 
         // PQ memory consumption = PQ pivots + PQ compressed table
         // PQ pivots: dim * num_centroids * sizeof::<T>()
@@ -392,9 +522,10 @@ where
             num_pq_chunks
         };
 
-        println!(
+        log::info!(
             "Compressing {}-dimensional data into {} bytes per vector.",
-            dim, num_pq_chunks
+            dim,
+            num_pq_chunks
         );
 
         // TODO: Decouple PQ from file access
@@ -403,8 +534,11 @@ where
             num_pq_chunks,
             codebook_prefix,
             self.storage.get_pq_storage(),
+            self.configuration.index_write_parameter.seed,
         )?;
         logger.log_checkpoint(DiskIndexConstructionCheckpoint::InmemIndexBuild)?;
+        // The in-memory graph takes most of the build, its own progress is reported as Build
+        self.build_stage_completed(20.0)?;
 
         // TODO: Decouple index from file access
         let inmem_index_path = self.storage.index_path_prefix().clone() + "_mem.index";
@@ -414,10 +548,63 @@ where
             inmem_index_path.as_str(),
         )?;
         logger.log_checkpoint(DiskIndexConstructionCheckpoint::DiskLayout)?;
+        self.build_stage_completed(80.0)
+    }
+
+    /// Report the build progress at the end of a stage, and stop there once it is cancelled
+    fn build_stage_completed(&self, percentage: f32) -> ANNResult<()> {
+        if let Some(observer) = &self.progress_observer {
+            observer.on_progress(IndexOperation::DiskBuild, percentage);
+            if observer.is_cancelled() {
+                return Err(ANNError::log_cancelled(
+                    IndexOperation::DiskBuild.to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn fetch_search_data(&self) -> ANNResult<&DiskIndexSearchData<T>> {
+        self.search_data.as_ref().ok_or_else(|| {
+            ANNError::log_index_error("Disk index is not loaded for search".to_string())
+        })
+    }
+}
+
+impl<T, const N: usize> ANNDiskIndex<T> for DiskIndex<T, N>
+where
+    T: VectorElement + Sync + Send,
+    [T; N]: FullPrecisionDistance<T, N>,
+{
+    fn build(&mut self, codebook_prefix: &str) -> ANNResult<()> {
+        if self.configuration.index_write_parameter.num_threads > 0 {
+            set_rayon_num_threads(self.configuration.index_write_parameter.num_threads);
+        }
+
+        log::info!(
+            "Starting index build: R={} L={} Query RAM budget={} Indexing RAM budget={} T={}",
+            self.configuration.index_write_parameter.max_degree,
+            self.configuration.index_write_parameter.search_list_size,
+            self.fetch_disk_build_param()?.search_ram_limit(),
+            self.fetch_disk_build_param()?.index_build_ram_limit(),
+            self.configuration.index_write_parameter.num_threads
+        );
+
+        let mut logger = DiskIndexBuildLogger::new(DiskIndexConstructionCheckpoint::PqConstruction);
+        // Every cancelled exit of the stages removes what they wrote so far
+        if let Err(err) = self.build_cancellable_stages(codebook_prefix, &mut logger) {
+            if let ANNError::Cancelled { .. } = err {
+                self.storage.index_build_cleanup()?;
+            }
+            return Err(err);
+        }
 
         self.storage.create_disk_layout()?;
         logger.log_checkpoint(DiskIndexConstructionCheckpoint::None)?;
 
+        let num_points = self.configuration.max_points;
         let ten_percent_points = ((num_points as f64) * 0.1_f64).ceil();
         let num_sample_points = if ten_percent_points > (MAX_SAMPLE_POINTS_FOR_WARMUP as f64) {
             MAX_SAMPLE_POINTS_FOR_WARMUP as f64
@@ -425,9 +612,216 @@ where
             ten_percent_points
         };
         let sample_sampling_rate = num_sample_points / (num_points as f64);
-        self.storage.gen_query_warmup_data(sample_sampling_rate)?;
+        self.storage.gen_query_warmup_data(
+            sample_sampling_rate,
+            self.configuration.index_write_parameter.seed,
+        )?;
 
         self.storage.index_build_cleanup()?;
+        IndexMetadata::new::<T>(self.configuration.clone()).save(&self.storage.metadata_file())?;
+        if let Some(observer) = &self.progress_observer {
+            observer.on_progress(IndexOperation::DiskBuild, 100.0);
+        }
+
+        Ok(())
+    }
+
+    fn set_progress_observer(&mut self, observer: Option<Arc<dyn ProgressObserver>>) {
+        self.progress_observer = observer;
+    }
+
+    fn load(&mut self, num_threads: u32) -> ANNResult<()> {
+        let disk_layout_meta = self.storage.load_disk_layout_meta()?;
+        if disk_layout_meta.dims as usize != self.configuration.dim
+            || disk_layout_meta.dims as usize > N
+        {
+            return Err(ANNError::log_index_error(format!(
+                "Disk index dim {} doesn't match the configured dim {} (aligned dim {})",
+                disk_layout_meta.dims, self.configuration.dim, N
+            )));
+        }
+
+        let (pq_compressed_data, num_pq_pts, num_pq_chunks) =
+            self.storage.load_pq_compressed_data()?;
+        if num_pq_pts as u64 != disk_layout_meta.num_pts {
+            return Err(ANNError::log_index_error(format!(
+                "PQ compressed table has {} points but the disk index has {} points",
+                num_pq_pts, disk_layout_meta.num_pts
+            )));
+        }
+
+        let pq_pivot_data = self.storage.load_pq_pivots_bin(&num_pq_chunks)?;
+        let pq_table = FixedChunkPQTable::new(
+            pq_pivot_data.dim,
+            num_pq_chunks,
+            pq_pivot_data.pq_table,
+            pq_pivot_data.centroids,
+            pq_pivot_data.chunk_offsets,
+        );
+
+        let reader = Arc::new(AlignedFileReader::new(
+            self.storage.disk_index_file().as_str(),
+        )?);
+
+        log::info!(
+            "Loaded disk index: #pts={} dim={} medoid={} max_node_len={}B nodes_per_sector={} pq_chunks={}",
+            disk_layout_meta.num_pts,
+            disk_layout_meta.dims,
+            disk_layout_meta.medoid,
+            disk_layout_meta.max_node_len,
+            disk_layout_meta.num_nodes_per_sector,
+            num_pq_chunks
+        );
+
+        self.query_scratch_queue.empty_queue()?;
+        self.query_scratch_queue.reserve(5 + num_threads as usize)?;
+        for _ in 0..(5 + num_threads) {
+            let scratch = Box::new(SSDQueryScratch::<T, N>::new(
+                VISITED_RESERVE,
+                self.configuration.index_write_parameter.search_list_size as usize,
+                true,
+            )?);
+            self.query_scratch_queue.push(scratch)?;
+        }
+
+        let metadata_file = self.storage.metadata_file();
+        if file_exists(&metadata_file) {
+            let metadata = IndexMetadata::load(&metadata_file)?;
+            metadata.check_data_type::<T>()?;
+            self.tuned_search = metadata.tuned_search;
+        }
+
+        self.search_data = Some(DiskIndexSearchData {
+            disk_layout_meta,
+            pq_table,
+            pq_compressed_data,
+            num_pq_chunks,
+            reader,
+            node_cache: None,
+        });
+
+        Ok(())
+    }
+
+    fn cache_bfs_levels(&self, num_nodes_to_cache: usize) -> ANNResult<Vec<u32>> {
+        let meta = &self.fetch_search_data()?.disk_layout_meta;
+        let num_nodes_to_cache = num_nodes_to_cache.min(meta.num_pts as usize);
+        if num_nodes_to_cache == 0 {
+            return Ok(Vec::new());
+        }
+
+        let medoid: u32 = meta.medoid.try_into()?;
+        let mut node_set: HashSet<u32> = HashSet::with_capacity(num_nodes_to_cache);
+        let mut node_list: Vec<u32> = Vec::with_capacity(num_nodes_to_cache);
+        node_set.insert(medoid);
+        node_list.push(medoid);
+
+        // Walk the graph level by level from the medoid until enough nodes are found
+        let mut cur_level = vec![medoid];
+        let mut num_levels = 1;
+        while node_list.len() < num_nodes_to_cache && !cur_level.is_empty() {
+            let mut next_level = Vec::new();
+            self.read_nodes(&cur_level, |_, _, neighbors| {
+                for &neighbor_id in neighbors.iter() {
+                    if node_list.len() + next_level.len() >= num_nodes_to_cache {
+                        break;
+                    }
+
+                    if (neighbor_id as u64) < meta.num_pts && node_set.insert(neighbor_id) {
+                        next_level.push(neighbor_id);
+                    }
+                }
+                Ok(())
+            })?;
+
+            node_list.extend_from_slice(&next_level);
+            cur_level = next_level;
+            num_levels += 1;
+        }
+
+        log::info!(
+            "Found {} nodes to cache in {} BFS levels",
+            node_list.len(),
+            num_levels
+        );
+
+        Ok(node_list)
+    }
+
+    fn generate_cache_list_from_sample_queries(
+        &self,
+        sample_file: &str,
+        l_value: u32,
+        beam_width: u32,
+        num_nodes_to_cache: usize,
+    ) -> ANNResult<Vec<u32>> {
+        let meta = &self.fetch_search_data()?.disk_layout_meta;
+        let (sample_data, num_samples, sample_dim) = load_bin::<T>(sample_file, 0)?;
+        if sample_dim != meta.dims as usize {
+            return Err(ANNError::log_index_error(format!(
+                "Sample query dim {} doesn't match the disk index dim {}",
+                sample_dim, meta.dims
+            )));
+        }
+
+        log::info!(
+            "Replaying {} sample queries with L={} W={} to generate the cache list",
+            num_samples,
+            l_value,
+            beam_width
+        );
+
+        let expanded_nodes = sample_data
+            .par_chunks(sample_dim)
+            .map(|query| {
+                let mut indices = [0u32; 1];
+                let mut distances = [0f32; 1];
+                let mut expanded_nodes = Vec::new();
+                self.cached_beam_search(
+                    query,
+                    1,
+                    l_value,
+                    beam_width,
+                    u32::MAX,
+                    &mut indices,
+                    &mut distances,
+                    None,
+                    Some(&mut expanded_nodes),
+                )?;
+                Ok(expanded_nodes)
+            })
+            .collect::<ANNResult<Vec<Vec<u32>>>>()?;
+
+        let mut visit_counts = vec![0u32; meta.num_pts as usize];
+        for &id in expanded_nodes.iter().flatten() {
+            visit_counts[id as usize] += 1;
+        }
+
+        // Most visited nodes first, ties broken by id to keep the list deterministic
+        let mut node_list: Vec<u32> = (0..meta.num_pts as u32)
+            .filter(|&id| visit_counts[id as usize] > 0)
+            .collect();
+        node_list.sort_by(|&a, &b| {
+            visit_counts[b as usize]
+                .cmp(&visit_counts[a as usize])
+                .then(a.cmp(&b))
+        });
+        node_list.truncate(num_nodes_to_cache);
+
+        Ok(node_list)
+    }
+
+    fn load_cache_list(&mut self, node_list: &[u32]) -> ANNResult<()> {
+        let mut node_cache = NodeCache::new(N, node_list.len())?;
+        self.read_nodes(node_list, |id, vector, neighbors| {
+            node_cache.insert(id, vector, neighbors)
+        })?;
+
+        log::info!("Loaded {} nodes into the node cache", node_cache.len());
+
+        if let Some(search_data) = self.search_data.as_mut() {
+            search_data.node_cache = Some(node_cache);
+        }
 
         Ok(())
     }
@@ -439,7 +833,8 @@ where
         l_value: u32,
         indices: &mut [u32],
     ) -> ANNResult<u32> {
-        unimplemented!()
+        let mut distances = vec![0f32; k_value];
+        self.search_with_distance(query, k_value, l_value, indices, &mut distances)
     }
 
     fn search_with_distance(
@@ -450,6 +845,303 @@ where
         indices: &mut [u32],
         distances: &mut [f32],
     ) -> ANNResult<u32> {
-        unimplemented!()
+        let beam_width = self
+            .tuned_search
+            .and_then(|tuned_search| tuned_search.beam_width)
+            .unwrap_or(DEFAULT_BEAM_WIDTH);
+        self.beam_search(
+            query,
+            k_value,
+            l_value,
+            beam_width,
+            u32::MAX,
+            indices,
+            distances,
+            None,
+        )
+    }
+
+    fn search_list_size(&self) -> u32 {
+        match self.tuned_search {
+            Some(tuned_search) => tuned_search.l_value,
+            None => self.configuration.index_write_parameter.search_list_size,
+        }
+    }
+
+    fn tuned_search_parameters(&self) -> Option<TunedSearchParameters> {
+        self.tuned_search
+    }
+
+    fn set_tuned_search_parameters(&mut self, tuned_search: Option<TunedSearchParameters>) {
+        self.tuned_search = tuned_search;
+    }
+
+    fn beam_search(
+        &self,
+        query: &[T],
+        k_value: usize,
+        l_value: u32,
+        beam_width: u32,
+        io_limit: u32,
+        indices: &mut [u32],
+        distances: &mut [f32],
+        stats: Option<&mut QueryStats>,
+    ) -> ANNResult<u32> {
+        let start = Instant::now();
+        let cmps = self.cached_beam_search(
+            query, k_value, l_value, beam_width, io_limit, indices, distances, stats, None,
+        )?;
+        metrics().record_search(start.elapsed(), cmps);
+
+        Ok(cmps)
+    }
+}
+
+#[cfg(test)]
+mod disk_index_test {
+    use std::fs;
+
+    use vector::Metric;
+
+    use crate::instrumentation::CancellationToken;
+    use crate::model::IndexWriteParametersBuilder;
+    use crate::test_utils::get_test_file_path;
+    use crate::utils::{file_exists, load_bin};
+
+    use super::*;
+
+    const TEST_DATA_FILE: &str = "tests/data/siftsmall_learn_256pts.fbin";
+    const INDEX_PATH_PREFIX: &str = "tests/data/disk_index_build_test_siftsmall_learn_256pts";
+    const SEARCH_INDEX_PATH_PREFIX: &str =
+        "tests/data/disk_index_search_test_siftsmall_learn_256pts";
+    const CANCEL_INDEX_PATH_PREFIX: &str =
+        "tests/data/disk_index_cancel_test_siftsmall_learn_256pts";
+    const TRUTH_DISK_LAYOUT: &str =
+        "tests/data/truth_disk_index_siftsmall_learn_256pts_R4_L50_A1.2_disk.index";
+    const R: u32 = 4;
+    const L: u32 = 50;
+
+    fn create_config() -> IndexConfiguration {
+        let index_write_parameters = IndexWriteParametersBuilder::new(L, R)
+            .with_saturate_graph(true)
+            .with_num_threads(1)
+            .with_num_rounds(1)
+            .build();
+        IndexConfiguration::new(
+            Metric::L2,
+            128,
+            128,
+            256,
+            false,
+            0,
+            false,
+            0,
+            1f32,
+            index_write_parameters,
+        )
+    }
+
+    fn build_disk_index(index_path_prefix: &str) -> DiskIndex<f32, 128> {
+        let storage = DiskIndexStorage::<f32>::new(
+            get_test_file_path(TEST_DATA_FILE),
+            get_test_file_path(index_path_prefix),
+        )
+        .unwrap();
+        let mut index = DiskIndex::<f32, 128>::new(
+            Some(DiskIndexBuildParameters::new(1.0, 1.0).unwrap()),
+            create_config(),
+            storage,
+        );
+
+        index.build("").unwrap();
+        index
+    }
+
+    fn generated_files(storage: &DiskIndexStorage<f32>) -> [String; 6] {
+        [
+            storage.disk_index_file(),
+            storage.metadata_file(),
+            storage.pq_pivot_file(),
+            storage.compressed_pq_pivot_file(),
+            storage.warmup_query_file(),
+            storage.index_path_prefix().clone() + "_sample_ids.bin",
+        ]
+    }
+
+    #[test]
+    fn build_disk_index_test() {
+        let index = build_disk_index(INDEX_PATH_PREFIX);
+
+        let storage = &index.storage;
+        let generated_files = generated_files(storage);
+        for file in generated_files.iter() {
+            assert!(file_exists(file), "{} was not generated", file);
+        }
+        assert!(!file_exists(
+            &(storage.index_path_prefix().clone() + "_mem.index")
+        ));
+
+        let disk_layout = fs::read(storage.disk_index_file()).unwrap();
+        let truth_disk_layout = fs::read(get_test_file_path(TRUTH_DISK_LAYOUT)).unwrap();
+        assert!(disk_layout == truth_disk_layout);
+
+        for file in generated_files.iter() {
+            fs::remove_file(file).expect("Failed to delete file");
+        }
+    }
+
+    #[test]
+    fn cancelled_build_cleanup_test() {
+        let storage = DiskIndexStorage::<f32>::new(
+            get_test_file_path(TEST_DATA_FILE),
+            get_test_file_path(CANCEL_INDEX_PATH_PREFIX),
+        )
+        .unwrap();
+        let mut index = DiskIndex::<f32, 128>::new(
+            Some(DiskIndexBuildParameters::new(1.0, 1.0).unwrap()),
+            create_config(),
+            storage,
+        );
+        let token = CancellationToken::new();
+        token.cancel();
+        index.set_progress_observer(Some(Arc::new(token)));
+
+        let result = index.build("");
+
+        assert!(matches!(result, Err(ANNError::Cancelled { .. })));
+        let storage = &index.storage;
+        for file in generated_files(storage) {
+            assert!(!file_exists(&file), "{} was left behind", file);
+        }
+        assert!(!file_exists(
+            &(storage.index_path_prefix().clone() + "_mem.index")
+        ));
+    }
+
+    #[test]
+    fn beam_search_test() {
+        let generated_files = generated_files(&build_disk_index(SEARCH_INDEX_PATH_PREFIX).storage);
+
+        let storage =
+            DiskIndexStorage::<f32>::new_for_search(get_test_file_path(SEARCH_INDEX_PATH_PREFIX))
+                .unwrap();
+        let mut index = DiskIndex::<f32, 128>::new(None, create_config(), storage);
+        let mut indices = [0u32; 1];
+        let mut distances = [0f32; 1];
+        assert!(index
+            .beam_search(
+                &[0f32; 128],
+                1,
+                L,
+                4,
+                u32::MAX,
+                &mut indices,
+                &mut distances,
+                None
+            )
+            .is_err());
+
+        index.load(1).unwrap();
+
+        // Every base point should find itself
+        let (data, num_pts, dim) = load_bin::<f32>(&get_test_file_path(TEST_DATA_FILE), 0).unwrap();
+        let mut num_found = 0;
+        let mut results = Vec::with_capacity(num_pts);
+        let mut num_ios = 0;
+        for (id, query) in data.chunks(dim).enumerate() {
+            let mut stats = QueryStats::default();
+            index
+                .beam_search(
+                    query,
+                    1,
+                    L,
+                    4,
+                    u32::MAX,
+                    &mut indices,
+                    &mut distances,
+                    Some(&mut stats),
+                )
+                .unwrap();
+            assert!(stats.n_ios > 0);
+            assert_eq!(stats.n_ios, stats.n_4k);
+            assert_eq!(stats.n_cache_hits, 0);
+            results.push(indices[0]);
+            num_ios += stats.n_ios;
+            if indices[0] == id as u32 {
+                assert_eq!(distances[0], 0f32);
+                num_found += 1;
+            }
+        }
+        assert!(num_found * 100 >= num_pts * 90, "found {}", num_found);
+
+        // Only the medoid is a candidate before the first hop
+        let mut stats = QueryStats::default();
+        index
+            .beam_search(
+                &data[..dim],
+                1,
+                L,
+                4,
+                1,
+                &mut indices,
+                &mut distances,
+                Some(&mut stats),
+            )
+            .unwrap();
+        assert_eq!(stats.n_ios, 1);
+        assert_eq!(stats.n_hops, 1);
+
+        assert!(index
+            .beam_search(&data[..dim], 1, L, 0, 1, &mut indices, &mut distances, None)
+            .is_err());
+        assert!(index
+            .search_with_distance(&data[..dim], 2, 1, &mut [0u32; 2], &mut [0f32; 2])
+            .is_err());
+
+        // BFS cache starts from the medoid and has no duplicates
+        let medoid = index.storage.load_disk_layout_meta().unwrap().medoid as u32;
+        let node_list = index.cache_bfs_levels(64).unwrap();
+        assert_eq!(node_list.len(), 64);
+        assert_eq!(node_list[0], medoid);
+        assert_eq!(node_list.iter().collect::<HashSet<_>>().len(), 64);
+        let reachable = index.cache_bfs_levels(1000).unwrap().len();
+        assert!(reachable > 64 && reachable <= num_pts);
+
+        // Cached nodes are expanded without IO and don't change the results
+        index.load_cache_list(&node_list).unwrap();
+        let mut num_ios_with_cache = 0;
+        for (id, query) in data.chunks(dim).enumerate() {
+            let mut stats = QueryStats::default();
+            index
+                .beam_search(
+                    query,
+                    1,
+                    L,
+                    4,
+                    u32::MAX,
+                    &mut indices,
+                    &mut distances,
+                    Some(&mut stats),
+                )
+                .unwrap();
+            assert!(stats.n_cache_hits > 0);
+            assert_eq!(indices[0], results[id]);
+            num_ios_with_cache += stats.n_ios;
+        }
+        assert!(num_ios_with_cache < num_ios);
+
+        // Sample based cache list only has visited nodes
+        let node_list = index
+            .generate_cache_list_from_sample_queries(&index.storage.warmup_query_file(), 15, 6, 32)
+            .unwrap();
+        assert!(!node_list.is_empty() && node_list.len() <= 32);
+        assert!(node_list.contains(&medoid));
+        index.load_cache_list(&node_list).unwrap();
+
+        assert!(index.load_cache_list(&[num_pts as u32]).is_err());
+
+        for file in generated_files.iter() {
+            fs::remove_file(file).expect("Failed to delete file");
+        }
     }
 }
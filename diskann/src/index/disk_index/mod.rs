@@ -4,8 +4,13 @@
  */
 #[allow(clippy::module_inception)]
 mod disk_index;
-pub use disk_index::DiskIndex;
+pub use disk_index::{DiskIndex, DEFAULT_BEAM_WIDTH};
 
 pub mod ann_disk_index;
 // pub mod utils;
-// pub mod percentile_stats;
+
+pub mod percentile_stats;
+pub use percentile_stats::*;
+
+pub mod node_cache;
+pub use node_cache::*;
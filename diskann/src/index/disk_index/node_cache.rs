@@ -0,0 +1,140 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+#![warn(missing_docs)]
+
+//! In-RAM cache of disk index nodes
+
+use std::mem;
+
+use hashbrown::HashMap;
+
+use crate::common::{ANNError, ANNResult, AlignedBoxWithSlice};
+
+/// Full precision vectors and neighbors of disk index nodes kept in memory.
+/// Beam search expands cached nodes without issuing sector reads.
+#[derive(Debug)]
+pub struct NodeCache<T> {
+    /// Aligned dim of the cached fp vectors
+    aligned_dim: usize,
+
+    /// Max number of cached nodes
+    capacity: usize,
+
+    /// Aligned fp vectors of the cached nodes: aligned_dim per slot
+    coords: AlignedBoxWithSlice<T>,
+
+    /// Neighbors of the cached nodes stored back to back
+    neighbors: Vec<u32>,
+
+    /// Neighbors of slot i are neighbors[neighbor_offsets[i]..neighbor_offsets[i + 1]]
+    neighbor_offsets: Vec<usize>,
+
+    /// Vertex id to slot
+    slots: HashMap<u32, usize>,
+}
+
+impl<T: Copy> NodeCache<T> {
+    /// Create an empty cache with room for capacity nodes
+    pub fn new(aligned_dim: usize, capacity: usize) -> ANNResult<Self> {
+        let mut neighbor_offsets = Vec::with_capacity(capacity + 1);
+        neighbor_offsets.push(0);
+
+        Ok(Self {
+            aligned_dim,
+            capacity,
+            coords: AlignedBoxWithSlice::new(
+                capacity.max(1) * aligned_dim,
+                mem::size_of::<T>() * 16,
+            )?,
+            neighbors: Vec::new(),
+            neighbor_offsets,
+            slots: HashMap::with_capacity(capacity),
+        })
+    }
+
+    /// Cache the aligned fp vector and neighbors of a node, nodes already cached are kept as is
+    pub fn insert(&mut self, id: u32, vector: &[T], neighbors: &[u32]) -> ANNResult<()> {
+        if vector.len() != self.aligned_dim {
+            return Err(ANNError::log_index_error(format!(
+                "NodeCache: vector len {} of node {} doesn't match aligned dim {}",
+                vector.len(),
+                id,
+                self.aligned_dim
+            )));
+        }
+
+        if self.slots.contains_key(&id) {
+            return Ok(());
+        }
+
+        let slot = self.slots.len();
+        if slot == self.capacity {
+            return Err(ANNError::log_index_error(format!(
+                "NodeCache: unable to cache node {}, the cache is full with {} nodes",
+                id, self.capacity
+            )));
+        }
+
+        self.slots.insert(id, slot);
+        self.coords[slot * self.aligned_dim..(slot + 1) * self.aligned_dim].copy_from_slice(vector);
+        self.neighbors.extend_from_slice(neighbors);
+        self.neighbor_offsets.push(self.neighbors.len());
+
+        Ok(())
+    }
+
+    /// Get the aligned fp vector and neighbors of a cached node
+    #[inline]
+    pub fn get(&self, id: u32) -> Option<(&[T], &[u32])> {
+        self.slots.get(&id).map(|&slot| {
+            (
+                &self.coords[slot * self.aligned_dim..(slot + 1) * self.aligned_dim],
+                &self.neighbors[self.neighbor_offsets[slot]..self.neighbor_offsets[slot + 1]],
+            )
+        })
+    }
+
+    /// Number of cached nodes
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Whether no node is cached
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod node_cache_test {
+    use super::*;
+
+    #[test]
+    fn insert_and_get_test() {
+        let mut cache = NodeCache::<f32>::new(2, 3).unwrap();
+        assert!(cache.is_empty());
+        assert!(cache.get(7).is_none());
+
+        cache.insert(7, &[1.0, 2.0], &[1, 2, 3]).unwrap();
+        cache.insert(3, &[3.0, 4.0], &[]).unwrap();
+        cache.insert(5, &[5.0, 6.0], &[7]).unwrap();
+
+        // Nodes already cached are not replaced
+        cache.insert(7, &[0.0, 0.0], &[0]).unwrap();
+
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.get(7), Some((&[1.0, 2.0][..], &[1, 2, 3][..])));
+        assert_eq!(cache.get(3), Some((&[3.0, 4.0][..], &[][..])));
+        assert_eq!(cache.get(5), Some((&[5.0, 6.0][..], &[7][..])));
+        assert!(cache.get(1).is_none());
+
+        assert!(cache.insert(1, &[1.0], &[]).is_err());
+
+        // No room for a fourth node
+        assert!(cache.insert(1, &[1.0, 2.0], &[]).is_err());
+    }
+}
@@ -1,46 +1,105 @@
-#[derive(Debug, Default)]
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+#![warn(missing_docs)]
+
+//! Per query statistics of disk index search
+
+/// Statistics collected while searching one query
+#[derive(Debug, Default, Clone, Copy)]
 pub struct QueryStats {
-    pub total_us: f32, // total time to process query in micros
-    pub io_us: f32,    // total time spent in IO
-    pub cpu_us: f32,   // total time spent in CPU
-
-    n_4k: u32,         // # of 4kB reads
-    n_8k: u32,         // # of 8kB reads
-    n_12k: u32,        // # of 12kB reads
-    pub n_ios: u32,        // total # of IOs issued
-    read_size: u32,    // total # of bytes read
-    n_cmps_saved: u32, // # cmps saved
-    n_cmps: u32,       // # cmps
-    n_cache_hits: u32, // # cache_hits
-    n_hops: u32,       // # search hops
+    /// total time to process query in micros
+    pub total_us: f32,
+
+    /// total time spent in IO
+    pub io_us: f32,
+
+    /// total time spent in CPU
+    pub cpu_us: f32,
+
+    /// # of 4kB reads
+    pub n_4k: u32,
+
+    /// total # of IOs issued
+    pub n_ios: u32,
+
+    /// total # of bytes read
+    pub read_size: u32,
+
+    /// # cmps
+    pub n_cmps: u32,
+
+    /// # search hops
+    pub n_hops: u32,
+
+    /// # nodes expanded from the node cache
+    pub n_cache_hits: u32,
 }
 
-#[inline]
-pub fn get_percentile_stats<T: Default>(
+/// Get the value at the given percentile of a member of QueryStats
+pub fn get_percentile_stats<T>(
     stats: &[QueryStats],
     percentile: f32,
     member_fn: impl Fn(&QueryStats) -> T,
-) -> T {
-    let len = stats.len();
-    let mut vals = vec![T::default(); len];
-
-    for i in 0..len {
-        vals[i] = member_fn(&stats[i]);
+) -> T
+where
+    T: Default + PartialOrd,
+{
+    if stats.is_empty() {
+        return T::default();
     }
 
-    vals.sort();
-    vals[percentile * len]
+    let mut vals: Vec<T> = stats.iter().map(member_fn).collect();
+    vals.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+    let index = ((percentile * vals.len() as f32) as usize).min(vals.len() - 1);
+    vals.swap_remove(index)
 }
 
-#[inline]
+/// Get the mean value of a member of QueryStats
 pub fn get_mean_stats<T>(stats: &[QueryStats], member_fn: impl Fn(&QueryStats) -> T) -> f64
 where
     T: Into<f64>,
 {
-    let mut avg = 0.0;
-    for e in stats {
-        avg += member_fn(e).into();
+    if stats.is_empty() {
+        return 0.0;
+    }
+
+    let sum: f64 = stats.iter().map(|stat| member_fn(stat).into()).sum();
+    sum / stats.len() as f64
+}
+
+#[cfg(test)]
+mod percentile_stats_test {
+    use super::*;
+
+    fn create_stats() -> Vec<QueryStats> {
+        (1..=1000)
+            .map(|i| QueryStats {
+                total_us: i as f32,
+                n_ios: i % 10,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn get_percentile_stats_test() {
+        let stats = create_stats();
+
+        assert_eq!(get_percentile_stats(&stats, 0.5, |s| s.total_us), 501.0);
+        assert_eq!(get_percentile_stats(&stats, 0.999, |s| s.total_us), 1000.0);
+        assert_eq!(get_percentile_stats(&stats, 1.0, |s| s.total_us), 1000.0);
+        assert_eq!(get_percentile_stats(&[], 0.999, |s| s.total_us), 0.0);
     }
 
-    return avg / stats.len() as f64;
+    #[test]
+    fn get_mean_stats_test() {
+        let stats = create_stats();
+
+        assert_eq!(get_mean_stats(&stats, |s| s.total_us), 500.5);
+        assert_eq!(get_mean_stats(&stats, |s| s.n_ios), 4.5);
+        assert_eq!(get_mean_stats(&[], |s| s.n_ios), 0.0);
+    }
 }
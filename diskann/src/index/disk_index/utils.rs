@@ -9,57 +9,6 @@ pub fn file_exists(path: &str) -> bool {
     Path::new(path).exists()
 }
 
-pub fn load_aligned_bin_impl(
-    reader: impl Read,
-    actual_file_size: u64,
-    data: &mut AlignedBoxWithSlice,
-    npts: &mut usize,
-    dim: &mut usize,
-    rounded_dim: &mut usize,
-) -> ANNResult<()> {
-    let tsize = mem::sizeof::<T>;
-    *npts = reader.read_i32::<NativeEndian>()? as usize;
-    *dim = reader.read_i32::<NativeEndian>()? as usize;
-    *rounded_dim = round_up(*dim, 8);
-    let npts = *npts;
-    let dim = *dim;
-    let rounded_dim = *rounded_dim;
-
-    let expected_actual_file_size = (npts * dim * tsize + 2 * mem::size_of::<u32>()) as u64;
-    if actual_file_size != expected_actual_file_size {
-        return Err(ANNError::FileSizeMismatch {
-            message: "Error. File size mismatch".to_string(),
-            actual_size,
-            expected_actual_file_size,
-        });
-    }
-
-    //let allocSize = npts * rounded_dim * mem::sizeof::<T>();
-    let alloc_size = npts * rounded_dim;
-    // allocating aligned memory of
-    *data = AlignedBoxWithSlice::<T>::new(alloc_size, 8 * tsize);
-    let data = data.as_mut_slice();
-
-    // done. Copying data to mem_aligned buffer...
-    for i in 0..npts {
-        let buf = data[i * rounded_dim..i * rounded_dim + dim].as_mut_ptr() as *mut u8;
-        unsafe { std::slice::from_raw_parts_mut(buf, dim * tsize) }
-        reader.read(buf)?;
-    }
-}
-
-pub fn load_aligned_bin(
-    bin_file: &str,
-    data: &mut AlignedBoxWithSlice,
-    npts: &mut usize,
-    dim: &mut usize,
-    rounded_dim: &mut usize,
-) -> ANNResult<()> {
-    let reader = File::open(bin_file)?;
-    let fsize = reader.metadata()?.file_size();
-    Self::load_aligned_bin_impl(reader, fsize, data, npts, dim, rounded_dim)
-}
-
 pub fn load_truthset(
     bin_file: &str,
     ids: &mut Vec<u32>,
@@ -6,26 +6,30 @@
 
 //! ANN in-memory index abstraction
 
+use std::sync::Arc;
+
 use vector::FullPrecisionDistance;
 
 use crate::common::{ANNError, ANNResult};
+use crate::instrumentation::ProgressObserver;
 use crate::model::{
     vertex::{DIM_104, DIM_128, DIM_256, DIM_512},
-    IndexConfiguration,
+    IndexConfiguration, IndexMetadata, Payload, PayloadSchema, TunedSearchParameters,
 };
+use crate::utils::{load_metadata_from_file, VectorElement};
 
-use super::InmemIndex;
+use super::{GraphDiagnostics, GraphStats, InmemIndex};
 
 /// ANN inmem-index abstraction for custom <T, N>
 pub trait ANNInmemIndex<T>: Sync + Send
 where
-    T: Default + Copy + Sync + Send + Into<f32>,
+    T: VectorElement + Sync + Send,
 {
     /// Build index
     fn build(&mut self, filename: &str, num_points_to_load: usize) -> ANNResult<()>;
 
     /// insert index
-    fn build_vector(&mut self, points: &Vec<Vec<T>>) -> ANNResult<()>;
+    fn build_vector(&mut self, points: &[Vec<T>]) -> ANNResult<()>;
 
     /// Save index
     fn save(&mut self, filename: &str) -> ANNResult<()>;
@@ -38,7 +42,7 @@ where
 
     /// insert index
     /// Return (id_start, id_len)
-    fn insert_vector(&mut self, points: &Vec<Vec<T>>) -> ANNResult<(usize, usize)>;
+    fn insert_vector(&mut self, points: &[Vec<T>]) -> ANNResult<(usize, usize)>;
 
     /// Search the index for K nearest neighbors of query using given L value, for benchmarking purposes
     fn search(
@@ -50,6 +54,7 @@ where
     ) -> ANNResult<u32>;
 
     /// Search the index for K nearest neighbors of query using given L value, for benchmarking purposes
+    /// Payloads of the neighbors are copied to payloads if given, None for those without one
     fn search_with_distance(
         &self,
         query: &[T],
@@ -57,14 +62,88 @@ where
         l_value: u32,
         indices: &mut [u32],
         distances: &mut [f32],
+        payloads: Option<&mut [Option<Payload>]>,
     ) -> ANNResult<u32>;
 
+    /// Search the index for K nearest neighbors of query among the points whose id, as returned
+    /// by search, passes predicate. The points failing it are still traversed like the deleted
+    /// ones but never returned. When few points pass, they are compared with the query directly.
+    /// selectivity is the fraction of points expected to pass, from estimate_selectivity or the
+    /// caller, and is estimated on each call when None. Returns the number of comparisons and of
+    /// result slots filled.
+    #[allow(clippy::too_many_arguments)]
+    fn search_with_predicate(
+        &self,
+        query: &[T],
+        k_value: usize,
+        l_value: u32,
+        predicate: &dyn Fn(u32) -> bool,
+        selectivity: Option<f32>,
+        indices: &mut [u32],
+        distances: &mut [f32],
+    ) -> ANNResult<(u32, usize)>;
+
+    /// Fraction of the points whose id passes predicate, estimated from a sample. Compute it
+    /// once per predicate and pass it to search_with_predicate for every query.
+    fn estimate_selectivity(&self, predicate: &dyn Fn(u32) -> bool) -> f32;
+
     /// Soft deletes the nodes with the ids in the given array.
     fn soft_delete(
         &mut self,
         vertex_ids_to_delete: Vec<u32>,
         num_points_to_delete: usize,
     ) -> ANNResult<()>;
+
+    /// Compare the query with every point that is not deleted, for the exact K nearest
+    /// neighbors with the ids search returns. Serves as ground truth when tuning L.
+    fn exact_search(
+        &self,
+        query: &[T],
+        k_value: usize,
+        indices: &mut [u32],
+        distances: &mut [f32],
+    ) -> ANNResult<u32>;
+
+    /// Search list size searches default to: the tuned L if there is one, else the
+    /// search list size of the configuration
+    fn search_list_size(&self) -> u32;
+
+    /// Search parameters tuned for a target recall, saved and loaded with the index
+    fn tuned_search_parameters(&self) -> Option<TunedSearchParameters>;
+
+    /// Set the search parameters tuned for a target recall, they are saved with the index
+    fn set_tuned_search_parameters(&mut self, tuned_search: Option<TunedSearchParameters>);
+
+    /// Degree statistics of the graph
+    fn graph_stats(&self) -> ANNResult<GraphStats>;
+
+    /// Structural health checks of the graph
+    fn diagnostics(&self) -> ANNResult<GraphDiagnostics>;
+
+    /// Reconnect the points unreachable from the entry points of the graph
+    /// Return the number of points that are reachable again
+    fn repair_connectivity(&mut self) -> ANNResult<usize>;
+
+    /// Drop the deleted points from the dataset and the graph and renumber the others densely.
+    /// Return the new id of each id given to soft_delete, None for the deleted ones.
+    fn compact(&mut self) -> ANNResult<Vec<Option<u32>>>;
+
+    /// Report the progress of build, build_vector, insert and insert_vector to observer.
+    /// Once it is cancelled they stop with ANNError::Cancelled: the index must then be built
+    /// again, while an insert keeps the points it linked and repair_connectivity links the others.
+    fn set_progress_observer(&mut self, observer: Option<Arc<dyn ProgressObserver>>);
+
+    /// Attach an empty payload store, of byte payloads or of records of the given schema.
+    /// It is saved with the index to `{filename}.payload` and loaded back with it.
+    fn enable_payloads(&mut self, schema: Option<PayloadSchema>) -> ANNResult<()>;
+
+    /// insert index with one payload per point
+    /// Return (id_start, id_len)
+    fn insert_vector_with_payloads(
+        &mut self,
+        points: &[Vec<T>],
+        payloads: Vec<Payload>,
+    ) -> ANNResult<(usize, usize)>;
 }
 
 /// Create Index<T, N> based on configuration
@@ -72,7 +151,7 @@ pub fn create_inmem_index<'a, T>(
     config: IndexConfiguration,
 ) -> ANNResult<Box<dyn ANNInmemIndex<T> + 'a>>
 where
-    T: Default + Copy + Sync + Send + Into<f32> + 'a,
+    T: VectorElement + Sync + Send + 'a,
     [T; DIM_104]: FullPrecisionDistance<T, DIM_104>,
     [T; DIM_128]: FullPrecisionDistance<T, DIM_128>,
     [T; DIM_256]: FullPrecisionDistance<T, DIM_256>,
@@ -102,11 +181,37 @@ where
     }
 }
 
+/// Load the index saved to index_path with the configuration of its metadata file.
+/// Fails with DataTypeMismatch when its vectors are not of type T.
+pub fn load_inmem_index<'a, T>(index_path: &str) -> ANNResult<Box<dyn ANNInmemIndex<T> + 'a>>
+where
+    T: VectorElement + Sync + Send + 'a,
+    [T; DIM_104]: FullPrecisionDistance<T, DIM_104>,
+    [T; DIM_128]: FullPrecisionDistance<T, DIM_128>,
+    [T; DIM_256]: FullPrecisionDistance<T, DIM_256>,
+    [T; DIM_512]: FullPrecisionDistance<T, DIM_512>,
+{
+    let metadata = IndexMetadata::load(&IndexMetadata::file_name(index_path))?;
+    metadata.check_data_type::<T>()?;
+
+    // The index grows to the saved points, frozen points included, as it loads them
+    let mut config = metadata.configuration;
+    config.max_points = 0;
+    let (num_points, _) = load_metadata_from_file(&format!("{}.data", index_path))?;
+
+    let mut index = create_inmem_index::<T>(config)?;
+    index.load_with_enhance(index_path, num_points)?;
+
+    Ok(index)
+}
+
 #[cfg(test)]
 mod dataset_test {
-    use vector::Metric;
+    use vector::{Half, Metric};
 
     use crate::model::configuration::index_write_parameters::IndexWriteParametersBuilder;
+    use crate::test_utils::get_test_file_path;
+    use crate::utils::round_up;
 
     use super::*;
 
@@ -134,4 +239,62 @@ mod dataset_test {
         let mut index = create_inmem_index::<f32>(config).unwrap();
         index.build("fake_file", 100).unwrap();
     }
+
+    #[test]
+    fn load_inmem_index_test() {
+        const TEST_DATA_FILE: &str = "tests/data/siftsmall_learn_256pts.fbin";
+        const SAVE_PATH: &str = "tests/data/load_inmem_index_test_index";
+
+        let (data_num, dim) =
+            load_metadata_from_file(get_test_file_path(TEST_DATA_FILE).as_str()).unwrap();
+        let index_write_parameters = IndexWriteParametersBuilder::new(50, 8)
+            .with_alpha(1.2)
+            .with_num_threads(1)
+            .with_seed(3)
+            .build();
+        let config = IndexConfiguration::new(
+            Metric::Cosine,
+            dim,
+            round_up(dim as u64, 8_u64) as usize,
+            data_num,
+            false,
+            0,
+            false,
+            1,
+            1f32,
+            index_write_parameters,
+        );
+        let mut index = create_inmem_index::<f32>(config).unwrap();
+        index
+            .build(get_test_file_path(TEST_DATA_FILE).as_str(), data_num)
+            .unwrap();
+        index.save(SAVE_PATH).unwrap();
+
+        let loaded = load_inmem_index::<f32>(SAVE_PATH).unwrap();
+        let mismatch = load_inmem_index::<Half>(SAVE_PATH).err();
+        let metadata = IndexMetadata::load(&IndexMetadata::file_name(SAVE_PATH)).unwrap();
+        for suffix in ["", ".data", ".metadata"] {
+            std::fs::remove_file(format!("{}{}", SAVE_PATH, suffix)).unwrap();
+        }
+
+        assert_eq!(metadata.data_type, "float");
+        assert_eq!(metadata.configuration.dist_metric, Metric::Cosine);
+        assert_eq!(metadata.configuration.num_frozen_pts, 1);
+        assert_eq!(
+            metadata.configuration.index_write_parameter,
+            index_write_parameters
+        );
+        assert!(matches!(
+            mismatch,
+            Some(ANNError::DataTypeMismatch { expected, found })
+                if expected == "f16" && found == "float"
+        ));
+
+        let query: Vec<f32> = (0..dim).map(|i| (i % 7) as f32).collect();
+        let (mut indices, mut loaded_indices) = (vec![0u32; 10], vec![0u32; 10]);
+        index.search(&query, 10, 50, &mut indices).unwrap();
+        loaded.search(&query, 10, 50, &mut loaded_indices).unwrap();
+        assert_eq!(indices, loaded_indices);
+        assert_eq!(loaded.graph_stats().unwrap(), index.graph_stats().unwrap());
+    }
 }
@@ -0,0 +1,329 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+#![warn(missing_docs)]
+
+//! Structural health checks of an in-memory graph
+
+use std::collections::VecDeque;
+
+use hashbrown::HashSet;
+use serde::{Deserialize, Serialize};
+use vector::FullPrecisionDistance;
+
+use crate::common::{ANNError, ANNResult};
+
+use super::InmemIndex;
+
+/// Limits past which a mutated graph is better rebuilt than searched
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RebuildPolicy {
+    /// Largest fraction of soft deleted points
+    pub max_deleted_ratio: f32,
+
+    /// Largest fraction of live points that search cannot reach
+    pub max_unreachable_ratio: f32,
+
+    /// Largest fraction of edges pointing at deleted or missing points
+    pub max_dangling_edge_ratio: f32,
+}
+
+impl Default for RebuildPolicy {
+    fn default() -> Self {
+        Self {
+            max_deleted_ratio: 0.2,
+            max_unreachable_ratio: 0.01,
+            max_dangling_edge_ratio: 0.1,
+        }
+    }
+}
+
+/// Structure of the graph of an in-memory index
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GraphDiagnostics {
+    /// Number of active points, the deleted ones included
+    pub num_points: usize,
+
+    /// Number of frozen points
+    pub num_frozen_points: usize,
+
+    /// Number of edges of the active and frozen points
+    pub num_edges: usize,
+
+    /// Number of points per out-degree, indexed by degree
+    pub out_degree_histogram: Vec<usize>,
+
+    /// Number of points per in-degree, indexed by degree
+    pub in_degree_histogram: Vec<usize>,
+
+    /// Points that are not deleted but that a search from the start and frozen points
+    /// never reaches
+    pub unreachable: Vec<u32>,
+
+    /// Edges pointing at soft deleted points
+    pub num_dangling_edges: usize,
+
+    /// Edges pointing at ids that are neither active nor frozen points
+    pub num_invalid_edges: usize,
+
+    /// Edges from a point to itself
+    pub num_self_loops: usize,
+
+    /// Edges repeated in the neighbor list of a point
+    pub num_duplicate_edges: usize,
+
+    /// Number of soft deleted points
+    pub num_deleted: usize,
+}
+
+impl GraphDiagnostics {
+    /// Fraction of the active points that are soft deleted
+    pub fn deleted_ratio(&self) -> f32 {
+        ratio(self.num_deleted, self.num_points)
+    }
+
+    /// Fraction of the live points that are unreachable
+    pub fn unreachable_ratio(&self) -> f32 {
+        ratio(
+            self.unreachable.len(),
+            self.num_points.saturating_sub(self.num_deleted),
+        )
+    }
+
+    /// Fraction of the edges pointing at deleted or missing points
+    pub fn dangling_edge_ratio(&self) -> f32 {
+        ratio(
+            self.num_dangling_edges + self.num_invalid_edges,
+            self.num_edges,
+        )
+    }
+
+    /// Why the graph should be rebuilt under policy, empty when it is healthy
+    pub fn rebuild_reasons(&self, policy: &RebuildPolicy) -> Vec<String> {
+        let mut reasons = Vec::new();
+
+        if self.deleted_ratio() > policy.max_deleted_ratio {
+            reasons.push(format!(
+                "{:.1}% of the points are deleted, more than {:.1}%",
+                100.0 * self.deleted_ratio(),
+                100.0 * policy.max_deleted_ratio
+            ));
+        }
+
+        if self.unreachable_ratio() > policy.max_unreachable_ratio {
+            reasons.push(format!(
+                "{:.1}% of the live points are unreachable, more than {:.1}%",
+                100.0 * self.unreachable_ratio(),
+                100.0 * policy.max_unreachable_ratio
+            ));
+        }
+
+        if self.dangling_edge_ratio() > policy.max_dangling_edge_ratio {
+            reasons.push(format!(
+                "{:.1}% of the edges point at deleted or missing points, more than {:.1}%",
+                100.0 * self.dangling_edge_ratio(),
+                100.0 * policy.max_dangling_edge_ratio
+            ));
+        }
+
+        if self.num_invalid_edges > 0 || self.num_self_loops > 0 {
+            reasons.push(format!(
+                "the graph has {} edges to missing points and {} self-loops",
+                self.num_invalid_edges, self.num_self_loops
+            ));
+        }
+
+        reasons
+    }
+}
+
+fn ratio(count: usize, total: usize) -> f32 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f32 / total as f32
+    }
+}
+
+fn count_into(histogram: &mut Vec<usize>, value: usize) {
+    if histogram.len() <= value {
+        histogram.resize(value + 1, 0);
+    }
+    histogram[value] += 1;
+}
+
+impl<T, const N: usize> InmemIndex<T, N>
+where
+    T: Default + Copy + Sync + Send + Into<f32>,
+    [T; N]: FullPrecisionDistance<T, N>,
+{
+    /// Check the structure of the graph: degrees, reachability and bad edges
+    pub fn diagnostics(&self) -> ANNResult<GraphDiagnostics> {
+        let frozen_start = self.configuration.max_points;
+        let frozen_end = frozen_start + self.configuration.num_frozen_pts;
+        let is_point =
+            |id: usize| id < self.num_active_pts || (frozen_start..frozen_end).contains(&id);
+        let points: Vec<u32> = (0..self.num_active_pts)
+            .chain(frozen_start..frozen_end)
+            .map(|id| id as u32)
+            .collect();
+
+        let delete_set = self.delete_set.read().map_err(|_| {
+            ANNError::log_lock_poison_error(
+                "Failed to acquire delete_set lock, cannot check the graph".to_string(),
+            )
+        })?;
+
+        let mut diagnostics = GraphDiagnostics {
+            num_points: self.num_active_pts,
+            num_frozen_points: self.configuration.num_frozen_pts,
+            num_deleted: delete_set.len(),
+            ..Default::default()
+        };
+
+        let mut in_degrees = vec![0usize; self.final_graph.size()];
+        let mut seen = HashSet::new();
+        for &id in &points {
+            let vertex = self.final_graph.read_vertex_and_neighbors(id)?;
+            let neighbors = vertex.get_neighbors();
+            count_into(&mut diagnostics.out_degree_histogram, neighbors.len());
+            diagnostics.num_edges += neighbors.len();
+
+            seen.clear();
+            for &neighbor in neighbors.iter() {
+                if !seen.insert(neighbor) {
+                    diagnostics.num_duplicate_edges += 1;
+                }
+                if neighbor == id {
+                    diagnostics.num_self_loops += 1;
+                }
+
+                if !is_point(neighbor as usize) {
+                    diagnostics.num_invalid_edges += 1;
+                } else {
+                    in_degrees[neighbor as usize] += 1;
+                    if delete_set.contains(&neighbor) {
+                        diagnostics.num_dangling_edges += 1;
+                    }
+                }
+            }
+        }
+
+        for &id in &points {
+            count_into(
+                &mut diagnostics.in_degree_histogram,
+                in_degrees[id as usize],
+            );
+        }
+
+        // Search enters the graph from the start point and the frozen points, and walks
+        // through the deleted points as well
+        let mut visited = vec![false; self.final_graph.size()];
+        let mut queue: VecDeque<u32> = VecDeque::new();
+        let entry_points =
+            std::iter::once(self.start).chain(frozen_start as u32..frozen_end as u32);
+        for entry_point in entry_points {
+            if is_point(entry_point as usize) && !visited[entry_point as usize] {
+                visited[entry_point as usize] = true;
+                queue.push_back(entry_point);
+            }
+        }
+
+        while let Some(id) = queue.pop_front() {
+            let vertex = self.final_graph.read_vertex_and_neighbors(id)?;
+            for &neighbor in vertex.get_neighbors().iter() {
+                if is_point(neighbor as usize) && !visited[neighbor as usize] {
+                    visited[neighbor as usize] = true;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+
+        diagnostics.unreachable = (0..self.num_active_pts as u32)
+            .filter(|&id| !visited[id as usize] && !delete_set.contains(&id))
+            .collect();
+
+        Ok(diagnostics)
+    }
+}
+
+#[cfg(test)]
+mod graph_diagnostics_test {
+    use vector::Metric;
+
+    use super::*;
+    use crate::index::ANNInmemIndex;
+    use crate::model::{
+        configuration::index_write_parameters::IndexWriteParametersBuilder, graph::AdjacencyList,
+        vertex::DIM_128, IndexConfiguration,
+    };
+    use crate::test_utils::get_test_file_path;
+    use crate::utils::{load_metadata_from_file, round_up};
+
+    const TEST_DATA_FILE: &str = "tests/data/siftsmall_learn_256pts.fbin";
+
+    #[test]
+    fn diagnostics_test() {
+        let (data_num, dim) =
+            load_metadata_from_file(get_test_file_path(TEST_DATA_FILE).as_str()).unwrap();
+        let index_write_parameters = IndexWriteParametersBuilder::new(50, 8)
+            .with_alpha(1.2)
+            .with_num_threads(1)
+            .build();
+        let config = IndexConfiguration::new(
+            Metric::L2,
+            dim,
+            round_up(dim as u64, 16_u64) as usize,
+            data_num,
+            false,
+            0,
+            false,
+            0,
+            1.0f32,
+            index_write_parameters,
+        );
+        let mut index: InmemIndex<f32, DIM_128> = InmemIndex::new(config).unwrap();
+        index
+            .build(get_test_file_path(TEST_DATA_FILE).as_str(), data_num)
+            .unwrap();
+
+        let healthy = index.diagnostics().unwrap();
+        assert_eq!(healthy.num_points, data_num);
+        assert_eq!(healthy.out_degree_histogram.iter().sum::<usize>(), data_num);
+        assert_eq!(healthy.in_degree_histogram.iter().sum::<usize>(), data_num);
+        assert_eq!(healthy.num_self_loops + healthy.num_duplicate_edges, 0);
+        assert_eq!(healthy.num_dangling_edges + healthy.num_invalid_edges, 0);
+        assert!(healthy.unreachable.is_empty());
+        assert!(healthy
+            .rebuild_reasons(&RebuildPolicy::default())
+            .is_empty());
+
+        // Cut every edge to an isolated point, and add a self-loop and a duplicate edge
+        let isolated = if index.start == 10 { 11 } else { 10 };
+        for id in 0..data_num as u32 {
+            let mut vertex = index.final_graph.write_vertex_and_neighbors(id).unwrap();
+            let mut neighbors = vertex.get_neighbors().to_vec();
+            neighbors.retain(|&neighbor| neighbor != isolated);
+            if id == 20 {
+                neighbors.push(20);
+                neighbors.push(neighbors[0]);
+            }
+            vertex.set_neighbors(AdjacencyList::from(neighbors));
+        }
+        let deleted: Vec<u32> = (100..160).collect();
+        index.soft_delete(deleted, 60).unwrap();
+
+        let diagnostics = index.diagnostics().unwrap();
+        assert_eq!(diagnostics.unreachable, vec![isolated]);
+        assert_eq!(diagnostics.num_self_loops, 1);
+        assert_eq!(diagnostics.num_duplicate_edges, 1);
+        assert_eq!(diagnostics.num_deleted, 60);
+        assert!(diagnostics.num_dangling_edges > 0);
+        assert!(diagnostics.in_degree_histogram[0] >= 1);
+        assert_eq!(
+            diagnostics.rebuild_reasons(&RebuildPolicy::default()).len(),
+            3
+        );
+    }
+}
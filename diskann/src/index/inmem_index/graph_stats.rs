@@ -0,0 +1,60 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+#![warn(missing_docs)]
+
+//! Degree statistics of an in-memory graph
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Degree statistics of the active points of an in-memory index
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct GraphStats {
+    /// Number of active points
+    pub num_points: usize,
+
+    /// Largest out-degree
+    pub max_degree: usize,
+
+    /// Smallest out-degree
+    pub min_degree: usize,
+
+    /// Average out-degree
+    pub avg_degree: f32,
+
+    /// Number of points with fewer than 2 out-neighbors
+    pub num_low_degree: usize,
+
+    /// Number of soft deleted points
+    pub num_deleted: usize,
+}
+
+impl GraphStats {
+    /// Fraction of the active points that are soft deleted
+    pub fn deleted_ratio(&self) -> f32 {
+        if self.num_points == 0 {
+            0.0
+        } else {
+            self.num_deleted as f32 / self.num_points as f32
+        }
+    }
+}
+
+impl fmt::Display for GraphStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "points={} max_degree={} avg_degree={:.3} min_degree={} low_degree_points={} deleted_points={} deleted_ratio={:.3}",
+            self.num_points,
+            self.max_degree,
+            self.avg_degree,
+            self.min_degree,
+            self.num_low_degree,
+            self.num_deleted,
+            self.deleted_ratio()
+        )
+    }
+}
@@ -3,29 +3,43 @@
  * Licensed under the MIT license.
  */
 use std::cmp;
-use std::sync::RwLock;
-use std::time::Duration;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
 use hashbrown::hash_set::Entry::*;
 use hashbrown::HashSet;
+use rand::seq::SliceRandom;
 use vector::FullPrecisionDistance;
 
 use crate::common::{ANNError, ANNResult};
-use crate::index::ANNInmemIndex;
-use crate::instrumentation::IndexLogger;
+use crate::index::{ANNInmemIndex, GraphDiagnostics, GraphStats};
+use crate::instrumentation::metrics::Histogram;
+use crate::instrumentation::{metrics, IndexLogger, IndexOperation, ProgressObserver};
 use crate::model::graph::AdjacencyList;
 use crate::model::{
-    ArcConcurrentBoxedQueue, InMemQueryScratch, InMemoryGraph, IndexConfiguration, InmemDataset,
-    Neighbor, ScratchStoreManager, Vertex,
+    ArcConcurrentBoxedQueue, InMemQueryScratch, InMemoryGraph, IndexConfiguration, IndexMetadata,
+    InmemDataset, Neighbor, NeighborPriorityQueue, Payload, PayloadSchema, PayloadStore,
+    ScratchStoreManager, TunedSearchParameters, Vertex,
 };
 
 use crate::utils::file_util::{file_exists, load_metadata_from_file};
 use crate::utils::rayon_util::execute_with_rayon;
-use crate::utils::{set_rayon_num_threads, Timer};
+use crate::utils::{
+    calc_distance, create_rng, k_means_clustering, set_rayon_num_threads, Timer, VectorElement,
+};
 
 /// Used for warmup dataset, or it will cannot build graph and crash
 pub const INIT_WARMUP_DATA_LEN: u32 = 5;
 
+/// Max number of points sampled to run k-means for the frozen points
+const MAX_FROZEN_POINTS_KMEANS_SAMPLE: usize = 100_000;
+
+/// Number of Lloyd's iterations run for the frozen points
+const FROZEN_POINTS_KMEANS_REPS: usize = 12;
+
+/// Max number of points the predicate of a filtered search is sampled on
+const PREDICATE_SAMPLE_SIZE: usize = 1000;
+
 /// In-memory Index
 pub struct InmemIndex<T, const N: usize>
 where
@@ -55,6 +69,15 @@ where
     query_scratch_queue: ArcConcurrentBoxedQueue<InMemQueryScratch<T, N>>,
 
     pub delete_set: RwLock<HashSet<u32>>,
+
+    /// Payloads of the vertices, None until payloads are enabled
+    pub payload_store: Option<PayloadStore>,
+
+    /// Follows and cancels the builds and inserts
+    progress_observer: Option<Arc<dyn ProgressObserver>>,
+
+    /// Search parameters tuned for a target recall, saved in the metadata
+    tuned_search: Option<TunedSearchParameters>,
 }
 
 impl<T, const N: usize> InmemIndex<T, N>
@@ -70,6 +93,12 @@ where
             config.max_points = 1;
         }
 
+        // Frozen points can be requested by either the configuration or the write parameters
+        config.num_frozen_pts = cmp::max(
+            config.num_frozen_pts,
+            config.index_write_parameter.num_frozen_points as usize,
+        );
+
         let total_internal_points = config.max_points + config.num_frozen_pts;
 
         if config.use_pq_dist {
@@ -94,6 +123,9 @@ where
             num_active_pts: 0,
             query_scratch_queue,
             delete_set,
+            payload_store: None,
+            progress_observer: None,
+            tuned_search: None,
         })
     }
 
@@ -108,7 +140,7 @@ where
     }
 
     fn build_with_data_populated(&mut self) -> ANNResult<()> {
-        println!(
+        log::info!(
             "Starting index build with {} points...",
             self.num_active_pts
         );
@@ -126,11 +158,171 @@ where
             )?;
         }
 
-        // TODO: generate_frozen_point()
+        self.generate_frozen_points()?;
 
         self.link()?;
 
-        self.print_stats()?;
+        self.update_graph_stats()?;
+
+        Ok(())
+    }
+
+    /// Fill the frozen points at [max_points, max_points + num_frozen_pts) with copies of
+    /// the points closest to k-means centers of the dataset, so that search enters the graph
+    /// from every dense region. Frozen points are never returned by search.
+    fn generate_frozen_points(&mut self) -> ANNResult<()> {
+        let num_frozen_pts = self.configuration.num_frozen_pts;
+        if num_frozen_pts == 0 {
+            return Ok(());
+        }
+
+        let source_ids = self.select_frozen_point_sources()?;
+        log::debug!(
+            "Generating {} frozen points from points {:?}",
+            num_frozen_pts,
+            source_ids
+        );
+
+        let frozen_start = self.configuration.max_points;
+        self.dataset
+            .data
+            .ensure_capacity((frozen_start + num_frozen_pts) * N)?;
+        for i in 0..num_frozen_pts {
+            let source = source_ids[i % source_ids.len()] as usize;
+            self.dataset
+                .data
+                .copy_within(source * N..(source + 1) * N, (frozen_start + i) * N);
+        }
+
+        Ok(())
+    }
+
+    /// Run k-means with num_frozen_pts centers on a sample of the active points and
+    /// return the id of the sampled point closest to each center
+    fn select_frozen_point_sources(&self) -> ANNResult<Vec<u32>> {
+        let dim = self.configuration.dim;
+        let stride = self
+            .num_active_pts
+            .div_ceil(MAX_FROZEN_POINTS_KMEANS_SAMPLE);
+        let sample_ids: Vec<u32> = (0..self.num_active_pts as u32).step_by(stride).collect();
+
+        let mut sample_data = Vec::with_capacity(sample_ids.len() * dim);
+        for &id in sample_ids.iter() {
+            let vertex = self.dataset.get_vertex(id)?;
+            sample_data.extend(vertex.vector()[..dim].iter().map(|&value| value.into()));
+        }
+
+        let num_centers = cmp::min(self.configuration.num_frozen_pts, sample_ids.len());
+        let mut centers = vec![0f32; num_centers * dim];
+        let (closest_docs, _, _) = k_means_clustering(
+            &sample_data,
+            sample_ids.len(),
+            dim,
+            &mut centers,
+            num_centers,
+            FROZEN_POINTS_KMEANS_REPS,
+            &mut create_rng(self.configuration.index_write_parameter.seed),
+        )?;
+
+        let mut source_ids = Vec::with_capacity(num_centers);
+        for (center_id, docs) in closest_docs.iter().enumerate() {
+            let center = &centers[center_id * dim..(center_id + 1) * dim];
+            let closest = docs.iter().min_by(|&&a, &&b| {
+                calc_distance(&sample_data[a * dim..(a + 1) * dim], center, dim).total_cmp(
+                    &calc_distance(&sample_data[b * dim..(b + 1) * dim], center, dim),
+                )
+            });
+
+            // An empty cluster falls back to the sampled point with the same index
+            let doc = closest.copied().unwrap_or(center_id);
+            if !source_ids.contains(&sample_ids[doc]) {
+                source_ids.push(sample_ids[doc]);
+            }
+        }
+
+        Ok(source_ids)
+    }
+
+    /// Move the frozen points from locations [from, from + num_frozen_pts) to
+    /// [to, to + num_frozen_pts), updating their vectors, the edges pointing to them and start.
+    /// The graph must already have room for the new locations.
+    fn reposition_frozen_points(&mut self, from: usize, to: usize) -> ANNResult<()> {
+        let num_frozen_pts = self.configuration.num_frozen_pts;
+        if num_frozen_pts == 0 || from == to {
+            return Ok(());
+        }
+
+        if to + num_frozen_pts > self.final_graph.size() {
+            return Err(ANNError::log_index_error(format!(
+                "Cannot move {} frozen points to location {}, final_graph has {} vertices",
+                num_frozen_pts,
+                to,
+                self.final_graph.size()
+            )));
+        }
+
+        self.dataset
+            .data
+            .ensure_capacity((to + num_frozen_pts) * N)?;
+        self.dataset
+            .data
+            .copy_within(from * N..(from + num_frozen_pts) * N, to * N);
+
+        // Move the farthest point first so that overlapping ranges are not overwritten
+        let offsets: Vec<usize> = if to > from {
+            (0..num_frozen_pts).rev().collect()
+        } else {
+            (0..num_frozen_pts).collect()
+        };
+        let range = self.configuration.index_write_parameter.max_degree as usize;
+        for offset in offsets {
+            let neighbors = adjacency_list_for_range(
+                self.final_graph
+                    .read_vertex_and_neighbors((from + offset) as u32)?
+                    .get_neighbors()
+                    .iter()
+                    .copied(),
+                range,
+            );
+            self.final_graph
+                .write_vertex_and_neighbors((to + offset) as u32)?
+                .set_neighbors(neighbors);
+
+            let vacated = from + offset;
+            if vacated < to || vacated >= to + num_frozen_pts {
+                self.final_graph
+                    .write_vertex_and_neighbors(vacated as u32)?
+                    .set_neighbors(AdjacencyList::for_range(range));
+            }
+        }
+
+        let frozen_range = from..from + num_frozen_pts;
+        let relocate = |id: u32| {
+            if frozen_range.contains(&(id as usize)) {
+                (id as usize - from + to) as u32
+            } else {
+                id
+            }
+        };
+
+        for vertex_id in 0..self.final_graph.size() {
+            let mut vertex = self
+                .final_graph
+                .write_vertex_and_neighbors(vertex_id as u32)?;
+            if vertex
+                .get_neighbors()
+                .iter()
+                .any(|&id| frozen_range.contains(&(id as usize)))
+            {
+                let neighbors = adjacency_list_for_range(
+                    vertex.get_neighbors().iter().map(|&id| relocate(id)),
+                    range,
+                );
+                vertex.set_neighbors(neighbors);
+            }
+        }
+
+        self.start = relocate(self.start);
 
         Ok(())
     }
@@ -159,30 +351,66 @@ where
 
         let timer = Timer::new();
 
+        // Vamana builds in num_rounds passes: the first ones at alpha=1.0 and the last one
+        // at the configured alpha, each in a fresh random order.
+        // A single pass keeps the natural order, several are reproducible with a seed.
+        let num_rounds = cmp::max(self.configuration.index_write_parameter.num_rounds, 1);
+        let alpha = self.configuration.index_write_parameter.alpha;
         let range = visit_order.len();
-        let logger = IndexLogger::new(range);
+        let mut rng = create_rng(self.configuration.index_write_parameter.seed);
 
-        execute_with_rayon(
-            0..range,
-            self.configuration.index_write_parameter.num_threads,
-            |idx| {
-                self.insert_vertex_id(visit_order[idx])?;
-                logger.vertex_processed()?;
+        // A single logger over all the passes, so that the progress covers the whole build
+        let logger = IndexLogger::with_observer(
+            range * num_rounds as usize,
+            IndexOperation::Build,
+            self.progress_observer.clone(),
+        );
 
-                Ok(())
-            },
-        )?;
+        for round in 0..num_rounds {
+            let last_round = round + 1 == num_rounds;
+            let round_alpha = if last_round { alpha } else { 1.0 };
+            if num_rounds > 1 {
+                visit_order.shuffle(&mut rng);
+                log::info!(
+                    "Starting pass {} of {} with alpha={}",
+                    round + 1,
+                    num_rounds,
+                    round_alpha
+                );
+            }
 
-        self.cleanup_graph(&visit_order)?;
+            let result = logger
+                .check_cancelled()
+                .and_then(|_| {
+                    execute_with_rayon(
+                        0..range,
+                        self.configuration.index_write_parameter.num_threads,
+                        |idx| {
+                            self.insert_vertex_id(visit_order[idx], round > 0, round_alpha)?;
+                            logger.vertex_processed()?;
+
+                            Ok(())
+                        },
+                    )
+                })
+                .and_then(|_| self.cleanup_graph(&visit_order, round_alpha));
+
+            result?;
+        }
 
         if self.num_active_pts > 0 {
-            println!("{}", timer.elapsed_seconds_for_step("Link time: "));
+            log::info!("{}", timer.elapsed_seconds_for_step("Link time: "));
         }
 
+        let metrics = metrics();
+        metrics.builds.inc();
+        metrics.build_duration.observe_duration(timer.elapsed());
+        metrics.build_gcycles.set(timer.elapsed_gcycles() as f64);
+
         Ok(())
     }
 
-    fn insert_vertex_id(&self, vertex_id: u32) -> ANNResult<()> {
+    fn insert_vertex_id(&self, vertex_id: u32, keep_neighbors: bool, alpha: f32) -> ANNResult<()> {
         let mut scratch_manager =
             ScratchStoreManager::new(self.query_scratch_queue.clone(), Duration::from_millis(10))?;
         let scratch = scratch_manager.scratch_space().ok_or_else(|| {
@@ -191,9 +419,10 @@ where
             )
         })?;
 
-        let new_neighbors = self.search_for_point_and_prune(scratch, vertex_id)?;
+        let new_neighbors =
+            self.search_for_point_and_prune(scratch, vertex_id, keep_neighbors, alpha)?;
         self.update_vertex_with_neighbors(vertex_id, new_neighbors)?;
-        self.update_neighbors_of_vertex(vertex_id, scratch)?;
+        self.update_neighbors_of_vertex(vertex_id, alpha, scratch)?;
 
         Ok(())
     }
@@ -201,14 +430,22 @@ where
     fn update_neighbors_of_vertex(
         &self,
         vertex_id: u32,
+        alpha: f32,
         scratch: &mut InMemQueryScratch<T, N>,
     ) -> Result<(), ANNError> {
         let vertex = self.final_graph.read_vertex_and_neighbors(vertex_id)?;
         assert!(vertex.size() <= self.configuration.index_write_parameter.max_degree as usize);
+
+        // The start point may have no neighbors yet, see search_for_point_and_prune
+        if vertex.size() == 0 {
+            return Ok(());
+        }
+
         self.inter_insert(
             vertex_id,
             vertex.get_neighbors(),
             self.configuration.index_write_parameter.max_degree,
+            alpha,
             scratch,
         )?;
         Ok(())
@@ -229,15 +466,35 @@ where
         &self,
         scratch: &mut InMemQueryScratch<T, N>,
         vertex_id: u32,
+        keep_neighbors: bool,
+        alpha: f32,
     ) -> ANNResult<AdjacencyList> {
         let mut pruned_list =
             AdjacencyList::for_range(self.configuration.index_write_parameter.max_degree as usize);
         let vertex = self.dataset.get_vertex(vertex_id)?;
         let mut visited_nodes = self.search_for_point(&vertex, scratch)?;
 
-        self.prune_neighbors(vertex_id, &mut visited_nodes, &mut pruned_list, scratch)?;
+        // Later build passes prune the current neighbors together with the visited nodes
+        if keep_neighbors {
+            let visited_ids: HashSet<u32> = visited_nodes.iter().map(|n| n.id).collect();
+            visited_nodes.extend(
+                self.get_neighbors_for_vertex(vertex_id)?
+                    .into_iter()
+                    .filter(|n| !visited_ids.contains(&n.id)),
+            );
+        }
+
+        self.prune_neighbors(
+            vertex_id,
+            &mut visited_nodes,
+            alpha,
+            &mut pruned_list,
+            scratch,
+        )?;
 
-        if pruned_list.is_empty() {
+        // A shuffled pass can visit the start point before any vertex links to it, so it
+        // finds nothing and gets its neighbors from the vertices inserted after it
+        if pruned_list.is_empty() && vertex_id != self.start {
             return Err(ANNError::log_index_error(
                 "pruned_list is empty.".to_string(),
             ));
@@ -263,7 +520,44 @@ where
         l_value: u32,
         indices: &mut [u32],
         distances: Option<&mut [f32]>,
+        payloads: Option<&mut [Option<Payload>]>,
     ) -> ANNResult<u32> {
+        let start = Instant::now();
+        let (cmp, pos) = self.search_and_filter(
+            query,
+            k_value,
+            l_value,
+            &|_| true,
+            indices,
+            distances,
+            payloads,
+        )?;
+        metrics().record_search(start.elapsed(), cmp);
+
+        if pos < k_value {
+            log::debug!(
+                "Found fewer than K elements for query! Found: {} but K: {}",
+                pos,
+                k_value
+            );
+        }
+
+        Ok(cmp)
+    }
+
+    /// Search the graph and report the first k_value candidates that are not deleted and
+    /// whose id passes predicate. Returns the number of comparisons and of reported points.
+    #[allow(clippy::too_many_arguments)]
+    fn search_and_filter(
+        &self,
+        query: &Vertex<T, N>,
+        k_value: usize,
+        l_value: u32,
+        predicate: &dyn Fn(u32) -> bool,
+        indices: &mut [u32],
+        distances: Option<&mut [f32]>,
+        mut payloads: Option<&mut [Option<Payload>]>,
+    ) -> ANNResult<(u32, usize)> {
         if k_value > l_value as usize {
             return Err(ANNError::log_index_error(format!(
                 "Set L: {} to a value of at least K: {}",
@@ -281,9 +575,9 @@ where
         })?;
 
         if l_value > scratch.candidate_size {
-            println!("Attempting to expand query scratch_space. Was created with Lsize: {} but search L is: {}", scratch.candidate_size, l_value);
+            log::debug!("Attempting to expand query scratch_space. Was created with Lsize: {} but search L is: {}", scratch.candidate_size, l_value);
             scratch.resize_for_new_candidate_size(l_value);
-            println!(
+            log::debug!(
                 "Resize completed. New scratch size is: {}",
                 scratch.candidate_size
             );
@@ -292,29 +586,41 @@ where
         let cmp = self.search_with_l_override(query, scratch, l_value as usize)?;
         let mut pos = 0;
 
-        let mut dummy_distance = vec![0f32];
+        let mut dummy_distance = [0f32];
         let (distances, with_distance) = if let Some(distances) = distances {
             (distances, true)
         } else {
             (&mut dummy_distance[..], false)
         };
 
+        let delete_set_guard = self.delete_set.read().map_err(|_| {
+            ANNError::log_lock_poison_error(
+                "failed to acquire the lock for delete_set.".to_string(),
+            )
+        })?;
+
         for i in 0..scratch.best_candidates.size() {
-            if scratch.best_candidates[i].id < self.configuration.max_points as u32 {
-                // Filter out the deleted points.
-                if let Ok(delete_set_guard) = self.delete_set.read() {
-                    if !delete_set_guard.contains(&scratch.best_candidates[i].id) {
-                        indices[pos] = scratch.best_candidates[i].id - INIT_WARMUP_DATA_LEN;
-                        if with_distance {
-                            distances[pos] = scratch.best_candidates[i].distance;
-                        }
-                        pos += 1;
-                    }
-                } else {
-                    return Err(ANNError::log_lock_poison_error(
-                        "failed to acquire the lock for delete_set.".to_string(),
-                    ));
+            let id = scratch.best_candidates[i].id;
+
+            // The warmup points have no id of their own to report, the deleted points and
+            // the ones failing the predicate are traversed but not reported
+            if id >= INIT_WARMUP_DATA_LEN
+                && id < self.configuration.max_points as u32
+                && !delete_set_guard.contains(&id)
+                && predicate(id - INIT_WARMUP_DATA_LEN)
+            {
+                indices[pos] = id - INIT_WARMUP_DATA_LEN;
+                if with_distance {
+                    distances[pos] = scratch.best_candidates[i].distance;
                 }
+                if let Some(payloads) = payloads.as_deref_mut() {
+                    payloads[pos] = self
+                        .payload_store
+                        .as_ref()
+                        .and_then(|store| store.get(id))
+                        .cloned();
+                }
+                pos += 1;
             }
 
             if pos == k_value {
@@ -322,14 +628,7 @@ where
             }
         }
 
-        if pos < k_value {
-            eprintln!(
-                "Found fewer than K elements for query! Found: {} but K: {}",
-                pos, k_value
-            );
-        }
-
-        Ok(cmp)
+        Ok((cmp, pos))
     }
 
     fn search(
@@ -339,12 +638,127 @@ where
         l_value: u32,
         indices: &mut [u32],
     ) -> ANNResult<u32> {
-        self.search_with_distance(query, k_value, l_value, indices, None)
+        self.search_with_distance(query, k_value, l_value, indices, None, None)
+    }
+
+    /// Search the k_value nearest neighbors among the points whose id passes predicate.
+    /// The graph is searched unless few enough points pass that scanning them is cheaper,
+    /// or that the graph search finds fewer than k_value of them.
+    /// selectivity is the fraction of points expected to pass, estimated from a sample when None.
+    /// Returns the number of comparisons and of result slots filled.
+    #[allow(clippy::too_many_arguments)]
+    pub fn search_with_predicate(
+        &self,
+        query: &Vertex<T, N>,
+        k_value: usize,
+        l_value: u32,
+        predicate: &dyn Fn(u32) -> bool,
+        selectivity: Option<f32>,
+        indices: &mut [u32],
+        distances: &mut [f32],
+    ) -> ANNResult<(u32, usize)> {
+        let start = Instant::now();
+        let selectivity = selectivity.unwrap_or_else(|| self.estimate_selectivity(predicate));
+        let (cmp, pos) = if self.is_selective(selectivity, l_value) {
+            self.brute_force_search(query, k_value, predicate, indices, distances)?
+        } else {
+            let (cmp, pos) = self.search_and_filter(
+                query,
+                k_value,
+                l_value,
+                predicate,
+                indices,
+                Some(distances),
+                None,
+            )?;
+            if pos < k_value {
+                let (brute_force_cmp, pos) =
+                    self.brute_force_search(query, k_value, predicate, indices, distances)?;
+                (cmp + brute_force_cmp, pos)
+            } else {
+                (cmp, pos)
+            }
+        };
+        metrics().record_search(start.elapsed(), cmp);
+
+        Ok((cmp, pos))
     }
 
-    fn cleanup_graph(&mut self, visit_order: &Vec<u32>) -> ANNResult<()> {
+    /// Fraction of the points passing predicate, estimated from a sample of up to
+    /// PREDICATE_SAMPLE_SIZE of them. Compute it once to reuse it across searches.
+    pub fn estimate_selectivity(&self, predicate: &dyn Fn(u32) -> bool) -> f32 {
+        let num_points = self.num_searchable_points();
+        if num_points == 0 {
+            return 0.0;
+        }
+
+        let sample_size = num_points.min(PREDICATE_SAMPLE_SIZE);
+        let step = num_points / sample_size;
+        let num_passing = (0..sample_size)
+            .filter(|i| predicate((i * step) as u32))
+            .count();
+
+        num_passing as f32 / sample_size as f32
+    }
+
+    /// Whether the points expected to pass are fewer than the around L * R distances
+    /// a graph search computes
+    fn is_selective(&self, selectivity: f32, l_value: u32) -> bool {
+        let estimated_passing = (selectivity * self.num_searchable_points() as f32) as usize;
+        estimated_passing
+            <= l_value as usize * self.configuration.index_write_parameter.max_degree as usize
+    }
+
+    /// Number of points that search can return, warmup points excluded
+    fn num_searchable_points(&self) -> usize {
+        self.num_active_pts
+            .min(self.configuration.max_points)
+            .saturating_sub(INIT_WARMUP_DATA_LEN as usize)
+    }
+
+    /// Compare the query with every point that is not deleted and passes predicate.
+    /// Returns the number of comparisons and of result slots filled.
+    fn brute_force_search(
+        &self,
+        query: &Vertex<T, N>,
+        k_value: usize,
+        predicate: &dyn Fn(u32) -> bool,
+        indices: &mut [u32],
+        distances: &mut [f32],
+    ) -> ANNResult<(u32, usize)> {
+        let delete_set_guard = self.delete_set.read().map_err(|_| {
+            ANNError::log_lock_poison_error(
+                "failed to acquire the lock for delete_set.".to_string(),
+            )
+        })?;
+
+        let end = self.num_active_pts.min(self.configuration.max_points) as u32;
+        let mut best_candidates = NeighborPriorityQueue::with_capacity(k_value);
+        let mut cmp = 0;
+        for id in INIT_WARMUP_DATA_LEN..end {
+            if delete_set_guard.contains(&id) || !predicate(id - INIT_WARMUP_DATA_LEN) {
+                continue;
+            }
+
+            let vertex = self.dataset.get_vertex(id)?;
+            best_candidates.insert(Neighbor::new(
+                id,
+                query.compare(&vertex, self.configuration.dist_metric),
+            ));
+            cmp += 1;
+        }
+
+        for i in 0..best_candidates.size() {
+            indices[i] = best_candidates[i].id - INIT_WARMUP_DATA_LEN;
+            distances[i] = best_candidates[i].distance;
+        }
+
+        Ok((cmp, best_candidates.size()))
+    }
+
+    fn cleanup_graph(&mut self, visit_order: &[u32], alpha: f32) -> ANNResult<()> {
         if self.num_active_pts > 0 {
-            println!("Starting final cleanup..");
+            log::debug!("Starting final cleanup..");
         }
 
         execute_with_rayon(
@@ -375,7 +789,13 @@ where
                 let mut new_out_neighbors = AdjacencyList::for_range(
                     self.configuration.index_write_parameter.max_degree as usize,
                 );
-                self.prune_neighbors(vertex_id, &mut dummy_pool, &mut new_out_neighbors, scratch)?;
+                self.prune_neighbors(
+                    vertex_id,
+                    &mut dummy_pool,
+                    alpha,
+                    &mut new_out_neighbors,
+                    scratch,
+                )?;
 
                 self.final_graph
                     .write_vertex_and_neighbors(vertex_id)?
@@ -413,7 +833,7 @@ where
     /// Returns an `ANNError` if there is an error retrieving the vertex or one of its neighbors.
     pub fn get_unique_neighbors(
         &self,
-        neighbors: &Vec<u32>,
+        neighbors: &[u32],
         vertex_id: u32,
     ) -> Result<Vec<Neighbor>, ANNError> {
         let vertex = self.dataset.get_vertex(vertex_id)?;
@@ -494,7 +914,8 @@ where
     }
 
     fn soft_delete_vertex(&self, vertex_id_to_delete: u32) -> ANNResult<()> {
-        if vertex_id_to_delete as usize > self.num_active_pts {
+        // Frozen points live past the active points, so they can never be deleted
+        if vertex_id_to_delete as usize >= self.num_active_pts {
             return Err(ANNError::log_index_error(format!(
                 "vertex_id_to_delete: {} is greater than the number of active points in the graph: {}",
                 vertex_id_to_delete, self.num_active_pts
@@ -534,11 +955,23 @@ where
         Ok(())
     }
 
-    fn print_stats(&mut self) -> ANNResult<()> {
-        let mut max = 0;
-        let mut min = usize::MAX;
+    /// Report the progress of the builds and inserts to observer, which can cancel them
+    pub fn set_progress_observer(&mut self, observer: Option<Arc<dyn ProgressObserver>>) {
+        self.progress_observer = observer;
+    }
+
+    /// Degree statistics of the active points
+    pub fn graph_stats(&self) -> ANNResult<GraphStats> {
+        self.compute_graph_stats(None)
+    }
+
+    /// Degree statistics of the active points, with their degrees observed in degree_histogram
+    fn compute_graph_stats(&self, degree_histogram: Option<&Histogram>) -> ANNResult<GraphStats> {
+        let mut stats = GraphStats {
+            num_points: self.num_active_pts,
+            ..Default::default()
+        };
         let mut total = 0;
-        let mut cnt = 0;
 
         for i in 0..self.num_active_pts {
             let vertex_id = i.try_into()?;
@@ -546,48 +979,237 @@ where
                 .final_graph
                 .read_vertex_and_neighbors(vertex_id)?
                 .size();
-            max = cmp::max(max, pool_size);
-            min = cmp::min(min, pool_size);
+            stats.max_degree = cmp::max(stats.max_degree, pool_size);
+            stats.min_degree = if i == 0 {
+                pool_size
+            } else {
+                cmp::min(stats.min_degree, pool_size)
+            };
             total += pool_size;
+            if let Some(degree_histogram) = degree_histogram {
+                degree_histogram.observe(pool_size as f64);
+            }
             if pool_size < 2 {
-                cnt += 1;
+                stats.num_low_degree += 1;
+            }
+        }
+
+        if self.num_active_pts > 0 {
+            stats.avg_degree = total as f32 / self.num_active_pts as f32;
+        }
+
+        stats.num_deleted = self
+            .delete_set
+            .read()
+            .map_err(|_| {
+                ANNError::log_lock_poison_error(
+                    "Failed to acquire delete_set lock, cannot get the number of deleted vertices"
+                        .to_string(),
+                )
+            })?
+            .len();
+
+        Ok(stats)
+    }
+
+    /// Reconnect the live points that a search from the start and frozen points cannot reach,
+    /// by searching and pruning their neighbors again like an insert does.
+    /// Returns the number of points that are reachable again.
+    pub fn repair_connectivity(&mut self) -> ANNResult<usize> {
+        let unreachable = self.diagnostics()?.unreachable;
+        if unreachable.is_empty() {
+            return Ok(0);
+        }
+
+        if self.query_scratch_queue.size()? == 0 {
+            self.initialize_query_scratch(
+                5 + self.configuration.index_write_parameter.num_threads,
+                self.configuration.index_write_parameter.search_list_size,
+            )?;
+        }
+
+        log::info!("Reconnecting {} unreachable vectors.", unreachable.len());
+
+        let alpha = self.configuration.index_write_parameter.alpha;
+        let logger = IndexLogger::new(unreachable.len());
+        let timer = Timer::new();
+        execute_with_rayon(
+            0..unreachable.len(),
+            self.configuration.index_write_parameter.num_threads,
+            |idx| {
+                self.insert_vertex_id(unreachable[idx], true, alpha)?;
+                logger.vertex_processed()?;
+
+                Ok(())
+            },
+        )?;
+
+        let mut visit_order =
+            Vec::with_capacity(self.num_active_pts + self.configuration.num_frozen_pts);
+        for i in 0..self.num_active_pts {
+            visit_order.push(i as u32);
+        }
+        for frozen in self.configuration.max_points
+            ..(self.configuration.max_points + self.configuration.num_frozen_pts)
+        {
+            visit_order.push(frozen as u32);
+        }
+
+        self.cleanup_graph(&visit_order, alpha)?;
+        log::info!("{}", timer.elapsed_seconds_for_step("Repair time: "));
+
+        let still_unreachable: HashSet<u32> = self.diagnostics()?.unreachable.into_iter().collect();
+        let num_repaired = unreachable
+            .iter()
+            .filter(|&id| !still_unreachable.contains(id))
+            .count();
+        if !still_unreachable.is_empty() {
+            log::warn!(
+                "{} vectors are still unreachable after the repair",
+                still_unreachable.len()
+            );
+        }
+
+        self.update_graph_stats()?;
+
+        Ok(num_repaired)
+    }
+
+    /// Drop the deleted points and renumber the others densely, keeping their order.
+    /// The dataset and the graph shrink to the remaining points, followed by the frozen points.
+    /// The points that lost neighbors to the deletes are linked again, like an insert does.
+    /// Returns the new id of each active id, None for the deleted ones.
+    pub fn compact(&mut self) -> ANNResult<Vec<Option<u32>>> {
+        let delete_set = self
+            .delete_set
+            .read()
+            .map_err(|_| {
+                ANNError::log_lock_poison_error(
+                    "Poisoned lock on delete set. Can't compact the index.".to_string(),
+                )
+            })?
+            .clone();
+
+        let num_frozen_pts = self.configuration.num_frozen_pts;
+        let frozen_range =
+            self.configuration.max_points..self.configuration.max_points + num_frozen_pts;
+        let mut new_ids = Vec::with_capacity(self.num_active_pts);
+        let mut kept_ids = Vec::with_capacity(self.num_active_pts + num_frozen_pts);
+        for id in 0..self.num_active_pts as u32 {
+            if delete_set.contains(&id) {
+                new_ids.push(None);
+            } else {
+                new_ids.push(Some(kept_ids.len() as u32));
+                kept_ids.push(id);
             }
         }
+        let num_active_pts = kept_ids.len();
+        kept_ids.extend(frozen_range.clone().map(|id| id as u32));
+
+        let new_id = |id: u32| match new_ids.get(id as usize) {
+            Some(new_id) => *new_id,
+            None if frozen_range.contains(&(id as usize)) => {
+                Some((id as usize - frozen_range.start + num_active_pts) as u32)
+            }
+            None => None,
+        };
 
-        println!(
-            "Index built with degree: max: {} avg: {} min: {} count(deg<2): {}",
-            max,
-            (total as f32) / ((self.num_active_pts + self.configuration.num_frozen_pts) as f32),
-            min,
-            cnt
+        log::info!(
+            "Compacting the index from {} to {} points.",
+            self.num_active_pts,
+            num_active_pts
         );
+        let timer = Timer::new();
 
-        match self.delete_set.read() {
-            Ok(guard) => {
-                println!(
-                    "Number of soft deleted vertices {}, soft deleted percentage: {}",
-                    guard.len(),
-                    (guard.len() as f32)
-                        / ((self.num_active_pts + self.configuration.num_frozen_pts) as f32),
-                );
+        // Edges to deleted points are dropped, the points that had some are linked again below
+        let max_degree = self.configuration.index_write_parameter.max_degree;
+        let final_graph = InMemoryGraph::new(kept_ids.len(), max_degree);
+        let mut damaged = Vec::new();
+        for (vertex_id, &old_id) in kept_ids.iter().enumerate() {
+            let vertex = self.final_graph.read_vertex_and_neighbors(old_id)?;
+            let neighbors: Vec<u32> = vertex
+                .get_neighbors()
+                .iter()
+                .filter_map(|&id| new_id(id))
+                .collect();
+            if neighbors.len() < vertex.size() {
+                damaged.push(vertex_id as u32);
             }
+            final_graph
+                .write_vertex_and_neighbors(vertex_id as u32)?
+                .set_neighbors(adjacency_list_for_range(
+                    neighbors.into_iter(),
+                    max_degree as usize,
+                ));
+        }
+
+        let start = new_id(self.start);
+        if let Some(payload_store) = self.payload_store.as_mut() {
+            payload_store.remap(new_id);
+        }
+
+        self.dataset.compact(&kept_ids, num_active_pts)?;
+        self.final_graph = final_graph;
+        self.num_active_pts = num_active_pts;
+        self.configuration.max_points = num_active_pts;
+        self.start = match start {
+            Some(start) => start,
+            // Only a start without frozen points can be deleted
+            None if num_active_pts > 0 => self.dataset.calculate_medoid_point_id()?,
+            None => num_active_pts as u32,
+        };
+        match self.delete_set.write() {
+            Ok(mut delete_set) => delete_set.clear(),
             Err(_) => {
                 return Err(ANNError::log_lock_poison_error(
-                    "Failed to acquire delete_set lock, cannot get the number of deleted vertices"
-                        .to_string(),
-                ));
+                    "Poisoned lock on delete set. Can't compact the index.".to_string(),
+                ))
             }
-        };
+        }
 
-        self.max_observed_degree = cmp::max(max as u32, self.max_observed_degree);
+        if !damaged.is_empty() {
+            if self.query_scratch_queue.size()? == 0 {
+                self.initialize_query_scratch(
+                    5 + self.configuration.index_write_parameter.num_threads,
+                    self.configuration.index_write_parameter.search_list_size,
+                )?;
+            }
 
-        Ok(())
+            let alpha = self.configuration.index_write_parameter.alpha;
+            execute_with_rayon(
+                0..damaged.len(),
+                self.configuration.index_write_parameter.num_threads,
+                |idx| self.insert_vertex_id(damaged[idx], true, alpha),
+            )?;
+
+            let visit_order: Vec<u32> = (0..(num_active_pts + num_frozen_pts) as u32).collect();
+            self.cleanup_graph(&visit_order, alpha)?;
+        }
+        log::info!("{}", timer.elapsed_seconds_for_step("Compaction time: "));
+
+        self.repair_connectivity()?;
+        self.update_graph_stats()?;
+
+        Ok(new_ids)
+    }
+
+    /// Log the graph stats after it changed and keep track of the max degree
+    fn update_graph_stats(&mut self) -> ANNResult<GraphStats> {
+        let metrics = metrics();
+        metrics.graph_degree.reset();
+        let stats = self.compute_graph_stats(Some(&metrics.graph_degree))?;
+        metrics.delete_ratio.set(stats.deleted_ratio() as f64);
+        log::info!("Index graph: {}", stats);
+
+        self.max_observed_degree = cmp::max(stats.max_degree as u32, self.max_observed_degree);
+
+        Ok(stats)
     }
 }
 
 impl<T, const N: usize> ANNInmemIndex<T> for InmemIndex<T, N>
 where
-    T: Default + Copy + Sync + Send + Into<f32>,
+    T: VectorElement + Sync + Send,
     [T; N]: FullPrecisionDistance<T, N>,
 {
     fn build(&mut self, filename: &str, num_points_to_load: usize) -> ANNResult<()> {
@@ -635,7 +1257,7 @@ where
 
         self.dataset.build_from_file(filename, num_points_to_load)?;
 
-        println!("Using only first {} from file.", num_points_to_load);
+        log::debug!("Using only first {} from file.", num_points_to_load);
 
         // TODO: tag_lock
 
@@ -645,17 +1267,19 @@ where
         Ok(())
     }
 
-    fn build_vector(&mut self, vector: &Vec<Vec<T>>) -> ANNResult<()> {
+    fn build_vector(&mut self, vector: &[Vec<T>]) -> ANNResult<()> {
         let num_points_to_insert = vector.len();
         if num_points_to_insert == 0 {
             return Ok(());
         }
 
-        let dim = N;
-        if dim != self.configuration.dim {
+        // Points shorter than N are zero padded like the points loaded from a file
+        let dim = self.configuration.dim;
+        if let Some(point) = vector.iter().find(|point| point.len() != dim) {
             return Err(ANNError::log_index_error(format!(
-                "ERROR: Driver requests loading {} dimension, but file has {} dimension.",
-                self.configuration.dim, dim
+                "ERROR: Driver requests loading {} dimension, but vector has {} dimension.",
+                dim,
+                point.len()
             )));
         }
 
@@ -671,7 +1295,7 @@ where
         self.or_increase_capacity(vector.len())?;
         self.dataset.build_from_vector(vector)?;
 
-        println!("Using only first {} from file.", num_points_to_insert);
+        log::debug!("Using only first {} from file.", num_points_to_insert);
 
         // TODO: tag_lock
 
@@ -729,60 +1353,84 @@ where
             );
         }
 
-        self.dataset
-            .append_from_file(filename, num_points_to_insert)?;
         self.final_graph.extend(
             num_points_to_insert,
             self.configuration.index_write_parameter.max_degree,
         );
 
+        // Move the frozen points out of the way before the new points overwrite them
+        let previous_max_points = self.configuration.max_points;
+        self.reposition_frozen_points(
+            previous_max_points,
+            previous_max_points + num_points_to_insert,
+        )?;
+        self.dataset
+            .append_from_file(filename, num_points_to_insert)?;
+
         // TODO: this should not consider frozen points
         let previous_last_pt = self.num_active_pts;
         self.num_active_pts += num_points_to_insert;
         self.configuration.max_points += num_points_to_insert;
 
-        println!("Inserting {} vectors from file.", num_points_to_insert);
+        log::info!("Inserting {} vectors from file.", num_points_to_insert);
 
         // TODO: tag_lock
-        let logger = IndexLogger::new(num_points_to_insert);
+        let logger = IndexLogger::with_observer(
+            num_points_to_insert,
+            IndexOperation::Insert,
+            self.progress_observer.clone(),
+        );
+        let alpha = self.configuration.index_write_parameter.alpha;
         let timer = Timer::new();
-        execute_with_rayon(
-            previous_last_pt..self.num_active_pts,
-            self.configuration.index_write_parameter.num_threads,
-            |idx| {
-                self.insert_vertex_id(idx as u32)?;
-                logger.vertex_processed()?;
-
-                Ok(())
-            },
-        )?;
+        let link_result = logger.check_cancelled().and_then(|_| {
+            execute_with_rayon(
+                previous_last_pt..self.num_active_pts,
+                self.configuration.index_write_parameter.num_threads,
+                |idx| {
+                    self.insert_vertex_id(idx as u32, false, alpha)?;
+                    logger.vertex_processed()?;
+
+                    Ok(())
+                },
+            )
+        });
 
         let mut visit_order =
             Vec::with_capacity(self.num_active_pts + self.configuration.num_frozen_pts);
         for i in 0..self.num_active_pts {
             visit_order.push(i as u32);
         }
+        for frozen in self.configuration.max_points
+            ..(self.configuration.max_points + self.configuration.num_frozen_pts)
+        {
+            visit_order.push(frozen as u32);
+        }
 
-        self.cleanup_graph(&visit_order)?;
-        println!("{}", timer.elapsed_seconds_for_step("Insert time: "));
+        // A cancelled insert keeps the points it linked, the others stay unreachable
+        // until repair_connectivity links them
+        self.cleanup_graph(&visit_order, alpha)?;
+        link_result?;
+        log::info!("{}", timer.elapsed_seconds_for_step("Insert time: "));
+        metrics().inserts.add(num_points_to_insert as u64);
 
-        self.print_stats()?;
+        self.update_graph_stats()?;
 
         Ok(())
     }
 
-    fn insert_vector(&mut self, vector: &Vec<Vec<T>>) -> ANNResult<(usize, usize)> {
+    fn insert_vector(&mut self, vector: &[Vec<T>]) -> ANNResult<(usize, usize)> {
         let num_points_to_insert = vector.len();
         if num_points_to_insert == 0 {
             return Ok((0, 0));
         }
 
-        let dim = N;
-
-        if dim != self.configuration.dim {
+        // Points shorter than N are zero padded like the points loaded from a file
+        let dim = self.configuration.dim;
+        if let Some(point) = vector.iter().find(|point| point.len() != dim) {
             return Err(ANNError::log_index_error(format!(
-                "ERROR: Driver requests loading {}  dimension, but file has {} dimension.",
-                self.configuration.dim, dim
+                "ERROR: Driver requests loading {} dimension, but vector has {} dimension.",
+                dim,
+                point.len()
             )));
         }
 
@@ -805,43 +1453,66 @@ where
         }
 
         self.or_increase_capacity(vector.len())?;
-        let result = self.dataset.append_from_vector(vector)?;
         self.final_graph.extend(
             num_points_to_insert,
             self.configuration.index_write_parameter.max_degree,
         );
 
+        // Move the frozen points out of the way before the new points overwrite them
+        let previous_max_points = self.configuration.max_points;
+        self.reposition_frozen_points(
+            previous_max_points,
+            previous_max_points + num_points_to_insert,
+        )?;
+        let result = self.dataset.append_from_vector(vector)?;
+
         // TODO: this should not consider frozen points
         let previous_last_pt = self.num_active_pts;
         self.num_active_pts += num_points_to_insert;
         self.configuration.max_points += num_points_to_insert;
 
-        println!("Inserting {} vectors from file.", num_points_to_insert);
+        log::info!("Inserting {} vectors from file.", num_points_to_insert);
 
         // TODO: tag_lock
-        let logger = IndexLogger::new(num_points_to_insert);
+        let logger = IndexLogger::with_observer(
+            num_points_to_insert,
+            IndexOperation::Insert,
+            self.progress_observer.clone(),
+        );
+        let alpha = self.configuration.index_write_parameter.alpha;
         let timer = Timer::new();
-        execute_with_rayon(
-            previous_last_pt..self.num_active_pts,
-            self.configuration.index_write_parameter.num_threads,
-            |idx| {
-                self.insert_vertex_id(idx as u32)?;
-                logger.vertex_processed()?;
-
-                Ok(())
-            },
-        )?;
+        let link_result = logger.check_cancelled().and_then(|_| {
+            execute_with_rayon(
+                previous_last_pt..self.num_active_pts,
+                self.configuration.index_write_parameter.num_threads,
+                |idx| {
+                    self.insert_vertex_id(idx as u32, false, alpha)?;
+                    logger.vertex_processed()?;
+
+                    Ok(())
+                },
+            )
+        });
 
         let mut visit_order =
             Vec::with_capacity(self.num_active_pts + self.configuration.num_frozen_pts);
         for i in 0..self.num_active_pts {
             visit_order.push(i as u32);
         }
+        for frozen in self.configuration.max_points
+            ..(self.configuration.max_points + self.configuration.num_frozen_pts)
+        {
+            visit_order.push(frozen as u32);
+        }
 
-        self.cleanup_graph(&visit_order)?;
-        println!("{}", timer.elapsed_seconds_for_step("Insert time: "));
+        // A cancelled insert keeps the points it linked, the others stay unreachable
+        // until repair_connectivity links them
+        self.cleanup_graph(&visit_order, alpha)?;
+        link_result?;
+        log::info!("{}", timer.elapsed_seconds_for_step("Insert time: "));
+        metrics().inserts.add(num_points_to_insert as u64);
 
-        self.print_stats()?;
+        self.update_graph_stats()?;
 
         Ok(result)
     }
@@ -849,11 +1520,21 @@ where
     fn save(&mut self, filename: &str) -> ANNResult<()> {
         let data_file = filename.to_string() + ".data";
         let delete_file = filename.to_string() + ".delete";
+        let payload_file = filename.to_string() + ".payload";
 
+        // The frozen points are written directly after the active points
         self.save_graph(filename)?;
         self.save_data(data_file.as_str())?;
         self.save_delete_list(delete_file.as_str())?;
 
+        if let Some(payload_store) = &self.payload_store {
+            payload_store.save(payload_file.as_str())?;
+        }
+
+        let mut metadata = IndexMetadata::new::<T>(self.configuration.clone());
+        metadata.tuned_search = self.tuned_search;
+        metadata.save(&IndexMetadata::file_name(filename))?;
+
         Ok(())
     }
 
@@ -864,7 +1545,15 @@ where
         self.dataset
             .build_from_file_with_enhance(&format!("{}.data", filename), expected_num_points)?;
 
-        let diff = self.dataset.num_active_pts - num_active_pts_saved;
+        // The saved frozen points follow the active points but are not active themselves
+        self.dataset.num_active_pts = self
+            .dataset
+            .num_active_pts
+            .saturating_sub(self.configuration.num_frozen_pts);
+        let diff = self
+            .dataset
+            .num_active_pts
+            .saturating_sub(num_active_pts_saved);
         self.num_active_pts = self.dataset.num_active_pts;
 
         self.final_graph
@@ -872,9 +1561,25 @@ where
 
         self.configuration.max_points += diff;
 
-        self.load_graph(filename, expected_num_points)?;
+        let nodes_read = self.load_graph(filename, expected_num_points)?;
         self.load_delete_list(&format!("{}.delete", filename))?;
 
+        let payload_file = format!("{}.payload", filename);
+        if file_exists(&payload_file) {
+            self.payload_store = Some(PayloadStore::load(&payload_file)?);
+        }
+
+        let metadata_file = IndexMetadata::file_name(filename);
+        if file_exists(&metadata_file) {
+            self.tuned_search = IndexMetadata::load(&metadata_file)?.tuned_search;
+        }
+
+        // Frozen points were saved after the active points, move them back to max_points
+        if self.configuration.num_frozen_pts > 0 {
+            let saved_frozen_start = nodes_read - self.configuration.num_frozen_pts;
+            self.reposition_frozen_points(saved_frozen_start, self.configuration.max_points)?;
+        }
+
         if self.query_scratch_queue.size()? == 0 {
             self.initialize_query_scratch(
                 5 + self.configuration.index_write_parameter.num_threads,
@@ -903,6 +1608,7 @@ where
         l_value: u32,
         indices: &mut [u32],
         distances: &mut [f32],
+        payloads: Option<&mut [Option<Payload>]>,
     ) -> ANNResult<u32> {
         let query_vector = Vertex::new(<&[T; N]>::try_from(query)?, 0);
         InmemIndex::search_with_distance(
@@ -912,15 +1618,43 @@ where
             l_value,
             indices,
             Some(distances),
+            payloads,
+        )
+    }
+
+    fn search_with_predicate(
+        &self,
+        query: &[T],
+        k_value: usize,
+        l_value: u32,
+        predicate: &dyn Fn(u32) -> bool,
+        selectivity: Option<f32>,
+        indices: &mut [u32],
+        distances: &mut [f32],
+    ) -> ANNResult<(u32, usize)> {
+        let query_vector = Vertex::new(<&[T; N]>::try_from(query)?, 0);
+        InmemIndex::search_with_predicate(
+            self,
+            &query_vector,
+            k_value,
+            l_value,
+            predicate,
+            selectivity,
+            indices,
+            distances,
         )
     }
 
+    fn estimate_selectivity(&self, predicate: &dyn Fn(u32) -> bool) -> f32 {
+        InmemIndex::estimate_selectivity(self, predicate)
+    }
+
     fn soft_delete(
         &mut self,
         vertex_ids_to_delete: Vec<u32>,
         num_points_to_delete: usize,
     ) -> ANNResult<()> {
-        println!("Deleting {} vectors from file.", num_points_to_delete);
+        log::info!("Deleting {} vectors from file.", num_points_to_delete);
 
         let logger = IndexLogger::new(num_points_to_delete);
         let timer = Timer::new();
@@ -936,25 +1670,140 @@ where
             },
         )?;
 
-        println!("{}", timer.elapsed_seconds_for_step("Delete time: "));
-        self.print_stats()?;
+        log::info!("{}", timer.elapsed_seconds_for_step("Delete time: "));
+        metrics().deletes.add(num_points_to_delete as u64);
+        self.update_graph_stats()?;
 
+        if let Some(payload_store) = self.payload_store.as_mut() {
+            for vertex_id in &vertex_ids_to_delete[..num_points_to_delete] {
+                payload_store.remove(*vertex_id);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exact_search(
+        &self,
+        query: &[T],
+        k_value: usize,
+        indices: &mut [u32],
+        distances: &mut [f32],
+    ) -> ANNResult<u32> {
+        let query_vector = Vertex::new(<&[T; N]>::try_from(query)?, 0);
+        let (cmp, _) =
+            self.brute_force_search(&query_vector, k_value, &|_| true, indices, distances)?;
+        Ok(cmp)
+    }
+
+    fn search_list_size(&self) -> u32 {
+        match self.tuned_search {
+            Some(tuned_search) => tuned_search.l_value,
+            None => self.configuration.index_write_parameter.search_list_size,
+        }
+    }
+
+    fn tuned_search_parameters(&self) -> Option<TunedSearchParameters> {
+        self.tuned_search
+    }
+
+    fn set_tuned_search_parameters(&mut self, tuned_search: Option<TunedSearchParameters>) {
+        self.tuned_search = tuned_search;
+    }
+
+    fn graph_stats(&self) -> ANNResult<GraphStats> {
+        InmemIndex::graph_stats(self)
+    }
+
+    fn diagnostics(&self) -> ANNResult<GraphDiagnostics> {
+        InmemIndex::diagnostics(self)
+    }
+
+    fn repair_connectivity(&mut self) -> ANNResult<usize> {
+        InmemIndex::repair_connectivity(self)
+    }
+
+    fn compact(&mut self) -> ANNResult<Vec<Option<u32>>> {
+        InmemIndex::compact(self)
+    }
+
+    fn set_progress_observer(&mut self, observer: Option<Arc<dyn ProgressObserver>>) {
+        InmemIndex::set_progress_observer(self, observer)
+    }
+
+    fn enable_payloads(&mut self, schema: Option<PayloadSchema>) -> ANNResult<()> {
+        if self.payload_store.is_some() {
+            return Err(ANNError::log_index_error(
+                "Payloads are already enabled for this index".to_string(),
+            ));
+        }
+
+        self.payload_store = Some(PayloadStore::new(schema));
         Ok(())
     }
+
+    fn insert_vector_with_payloads(
+        &mut self,
+        points: &[Vec<T>],
+        payloads: Vec<Payload>,
+    ) -> ANNResult<(usize, usize)> {
+        let payload_store = self.payload_store.as_ref().ok_or_else(|| {
+            ANNError::log_index_error(
+                "Payloads are not enabled for this index, call enable_payloads first".to_string(),
+            )
+        })?;
+        if payloads.len() != points.len() {
+            return Err(ANNError::log_index_error(format!(
+                "Got {} payloads for {} points",
+                payloads.len(),
+                points.len()
+            )));
+        }
+        // Checked before inserting, so that a bad payload doesn't leave points without one
+        for payload in &payloads {
+            payload_store.validate(payload)?;
+        }
+
+        let (id_start, id_len) = ANNInmemIndex::insert_vector(self, points)?;
+
+        if let Some(payload_store) = self.payload_store.as_mut() {
+            let first_vertex_id = id_start as u32 + INIT_WARMUP_DATA_LEN;
+            for (offset, payload) in payloads.into_iter().enumerate() {
+                payload_store.insert(first_vertex_id + offset as u32, payload)?;
+            }
+        }
+
+        Ok((id_start, id_len))
+    }
+}
+
+/// Collect neighbors into an AdjacencyList with the slack capacity for range,
+/// so that later inserts can push into it
+fn adjacency_list_for_range(neighbors: impl Iterator<Item = u32>, range: usize) -> AdjacencyList {
+    let mut adjacency_list = AdjacencyList::for_range(range);
+    adjacency_list.extend(neighbors);
+    adjacency_list
 }
 
 #[cfg(test)]
 mod index_test {
+    use std::sync::Mutex;
+
+    use rand::Rng;
     use vector::Metric;
 
     use super::*;
     use crate::{
+        common::AlignedBoxWithSlice,
+        instrumentation::CancellationToken,
+        model::scratch::Scratch,
         model::{
             configuration::index_write_parameters::IndexWriteParametersBuilder, vertex::DIM_128,
+            PayloadType, PayloadValue,
         },
         test_utils::get_test_file_path,
         utils::file_util::load_ids_to_delete_from_file,
-        utils::round_up,
+        utils::{load_bin, round_up},
     };
 
     const TEST_DATA_FILE: &str = "tests/data/siftsmall_learn_256pts.fbin";
@@ -976,6 +1825,7 @@ mod index_test {
             let index_write_parameters = IndexWriteParametersBuilder::new(L, R)
                 .with_alpha(ALPHA)
                 .with_num_threads(1)
+                .with_num_rounds(1)
                 .with_saturate_graph($saturate_graph)
                 .build();
             let config = IndexConfiguration::new(
@@ -1070,6 +1920,7 @@ mod index_test {
             let index_write_parameters = IndexWriteParametersBuilder::new(L, R)
                 .with_alpha(ALPHA)
                 .with_num_threads(1)
+                .with_num_rounds(1)
                 .with_saturate_graph($saturate_graph)
                 .build();
             let config = IndexConfiguration::new(
@@ -1166,6 +2017,826 @@ mod index_test {
         index_insert_end_to_end_test_singlethread!(true, INSERT_TRUTH_GRAPH_WITH_SATURATED);
     }
 
+    #[test]
+    fn frozen_points_test() {
+        const NUM_FROZEN_PTS: usize = 2;
+        const SAVE_PATH: &str = "tests/data/frozen_points_test_index";
+
+        let (data_num, dim) =
+            load_metadata_from_file(get_test_file_path(TEST_DATA_FILE).as_str()).unwrap();
+
+        let index_write_parameters = IndexWriteParametersBuilder::new(L, R)
+            .with_alpha(ALPHA)
+            .with_num_threads(1)
+            .with_num_rounds(1)
+            .build();
+        let config = IndexConfiguration::new(
+            Metric::L2,
+            dim,
+            round_up(dim as u64, 16_u64) as usize,
+            data_num,
+            false,
+            0,
+            false,
+            NUM_FROZEN_PTS,
+            2.0f32,
+            index_write_parameters,
+        );
+        let mut index: InmemIndex<f32, DIM_128> = InmemIndex::new(config).unwrap();
+        index
+            .build(get_test_file_path(TEST_DATA_FILE).as_str(), data_num)
+            .unwrap();
+
+        // Frozen points are copies of real points placed after max_points
+        assert_eq!(index.start, data_num as u32);
+        let frozen_ids: Vec<u32> = (data_num..data_num + NUM_FROZEN_PTS)
+            .map(|id| id as u32)
+            .collect();
+        let frozen_vectors: Vec<[f32; DIM_128]> = frozen_ids
+            .iter()
+            .map(|&id| *index.dataset.get_vertex(id).unwrap().vector())
+            .collect();
+        for (&id, frozen_vector) in frozen_ids.iter().zip(frozen_vectors.iter()) {
+            assert!((0..data_num as u32)
+                .any(|i| index.dataset.get_vertex(i).unwrap().vector() == frozen_vector));
+            assert_ne!(
+                index
+                    .final_graph
+                    .read_vertex_and_neighbors(id)
+                    .unwrap()
+                    .size(),
+                0
+            );
+        }
+
+        // Frozen points can't be deleted, and search still finds the remaining points
+        assert!(index.soft_delete(vec![data_num as u32], 1).is_err());
+        let (num_points_to_delete, vertex_ids_to_delete) =
+            load_ids_to_delete_from_file(TEST_DELETE_FILE).unwrap();
+        index
+            .soft_delete(vertex_ids_to_delete.clone(), num_points_to_delete)
+            .unwrap();
+
+        let mut indices = [0u32; 1];
+        let mut num_queries = 0;
+        let mut num_found = 0;
+        for id in INIT_WARMUP_DATA_LEN..data_num as u32 {
+            if vertex_ids_to_delete.contains(&id) {
+                continue;
+            }
+
+            let query = *index.dataset.get_vertex(id).unwrap().vector();
+            InmemIndex::search(&index, &Vertex::new(&query, id), 1, L, &mut indices).unwrap();
+            assert!(indices[0] + INIT_WARMUP_DATA_LEN < data_num as u32);
+            num_queries += 1;
+            if indices[0] + INIT_WARMUP_DATA_LEN == id {
+                num_found += 1;
+            }
+        }
+        assert!(num_found * 100 >= num_queries * 90, "found {}", num_found);
+
+        // Frozen points are saved right after the active points
+        index.save(SAVE_PATH).unwrap();
+        assert_eq!(index.start, data_num as u32);
+        let mut truth_index: InmemIndex<f32, DIM_128> =
+            InmemIndex::new(index.configuration.clone()).unwrap();
+        truth_index
+            .load_graph(SAVE_PATH, data_num + NUM_FROZEN_PTS)
+            .unwrap();
+        assert_eq!(truth_index.start, index.num_active_pts as u32);
+        for suffix in ["", ".data", ".delete", ".metadata"] {
+            std::fs::remove_file(format!("{}{}", SAVE_PATH, suffix)).unwrap();
+        }
+
+        // Inserting moves the frozen points past the new points
+        index
+            .insert(get_test_file_path(TEST_DATA_FILE_2).as_str(), data_num)
+            .unwrap();
+        let new_max_points = 2 * data_num;
+        assert_eq!(index.configuration.max_points, new_max_points);
+        assert_eq!(index.start, new_max_points as u32);
+        for (i, frozen_vector) in frozen_vectors.iter().enumerate() {
+            let id = (new_max_points + i) as u32;
+            assert_eq!(
+                index.dataset.get_vertex(id).unwrap().vector(),
+                frozen_vector
+            );
+            assert_ne!(
+                index
+                    .final_graph
+                    .read_vertex_and_neighbors(id)
+                    .unwrap()
+                    .size(),
+                0
+            );
+        }
+        for i in 0..index.final_graph.size() {
+            assert!(index
+                .final_graph
+                .read_vertex_and_neighbors(i as u32)
+                .unwrap()
+                .get_neighbors()
+                .iter()
+                .all(|&id| (id as usize) < new_max_points + NUM_FROZEN_PTS));
+        }
+    }
+
+    /// Build the index with TEST_DATA_FILE in num_rounds passes and return the recall@K
+    /// of searching TEST_DATA_FILE_2 with search list size l_value
+    fn build_and_get_recall(num_rounds: u32, k_value: usize, l_value: u32) -> f32 {
+        let (data_num, dim) =
+            load_metadata_from_file(get_test_file_path(TEST_DATA_FILE).as_str()).unwrap();
+
+        // A degree of R=4 is too sparse on 256 points for the extra pass to pay off
+        let index_write_parameters = IndexWriteParametersBuilder::new(L, 12)
+            .with_alpha(ALPHA)
+            .with_num_threads(1)
+            .with_num_rounds(num_rounds)
+            .with_seed(7)
+            .build();
+        let config = IndexConfiguration::new(
+            Metric::L2,
+            dim,
+            round_up(dim as u64, 16_u64) as usize,
+            data_num,
+            false,
+            0,
+            false,
+            0,
+            1.0f32,
+            index_write_parameters,
+        );
+        let mut index: InmemIndex<f32, DIM_128> = InmemIndex::new(config).unwrap();
+        index
+            .build(get_test_file_path(TEST_DATA_FILE).as_str(), data_num)
+            .unwrap();
+
+        let (queries, num_queries, _) =
+            load_bin::<f32>(get_test_file_path(TEST_DATA_FILE_2).as_str(), 0).unwrap();
+        let mut scratch =
+            InMemQueryScratch::<f32, DIM_128>::new(l_value, &index_write_parameters, false)
+                .unwrap();
+        let mut aligned_query = AlignedBoxWithSlice::<f32>::new(DIM_128, 64).unwrap();
+        let mut num_found = 0;
+        for query in queries.chunks(dim) {
+            aligned_query[..dim].copy_from_slice(query);
+            let query_vertex = Vertex::new(aligned_query.as_slice().try_into().unwrap(), u32::MAX);
+
+            let mut truth: Vec<Neighbor> = (0..data_num as u32)
+                .map(|id| {
+                    let vertex = index.dataset.get_vertex(id).unwrap();
+                    Neighbor::new(id, query_vertex.compare(&vertex, Metric::L2))
+                })
+                .collect();
+            truth.sort_unstable();
+            let truth_ids: HashSet<u32> = truth[..k_value].iter().map(|n| n.id).collect();
+
+            scratch.clear();
+            index
+                .search_with_l_override(&query_vertex, &mut scratch, l_value as usize)
+                .unwrap();
+            num_found += (0..k_value)
+                .filter(|&i| truth_ids.contains(&scratch.best_candidates[i].id))
+                .count();
+        }
+
+        num_found as f32 / (num_queries * k_value) as f32
+    }
+
+    #[test]
+    fn num_rounds_improves_recall_test() {
+        // Both builds are seeded, so that the shuffled passes are the same on every run
+        let single_pass_recall = build_and_get_recall(1, 10, 10);
+        let two_pass_recall = build_and_get_recall(2, 10, 10);
+
+        assert!(two_pass_recall > single_pass_recall);
+    }
+
+    #[test]
+    fn search_skips_warmup_points_test() {
+        let (data_num, dim) =
+            load_metadata_from_file(get_test_file_path(TEST_DATA_FILE).as_str()).unwrap();
+
+        let index_write_parameters = IndexWriteParametersBuilder::new(L, R)
+            .with_alpha(ALPHA)
+            .with_num_threads(1)
+            .build();
+        let config = IndexConfiguration::new(
+            Metric::L2,
+            dim,
+            round_up(dim as u64, 16_u64) as usize,
+            data_num,
+            false,
+            0,
+            false,
+            0,
+            1.0f32,
+            index_write_parameters,
+        );
+        let mut index: InmemIndex<f32, DIM_128> = InmemIndex::new(config).unwrap();
+        index
+            .build(get_test_file_path(TEST_DATA_FILE).as_str(), data_num)
+            .unwrap();
+
+        // The nearest neighbors of a warmup point include the warmup points, which have no id
+        let query = *index.dataset.get_vertex(0).unwrap().vector();
+        let mut indices = [0u32; 10];
+        InmemIndex::search(&index, &Vertex::new(&query, 0), 10, L, &mut indices).unwrap();
+        assert!(indices
+            .iter()
+            .all(|&id| id + INIT_WARMUP_DATA_LEN < data_num as u32));
+    }
+
+    #[test]
+    fn search_with_predicate_test() {
+        const K: usize = 5;
+
+        let (data_num, dim) =
+            load_metadata_from_file(get_test_file_path(TEST_DATA_FILE).as_str()).unwrap();
+        let index_write_parameters = IndexWriteParametersBuilder::new(L, R)
+            .with_alpha(ALPHA)
+            .with_num_threads(1)
+            .build();
+        let config = IndexConfiguration::new(
+            Metric::L2,
+            dim,
+            round_up(dim as u64, 16_u64) as usize,
+            data_num,
+            false,
+            0,
+            false,
+            0,
+            2.0f32,
+            index_write_parameters,
+        );
+        let mut index: InmemIndex<f32, DIM_128> = InmemIndex::new(config).unwrap();
+        index
+            .build(get_test_file_path(TEST_DATA_FILE).as_str(), data_num)
+            .unwrap();
+
+        // Exact neighbors among the points passing predicate
+        let truth = |query: &Vertex<f32, DIM_128>, predicate: &dyn Fn(u32) -> bool| {
+            let mut neighbors: Vec<Neighbor> = (INIT_WARMUP_DATA_LEN..data_num as u32)
+                .filter(|&id| predicate(id - INIT_WARMUP_DATA_LEN))
+                .map(|id| {
+                    let vertex = index.dataset.get_vertex(id).unwrap();
+                    Neighbor::new(
+                        id - INIT_WARMUP_DATA_LEN,
+                        query.compare(&vertex, Metric::L2),
+                    )
+                })
+                .collect();
+            neighbors.sort_unstable();
+            neighbors.truncate(K);
+            neighbors
+        };
+
+        // Most points pass, the graph is searched and the others are only traversed
+        let not_tenth = |id: u32| !id.is_multiple_of(10);
+        let selectivity = index.estimate_selectivity(&not_tenth);
+        assert!(!index.is_selective(selectivity, L));
+        let mut num_found = 0;
+        for id in (INIT_WARMUP_DATA_LEN..data_num as u32).step_by(7) {
+            let query = *index.dataset.get_vertex(id).unwrap().vector();
+            let query = Vertex::new(&query, id);
+            let mut indices = [u32::MAX; K];
+            let mut distances = [0f32; K];
+            let (_, num_filled) = index
+                .search_with_predicate(
+                    &query,
+                    K,
+                    L,
+                    &not_tenth,
+                    Some(selectivity),
+                    &mut indices,
+                    &mut distances,
+                )
+                .unwrap();
+
+            assert_eq!(num_filled, K);
+            assert!(indices.iter().all(|&id| not_tenth(id)));
+            let truth_ids: HashSet<u32> = truth(&query, &not_tenth).iter().map(|n| n.id).collect();
+            num_found += indices.iter().filter(|&id| truth_ids.contains(id)).count();
+        }
+        let num_queries = (INIT_WARMUP_DATA_LEN..data_num as u32).step_by(7).count();
+        assert!(
+            num_found * 100 >= num_queries * K * 90,
+            "found {}",
+            num_found
+        );
+
+        // Few points pass, they are compared with the query directly
+        let first_twenty = |id: u32| id < 20;
+        assert!(index.is_selective(index.estimate_selectivity(&first_twenty), L));
+        let query = *index.dataset.get_vertex(200).unwrap().vector();
+        let query = Vertex::new(&query, 200);
+        let mut indices = [u32::MAX; K];
+        let mut distances = [0f32; K];
+        let (cmp, num_filled) = index
+            .search_with_predicate(
+                &query,
+                K,
+                L,
+                &first_twenty,
+                None,
+                &mut indices,
+                &mut distances,
+            )
+            .unwrap();
+        assert_eq!(cmp, 20);
+        assert_eq!(num_filled, K);
+        let expected = truth(&query, &first_twenty);
+        assert_eq!(
+            indices.to_vec(),
+            expected.iter().map(|n| n.id).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            distances.to_vec(),
+            expected.iter().map(|n| n.distance).collect::<Vec<_>>()
+        );
+
+        // A wrong hint only changes the strategy, the fallback reports the slots it filled
+        let first_three = |id: u32| id < 3;
+        let mut indices = [u32::MAX; K];
+        let (_, num_filled) = index
+            .search_with_predicate(
+                &query,
+                K,
+                L,
+                &first_three,
+                Some(1.0),
+                &mut indices,
+                &mut distances,
+            )
+            .unwrap();
+        assert_eq!(num_filled, 3);
+        assert!(indices[..3].iter().all(|&id| first_three(id)));
+        assert_eq!(indices[3..], [u32::MAX; K - 3]);
+
+        let mut indices = [u32::MAX; K];
+        let (_, num_filled) = index
+            .search_with_predicate(&query, K, L, &|_| false, None, &mut indices, &mut distances)
+            .unwrap();
+        assert_eq!(num_filled, 0);
+        assert_eq!(indices, [u32::MAX; K]);
+    }
+
+    #[test]
+    fn graph_stats_test() {
+        let (data_num, dim) =
+            load_metadata_from_file(get_test_file_path(TEST_DATA_FILE).as_str()).unwrap();
+        let index_write_parameters = IndexWriteParametersBuilder::new(L, R)
+            .with_alpha(ALPHA)
+            .with_num_threads(1)
+            .build();
+        let config = IndexConfiguration::new(
+            Metric::L2,
+            dim,
+            round_up(dim as u64, 16_u64) as usize,
+            data_num,
+            false,
+            0,
+            false,
+            0,
+            1.0f32,
+            index_write_parameters,
+        );
+        let mut index: InmemIndex<f32, DIM_128> = InmemIndex::new(config).unwrap();
+        index
+            .build(get_test_file_path(TEST_DATA_FILE).as_str(), data_num)
+            .unwrap();
+
+        let stats = index.graph_stats().unwrap();
+        assert_eq!(stats.num_points, data_num);
+        assert!(stats.min_degree >= 1 && stats.min_degree <= stats.max_degree);
+        assert!(stats.max_degree <= R as usize);
+        assert!(stats.avg_degree >= stats.min_degree as f32);
+        assert!(stats.avg_degree <= stats.max_degree as f32);
+        assert_eq!(stats.num_deleted, 0);
+        assert_eq!(index.max_observed_degree, stats.max_degree as u32);
+
+        index.soft_delete(vec![1, 2, 3], 3).unwrap();
+        let stats = ANNInmemIndex::graph_stats(&index).unwrap();
+        assert_eq!(stats.num_deleted, 3);
+        assert_eq!(stats.deleted_ratio(), 3.0 / data_num as f32);
+    }
+
+    #[test]
+    fn repair_connectivity_test() {
+        let (data_num, dim) =
+            load_metadata_from_file(get_test_file_path(TEST_DATA_FILE).as_str()).unwrap();
+        let index_write_parameters = IndexWriteParametersBuilder::new(L, 8)
+            .with_alpha(ALPHA)
+            .with_num_threads(1)
+            .build();
+        let config = IndexConfiguration::new(
+            Metric::L2,
+            dim,
+            round_up(dim as u64, 16_u64) as usize,
+            data_num,
+            false,
+            0,
+            false,
+            0,
+            1.0f32,
+            index_write_parameters,
+        );
+        let mut index: InmemIndex<f32, DIM_128> = InmemIndex::new(config).unwrap();
+        index
+            .build(get_test_file_path(TEST_DATA_FILE).as_str(), data_num)
+            .unwrap();
+        assert_eq!(index.repair_connectivity().unwrap(), 0);
+
+        // Cut every edge to a few points, like deletes of all their in-neighbors would
+        let isolated: Vec<u32> = (10..15).filter(|&id| id != index.start).collect();
+        for id in 0..data_num as u32 {
+            let mut vertex = index.final_graph.write_vertex_and_neighbors(id).unwrap();
+            let mut neighbors = AdjacencyList::for_range(8);
+            for &neighbor in vertex.get_neighbors().iter() {
+                if !isolated.contains(&neighbor) {
+                    neighbors.push(neighbor);
+                }
+            }
+            vertex.set_neighbors(neighbors);
+        }
+        index.soft_delete((100..150).collect(), 50).unwrap();
+        assert_eq!(index.diagnostics().unwrap().unreachable, isolated);
+
+        let num_repaired = ANNInmemIndex::repair_connectivity(&mut index).unwrap();
+        assert_eq!(num_repaired, isolated.len());
+        assert!(index.diagnostics().unwrap().unreachable.is_empty());
+
+        let mut indices = [0u32; 1];
+        for &id in &isolated {
+            let query = index.dataset.get_vertex(id).unwrap();
+            index.search(&query, 1, L, &mut indices).unwrap();
+            assert_eq!(indices[0], id - INIT_WARMUP_DATA_LEN);
+        }
+    }
+
+    #[test]
+    fn compact_test() {
+        let (data_num, dim) =
+            load_metadata_from_file(get_test_file_path(TEST_DATA_FILE).as_str()).unwrap();
+        let index_write_parameters = IndexWriteParametersBuilder::new(L, 8)
+            .with_alpha(ALPHA)
+            .with_num_threads(1)
+            .build();
+        let config = IndexConfiguration::new(
+            Metric::L2,
+            dim,
+            round_up(dim as u64, 16_u64) as usize,
+            data_num,
+            false,
+            0,
+            false,
+            1,
+            1.0f32,
+            index_write_parameters,
+        );
+        let mut index: InmemIndex<f32, DIM_128> = InmemIndex::new(config).unwrap();
+        index
+            .build(get_test_file_path(TEST_DATA_FILE).as_str(), data_num)
+            .unwrap();
+        index.enable_payloads(None).unwrap();
+        let points: Vec<Vec<f32>> = (0..20u32)
+            .map(|id| {
+                let vertex = index.dataset.get_vertex(id).unwrap();
+                vertex.vector()[..dim].iter().map(|x| x + 1.0).collect()
+            })
+            .collect();
+        let payloads = (0..20u8).map(|i| Payload::Bytes(vec![i])).collect();
+        index
+            .insert_vector_with_payloads(&points, payloads)
+            .unwrap();
+
+        let num_points = data_num + points.len();
+        let deleted: Vec<u32> = (0..50)
+            .chain(num_points as u32 - 5..num_points as u32)
+            .collect();
+        index.soft_delete(deleted.clone(), deleted.len()).unwrap();
+        let vectors: Vec<[f32; DIM_128]> = (0..num_points as u32)
+            .map(|id| *index.dataset.get_vertex(id).unwrap().vector())
+            .collect();
+
+        let new_ids = ANNInmemIndex::compact(&mut index).unwrap();
+
+        let num_active_pts = num_points - deleted.len();
+        assert_eq!(new_ids.len(), num_points);
+        let kept: Vec<u32> = (0..num_points as u32)
+            .filter(|id| !deleted.contains(id))
+            .collect();
+        for (id, new_id) in new_ids.iter().enumerate() {
+            match kept.iter().position(|&kept_id| kept_id == id as u32) {
+                Some(position) => assert_eq!(*new_id, Some(position as u32)),
+                None => assert_eq!(*new_id, None),
+            }
+        }
+
+        assert_eq!(index.num_active_pts, num_active_pts);
+        assert_eq!(index.configuration.max_points, num_active_pts);
+        assert_eq!(index.final_graph.size(), num_active_pts + 1);
+        assert_eq!(
+            index.dataset.data.capacity(),
+            (num_active_pts + 1) * DIM_128
+        );
+        assert_eq!(index.start, num_active_pts as u32);
+        for (old_id, new_id) in new_ids.iter().enumerate() {
+            if let Some(new_id) = new_id {
+                assert_eq!(
+                    index.dataset.get_vertex(*new_id).unwrap().vector(),
+                    &vectors[old_id]
+                );
+            }
+        }
+
+        let payload_store = index.payload_store.as_ref().unwrap();
+        assert_eq!(payload_store.len(), 15);
+        let new_id = new_ids[data_num + 7].unwrap();
+        assert_eq!(payload_store.get(new_id), Some(&Payload::Bytes(vec![7])));
+
+        let diagnostics = index.diagnostics().unwrap();
+        assert_eq!(diagnostics.num_deleted, 0);
+        assert_eq!(diagnostics.num_invalid_edges, 0);
+        assert!(diagnostics.unreachable.is_empty());
+        assert_eq!(diagnostics.out_degree_histogram[0], 0);
+
+        let mut indices = [0u32; 1];
+        let mut num_found = 0;
+        for id in INIT_WARMUP_DATA_LEN..num_active_pts as u32 {
+            let query = *index.dataset.get_vertex(id).unwrap().vector();
+            index
+                .search(&Vertex::new(&query, id), 1, L, &mut indices)
+                .unwrap();
+            if indices[0] + INIT_WARMUP_DATA_LEN == id {
+                num_found += 1;
+            }
+        }
+        assert!(
+            num_found * 100 >= (num_active_pts - 5) * 90,
+            "found {}",
+            num_found
+        );
+
+        // The compacted index grows again with the next insert
+        ANNInmemIndex::insert_vector(&mut index, &points).unwrap();
+        assert_eq!(index.num_active_pts, num_active_pts + points.len());
+        assert_eq!(index.start, (num_active_pts + points.len()) as u32);
+    }
+
+    /// Cancels its token once the progress reaches a percentage
+    struct CancelAt {
+        token: CancellationToken,
+        percentage: f32,
+    }
+
+    impl ProgressObserver for CancelAt {
+        fn on_progress(&self, _operation: IndexOperation, percentage: f32) {
+            if percentage >= self.percentage {
+                self.token.cancel();
+            }
+        }
+
+        fn is_cancelled(&self) -> bool {
+            self.token.is_cancelled()
+        }
+    }
+
+    #[test]
+    fn progress_observer_test() {
+        let (data_num, dim) =
+            load_metadata_from_file(get_test_file_path(TEST_DATA_FILE).as_str()).unwrap();
+        let index_write_parameters = IndexWriteParametersBuilder::new(L, 8)
+            .with_alpha(ALPHA)
+            .with_num_threads(1)
+            .build();
+        let config = IndexConfiguration::new(
+            Metric::L2,
+            dim,
+            round_up(dim as u64, 16_u64) as usize,
+            data_num,
+            false,
+            0,
+            false,
+            0,
+            1.0f32,
+            index_write_parameters,
+        );
+
+        let progress = Arc::new(Mutex::new(Vec::new()));
+        let reported = progress.clone();
+        let mut index: InmemIndex<f32, DIM_128> = InmemIndex::new(config.clone()).unwrap();
+        index.set_progress_observer(Some(Arc::new(
+            move |operation: IndexOperation, percentage: f32| {
+                reported.lock().unwrap().push((operation, percentage));
+            },
+        )));
+        index
+            .build(get_test_file_path(TEST_DATA_FILE).as_str(), data_num)
+            .unwrap();
+
+        let progress = progress.lock().unwrap();
+        assert!(progress.len() >= 100);
+        assert!(progress
+            .iter()
+            .all(|(operation, _)| *operation == IndexOperation::Build));
+        assert!(progress.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+        assert_eq!(progress.last().unwrap().1, 100.0);
+
+        let mut cancelled_index: InmemIndex<f32, DIM_128> = InmemIndex::new(config).unwrap();
+        cancelled_index.set_progress_observer(Some(Arc::new(CancelAt {
+            token: CancellationToken::new(),
+            percentage: 50.0,
+        })));
+        assert!(matches!(
+            cancelled_index.build(get_test_file_path(TEST_DATA_FILE).as_str(), data_num),
+            Err(ANNError::Cancelled { .. })
+        ));
+
+        // A cancelled insert keeps the first half of the points it linked
+        let points: Vec<Vec<f32>> = (0..20u32)
+            .map(|id| {
+                let vertex = index.dataset.get_vertex(id).unwrap();
+                vertex.vector()[..dim].iter().map(|x| x + 1.0).collect()
+            })
+            .collect();
+        let token = CancellationToken::new();
+        index.set_progress_observer(Some(Arc::new(CancelAt {
+            token: token.clone(),
+            percentage: 50.0,
+        })));
+        assert!(matches!(
+            ANNInmemIndex::insert_vector(&mut index, &points),
+            Err(ANNError::Cancelled { .. })
+        ));
+        assert!(token.is_cancelled());
+        assert_eq!(index.num_active_pts, data_num + points.len());
+
+        let unreachable = index.diagnostics().unwrap().unreachable;
+        assert_eq!(
+            unreachable,
+            (data_num as u32 + 10..data_num as u32 + 20).collect::<Vec<_>>()
+        );
+        index.set_progress_observer(None);
+        assert_eq!(index.repair_connectivity().unwrap(), 10);
+    }
+
+    #[test]
+    fn seeded_build_test() {
+        let (data_num, dim) =
+            load_metadata_from_file(get_test_file_path(TEST_DATA_FILE).as_str()).unwrap();
+        let build = |seed: u64| {
+            let index_write_parameters = IndexWriteParametersBuilder::new(L, R)
+                .with_alpha(ALPHA)
+                .with_num_threads(1)
+                .with_num_rounds(2)
+                .with_seed(seed)
+                .build();
+            let config = IndexConfiguration::new(
+                Metric::L2,
+                dim,
+                round_up(dim as u64, 16_u64) as usize,
+                data_num,
+                false,
+                0,
+                false,
+                2,
+                1.0f32,
+                index_write_parameters,
+            );
+            let mut index: InmemIndex<f32, DIM_128> = InmemIndex::new(config).unwrap();
+            index
+                .build(get_test_file_path(TEST_DATA_FILE).as_str(), data_num)
+                .unwrap();
+
+            let graph: Vec<Vec<u32>> = (0..index.final_graph.size())
+                .map(|id| {
+                    index
+                        .final_graph
+                        .read_vertex_and_neighbors(id as u32)
+                        .unwrap()
+                        .get_neighbors()
+                        .to_vec()
+                })
+                .collect();
+            let frozen_vectors: Vec<Vec<f32>> = (data_num..data_num + 2)
+                .map(|id| {
+                    index
+                        .dataset
+                        .get_vertex(id as u32)
+                        .unwrap()
+                        .vector()
+                        .to_vec()
+                })
+                .collect();
+            (index.start, graph, frozen_vectors)
+        };
+
+        // Single threaded builds with the same seed are identical, frozen points included
+        assert_eq!(build(42), build(42));
+    }
+
+    #[test]
+    fn payload_test() {
+        const SAVE_PATH: &str = "tests/data/payload_test_index";
+        const DIM: usize = 128;
+
+        let mut rng = create_rng(Some(17));
+        let mut random_points = |num_points: usize| -> Vec<Vec<f32>> {
+            (0..num_points)
+                .map(|_| (0..DIM).map(|_| rng.gen_range(0f32..1f32)).collect())
+                .collect()
+        };
+        let record = |id: usize| {
+            Payload::Record(vec![
+                PayloadValue::Text(format!("doc {}", id)),
+                PayloadValue::Int(id as i64),
+            ])
+        };
+
+        let index_write_parameters = IndexWriteParametersBuilder::new(L, 16)
+            .with_alpha(ALPHA)
+            .with_num_threads(1)
+            .build();
+        let config = IndexConfiguration::new(
+            Metric::L2,
+            DIM,
+            DIM,
+            INIT_WARMUP_DATA_LEN as usize,
+            false,
+            0,
+            false,
+            0,
+            2.0f32,
+            index_write_parameters,
+        );
+        let mut index: InmemIndex<f32, DIM_128> = InmemIndex::new(config.clone()).unwrap();
+        index
+            .build_vector(&random_points(INIT_WARMUP_DATA_LEN as usize))
+            .unwrap();
+
+        let points = random_points(20);
+        let payloads: Vec<Payload> = (0..points.len()).map(record).collect();
+        assert!(index
+            .insert_vector_with_payloads(&points, payloads.clone())
+            .is_err());
+
+        let schema = PayloadSchema::new()
+            .with_field("title", PayloadType::Text)
+            .with_field("id", PayloadType::Int);
+        index.enable_payloads(Some(schema)).unwrap();
+        assert!(index
+            .insert_vector_with_payloads(&points, payloads[..3].to_vec())
+            .is_err());
+        assert_eq!(
+            index
+                .insert_vector_with_payloads(&points, payloads)
+                .unwrap(),
+            (0, 20)
+        );
+
+        let search = |index: &InmemIndex<f32, DIM_128>, id: usize| {
+            let mut indices = [u32::MAX; 3];
+            let mut distances = [0f32; 3];
+            let mut payloads = vec![None; 3];
+            ANNInmemIndex::search_with_distance(
+                index,
+                &points[id],
+                3,
+                L,
+                &mut indices,
+                &mut distances,
+                Some(&mut payloads),
+            )
+            .unwrap();
+            (indices, payloads)
+        };
+
+        let (indices, payloads) = search(&index, 7);
+        assert_eq!(indices[0], 7);
+        for (id, payload) in indices.iter().zip(payloads) {
+            assert_eq!(payload, Some(record(*id as usize)));
+        }
+
+        // Deleted points lose their payload, the others keep it through save and load
+        ANNInmemIndex::soft_delete(&mut index, vec![7 + INIT_WARMUP_DATA_LEN], 1).unwrap();
+        assert_eq!(index.payload_store.as_ref().unwrap().len(), 19);
+        index.save(SAVE_PATH).unwrap();
+
+        let mut loaded_index: InmemIndex<f32, DIM_128> = InmemIndex::new(config).unwrap();
+        loaded_index.load_with_enhance(SAVE_PATH, 0).unwrap();
+        for suffix in ["", ".data", ".delete", ".payload", ".metadata"] {
+            std::fs::remove_file(format!("{}{}", SAVE_PATH, suffix)).unwrap();
+        }
+
+        assert_eq!(loaded_index.payload_store.as_ref().unwrap().len(), 19);
+        let (indices, payloads) = search(&loaded_index, 12);
+        assert_eq!(indices[0], 12);
+        assert_eq!(payloads[0], Some(record(12)));
+        let (indices, _) = search(&loaded_index, 7);
+        assert!(!indices.contains(&7));
+    }
+
     fn compare_graphs(index: &InmemIndex<f32, DIM_128>, truth_index: &InmemIndex<f32, DIM_128>) {
         assert_eq!(index.start, truth_index.start);
         assert_eq!(index.max_observed_degree, truth_index.max_observed_degree);
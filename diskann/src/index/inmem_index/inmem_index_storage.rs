@@ -2,6 +2,7 @@
  * Copyright (c) Microsoft Corporation. All rights reserved.
  * Licensed under the MIT license.
  */
+use std::cmp;
 use std::fs::File;
 use std::io::{BufReader, BufWriter, Seek, SeekFrom, Write};
 use std::path::Path;
@@ -12,10 +13,17 @@ use vector::FullPrecisionDistance;
 use crate::common::{ANNError, ANNResult};
 use crate::model::graph::AdjacencyList;
 use crate::model::InMemoryGraph;
-use crate::utils::{file_exists, save_data_in_base_dimensions};
+use crate::utils::file_exists;
 
 use super::InmemIndex;
 
+/// First word of a delete list saved with a version header, "DLST" in little endian.
+/// Files without it have the legacy layout: a little endian count and big endian ids.
+const DELETE_LIST_MAGIC: u32 = u32::from_le_bytes(*b"DLST");
+
+/// Version of the delete list layout after the magic: count and ids, all little endian
+const DELETE_LIST_VERSION: u32 = 1;
+
 impl<T, const N: usize> InmemIndex<T, N>
 where
     T: Default + Copy + Sync + Send + Into<f32>,
@@ -34,7 +42,7 @@ where
 
         let vamana_metadata_size = 24;
 
-        println!("From graph header, expected_file_size: {}, max_observed_degree: {}, start: {}, file_frozen_pts: {}",
+        log::debug!("From graph header, expected_file_size: {}, max_observed_degree: {}, start: {}, file_frozen_pts: {}",
             expected_file_size, self.max_observed_degree, self.start, file_frozen_pts);
 
         if file_frozen_pts != self.configuration.num_frozen_pts {
@@ -51,14 +59,14 @@ where
             }
         }
 
-        println!("Loading vamana graph {}...", filename);
+        log::info!("Loading vamana graph {}...", filename);
 
         let expected_max_points = expected_num_points - file_frozen_pts;
 
         // If user provides more points than max_points
         // resize the _final_graph to the larger size.
         if self.configuration.max_points < expected_max_points {
-            println!("Number of points in data: {} is greater than max_points: {} Setting max points to: {}", expected_max_points, self.configuration.max_points, expected_max_points);
+            log::info!("Number of points in data: {} is greater than max_points: {} Setting max points to: {}", expected_max_points, self.configuration.max_points, expected_max_points);
 
             self.configuration.max_points = expected_max_points;
             self.final_graph = InMemoryGraph::new(
@@ -89,20 +97,26 @@ where
 
             num_edges += num_nbrs;
             nodes_read += 1;
-            let mut tmp: Vec<u32> = Vec::with_capacity(num_nbrs as usize);
+            // Leave the slack of a built graph, so that inserts can link to loaded points
+            let mut neighbors = AdjacencyList::for_range(cmp::max(
+                num_nbrs,
+                self.configuration.index_write_parameter.max_degree,
+            ) as usize);
             for _ in 0..num_nbrs {
-                tmp.push(in_file.read_u32::<LittleEndian>()?);
+                neighbors.push(in_file.read_u32::<LittleEndian>()?);
             }
 
             self.final_graph
                 .write_vertex_and_neighbors(nodes_read - 1)?
-                .set_neighbors(AdjacencyList::from(tmp));
+                .set_neighbors(neighbors);
             bytes_read += 4 * (num_nbrs as usize + 1);
         }
 
-        println!(
+        log::info!(
             "Done. Index has {} nodes and {} out-edges, _start is set to {}",
-            nodes_read, num_edges, self.start
+            nodes_read,
+            num_edges,
+            self.start
         );
 
         self.max_observed_degree = max_observed_degree;
@@ -113,7 +127,7 @@ where
     /// For each point, first store the number of neighbors,
     /// and then the neighbor list (each as 4 byte u32)
     pub fn save_graph(&mut self, graph_file: &str) -> ANNResult<u64> {
-        println!("===================: {graph_file}");
+        log::debug!("Saving vamana graph {}...", graph_file);
         let file: File = File::create(graph_file)?;
         let mut out = BufWriter::new(file);
 
@@ -123,13 +137,10 @@ where
         let mut max_degree: u32 = 0;
         out.write_all(&index_size.to_le_bytes())?;
         out.write_all(&self.max_observed_degree.to_le_bytes())?;
-        out.write_all(&self.start.to_le_bytes())?;
+        out.write_all(&self.saved_location(self.start).to_le_bytes())?;
         out.write_all(&(self.configuration.num_frozen_pts as u64).to_le_bytes())?;
 
-        // At this point, either nd == max_points or any frozen points have
-        // been temporarily moved to nd, so nd + num_frozen_points is the valid
-        // location limit
-        for i in 0..self.num_active_pts + self.configuration.num_frozen_pts {
+        for i in self.saved_point_ids() {
             let idx = i as u32;
             let gk: u32 = self.final_graph.read_vertex_and_neighbors(idx)?.size() as u32;
             out.write_all(&gk.to_le_bytes())?;
@@ -139,7 +150,7 @@ where
                 .get_neighbors()
                 .iter()
             {
-                out.write_all(&neighbor.to_le_bytes())?;
+                out.write_all(&self.saved_location(*neighbor).to_le_bytes())?;
             }
             max_degree =
                 if self.final_graph.read_vertex_and_neighbors(idx)?.size() as u32 > max_degree {
@@ -158,17 +169,45 @@ where
 
     /// Save the data on a file.
     pub fn save_data(&mut self, data_file: &str) -> ANNResult<usize> {
-        // Note: at this point, either _nd == _max_points or any frozen points have
-        // been temporarily moved to _nd, so _nd + _num_frozen_points is the valid
-        // location limit.
-        Ok(save_data_in_base_dimensions(
-            data_file,
-            &mut self.dataset.data,
-            self.num_active_pts + self.configuration.num_frozen_pts,
-            self.configuration.dim,
-            self.configuration.aligned_dim,
-            0,
-        )?)
+        let npts = self.num_active_pts + self.configuration.num_frozen_pts;
+        let dim = self.configuration.dim;
+        let aligned_dim = self.configuration.aligned_dim;
+
+        let mut writer = BufWriter::new(File::create(data_file)?);
+        writer.write_all(&(npts as i32).to_le_bytes())?;
+        writer.write_all(&(dim as i32).to_le_bytes())?;
+        for i in self.saved_point_ids() {
+            let row = &self.dataset.data[i * aligned_dim..i * aligned_dim + dim];
+            // SAFETY: row is an initialized slice borrowed for the whole call, and the byte
+            // view covers exactly its size_of_val(row) bytes. u8 has an alignment of 1, and the
+            // element types are plain numbers without padding, so every byte is initialized.
+            let row_bytes = unsafe {
+                std::slice::from_raw_parts(row.as_ptr() as *const u8, std::mem::size_of_val(row))
+            };
+            writer.write_all(row_bytes)?;
+        }
+        writer.flush()?;
+
+        Ok(2 * std::mem::size_of::<u32>() + npts * dim * std::mem::size_of::<T>())
+    }
+
+    /// Ids of the points in the order they are saved: the active points
+    /// followed by the frozen points, which live at max_points in memory.
+    fn saved_point_ids(&self) -> impl Iterator<Item = usize> {
+        let frozen_start = self.configuration.max_points;
+        (0..self.num_active_pts)
+            .chain(frozen_start..frozen_start + self.configuration.num_frozen_pts)
+    }
+
+    /// Location of a point in the saved files, where the frozen points
+    /// directly follow the active points.
+    fn saved_location(&self, id: u32) -> u32 {
+        let frozen_start = self.configuration.max_points;
+        if id as usize >= frozen_start {
+            (id as usize - frozen_start + self.num_active_pts) as u32
+        } else {
+            id
+        }
     }
 
     /// Save the delete list to a file only if the delete list length is not zero.
@@ -181,13 +220,18 @@ where
                 let file: File = File::create(delete_list_file)?;
                 let mut writer = BufWriter::new(file);
 
+                // Write the format header.
+                writer.write_all(&DELETE_LIST_MAGIC.to_le_bytes())?;
+                writer.write_all(&DELETE_LIST_VERSION.to_le_bytes())?;
+                delete_file_size += 2 * std::mem::size_of::<u32>();
+
                 // Write the length of the set.
                 writer.write_all(&delete_set_len.to_le_bytes())?;
                 delete_file_size += std::mem::size_of::<u32>();
 
                 // Write the elements of the set.
                 for &item in delete_set.iter() {
-                    writer.write_all(&item.to_be_bytes())?;
+                    writer.write_all(&item.to_le_bytes())?;
                     delete_file_size += std::mem::size_of::<u32>();
                 }
 
@@ -203,20 +247,51 @@ where
     }
 
     // load the deleted list from the delete file if it exists.
+    // The ids used to be saved in big endian after a little endian length. Such a file is told
+    // apart by ids out of range in little endian only, and is saved in little endian next time.
     pub fn load_delete_list(&mut self, delete_list_file: &str) -> ANNResult<usize> {
         let mut len = 0;
 
         if file_exists(delete_list_file) {
-            let file = File::open(delete_list_file)?;
-            let mut reader = BufReader::new(file);
+            let bytes = std::fs::read(delete_list_file)?;
+            let words: Vec<[u8; 4]> = bytes
+                .chunks_exact(std::mem::size_of::<u32>())
+                .map(|chunk| [chunk[0], chunk[1], chunk[2], chunk[3]])
+                .collect();
+            let word = |i: usize| words.get(i).map(|&word| u32::from_le_bytes(word));
+
+            // The layout is told by the magic, never by the values of the ids
+            let (header_len, from_bytes): (usize, fn([u8; 4]) -> u32) =
+                if word(0) == Some(DELETE_LIST_MAGIC) {
+                    if word(1) != Some(DELETE_LIST_VERSION) {
+                        return Err(ANNError::log_index_error(format!(
+                            "ERROR: Delete list {} has unsupported version {:?}",
+                            delete_list_file,
+                            word(1)
+                        )));
+                    }
+                    (2, u32::from_le_bytes)
+                } else {
+                    log::warn!(
+                        "Delete list {} has the legacy layout, saving the index rewrites it",
+                        delete_list_file
+                    );
+                    (0, u32::from_be_bytes)
+                };
 
-            len = reader.read_u32::<LittleEndian>()? as usize;
+            len = word(header_len).unwrap_or(0) as usize;
+            if bytes.len() != (header_len + 1 + len) * std::mem::size_of::<u32>() {
+                return Err(ANNError::log_index_error(format!(
+                    "ERROR: Delete list {} has {} bytes, which does not match its {} ids",
+                    delete_list_file,
+                    bytes.len(),
+                    len
+                )));
+            }
+            let items = words[header_len + 1..].iter().map(|&word| from_bytes(word));
 
             if let Ok(mut delete_set) = self.delete_set.write() {
-                for _ in 0..len {
-                    let item = reader.read_u32::<LittleEndian>()?;
-                    delete_set.insert(item);
-                }
+                delete_set.extend(items);
             } else {
                 return Err(ANNError::log_lock_poison_error(
                     "Poisoned lock on delete set. Can't load deleted list.".to_string(),
@@ -241,7 +316,7 @@ mod index_test {
             configuration::index_write_parameters::IndexWriteParametersBuilder, vertex::DIM_128,
             IndexConfiguration,
         },
-        utils::{load_metadata_from_file, round_up},
+        utils::{load_bin, load_metadata_from_file, round_up},
     };
 
     const TEST_DATA_FILE: &str = "tests/data/siftsmall_learn_256pts.fbin";
@@ -257,7 +332,10 @@ mod index_test {
         let config =
             IndexConfiguration::new(Metric::L2, 10, 16, 16, false, 0, false, 8, 1f32, parameters);
         let mut index = InmemIndex::<f32, 3>::new(config).unwrap();
-        let final_graph = InMemoryGraph::new(10, 3);
+        let final_graph = InMemoryGraph::new(
+            index.configuration.max_points + index.configuration.num_frozen_pts,
+            3,
+        );
         let num_active_pts = 2_usize;
         index.final_graph = final_graph;
         index.num_active_pts = num_active_pts;
@@ -302,4 +380,193 @@ mod index_test {
         );
         fs::remove_file(data_file).expect("Failed to delete file");
     }
+
+    fn delete_list_test_index(max_points: usize) -> InmemIndex<f32, 3> {
+        let parameters = IndexWriteParametersBuilder::new(50, 4)
+            .with_alpha(1.2)
+            .build();
+        let config = IndexConfiguration::new(
+            Metric::L2,
+            10,
+            16,
+            max_points,
+            false,
+            0,
+            false,
+            0,
+            1f32,
+            parameters,
+        );
+        InmemIndex::<f32, 3>::new(config).unwrap()
+    }
+
+    fn sorted_delete_set(index: &InmemIndex<f32, 3>) -> Vec<u32> {
+        let mut ids: Vec<u32> = index.delete_set.read().unwrap().iter().copied().collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn delete_list_round_trip_test() {
+        let mut index = delete_list_test_index(1000);
+        index.delete_set.write().unwrap().extend([3, 17, 300]);
+
+        let delete_file = "tests/data/test_delete_list_round_trip.delete";
+        let size = index.save_delete_list(delete_file).unwrap();
+        let mut loaded_index = delete_list_test_index(1000);
+        let result = loaded_index.load_delete_list(delete_file);
+        fs::remove_file(delete_file).expect("Failed to delete file");
+
+        assert_eq!(size, 6 * std::mem::size_of::<u32>());
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(sorted_delete_set(&loaded_index), vec![3, 17, 300]);
+    }
+
+    #[test]
+    fn load_legacy_delete_list_test() {
+        // The layout of the delete lists saved before the header: a little endian count
+        // and big endian ids. Read as little endian, these ids would be in range too.
+        let mut data = 3u32.to_le_bytes().to_vec();
+        for id in [256u32, 512, 768] {
+            data.extend_from_slice(&id.to_be_bytes());
+        }
+        let delete_file = "tests/data/test_load_legacy_delete_list.delete";
+        fs::write(delete_file, data).expect("Failed to write sample file");
+
+        let mut index = delete_list_test_index(1 << 18);
+        let result = index.load_delete_list(delete_file);
+        fs::remove_file(delete_file).expect("Failed to delete file");
+
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(sorted_delete_set(&index), vec![256, 512, 768]);
+    }
+
+    #[test]
+    fn load_invalid_delete_list_test() {
+        let mut unknown_version = DELETE_LIST_MAGIC.to_le_bytes().to_vec();
+        unknown_version.extend_from_slice(&2u32.to_le_bytes());
+        unknown_version.extend_from_slice(&0u32.to_le_bytes());
+        let mut truncated = 3u32.to_le_bytes().to_vec();
+        truncated.extend_from_slice(&17u32.to_be_bytes());
+
+        for (name, data) in [
+            ("unknown_version", unknown_version),
+            ("truncated", truncated),
+        ] {
+            let delete_file = format!("tests/data/test_load_invalid_delete_list_{}.delete", name);
+            fs::write(&delete_file, data).expect("Failed to write sample file");
+
+            let mut index = delete_list_test_index(1000);
+            let result = index.load_delete_list(&delete_file);
+            fs::remove_file(&delete_file).expect("Failed to delete file");
+
+            assert!(result.is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn insert_after_load_test() {
+        let (data_num, dim) = load_metadata_from_file(TEST_DATA_FILE).unwrap();
+
+        let index_write_parameters = IndexWriteParametersBuilder::new(L, R)
+            .with_alpha(ALPHA)
+            .with_num_threads(1)
+            .build();
+        let config = IndexConfiguration::new(
+            Metric::L2,
+            dim,
+            round_up(dim as u64, 16_u64) as usize,
+            data_num,
+            false,
+            0,
+            false,
+            0,
+            1f32,
+            index_write_parameters,
+        );
+        let mut index: InmemIndex<f32, DIM_128> = InmemIndex::new(config.clone()).unwrap();
+        index.build(TEST_DATA_FILE, data_num).unwrap();
+
+        let index_file = "tests/data/test_insert_after_load_index";
+        index.save(index_file).unwrap();
+        let mut loaded_index: InmemIndex<f32, DIM_128> = InmemIndex::new(config).unwrap();
+        let result = loaded_index.load_with_enhance(index_file, data_num);
+        for suffix in ["", ".data", ".metadata"] {
+            fs::remove_file(format!("{}{}", index_file, suffix)).expect("Failed to delete file");
+        }
+        result.unwrap();
+
+        // The new points are linked back from loaded points
+        let points: Vec<Vec<f32>> = (0..10)
+            .map(|id| loaded_index.dataset.get_vertex(id).unwrap().vector()[..dim].to_vec())
+            .collect();
+        loaded_index.insert_vector(&points).unwrap();
+        assert_eq!(loaded_index.num_active_pts, data_num + points.len());
+    }
+
+    #[test]
+    fn save_keeps_frozen_points_in_place_test() {
+        let (data_num, dim) = load_metadata_from_file(TEST_DATA_FILE).unwrap();
+
+        let index_write_parameters = IndexWriteParametersBuilder::new(L, R)
+            .with_alpha(ALPHA)
+            .with_num_threads(1)
+            .build();
+        let config = IndexConfiguration::new(
+            Metric::L2,
+            dim,
+            round_up(dim as u64, 16_u64) as usize,
+            data_num + 16,
+            false,
+            0,
+            false,
+            1,
+            1f32,
+            index_write_parameters,
+        );
+        let mut index: InmemIndex<f32, DIM_128> = InmemIndex::new(config.clone()).unwrap();
+        index.build(TEST_DATA_FILE, data_num).unwrap();
+        assert!(index.num_active_pts < index.configuration.max_points);
+
+        let neighbors = |index: &InmemIndex<f32, DIM_128>, id: usize| {
+            index
+                .final_graph
+                .read_vertex_and_neighbors(id as u32)
+                .unwrap()
+                .get_neighbors()
+                .to_vec()
+        };
+        let frozen_id = index.configuration.max_points;
+        let start = index.start;
+        let graph_before: Vec<Vec<u32>> = (0..index.final_graph.size())
+            .map(|id| neighbors(&index, id))
+            .collect();
+
+        let index_file = "tests/data/test_save_keeps_frozen_points_in_place_index";
+        index.save(index_file).unwrap();
+
+        // The in-memory graph is left untouched by save
+        assert_eq!(index.start, start);
+        for (id, before) in graph_before.iter().enumerate() {
+            assert_eq!(&neighbors(&index, id), before);
+        }
+
+        let graph = fs::read(index_file);
+        let data = load_bin::<f32>(&format!("{}.data", index_file), 0);
+        for suffix in ["", ".data", ".metadata"] {
+            fs::remove_file(format!("{}{}", index_file, suffix)).expect("Failed to delete file");
+        }
+        let graph = graph.unwrap();
+        let (data, saved_num, saved_dim) = data.unwrap();
+
+        // The frozen point is saved directly after the active points
+        let saved_start = u32::from_le_bytes(graph[12..16].try_into().unwrap());
+        assert_eq!(saved_start as usize, data_num);
+        assert_eq!(saved_num, data_num + 1);
+        assert_eq!(saved_dim, dim);
+        assert_eq!(
+            &data[data_num * dim..],
+            &index.dataset.get_vertex(frozen_id as u32).unwrap().vector()[..dim]
+        );
+    }
 }
@@ -8,4 +8,10 @@ pub use inmem_index::{InmemIndex, INIT_WARMUP_DATA_LEN};
 
 mod inmem_index_storage;
 
+mod graph_stats;
+pub use graph_stats::GraphStats;
+
+mod graph_diagnostics;
+pub use graph_diagnostics::{GraphDiagnostics, RebuildPolicy};
+
 pub mod ann_inmem_index;
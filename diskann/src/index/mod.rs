@@ -4,7 +4,12 @@
  */
 mod inmem_index;
 pub use inmem_index::ann_inmem_index::*;
-pub use inmem_index::{InmemIndex, INIT_WARMUP_DATA_LEN};
+pub use inmem_index::{
+    GraphDiagnostics, GraphStats, InmemIndex, RebuildPolicy, INIT_WARMUP_DATA_LEN,
+};
+
+mod search_tuning;
+pub use search_tuning::*;
 
 #[cfg(feature = "disk_store")]
 mod disk_index;
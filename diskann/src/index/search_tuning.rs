@@ -0,0 +1,498 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+#![warn(missing_docs)]
+
+//! Tuning of the search parameters for a target recall
+//!
+//! Recall@K grows with the search list size L, so the smallest L reaching a target recall on
+//! a sample of held-out queries is found by binary search between K and a max L. Disk indexes
+//! then get the smallest beamwidth that keeps the target at that L.
+
+use hashbrown::HashSet;
+use rayon::prelude::*;
+use vector::Metric;
+
+use crate::common::{ANNError, ANNResult};
+use crate::model::{IndexMetadata, TunedSearchParameters};
+use crate::utils::{file_exists, VectorElement};
+
+use super::ANNInmemIndex;
+
+#[cfg(feature = "disk_store")]
+use super::ann_disk_index::ANNDiskIndex;
+
+/// Recall@k_value of results against ground_truth, in percent: the fraction of the first
+/// k_value ground truth ids of the queries found among their first k_value results
+pub fn recall_at_k(ground_truth: &[Vec<u32>], results: &[Vec<u32>], k_value: usize) -> f32 {
+    let mut num_found = 0;
+    let mut num_expected = 0;
+    for (truth, result) in ground_truth.iter().zip(results) {
+        let truth: HashSet<u32> = truth.iter().take(k_value).copied().collect();
+        num_found += result
+            .iter()
+            .take(k_value)
+            .filter(|id| truth.contains(*id))
+            .count();
+        num_expected += truth.len();
+    }
+
+    100.0 * num_found as f32 / num_expected.max(1) as f32
+}
+
+/// Exact k_value nearest neighbors of each query among the points of base, both flat with
+/// aligned_dim values per vector. The ids are the positions of the points in base.
+pub fn compute_ground_truth<T>(
+    base: &[T],
+    queries: &[T],
+    aligned_dim: usize,
+    k_value: usize,
+    metric: Metric,
+) -> Vec<Vec<u32>>
+where
+    T: Copy + Sync + Into<f32>,
+{
+    let distance = |query: &[T], point: &[T]| -> f32 {
+        let pairs = query
+            .iter()
+            .zip(point)
+            .map(|(a, b)| ((*a).into(), (*b).into()));
+        match metric {
+            Metric::L2 => pairs.map(|(a, b): (f32, f32)| (a - b) * (a - b)).sum(),
+            Metric::InnerProduct => -pairs.map(|(a, b): (f32, f32)| a * b).sum::<f32>(),
+            Metric::Cosine => {
+                let (dot, norm_a, norm_b) = pairs.fold((0f32, 0f32, 0f32), |acc, (a, b)| {
+                    (acc.0 + a * b, acc.1 + a * a, acc.2 + b * b)
+                });
+                1.0 - dot / (norm_a.sqrt() * norm_b.sqrt()).max(f32::MIN_POSITIVE)
+            }
+        }
+    };
+
+    queries
+        .par_chunks(aligned_dim)
+        .map(|query| {
+            let mut neighbors: Vec<(f32, u32)> = base
+                .chunks(aligned_dim)
+                .enumerate()
+                .map(|(id, point)| (distance(query, point), id as u32))
+                .collect();
+            let k_value = k_value.min(neighbors.len());
+            if k_value < neighbors.len() {
+                neighbors.select_nth_unstable_by(k_value, |a, b| a.0.total_cmp(&b.0));
+                neighbors.truncate(k_value);
+            }
+            neighbors.sort_by(|a, b| a.0.total_cmp(&b.0));
+            neighbors.into_iter().map(|(_, id)| id).collect()
+        })
+        .collect()
+}
+
+/// Binary search the smallest L in [k_value, max_l_value] whose recall, as computed by
+/// recall_for, reaches target_recall. Return the L and its recall, or an error when even
+/// max_l_value falls short of the target.
+pub fn tune_l_value<F>(
+    k_value: usize,
+    target_recall: f32,
+    max_l_value: u32,
+    recall_for: F,
+) -> ANNResult<(u32, f32)>
+where
+    F: FnMut(u32) -> ANNResult<f32>,
+{
+    smallest_value_reaching("L", k_value as u32, max_l_value, target_recall, recall_for)
+}
+
+/// Binary search the smallest value in [min_value, max_value] whose recall reaches
+/// target_recall, the recall growing with the value
+fn smallest_value_reaching<F>(
+    name: &str,
+    min_value: u32,
+    max_value: u32,
+    target_recall: f32,
+    mut recall_for: F,
+) -> ANNResult<(u32, f32)>
+where
+    F: FnMut(u32) -> ANNResult<f32>,
+{
+    if min_value == 0 || max_value < min_value {
+        return Err(ANNError::log_index_config_error(
+            name.to_string(),
+            format!("Max {} {} is smaller than {}", name, max_value, min_value),
+        ));
+    }
+
+    let mut best = (max_value, recall_for(max_value)?);
+    if best.1 < target_recall {
+        return Err(ANNError::log_index_config_error(
+            "target_recall".to_string(),
+            format!(
+                "Recall {:.2} with {} {} is below the target {:.2}",
+                best.1, name, max_value, target_recall
+            ),
+        ));
+    }
+
+    let (mut low, mut high) = (min_value, max_value);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        let recall = recall_for(mid)?;
+        if recall >= target_recall {
+            high = mid;
+            best = (mid, recall);
+        } else {
+            low = mid + 1;
+        }
+    }
+
+    Ok(best)
+}
+
+/// Number of queries in the flat queries, checked against the ground truth and K
+fn num_queries<T>(
+    queries: &[T],
+    aligned_dim: usize,
+    ground_truth: Option<&[Vec<u32>]>,
+    k_value: usize,
+    target_recall: f32,
+) -> ANNResult<usize> {
+    if aligned_dim == 0 || queries.is_empty() || !queries.len().is_multiple_of(aligned_dim) {
+        return Err(ANNError::log_index_config_error(
+            "queries".to_string(),
+            format!(
+                "{} values are not a whole number of queries of dim {}",
+                queries.len(),
+                aligned_dim
+            ),
+        ));
+    }
+
+    let num_queries = queries.len() / aligned_dim;
+    if let Some(ground_truth) = ground_truth {
+        if ground_truth.len() != num_queries {
+            return Err(ANNError::log_index_config_error(
+                "ground_truth".to_string(),
+                format!(
+                    "Ground truth of {} queries given for {} queries",
+                    ground_truth.len(),
+                    num_queries
+                ),
+            ));
+        }
+    }
+
+    if k_value == 0 {
+        return Err(ANNError::log_index_config_error(
+            "k_value".to_string(),
+            "K should be at least 1".to_string(),
+        ));
+    }
+
+    if !(target_recall > 0.0 && target_recall <= 100.0) {
+        return Err(ANNError::log_index_config_error(
+            "target_recall".to_string(),
+            format!("Target recall {} should be in (0, 100]", target_recall),
+        ));
+    }
+
+    Ok(num_queries)
+}
+
+/// Tune the search list size of an in-memory index for recall@k_value on the queries, flat
+/// with aligned_dim values per query. Without ground_truth, it is computed by exact_search,
+/// otherwise its ids must be the ones search returns.
+pub fn tune_inmem_search<T>(
+    index: &dyn ANNInmemIndex<T>,
+    queries: &[T],
+    aligned_dim: usize,
+    ground_truth: Option<&[Vec<u32>]>,
+    k_value: usize,
+    target_recall: f32,
+    max_l_value: u32,
+) -> ANNResult<TunedSearchParameters>
+where
+    T: VectorElement + Sync + Send,
+{
+    let num_queries = num_queries(queries, aligned_dim, ground_truth, k_value, target_recall)?;
+    log::info!(
+        "Tuning L for recall@{} of {} on {} queries",
+        k_value,
+        target_recall,
+        num_queries
+    );
+
+    let computed_ground_truth;
+    let ground_truth = match ground_truth {
+        Some(ground_truth) => ground_truth,
+        None => {
+            computed_ground_truth = queries
+                .par_chunks(aligned_dim)
+                .map(|query| {
+                    let mut indices = vec![u32::MAX; k_value];
+                    let mut distances = vec![0f32; k_value];
+                    index.exact_search(query, k_value, &mut indices, &mut distances)?;
+                    Ok(indices)
+                })
+                .collect::<ANNResult<Vec<Vec<u32>>>>()?;
+            &computed_ground_truth
+        }
+    };
+
+    let (l_value, recall) = tune_l_value(k_value, target_recall, max_l_value, |l_value| {
+        let results = queries
+            .par_chunks(aligned_dim)
+            .map(|query| {
+                let mut indices = vec![u32::MAX; k_value];
+                index.search(query, k_value, l_value, &mut indices)?;
+                Ok(indices)
+            })
+            .collect::<ANNResult<Vec<Vec<u32>>>>()?;
+        let recall = recall_at_k(ground_truth, &results, k_value);
+        log::debug!("L {}: recall@{} {:.2}", l_value, k_value, recall);
+        Ok(recall)
+    })?;
+    log::info!("Tuned L {} with recall@{} {:.2}", l_value, k_value, recall);
+
+    Ok(TunedSearchParameters {
+        k_value,
+        target_recall,
+        recall,
+        l_value,
+        beam_width: None,
+    })
+}
+
+/// Tune the search list size and beamwidth of a loaded disk index for recall@k_value on the
+/// queries, flat with aligned_dim values per query. L is searched with max_beam_width, which
+/// reaches the highest recall for an L, then the beamwidth is lowered as far as the target
+/// allows at that L. The ground truth can be computed from the base points with
+/// compute_ground_truth.
+#[cfg(feature = "disk_store")]
+#[allow(clippy::too_many_arguments)]
+pub fn tune_disk_search<T>(
+    index: &dyn ANNDiskIndex<T>,
+    queries: &[T],
+    aligned_dim: usize,
+    ground_truth: &[Vec<u32>],
+    k_value: usize,
+    target_recall: f32,
+    max_l_value: u32,
+    max_beam_width: u32,
+) -> ANNResult<TunedSearchParameters>
+where
+    T: VectorElement + Sync + Send,
+{
+    let num_queries = num_queries(
+        queries,
+        aligned_dim,
+        Some(ground_truth),
+        k_value,
+        target_recall,
+    )?;
+    log::info!(
+        "Tuning L and beamwidth for recall@{} of {} on {} queries",
+        k_value,
+        target_recall,
+        num_queries
+    );
+
+    let recall_for = |l_value: u32, beam_width: u32| -> ANNResult<f32> {
+        let results = queries
+            .par_chunks(aligned_dim)
+            .map(|query| {
+                let mut indices = vec![u32::MAX; k_value];
+                let mut distances = vec![0f32; k_value];
+                index.beam_search(
+                    query,
+                    k_value,
+                    l_value,
+                    beam_width,
+                    u32::MAX,
+                    &mut indices,
+                    &mut distances,
+                    None,
+                )?;
+                Ok(indices)
+            })
+            .collect::<ANNResult<Vec<Vec<u32>>>>()?;
+        let recall = recall_at_k(ground_truth, &results, k_value);
+        log::debug!(
+            "L {} beamwidth {}: recall@{} {:.2}",
+            l_value,
+            beam_width,
+            k_value,
+            recall
+        );
+        Ok(recall)
+    };
+
+    let (l_value, _) = tune_l_value(k_value, target_recall, max_l_value, |l_value| {
+        recall_for(l_value, max_beam_width)
+    })?;
+    let (beam_width, recall) = smallest_value_reaching(
+        "beamwidth",
+        1,
+        max_beam_width,
+        target_recall,
+        |beam_width| recall_for(l_value, beam_width),
+    )?;
+    log::info!(
+        "Tuned L {} beamwidth {} with recall@{} {:.2}",
+        l_value,
+        beam_width,
+        k_value,
+        recall
+    );
+
+    Ok(TunedSearchParameters {
+        k_value,
+        target_recall,
+        recall,
+        l_value,
+        beam_width: Some(beam_width),
+    })
+}
+
+/// Save tuned_search to the metadata saved with an index, so that it is loaded with the index.
+/// metadata_file is IndexMetadata::file_name of the prefix of an in-memory index, or
+/// DiskIndexStorage::metadata_file of a disk index.
+pub fn save_tuned_search_parameters(
+    metadata_file: &str,
+    tuned_search: TunedSearchParameters,
+) -> ANNResult<()> {
+    if !file_exists(metadata_file) {
+        return Err(ANNError::log_index_error(format!(
+            "No metadata {} to save the tuned search parameters to, save or build the index again to write it",
+            metadata_file
+        )));
+    }
+
+    let mut metadata = IndexMetadata::load(metadata_file)?;
+    metadata.tuned_search = Some(tuned_search);
+    metadata.save(metadata_file)
+}
+
+#[cfg(test)]
+mod search_tuning_test {
+    use std::fs;
+
+    use crate::index::{create_inmem_index, load_inmem_index};
+    use crate::model::{IndexConfiguration, IndexWriteParametersBuilder};
+    use crate::test_utils::get_test_file_path;
+    use crate::utils::{load_bin, load_metadata_from_file};
+
+    use super::*;
+
+    #[test]
+    fn tune_l_value_test() {
+        // Recall grows by 10 every 10 L
+        let recall = |l_value: u32| (l_value / 10 * 10).min(100) as f32;
+
+        let mut num_calls = 0;
+        let (l_value, reached) = tune_l_value(10, 75.0, 1000, |l_value| {
+            num_calls += 1;
+            Ok(recall(l_value))
+        })
+        .unwrap();
+        assert_eq!((l_value, reached), (80, 80.0));
+        assert!(num_calls <= 11, "{} calls", num_calls);
+
+        assert_eq!(
+            tune_l_value(10, 10.0, 1000, |l| Ok(recall(l))).unwrap().0,
+            10
+        );
+        assert!(tune_l_value(10, 75.0, 50, |l| Ok(recall(l))).is_err());
+        assert!(tune_l_value(10, 75.0, 5, |l| Ok(recall(l))).is_err());
+    }
+
+    #[test]
+    fn recall_at_k_test() {
+        let ground_truth = vec![vec![1, 2, 3, 4], vec![5, 6, 7, 8]];
+        let results = vec![vec![3, 1, 9], vec![5, 0, 0]];
+        assert_eq!(recall_at_k(&ground_truth, &results, 2), 50.0);
+        assert_eq!(recall_at_k(&ground_truth, &results, 3), 50.0);
+
+        let base = [0f32, 0.0, 1.0, 0.0, 5.0, 5.0, 2.0, 0.0];
+        let queries = [1.9f32, 0.0, 0.0, 0.2];
+        let ground_truth = compute_ground_truth(&base, &queries, 2, 2, Metric::L2);
+        assert_eq!(ground_truth, vec![vec![3, 1], vec![0, 1]]);
+    }
+
+    #[test]
+    fn tune_inmem_search_test() {
+        const TEST_DATA_FILE: &str = "tests/data/siftsmall_learn_256pts.fbin";
+        const SAVE_PATH: &str = "tests/data/tune_inmem_search_test_index";
+
+        let (data_num, dim) =
+            load_metadata_from_file(get_test_file_path(TEST_DATA_FILE).as_str()).unwrap();
+        let index_write_parameters = IndexWriteParametersBuilder::new(50, 8)
+            .with_alpha(1.2)
+            .with_num_threads(1)
+            .with_seed(5)
+            .build();
+        let config = IndexConfiguration::new(
+            Metric::L2,
+            dim,
+            dim,
+            data_num,
+            false,
+            0,
+            false,
+            0,
+            1f32,
+            index_write_parameters,
+        );
+        let mut index = create_inmem_index::<f32>(config).unwrap();
+        index
+            .build(get_test_file_path(TEST_DATA_FILE).as_str(), data_num)
+            .unwrap();
+        assert_eq!(index.search_list_size(), 50);
+
+        // Shifted base points serve as held-out queries
+        let (data, _, _) = load_bin::<f32>(&get_test_file_path(TEST_DATA_FILE), 0).unwrap();
+        let queries: Vec<f32> = data[..dim * 20].iter().map(|value| value + 0.5).collect();
+
+        let tuned = tune_inmem_search(index.as_ref(), &queries, dim, None, 10, 90.0, 200).unwrap();
+        assert!(tuned.recall >= 90.0);
+        assert!(tuned.l_value >= 10 && tuned.l_value <= 200);
+        assert_eq!(tuned.beam_width, None);
+
+        // The same ground truth given explicitly gives the same L
+        let ground_truth: Vec<Vec<u32>> = queries
+            .chunks(dim)
+            .map(|query| {
+                let mut indices = vec![0u32; 10];
+                let mut distances = vec![0f32; 10];
+                index
+                    .exact_search(query, 10, &mut indices, &mut distances)
+                    .unwrap();
+                indices
+            })
+            .collect();
+        let given = tune_inmem_search(
+            index.as_ref(),
+            &queries,
+            dim,
+            Some(&ground_truth),
+            10,
+            90.0,
+            200,
+        )
+        .unwrap();
+        assert_eq!(given, tuned);
+        assert!(tune_inmem_search(index.as_ref(), &queries, dim, None, 10, 101.0, 200).is_err());
+
+        // Searches default to the tuned L once it is saved with the index
+        index.save(SAVE_PATH).unwrap();
+        let metadata_file = IndexMetadata::file_name(SAVE_PATH);
+        save_tuned_search_parameters(&metadata_file, tuned).unwrap();
+        let loaded = load_inmem_index::<f32>(SAVE_PATH).unwrap();
+        for suffix in ["", ".data", ".metadata"] {
+            fs::remove_file(format!("{}{}", SAVE_PATH, suffix)).unwrap();
+        }
+        assert_eq!(loaded.tuned_search_parameters(), Some(tuned));
+        assert_eq!(loaded.search_list_size(), tuned.l_value);
+        assert!(save_tuned_search_parameters(&metadata_file, tuned).is_err());
+    }
+}
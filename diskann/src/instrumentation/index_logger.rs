@@ -3,19 +3,23 @@
  * Licensed under the MIT license.
  */
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 use logger::logger::indexlog::IndexConstructionLog;
 use logger::logger::indexlog::Log;
 use logger::logger::indexlog::LogLevel;
 use logger::message_handler::send_log;
 
-use crate::common::ANNResult;
+use crate::common::{ANNError, ANNResult};
 use crate::utils::Timer;
 
+use super::{IndexOperation, ProgressObserver};
+
 pub struct IndexLogger {
     items_processed: AtomicUsize,
     timer: Timer,
     range: usize,
+    observer: Option<(IndexOperation, Arc<dyn ProgressObserver>)>,
 }
 
 impl IndexLogger {
@@ -24,12 +28,25 @@ impl IndexLogger {
             items_processed: AtomicUsize::new(0),
             timer: Timer::new(),
             range,
+            observer: None,
+        }
+    }
+
+    /// Also report the progress of operation to observer, and stop once it is cancelled
+    pub fn with_observer(
+        range: usize,
+        operation: IndexOperation,
+        observer: Option<Arc<dyn ProgressObserver>>,
+    ) -> Self {
+        Self {
+            observer: observer.map(|observer| (operation, observer)),
+            ..Self::new(range)
         }
     }
 
     pub fn vertex_processed(&self) -> ANNResult<()> {
         let count = self.items_processed.fetch_add(1, Ordering::Relaxed);
-        if count % 100_000 == 0 {
+        if count.is_multiple_of(100_000) {
             let mut log = Log::default();
             let index_construction_log = IndexConstructionLog {
                 percentage_complete: (100_f32 * count as f32) / (self.range as f32),
@@ -42,6 +59,27 @@ impl IndexLogger {
             send_log(log)?;
         }
 
-        Ok(())
+        if let Some((operation, observer)) = &self.observer {
+            let processed = count + 1;
+            let step = (self.range / 100).max(1);
+            if processed.is_multiple_of(step) || processed == self.range {
+                observer.on_progress(
+                    *operation,
+                    (100_f32 * processed as f32 / self.range as f32).min(100_f32),
+                );
+            }
+        }
+
+        self.check_cancelled()
+    }
+
+    /// Fail with Cancelled if the observer cancelled the operation
+    pub fn check_cancelled(&self) -> ANNResult<()> {
+        match &self.observer {
+            Some((operation, observer)) if observer.is_cancelled() => {
+                Err(ANNError::log_cancelled(operation.to_string()))
+            }
+            _ => Ok(()),
+        }
     }
 }
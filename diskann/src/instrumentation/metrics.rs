@@ -0,0 +1,331 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+#![warn(missing_docs)]
+
+//! Process wide metrics of the indexes
+//!
+//! The indexes record into the `IndexMetrics` returned by `metrics()`. They are plain atomics,
+//! so that recording stays cheap enough for the search path whether or not they are exported.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+
+/// Monotonically increasing count
+#[derive(Debug, Default)]
+pub struct Counter {
+    value: AtomicU64,
+}
+
+impl Counter {
+    /// Increase the count by one
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    /// Increase the count
+    pub fn add(&self, value: u64) {
+        self.value.fetch_add(value, Ordering::Relaxed);
+    }
+
+    /// Current count
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+/// Value that can go up and down
+#[derive(Debug, Default)]
+pub struct Gauge {
+    bits: AtomicU64,
+}
+
+impl Gauge {
+    /// Set the value
+    pub fn set(&self, value: f64) {
+        self.bits.store(value.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Current value
+    pub fn get(&self) -> f64 {
+        f64::from_bits(self.bits.load(Ordering::Relaxed))
+    }
+}
+
+/// Distribution of observed values over fixed buckets
+#[derive(Debug)]
+pub struct Histogram {
+    /// Inclusive upper bounds of the buckets, in increasing order
+    bounds: Vec<f64>,
+
+    /// Observations per bucket, the last one past the largest bound
+    counts: Vec<AtomicU64>,
+
+    sum_bits: AtomicU64,
+}
+
+impl Histogram {
+    /// Create a histogram with the given increasing bucket upper bounds
+    pub fn new(bounds: &[f64]) -> Self {
+        Self {
+            bounds: bounds.to_vec(),
+            counts: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            sum_bits: AtomicU64::new(0f64.to_bits()),
+        }
+    }
+
+    /// Buckets growing by factor from start
+    pub fn exponential(start: f64, factor: f64, num_buckets: usize) -> Self {
+        let bounds: Vec<f64> = (0..num_buckets)
+            .map(|i| start * factor.powi(i as i32))
+            .collect();
+        Self::new(&bounds)
+    }
+
+    /// Record a value
+    pub fn observe(&self, value: f64) {
+        let bucket = self.bounds.partition_point(|&bound| bound < value);
+        self.counts[bucket].fetch_add(1, Ordering::Relaxed);
+        let _ = self
+            .sum_bits
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some((f64::from_bits(bits) + value).to_bits())
+            });
+    }
+
+    /// Record a duration in seconds
+    pub fn observe_duration(&self, duration: Duration) {
+        self.observe(duration.as_secs_f64());
+    }
+
+    /// Forget the recorded values, for histograms of a snapshot like the degree distribution
+    pub fn reset(&self) {
+        for count in &self.counts {
+            count.store(0, Ordering::Relaxed);
+        }
+        self.sum_bits.store(0f64.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Upper bounds of the buckets
+    pub fn bounds(&self) -> &[f64] {
+        &self.bounds
+    }
+
+    /// Cumulative counts of the buckets, ending with the count of all values
+    pub fn cumulative_counts(&self) -> Vec<u64> {
+        self.counts
+            .iter()
+            .scan(0, |total, count| {
+                *total += count.load(Ordering::Relaxed);
+                Some(*total)
+            })
+            .collect()
+    }
+
+    /// Number of recorded values
+    pub fn count(&self) -> u64 {
+        self.counts
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// Sum of the recorded values
+    pub fn sum(&self) -> f64 {
+        f64::from_bits(self.sum_bits.load(Ordering::Relaxed))
+    }
+}
+
+/// A metric of IndexMetrics
+#[derive(Debug, Clone, Copy)]
+pub enum Metric<'a> {
+    /// Counter
+    Counter(&'a Counter),
+
+    /// Gauge
+    Gauge(&'a Gauge),
+
+    /// Histogram
+    Histogram(&'a Histogram),
+}
+
+/// Named metric with its description
+#[derive(Debug, Clone, Copy)]
+pub struct MetricFamily<'a> {
+    /// Name, prefixed with diskann_
+    pub name: &'static str,
+
+    /// Description
+    pub help: &'static str,
+
+    /// Values
+    pub metric: Metric<'a>,
+}
+
+/// Metrics of the searches, updates and graphs of all the indexes of the process
+#[derive(Debug)]
+pub struct IndexMetrics {
+    /// Number of searches
+    pub searches: Counter,
+
+    /// Latency of the searches in seconds
+    pub search_latency: Histogram,
+
+    /// Distance comparisons per search
+    pub search_comparisons: Histogram,
+
+    /// Number of inserted points
+    pub inserts: Counter,
+
+    /// Number of soft deleted points
+    pub deletes: Counter,
+
+    /// Fraction of the points of the last updated graph that are soft deleted
+    pub delete_ratio: Gauge,
+
+    /// Out-degree distribution of the last updated graph
+    pub graph_degree: Histogram,
+
+    /// Number of index builds
+    pub builds: Counter,
+
+    /// Duration of the graph builds in seconds
+    pub build_duration: Histogram,
+
+    /// Process cycles spent by the last graph build, in billions
+    pub build_gcycles: Gauge,
+
+    /// Number of times a search or an insert waited for a free scratch space
+    pub scratch_waits: Counter,
+
+    /// Time spent waiting for a free scratch space in seconds
+    pub scratch_wait_duration: Histogram,
+}
+
+impl Default for IndexMetrics {
+    fn default() -> Self {
+        Self {
+            searches: Counter::default(),
+            search_latency: Histogram::exponential(0.000_05, 2.0, 16),
+            search_comparisons: Histogram::exponential(16.0, 2.0, 12),
+            inserts: Counter::default(),
+            deletes: Counter::default(),
+            delete_ratio: Gauge::default(),
+            graph_degree: Histogram::exponential(1.0, 2.0, 10),
+            builds: Counter::default(),
+            build_duration: Histogram::exponential(0.1, 4.0, 10),
+            build_gcycles: Gauge::default(),
+            scratch_waits: Counter::default(),
+            scratch_wait_duration: Histogram::exponential(0.001, 2.0, 12),
+        }
+    }
+}
+
+impl IndexMetrics {
+    /// Record a search
+    pub fn record_search(&self, latency: Duration, comparisons: u32) {
+        self.searches.inc();
+        self.search_latency.observe_duration(latency);
+        self.search_comparisons.observe(comparisons as f64);
+    }
+
+    /// All the metrics with their names, in a stable order
+    pub fn families(&self) -> Vec<MetricFamily<'_>> {
+        vec![
+            MetricFamily {
+                name: "diskann_searches_total",
+                help: "Number of searches",
+                metric: Metric::Counter(&self.searches),
+            },
+            MetricFamily {
+                name: "diskann_search_latency_seconds",
+                help: "Latency of the searches",
+                metric: Metric::Histogram(&self.search_latency),
+            },
+            MetricFamily {
+                name: "diskann_search_comparisons",
+                help: "Distance comparisons per search",
+                metric: Metric::Histogram(&self.search_comparisons),
+            },
+            MetricFamily {
+                name: "diskann_inserts_total",
+                help: "Number of inserted points",
+                metric: Metric::Counter(&self.inserts),
+            },
+            MetricFamily {
+                name: "diskann_deletes_total",
+                help: "Number of soft deleted points",
+                metric: Metric::Counter(&self.deletes),
+            },
+            MetricFamily {
+                name: "diskann_delete_ratio",
+                help: "Fraction of the points of the last updated graph that are soft deleted",
+                metric: Metric::Gauge(&self.delete_ratio),
+            },
+            MetricFamily {
+                name: "diskann_graph_degree",
+                help: "Out-degree distribution of the last updated graph",
+                metric: Metric::Histogram(&self.graph_degree),
+            },
+            MetricFamily {
+                name: "diskann_builds_total",
+                help: "Number of index builds",
+                metric: Metric::Counter(&self.builds),
+            },
+            MetricFamily {
+                name: "diskann_build_duration_seconds",
+                help: "Duration of the graph builds",
+                metric: Metric::Histogram(&self.build_duration),
+            },
+            MetricFamily {
+                name: "diskann_build_gcycles",
+                help: "Process cycles spent by the last graph build, in billions",
+                metric: Metric::Gauge(&self.build_gcycles),
+            },
+            MetricFamily {
+                name: "diskann_scratch_waits_total",
+                help: "Number of times an operation waited for a free scratch space",
+                metric: Metric::Counter(&self.scratch_waits),
+            },
+            MetricFamily {
+                name: "diskann_scratch_wait_duration_seconds",
+                help: "Time spent waiting for a free scratch space",
+                metric: Metric::Histogram(&self.scratch_wait_duration),
+            },
+        ]
+    }
+}
+
+static METRICS: Lazy<IndexMetrics> = Lazy::new(IndexMetrics::default);
+
+/// Metrics recorded by the indexes of the process
+pub fn metrics() -> &'static IndexMetrics {
+    &METRICS
+}
+
+#[cfg(test)]
+mod metrics_test {
+    use super::*;
+
+    #[test]
+    fn histogram_test() {
+        let histogram = Histogram::new(&[1.0, 2.0, 4.0]);
+        for value in [0.5, 1.0, 1.5, 3.0, 10.0] {
+            histogram.observe(value);
+        }
+
+        assert_eq!(histogram.cumulative_counts(), vec![2, 3, 4, 5]);
+        assert_eq!(histogram.count(), 5);
+        assert_eq!(histogram.sum(), 16.0);
+
+        histogram.reset();
+        assert_eq!(histogram.cumulative_counts(), vec![0, 0, 0, 0]);
+        assert_eq!(histogram.sum(), 0.0);
+
+        let exponential = Histogram::exponential(1.0, 2.0, 4);
+        assert_eq!(exponential.bounds(), &[1.0, 2.0, 4.0, 8.0]);
+    }
+}
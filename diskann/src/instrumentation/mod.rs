@@ -7,3 +7,12 @@ pub use index_logger::IndexLogger;
 
 mod disk_index_build_logger;
 pub use disk_index_build_logger::DiskIndexBuildLogger;
+
+pub mod metrics;
+pub use metrics::{metrics, IndexMetrics};
+
+pub mod progress;
+pub use progress::{CancellationToken, IndexOperation, ProgressObserver};
+
+#[cfg(feature = "prometheus")]
+pub mod prometheus_exporter;
@@ -0,0 +1,119 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+#![warn(missing_docs)]
+
+//! Progress reporting and cancellation of long running index operations
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Long running operation reported to a ProgressObserver
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IndexOperation {
+    /// Graph build of an in-memory index, from a file or from vectors
+    Build,
+
+    /// Insert of points into an in-memory index
+    Insert,
+
+    /// Disk index build, reported at the end of each of its stages
+    DiskBuild,
+}
+
+impl fmt::Display for IndexOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            IndexOperation::Build => "index build",
+            IndexOperation::Insert => "insert",
+            IndexOperation::DiskBuild => "disk index build",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Follows and pre-empts the long running operations of an index.
+///
+/// Both methods are called from the build threads, is_cancelled after every processed
+/// point, so they should be cheap. A cancelled operation returns `ANNError::Cancelled`.
+pub trait ProgressObserver: Send + Sync {
+    /// Called each time operation completes about one more percent, percentage is in [0, 100]
+    fn on_progress(&self, operation: IndexOperation, percentage: f32);
+
+    /// Whether the running operation should stop
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
+
+impl<F> ProgressObserver for F
+where
+    F: Fn(IndexOperation, f32) + Send + Sync,
+{
+    fn on_progress(&self, operation: IndexOperation, percentage: f32) {
+        self(operation, percentage)
+    }
+}
+
+/// Cancels the operations it observes once cancel is called on any of its clones
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Create a token that is not cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop the observed operations
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+
+    /// Whether cancel was called
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+}
+
+impl ProgressObserver for CancellationToken {
+    fn on_progress(&self, _operation: IndexOperation, _percentage: f32) {}
+
+    fn is_cancelled(&self) -> bool {
+        CancellationToken::is_cancelled(self)
+    }
+}
+
+#[cfg(test)]
+mod progress_test {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn cancellation_token_test() {
+        let token = CancellationToken::new();
+        let observer: Arc<dyn ProgressObserver> = Arc::new(token.clone());
+        assert!(!observer.is_cancelled());
+
+        token.clone().cancel();
+        assert!(token.is_cancelled());
+        assert!(observer.is_cancelled());
+
+        let reported = Mutex::new(Vec::new());
+        let callback = |operation: IndexOperation, percentage: f32| {
+            reported.lock().unwrap().push((operation, percentage));
+        };
+        callback.on_progress(IndexOperation::Insert, 42.0);
+        assert!(!callback.is_cancelled());
+        assert_eq!(
+            *reported.lock().unwrap(),
+            vec![(IndexOperation::Insert, 42.0)]
+        );
+        assert_eq!(IndexOperation::DiskBuild.to_string(), "disk index build");
+    }
+}
@@ -0,0 +1,204 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+#![warn(missing_docs)]
+
+//! Export of the index metrics in the Prometheus text format
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use crate::common::ANNResult;
+
+use super::metrics::{metrics, IndexMetrics, Metric};
+
+/// Content type of the Prometheus text format
+pub const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Render the metrics in the Prometheus text format
+pub fn render_prometheus(metrics: &IndexMetrics) -> String {
+    let mut text = String::new();
+
+    for family in metrics.families() {
+        let metric_type = match family.metric {
+            Metric::Counter(_) => "counter",
+            Metric::Gauge(_) => "gauge",
+            Metric::Histogram(_) => "histogram",
+        };
+        let _ = writeln!(text, "# HELP {} {}", family.name, family.help);
+        let _ = writeln!(text, "# TYPE {} {}", family.name, metric_type);
+
+        match family.metric {
+            Metric::Counter(counter) => {
+                let _ = writeln!(text, "{} {}", family.name, counter.get());
+            }
+            Metric::Gauge(gauge) => {
+                let _ = writeln!(text, "{} {}", family.name, gauge.get());
+            }
+            Metric::Histogram(histogram) => {
+                let counts = histogram.cumulative_counts();
+                for (bound, count) in histogram.bounds().iter().zip(&counts) {
+                    let _ = writeln!(text, "{}_bucket{{le=\"{}\"}} {}", family.name, bound, count);
+                }
+                let _ = writeln!(
+                    text,
+                    "{}_bucket{{le=\"+Inf\"}} {}",
+                    family.name,
+                    histogram.count()
+                );
+                let _ = writeln!(text, "{}_sum {}", family.name, histogram.sum());
+                let _ = writeln!(text, "{}_count {}", family.name, histogram.count());
+            }
+        }
+    }
+
+    text
+}
+
+/// Write the metrics of the process to a file, for the node exporter textfile collector.
+/// The file is replaced at once, so that a scrape never reads it half written.
+pub fn write_prometheus_file(path: &str) -> ANNResult<()> {
+    let temp_path = format!("{}.tmp", path);
+    fs::write(&temp_path, render_prometheus(metrics()))?;
+    fs::rename(&temp_path, path)?;
+
+    Ok(())
+}
+
+/// Serves the metrics of the process on GET /metrics until dropped
+#[derive(Debug)]
+pub struct PrometheusServer {
+    local_addr: SocketAddr,
+
+    stopped: Arc<AtomicBool>,
+
+    handle: Option<JoinHandle<()>>,
+}
+
+impl PrometheusServer {
+    /// Listen on addr, use port 0 to pick a free port
+    pub fn start(addr: impl ToSocketAddrs) -> ANNResult<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let thread_stopped = stopped.clone();
+        let handle = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if thread_stopped.load(Ordering::Acquire) {
+                    break;
+                }
+
+                if let Ok(stream) = stream {
+                    if let Err(err) = respond(stream) {
+                        log::debug!("Failed to answer a metrics request: {}", err);
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            local_addr,
+            stopped,
+            handle: Some(handle),
+        })
+    }
+
+    /// Address the server listens on
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for PrometheusServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Release);
+        // Wake up the blocking accept
+        let _ = TcpStream::connect(self.local_addr);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn respond(stream: TcpStream) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // Skip the headers, the request has no body
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 && line != "\r\n" && line != "\n" {
+        line.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            let body = render_prometheus(metrics());
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                PROMETHEUS_CONTENT_TYPE,
+                body.len(),
+                body
+            )
+        }
+        _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+    };
+
+    let mut stream = reader.into_inner();
+    stream.write_all(response.as_bytes())?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod prometheus_exporter_test {
+    use std::io::Read;
+
+    use super::*;
+    use crate::instrumentation::metrics::IndexMetrics;
+
+    #[test]
+    fn render_test() {
+        let metrics = IndexMetrics::default();
+        metrics.searches.add(3);
+        metrics.delete_ratio.set(0.25);
+        metrics.search_comparisons.observe(20.0);
+        metrics.search_comparisons.observe(100_000.0);
+
+        let text = render_prometheus(&metrics);
+        assert!(text.contains("# TYPE diskann_searches_total counter\ndiskann_searches_total 3\n"));
+        assert!(text.contains("diskann_delete_ratio 0.25\n"));
+        assert!(text.contains("diskann_search_comparisons_bucket{le=\"16\"} 0\n"));
+        assert!(text.contains("diskann_search_comparisons_bucket{le=\"32\"} 1\n"));
+        assert!(text.contains("diskann_search_comparisons_bucket{le=\"+Inf\"} 2\n"));
+        assert!(text.contains("diskann_search_comparisons_sum 100020\n"));
+        assert!(text.contains("diskann_search_comparisons_count 2\n"));
+    }
+
+    #[test]
+    fn server_test() {
+        let server = PrometheusServer::start("127.0.0.1:0").unwrap();
+
+        let get = |path: &str| {
+            let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+            write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        let response = get("/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("# TYPE diskann_search_latency_seconds histogram"));
+        assert!(get("/other").starts_with("HTTP/1.1 404"));
+
+        drop(server);
+    }
+}
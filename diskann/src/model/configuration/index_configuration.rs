@@ -6,17 +6,19 @@
 
 //! Index configuration.
 
+use serde::{Deserialize, Serialize};
 use vector::Metric;
 
 use super::index_write_parameters::IndexWriteParameters;
 
 /// The index configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexConfiguration {
     /// Index write parameter
     pub index_write_parameter: IndexWriteParameters,
 
     /// Distance metric
+    #[serde(with = "metric_name")]
     pub dist_metric: Metric,
 
     /// Dimension of the raw data
@@ -89,3 +91,23 @@ impl IndexConfiguration {
         self.index_write_parameter.max_degree as usize
     }
 }
+
+/// Serialize a metric by the name it is parsed from: l2, cosine or ip
+mod metric_name {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use vector::Metric;
+
+    pub fn serialize<S: Serializer>(metric: &Metric, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(match metric {
+            Metric::L2 => "l2",
+            Metric::Cosine => "cosine",
+            Metric::InnerProduct => "ip",
+        })
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Metric, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
@@ -0,0 +1,190 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+#![warn(missing_debug_implementations, missing_docs)]
+
+//! Metadata saved with an in-memory index.
+//!
+//! It is written as JSON to `{index path}.metadata`, so that a saved index can be loaded
+//! without restating the configuration it was built with, and holds the search parameters
+//! tuned for it.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::{ANNError, ANNResult};
+use crate::utils::VectorElement;
+
+use super::IndexConfiguration;
+
+/// Version of the metadata file format
+pub const INDEX_METADATA_VERSION: u32 = 1;
+
+/// Smallest search parameters found to reach a target recall, see search_tuning
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TunedSearchParameters {
+    /// K of the recall@K that was tuned for
+    pub k_value: usize,
+
+    /// Recall@K to reach, in percent
+    pub target_recall: f32,
+
+    /// Recall@K reached on the tuning queries, in percent
+    pub recall: f32,
+
+    /// Search list size - L
+    pub l_value: u32,
+
+    /// Beamwidth of the disk index search, None for an in-memory index
+    pub beam_width: Option<u32>,
+}
+
+/// Element type and configuration of a saved index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexMetadata {
+    /// Version of the file format
+    pub version: u32,
+
+    /// Element type of the vectors, see VectorElement::DATA_TYPE_NAME
+    pub data_type: String,
+
+    /// Configuration of the index when it was saved.
+    /// max_points is the capacity it had, a loaded index is sized to the saved points.
+    pub configuration: IndexConfiguration,
+
+    /// Search parameters tuned for the index, searches default to them
+    #[serde(default)]
+    pub tuned_search: Option<TunedSearchParameters>,
+}
+
+impl IndexMetadata {
+    /// Metadata of an index of T vectors
+    pub fn new<T: VectorElement>(configuration: IndexConfiguration) -> Self {
+        Self {
+            version: INDEX_METADATA_VERSION,
+            data_type: T::DATA_TYPE_NAME.to_string(),
+            configuration,
+            tuned_search: None,
+        }
+    }
+
+    /// Metadata file of the index saved to index_path
+    pub fn file_name(index_path: &str) -> String {
+        format!("{}.metadata", index_path)
+    }
+
+    /// Save the metadata as JSON
+    pub fn save(&self, filename: &str) -> ANNResult<()> {
+        let mut writer = BufWriter::new(File::create(filename)?);
+        serde_json::to_writer_pretty(&mut writer, self).map_err(|err| {
+            ANNError::log_index_error(format!("Failed to save metadata to {}: {}", filename, err))
+        })?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Load the metadata saved with save
+    pub fn load(filename: &str) -> ANNResult<Self> {
+        let reader = BufReader::new(File::open(filename)?);
+        let metadata: Self = serde_json::from_reader(reader).map_err(|err| {
+            ANNError::log_index_error(format!(
+                "Failed to load metadata from {}: {}",
+                filename, err
+            ))
+        })?;
+
+        if metadata.version > INDEX_METADATA_VERSION {
+            return Err(ANNError::log_index_error(format!(
+                "Metadata {} has version {}, the latest supported is {}",
+                filename, metadata.version, INDEX_METADATA_VERSION
+            )));
+        }
+
+        Ok(metadata)
+    }
+
+    /// Fail with DataTypeMismatch unless the saved vectors are of type T
+    pub fn check_data_type<T: VectorElement>(&self) -> ANNResult<()> {
+        let expected = T::DATA_TYPE_NAME;
+        if self.data_type != expected {
+            return Err(ANNError::log_data_type_mismatch_error(
+                expected.to_string(),
+                self.data_type.clone(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod index_metadata_test {
+    use std::fs;
+
+    use vector::{BFloat16, Half, Metric};
+
+    use super::*;
+    use crate::model::IndexWriteParametersBuilder;
+
+    #[test]
+    fn save_and_load_test() {
+        const METADATA_FILE: &str = "tests/data/index_metadata_test.metadata";
+
+        let index_write_parameters = IndexWriteParametersBuilder::new(50, 32)
+            .with_alpha(1.5)
+            .with_num_threads(3)
+            .with_seed(7)
+            .build();
+        let config = IndexConfiguration::new(
+            Metric::Cosine,
+            100,
+            104,
+            1000,
+            false,
+            0,
+            false,
+            1,
+            1.2f32,
+            index_write_parameters,
+        );
+        let tuned_search = TunedSearchParameters {
+            k_value: 10,
+            target_recall: 95.0,
+            recall: 96.5,
+            l_value: 37,
+            beam_width: Some(4),
+        };
+        let mut metadata = IndexMetadata::new::<Half>(config.clone());
+        metadata.tuned_search = Some(tuned_search);
+        metadata.save(METADATA_FILE).unwrap();
+
+        let metadata = IndexMetadata::load(METADATA_FILE).unwrap();
+        fs::remove_file(METADATA_FILE).expect("Failed to delete file");
+
+        assert_eq!(metadata.version, INDEX_METADATA_VERSION);
+        assert_eq!(metadata.data_type, "f16");
+        assert_eq!(metadata.configuration.dist_metric, Metric::Cosine);
+        assert_eq!(metadata.configuration.dim, 100);
+        assert_eq!(metadata.configuration.aligned_dim, 104);
+        assert_eq!(metadata.configuration.max_points, 1000);
+        assert_eq!(metadata.configuration.num_frozen_pts, 1);
+        assert_eq!(metadata.configuration.growth_potential, 1.2);
+        assert_eq!(
+            metadata.configuration.index_write_parameter,
+            index_write_parameters
+        );
+        assert_eq!(metadata.tuned_search, Some(tuned_search));
+
+        assert!(metadata.check_data_type::<Half>().is_ok());
+        assert!(matches!(
+            metadata.check_data_type::<BFloat16>(),
+            Err(ANNError::DataTypeMismatch { expected, found })
+                if expected == "bf16" && found == "f16"
+        ));
+        assert_eq!(f32::DATA_TYPE_NAME, "float");
+        assert_eq!(u8::DATA_TYPE_NAME, "uint8");
+    }
+}
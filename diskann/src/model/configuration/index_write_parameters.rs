@@ -6,6 +6,8 @@
 
 //! Index write parameters.
 
+use serde::{Deserialize, Serialize};
+
 /// Default parameter values.
 pub mod default_param_vals {
     /// Default value of alpha.
@@ -37,10 +39,13 @@ pub mod default_param_vals {
 
     /// Default value of search list size.
     pub const SEARCH_LIST_SIZE: u32 = 100;
+
+    /// Default value of seed, the random choices are seeded from entropy.
+    pub const SEED: Option<u64> = None;
 }
 
 /// Index write parameters.
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub struct IndexWriteParameters {
     /// Search list size - L.
     pub search_list_size: u32,
@@ -65,6 +70,11 @@ pub struct IndexWriteParameters {
 
     /// Number of frozen points.
     pub num_frozen_points: u32,
+
+    /// Seed of the random choices of the build: visit order, samples and k-means pivots.
+    /// With a seed and a single thread, builds of the same data are identical.
+    #[serde(default)]
+    pub seed: Option<u64>,
 }
 
 impl Default for IndexWriteParameters {
@@ -79,6 +89,7 @@ impl Default for IndexWriteParameters {
             num_rounds: default_param_vals::NUM_ROUNDS,
             num_threads: default_param_vals::NUM_THREADS,
             num_frozen_points: default_param_vals::NUM_FROZEN_POINTS,
+            seed: default_param_vals::SEED,
         }
     }
 }
@@ -95,6 +106,7 @@ pub struct IndexWriteParametersBuilder {
     num_threads: Option<u32>,
     // filter_list_size: Option<u32>,
     num_frozen_points: Option<u32>,
+    seed: Option<u64>,
 }
 
 impl IndexWriteParametersBuilder {
@@ -110,6 +122,7 @@ impl IndexWriteParametersBuilder {
             num_threads: None,
             // filter_list_size: None,
             num_frozen_points: None,
+            seed: None,
         }
     }
 
@@ -156,6 +169,12 @@ impl IndexWriteParametersBuilder {
         self
     }
 
+    /// Set seed.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
     /// Build IndexWriteParameters from IndexWriteParametersBuilder.
     pub fn build(self) -> IndexWriteParameters {
         IndexWriteParameters {
@@ -174,6 +193,7 @@ impl IndexWriteParametersBuilder {
             num_frozen_points: self
                 .num_frozen_points
                 .unwrap_or(default_param_vals::NUM_FROZEN_POINTS),
+            seed: self.seed.or(default_param_vals::SEED),
         }
     }
 }
@@ -191,6 +211,7 @@ impl From<IndexWriteParameters> for IndexWriteParametersBuilder {
             num_threads: Some(param.num_threads),
             // filter_list_size: Some(param.filter_list_size),
             num_frozen_points: Some(param.num_frozen_points),
+            seed: param.seed,
         }
     }
 }
@@ -213,6 +234,7 @@ mod parameters_test {
         assert_eq!(wp1.num_rounds, default_param_vals::NUM_ROUNDS);
         assert_eq!(wp1.num_threads, default_param_vals::NUM_THREADS);
         assert_eq!(wp1.num_frozen_points, default_param_vals::NUM_FROZEN_POINTS);
+        assert_eq!(wp1.seed, default_param_vals::SEED);
     }
 
     #[test]
@@ -230,6 +252,7 @@ mod parameters_test {
         assert_eq!(wp1.num_rounds, default_param_vals::NUM_ROUNDS);
         assert_eq!(wp1.num_threads, default_param_vals::NUM_THREADS);
         assert_eq!(wp1.num_frozen_points, default_param_vals::NUM_FROZEN_POINTS);
+        assert_eq!(wp1.seed, default_param_vals::SEED);
 
         // build with custom values
         let wp2 = IndexWriteParametersBuilder::new(10, 20)
@@ -239,6 +262,7 @@ mod parameters_test {
             .with_num_rounds(40)
             .with_num_threads(50)
             .with_num_frozen_points(60)
+            .with_seed(70)
             .build();
         assert_eq!(wp2.search_list_size, 10);
         assert_eq!(wp2.max_degree, 20);
@@ -248,6 +272,7 @@ mod parameters_test {
         assert_eq!(wp2.num_rounds, 40);
         assert_eq!(wp2.num_threads, 50);
         assert_eq!(wp2.num_frozen_points, 60);
+        assert_eq!(wp2.seed, Some(70));
 
         // test from
         let wp3 = IndexWriteParametersBuilder::from(wp2).build();
@@ -8,5 +8,8 @@ pub use index_configuration::IndexConfiguration;
 pub mod index_write_parameters;
 pub use index_write_parameters::*;
 
+pub mod index_metadata;
+pub use index_metadata::{IndexMetadata, TunedSearchParameters};
+
 pub mod disk_index_build_parameter;
 pub use disk_index_build_parameter::DiskIndexBuildParameters;
@@ -60,7 +60,7 @@ where
 
         let aligned_dim_vector = &mut self.data[self.cur_index * N..(self.cur_index + 1) * N];
 
-        assert!(fp_vector_buf.len() % size_of::<T>() == 0);
+        assert!(fp_vector_buf.len().is_multiple_of(size_of::<T>()));
         assert!(fp_vector_buf.len() <= size_of_val(aligned_dim_vector));
 
         // memcpy from fp_vector_buf to aligned_dim_vector
@@ -6,19 +6,14 @@
 
 //! In-memory Dataset
 
-use byteorder::{LittleEndian, ReadBytesExt};
 use rayon::prelude::*;
-use std::fs::File;
-use std::io::Seek;
 use std::mem;
 use vector::{FullPrecisionDistance, Metric};
 
 use crate::common::{ANNError, ANNResult, AlignedBoxWithSlice};
 use crate::index::INIT_WARMUP_DATA_LEN;
 use crate::model::Vertex;
-use crate::utils::{
-    copy_aligned_data_from_file, copy_aligned_data_from_reader, copy_aligned_data_from_vector,
-};
+use crate::utils::{copy_aligned_data_from_file, copy_aligned_data_from_vector, VectorFileReader};
 
 /// Dataset of all in-memory FP points
 #[derive(Debug)]
@@ -69,39 +64,36 @@ where
         filename: &str,
         num_points_to_load: usize,
     ) -> ANNResult<()> {
-        println!(
+        log::debug!(
             "Loading {} vectors from file {} into dataset...",
-            num_points_to_load, filename
+            num_points_to_load,
+            filename
         );
 
         // read number of point firstly to increase later
-        let mut reader = File::open(filename)?;
-        let new_data_len = reader.read_i32::<LittleEndian>()? as usize;
-        reader.seek(std::io::SeekFrom::Start(0))?;
+        let mut reader = VectorFileReader::<T>::open(filename)?;
+        self.or_increase_capacity(reader.num_points())?;
 
-        self.or_increase_capacity(new_data_len)?;
+        // The saved points replace the dataset, like the graph loaded along with them
+        let npts = reader.copy_aligned_into(&mut self.data, N, 0)?;
+        self.num_active_pts = npts;
 
-        let pts_offset = self.num_active_pts;
-        let (npts, _dim) = copy_aligned_data_from_reader(reader, self.into_dto(), pts_offset)?;
-        if npts != num_points_to_load {
-            self.num_active_pts = npts;
-        }
-
-        println!("Dataset loaded.");
+        log::debug!("Dataset loaded.");
         Ok(())
     }
 
     /// Build the dataset from file
     pub fn build_from_file(&mut self, filename: &str, num_points_to_load: usize) -> ANNResult<()> {
-        println!(
+        log::debug!(
             "Loading {} vectors from file {} into dataset...",
-            num_points_to_load, filename
+            num_points_to_load,
+            filename
         );
         self.num_active_pts = num_points_to_load;
 
         copy_aligned_data_from_file(filename, self.into_dto(), 0)?;
 
-        println!("Dataset loaded.");
+        log::debug!("Dataset loaded.");
         Ok(())
     }
 
@@ -111,9 +103,10 @@ where
         filename: &str,
         num_points_to_append: usize,
     ) -> ANNResult<()> {
-        println!(
+        log::debug!(
             "Appending {} vectors from file {} into dataset...",
-            num_points_to_append, filename
+            num_points_to_append,
+            filename
         );
         if self.num_points + num_points_to_append > self.capacity {
             return Err(ANNError::log_index_error(format!(
@@ -128,43 +121,56 @@ where
         self.num_active_pts += num_points_to_append;
         self.num_points += num_points_to_append;
 
-        println!("Dataset appended.");
+        log::debug!("Dataset appended.");
         Ok(())
     }
 
+    /// Grow the dataset so that new_data_len more points fit, returns true if the capacity changed
     pub fn or_increase_capacity(&mut self, new_data_len: usize) -> ANNResult<bool> {
         let need_capacity = (self.num_active_pts + new_data_len) * N;
         if need_capacity > self.data.capacity() {
             self.data.ensure_capacity(need_capacity)?;
+            self.capacity = self.data.capacity();
             Ok(true)
         } else {
             Ok(false)
         }
     }
 
+    /// Keep only the vectors of ids, in this order, and shrink the storage to them.
+    /// The first num_active_pts ids are the active points.
+    pub fn compact(&mut self, ids: &[u32], num_active_pts: usize) -> ANNResult<()> {
+        let mut data = AlignedBoxWithSlice::new(ids.len().max(1) * N, self.data.alignment())?;
+        for (new_id, &id) in ids.iter().enumerate() {
+            let start = id as usize * N;
+            data[new_id * N..(new_id + 1) * N].copy_from_slice(&self.data[start..start + N]);
+        }
+
+        self.data = data;
+        self.num_points = ids.len();
+        self.num_active_pts = num_active_pts;
+        self.capacity = self.data.capacity();
+        Ok(())
+    }
+
     /// Build the dataset from file
-    pub fn build_from_vector(&mut self, vector: &Vec<Vec<T>>) -> ANNResult<()> {
+    pub fn build_from_vector(&mut self, vector: &[Vec<T>]) -> ANNResult<()> {
         let num_points_to_append = vector.len();
-        println!(
-            "Loading {} vectors from file {} into dataset...",
-            num_points_to_append, 1
-        );
+        log::debug!("Loading {} vectors into dataset...", num_points_to_append);
 
         self.num_active_pts = num_points_to_append;
-        copy_aligned_data_from_vector(vector, self.into_dto(), 0, N)?;
+        let dim = vector.first().map_or(N, |point| point.len());
+        copy_aligned_data_from_vector(vector, self.into_dto(), 0, dim)?;
 
-        println!("Dataset loaded.");
+        log::debug!("Dataset loaded.");
         Ok(())
     }
 
     /// Append the dataset from file
     /// Return (id_start, id_len)
-    pub fn append_from_vector(&mut self, vector: &Vec<Vec<T>>) -> ANNResult<(usize, usize)> {
+    pub fn append_from_vector(&mut self, vector: &[Vec<T>]) -> ANNResult<(usize, usize)> {
         let num_points_to_append = vector.len();
-        println!(
-            "Appending {} vectors from file {:?} into dataset...",
-            num_points_to_append, 1
-        );
+        log::debug!("Appending {} vectors into dataset...", num_points_to_append);
         if self.num_points + num_points_to_append > self.capacity {
             return Err(ANNError::log_index_error(format!(
                 "Cannot append {} points to dataset of capacity {}",
@@ -173,12 +179,13 @@ where
         }
 
         let pts_offset = self.num_active_pts;
-        copy_aligned_data_from_vector(vector, self.into_dto(), pts_offset, N)?;
+        let dim = vector.first().map_or(N, |point| point.len());
+        copy_aligned_data_from_vector(vector, self.into_dto(), pts_offset, dim)?;
 
         self.num_active_pts += num_points_to_append;
         self.num_points += num_points_to_append;
 
-        println!("Dataset appended.");
+        log::debug!("Dataset appended.");
         Ok((
             pts_offset - INIT_WARMUP_DATA_LEN as usize,
             num_points_to_append,
@@ -277,7 +284,7 @@ where
     }
 
     /// Convert into dto object
-    pub fn into_dto(&mut self) -> DatasetDto<T> {
+    pub fn into_dto(&mut self) -> DatasetDto<'_, T> {
         DatasetDto {
             data: &mut self.data,
             rounded_dim: N,
@@ -300,6 +307,8 @@ pub struct DatasetDto<'a, T> {
 mod dataset_test {
     use std::fs;
 
+    use vector::BFloat16;
+
     use super::*;
     use crate::model::vertex::DIM_128;
 
@@ -329,6 +338,33 @@ mod dataset_test {
         };
     }
 
+    #[test]
+    fn compact_test() {
+        let mut dataset = InmemDataset::<f32, DIM_128>::new(10, 1f32).unwrap();
+        for id in 0..10 {
+            dataset.data[id * DIM_128..(id + 1) * DIM_128].fill(id as f32);
+        }
+        dataset.num_active_pts = 8;
+
+        // Points 0, 3 and 7 stay active, 9 is a frozen point
+        dataset.compact(&[0, 3, 7, 9], 3).unwrap();
+
+        assert_eq!(dataset.num_active_pts, 3);
+        assert_eq!(dataset.data.capacity(), 4 * DIM_128);
+        for (new_id, id) in [0, 3, 7, 9].into_iter().enumerate() {
+            assert!(dataset
+                .get_vertex(new_id as u32)
+                .unwrap()
+                .vector()
+                .iter()
+                .all(|&value| value == id as f32));
+        }
+        assert!(dataset.get_vertex(4).is_err());
+
+        dataset.or_increase_capacity(2).unwrap();
+        assert_eq!(dataset.capacity, dataset.data.capacity());
+    }
+
     #[test]
     fn load_data_test() {
         let file_name = "dataset_test_load_data_test.bin";
@@ -363,4 +399,55 @@ mod dataset_test {
             }
         }
     }
+
+    #[test]
+    fn load_bf16_data_test() {
+        let file_name = "dataset_test_load_bf16_data_test.bin";
+        //npoints=2, dim=8, 2 vectors [1.0..8.0] [9.0..16.0]
+        let mut data = vec![2, 0, 0, 0, 8, 0, 0, 0];
+        for i in 1..=16 {
+            data.extend_from_slice(&BFloat16::from_f32(i as f32).to_bits().to_le_bytes());
+        }
+        std::fs::write(file_name, data).expect("Failed to write sample file");
+
+        let mut dataset = InmemDataset::<BFloat16, 8>::new(2, 1f32).unwrap();
+        let result = dataset.build_from_file(file_name, 2);
+        fs::remove_file(file_name).expect("Failed to delete file");
+        result.unwrap();
+
+        let second_vertex = dataset.get_vertex(1).unwrap();
+        let second_vector: Vec<f32> = second_vertex.vector().iter().map(|x| x.to_f32()).collect();
+        assert_eq!(
+            second_vector,
+            [9.0, 10.0, 11.0, 12.0, 13.0, 14.0, 15.0, 16.0]
+        );
+        assert_eq!(dataset.get_distance(0, 1, Metric::L2).unwrap(), 512.0);
+    }
+
+    #[test]
+    fn build_from_file_with_enhance_test() {
+        let file_name = "dataset_test_build_from_file_with_enhance_test.bin";
+        //npoints=2, dim=8, 2 vectors [1.0..8.0] [9.0..16.0]
+        let mut data = vec![2, 0, 0, 0, 8, 0, 0, 0];
+        for i in 1..=16 {
+            data.extend_from_slice(&(i as f32).to_le_bytes());
+        }
+        std::fs::write(file_name, data).expect("Failed to write sample file");
+
+        // A new dataset counts all the points it has room for as active
+        let mut dataset = InmemDataset::<f32, 8>::new(4, 1f32).unwrap();
+        let result = dataset.build_from_file_with_enhance(file_name, 2);
+        fs::remove_file(file_name).expect("Failed to delete file");
+        result.unwrap();
+
+        assert_eq!(dataset.num_active_pts, 2);
+        assert_eq!(
+            *dataset.get_vertex(0).unwrap().vector(),
+            [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0]
+        );
+        assert_eq!(
+            *dataset.get_vertex(1).unwrap().vector(),
+            [9.0, 10.0, 11.0, 12.0, 13.0, 14.0, 15.0, 16.0]
+        );
+    }
 }
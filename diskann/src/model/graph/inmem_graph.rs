@@ -52,7 +52,7 @@ impl InMemoryGraph {
     pub fn read_vertex_and_neighbors(
         &self,
         vertex_id: u32,
-    ) -> Result<RwLockReadGuard<VertexAndNeighbors>, ANNError> {
+    ) -> Result<RwLockReadGuard<'_, VertexAndNeighbors>, ANNError> {
         self.final_graph[vertex_id as usize].read().map_err(|err| {
             ANNError::log_lock_poison_error(format!(
                 "PoisonError: Lock poisoned when reading final_graph for vertex_id {}, err={}",
@@ -65,7 +65,7 @@ impl InMemoryGraph {
     pub fn write_vertex_and_neighbors(
         &self,
         vertex_id: u32,
-    ) -> Result<RwLockWriteGuard<VertexAndNeighbors>, ANNError> {
+    ) -> Result<RwLockWriteGuard<'_, VertexAndNeighbors>, ANNError> {
         self.final_graph[vertex_id as usize].write().map_err(|err| {
             ANNError::log_lock_poison_error(format!(
                 "PoisonError: Lock poisoned when writing final_graph for vertex_id {}, err={}",
@@ -108,13 +108,10 @@ mod graph_tests {
         assert_eq!(graph.size(), 20);
 
         let capacity = (GRAPH_SLACK_FACTOR * 10_f64).ceil() as usize;
-        let mut id: u32 = 0;
-
-        for i in 10..20 {
+        for (id, i) in (0_u32..).zip(10..20) {
             let neighbor = graph.final_graph[i].read().unwrap();
             assert_eq!(neighbor.vertex_id, id);
             assert_eq!(neighbor.get_neighbors().capacity(), capacity);
-            id += 1;
         }
     }
 
@@ -0,0 +1,223 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+use std::fs::File;
+use std::os::unix::fs::FileExt;
+use std::sync::Arc;
+use std::thread;
+
+use crossbeam::sync::ShardedLock;
+use hashbrown::HashMap;
+use once_cell::sync::Lazy;
+
+use crate::common::{ANNError, ANNResult};
+use crate::model::IOContext;
+
+pub const DISK_IO_ALIGNMENT: usize = 512;
+
+/// Aligned read struct for disk IO, it takes the ownership of the AlignedBoxedSlice and returns the AlignedBoxWithSlice data immutably.
+pub struct AlignedRead<'a, T> {
+    /// where to read from
+    /// offset needs to be aligned with DISK_IO_ALIGNMENT
+    offset: u64,
+
+    /// where to read into
+    /// aligned_buf and its len need to be aligned with DISK_IO_ALIGNMENT
+    aligned_buf: &'a mut [T],
+}
+
+impl<'a, T> AlignedRead<'a, T> {
+    pub fn new(offset: u64, aligned_buf: &'a mut [T]) -> ANNResult<Self> {
+        Self::assert_is_aligned(offset as usize)?;
+        Self::assert_is_aligned(std::mem::size_of_val(aligned_buf))?;
+
+        Ok(Self {
+            offset,
+            aligned_buf,
+        })
+    }
+
+    fn assert_is_aligned(val: usize) -> ANNResult<()> {
+        match val % DISK_IO_ALIGNMENT {
+            0 => Ok(()),
+            _ => Err(ANNError::log_disk_io_request_alignment_error(format!(
+                "The offset or length of AlignedRead request is not {} bytes aligned",
+                DISK_IO_ALIGNMENT
+            ))),
+        }
+    }
+
+    pub fn aligned_buf(&self) -> &[T] {
+        self.aligned_buf
+    }
+}
+
+pub struct LinuxAlignedFileReader {
+    file_name: String,
+
+    // ctx_map is the mapping from thread id to io context, see WindowsAlignedFileReader for why it is behind a ShardedLock.
+    // Each thread gets its own file descriptor so that positional reads never contend on a shared file offset.
+    ctx_map: Lazy<ShardedLock<HashMap<thread::ThreadId, Arc<IOContext>>>>,
+}
+
+impl LinuxAlignedFileReader {
+    pub fn new(fname: &str) -> ANNResult<Self> {
+        let reader: LinuxAlignedFileReader = LinuxAlignedFileReader {
+            file_name: fname.to_string(),
+            ctx_map: Lazy::new(|| ShardedLock::new(HashMap::new())),
+        };
+
+        reader.register_thread()?;
+        Ok(reader)
+    }
+
+    // Register the io context for a thread if it hasn't been registered.
+    pub fn register_thread(&self) -> ANNResult<()> {
+        let id = thread::current().id();
+
+        // Search threads register on every query, so check under the read lock first.
+        {
+            let ctx_map = self.ctx_map.read().map_err(|_| {
+                ANNError::log_lock_poison_error(
+                    "unable to acquire read lock on ctx_map".to_string(),
+                )
+            })?;
+            if ctx_map.contains_key(&id) {
+                return Ok(());
+            }
+        }
+
+        let mut ctx_map = self.ctx_map.write().map_err(|_| {
+            ANNError::log_lock_poison_error("unable to acquire write lock on ctx_map".to_string())
+        })?;
+
+        if ctx_map.contains_key(&id) {
+            return Ok(());
+        }
+
+        let mut ctx = IOContext::new();
+        match File::open(&self.file_name) {
+            Ok(file) => ctx.file_handle = Some(file),
+            Err(err) => {
+                return Err(ANNError::log_io_error(err));
+            }
+        }
+
+        ctx_map.insert(id, Arc::new(ctx));
+
+        Ok(())
+    }
+
+    // Get the reference counted io context for the current thread.
+    pub fn get_ctx(&self) -> ANNResult<Arc<IOContext>> {
+        let ctx_map = self.ctx_map.read().map_err(|_| {
+            ANNError::log_lock_poison_error("unable to acquire read lock on ctx_map".to_string())
+        })?;
+
+        let id = thread::current().id();
+        match ctx_map.get(&id) {
+            Some(ctx) => Ok(Arc::clone(ctx)),
+            None => Err(ANNError::log_index_error(format!(
+                "unable to find IOContext for thread_id {:?}",
+                id
+            ))),
+        }
+    }
+
+    // Read the data from the file with positional reads, one request after another.
+    pub fn read<T>(&self, read_requests: &mut [AlignedRead<T>], ctx: &IOContext) -> ANNResult<()> {
+        let file = ctx.file_handle.as_ref().ok_or_else(|| {
+            ANNError::log_index_error("IOContext has no open file handle".to_string())
+        })?;
+
+        for req in read_requests.iter_mut() {
+            let len = std::mem::size_of_val(req.aligned_buf);
+
+            // Safety: the buffer is a valid, exclusively borrowed slice of `len` bytes.
+            let buf = unsafe {
+                std::slice::from_raw_parts_mut(req.aligned_buf.as_mut_ptr() as *mut u8, len)
+            };
+            file.read_exact_at(buf, req.offset)
+                .map_err(ANNError::log_io_error)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{common::AlignedBoxWithSlice, model::SECTOR_LEN};
+
+    use super::*;
+    pub const TEST_INDEX_PATH: &str =
+        "./tests/data/disk_index_siftsmall_learn_256pts_R4_L50_A1.2_alligned_reader_test.index";
+
+    #[test]
+    fn test_new_aligned_file_reader() {
+        let result = LinuxAlignedFileReader::new(TEST_INDEX_PATH);
+        assert!(result.is_ok());
+
+        let reader = result.unwrap();
+        assert_eq!(reader.file_name, TEST_INDEX_PATH);
+    }
+
+    #[test]
+    fn test_read_disk_index_by_sector() {
+        let reader = LinuxAlignedFileReader::new(TEST_INDEX_PATH).unwrap();
+        let ctx = reader.get_ctx().unwrap();
+
+        let num_sector = 2;
+        let mut aligned_mem = AlignedBoxWithSlice::<u8>::new(SECTOR_LEN * num_sector, 512).unwrap();
+        let mut mem_slices = aligned_mem
+            .split_into_nonoverlapping_mut_slices(0..aligned_mem.len(), SECTOR_LEN)
+            .unwrap();
+
+        let mut aligned_reads: Vec<AlignedRead<'_, u8>> = mem_slices
+            .iter_mut()
+            .enumerate()
+            .map(|(sector_id, slice)| {
+                AlignedRead::new((sector_id * SECTOR_LEN) as u64, slice).unwrap()
+            })
+            .collect();
+
+        reader.read(&mut aligned_reads, &ctx).unwrap();
+
+        // The metadata sector starts with the {npts: i32}{dims: i32} header followed by num_pts and dims.
+        let meta = aligned_reads[0].aligned_buf();
+        let num_pts = u64::from_le_bytes(meta[8..16].try_into().unwrap());
+        let dims = u64::from_le_bytes(meta[16..24].try_into().unwrap());
+        assert_eq!(num_pts, 256);
+        assert_eq!(dims, 128);
+    }
+
+    #[test]
+    fn test_read_fail_invalid_file() {
+        let reader = LinuxAlignedFileReader::new("/invalid_path");
+        assert!(reader.is_err());
+    }
+
+    #[test]
+    fn test_read_fail_unaligned_request() {
+        let mut buf = vec![0u8; 100];
+        assert!(AlignedRead::new(0, &mut buf).is_err());
+    }
+
+    #[test]
+    fn test_register_thread() {
+        let reader = LinuxAlignedFileReader::new(TEST_INDEX_PATH).unwrap();
+        assert!(reader.register_thread().is_ok());
+
+        let handle = std::thread::scope(|s| {
+            s.spawn(|| {
+                assert!(reader.get_ctx().is_err());
+                reader.register_thread().unwrap();
+                reader.get_ctx().is_ok()
+            })
+            .join()
+            .unwrap()
+        });
+        assert!(handle);
+    }
+}
@@ -23,6 +23,9 @@ pub use scratch::*;
 pub mod vertex;
 pub use vertex::Vertex;
 
+pub mod payload;
+pub use payload::*;
+
 cfg_if! {
     if #[cfg(feature = "disk_store")] {
         pub mod pq;
@@ -32,9 +35,11 @@ cfg_if! {
             if #[cfg(target_os = "windows")] {
                 pub mod windows_aligned_file_reader;
                 pub use windows_aligned_file_reader::*;
+                pub type AlignedFileReader = WindowsAlignedFileReader;
             } else {
-                //pub mod linux_aligned_file_reader;
-                //pub use linux_aligned_file_reader::*;
+                pub mod linux_aligned_file_reader;
+                pub use linux_aligned_file_reader::*;
+                pub type AlignedFileReader = LinuxAlignedFileReader;
             }
         }
     }
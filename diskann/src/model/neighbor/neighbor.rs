@@ -62,6 +62,7 @@ impl Ord for Neighbor {
     }
 }
 
+#[allow(clippy::non_canonical_partial_ord_impl)]
 impl PartialOrd for Neighbor {
     #[inline]
     fn lt(&self, other: &Self) -> bool {
@@ -0,0 +1,290 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+#![warn(missing_debug_implementations, missing_docs)]
+
+//! Payloads attached to the vertices of an index
+//!
+//! A store either holds arbitrary bytes, or records checked against a small schema of
+//! named typed fields. It is saved with bincode to `{index path}.payload`.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::{ANNError, ANNResult};
+
+/// Type of a field of a payload schema
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PayloadType {
+    /// Boolean
+    Bool,
+
+    /// 64-bit signed integer
+    Int,
+
+    /// 64-bit float
+    Float,
+
+    /// UTF-8 string
+    Text,
+
+    /// Raw bytes
+    Bytes,
+}
+
+/// Value of a field of a payload record
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PayloadValue {
+    /// Boolean
+    Bool(bool),
+
+    /// 64-bit signed integer
+    Int(i64),
+
+    /// 64-bit float
+    Float(f64),
+
+    /// UTF-8 string
+    Text(String),
+
+    /// Raw bytes
+    Bytes(Vec<u8>),
+}
+
+impl PayloadValue {
+    /// Type of the value
+    pub fn payload_type(&self) -> PayloadType {
+        match self {
+            PayloadValue::Bool(_) => PayloadType::Bool,
+            PayloadValue::Int(_) => PayloadType::Int,
+            PayloadValue::Float(_) => PayloadType::Float,
+            PayloadValue::Text(_) => PayloadType::Text,
+            PayloadValue::Bytes(_) => PayloadType::Bytes,
+        }
+    }
+}
+
+/// Payload of a vertex
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Payload {
+    /// Arbitrary bytes, for stores without a schema
+    Bytes(Vec<u8>),
+
+    /// One value per field of the schema, in the order of the schema
+    Record(Vec<PayloadValue>),
+}
+
+/// Named typed fields of the payload records
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayloadSchema {
+    fields: Vec<(String, PayloadType)>,
+}
+
+impl PayloadSchema {
+    /// Create an empty schema
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a field
+    pub fn with_field(mut self, name: &str, payload_type: PayloadType) -> Self {
+        self.fields.push((name.to_string(), payload_type));
+        self
+    }
+
+    /// Fields in record order
+    pub fn fields(&self) -> &[(String, PayloadType)] {
+        &self.fields
+    }
+
+    /// Position of a field in the records
+    pub fn field_index(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|(field, _)| field == name)
+    }
+}
+
+/// Payloads keyed by vertex id
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PayloadStore {
+    schema: Option<PayloadSchema>,
+
+    payloads: HashMap<u32, Payload>,
+}
+
+impl PayloadStore {
+    /// Create a store of byte payloads, or of records if a schema is given
+    pub fn new(schema: Option<PayloadSchema>) -> Self {
+        Self {
+            schema,
+            payloads: HashMap::new(),
+        }
+    }
+
+    /// Schema of the records, None for byte payloads
+    pub fn schema(&self) -> Option<&PayloadSchema> {
+        self.schema.as_ref()
+    }
+
+    /// Number of vertices with a payload
+    pub fn len(&self) -> usize {
+        self.payloads.len()
+    }
+
+    /// Whether no vertex has a payload
+    pub fn is_empty(&self) -> bool {
+        self.payloads.is_empty()
+    }
+
+    /// Check a payload against the schema of the store
+    pub fn validate(&self, payload: &Payload) -> ANNResult<()> {
+        match (&self.schema, payload) {
+            (None, Payload::Bytes(_)) => Ok(()),
+            (None, Payload::Record(_)) => Err(ANNError::log_index_error(
+                "Record payloads need a payload schema".to_string(),
+            )),
+            (Some(_), Payload::Bytes(_)) => Err(ANNError::log_index_error(
+                "Payloads must be records of the payload schema".to_string(),
+            )),
+            (Some(schema), Payload::Record(values)) => {
+                if values.len() != schema.fields.len() {
+                    return Err(ANNError::log_index_error(format!(
+                        "Payload record has {} values, the schema has {} fields",
+                        values.len(),
+                        schema.fields.len()
+                    )));
+                }
+
+                for ((name, payload_type), value) in schema.fields.iter().zip(values) {
+                    if value.payload_type() != *payload_type {
+                        return Err(ANNError::log_index_error(format!(
+                            "Payload field {} expects {:?}, got {:?}",
+                            name,
+                            payload_type,
+                            value.payload_type()
+                        )));
+                    }
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Set the payload of a vertex after checking it against the schema
+    pub fn insert(&mut self, vertex_id: u32, payload: Payload) -> ANNResult<()> {
+        self.validate(&payload)?;
+        self.payloads.insert(vertex_id, payload);
+        Ok(())
+    }
+
+    /// Payload of a vertex
+    pub fn get(&self, vertex_id: u32) -> Option<&Payload> {
+        self.payloads.get(&vertex_id)
+    }
+
+    /// Remove the payload of a vertex
+    pub fn remove(&mut self, vertex_id: u32) -> Option<Payload> {
+        self.payloads.remove(&vertex_id)
+    }
+
+    /// Move every payload to the vertex id new_id maps its vertex to, dropping it on None
+    pub fn remap(&mut self, new_id: impl Fn(u32) -> Option<u32>) {
+        self.payloads = self
+            .payloads
+            .drain()
+            .filter_map(|(vertex_id, payload)| new_id(vertex_id).map(|id| (id, payload)))
+            .collect();
+    }
+
+    /// Save the store to a file
+    pub fn save(&self, filename: &str) -> ANNResult<()> {
+        let writer = BufWriter::new(File::create(filename)?);
+        bincode::serialize_into(writer, self).map_err(|err| {
+            ANNError::log_index_error(format!("Failed to save payloads to {}: {}", filename, err))
+        })
+    }
+
+    /// Load a store saved with save
+    pub fn load(filename: &str) -> ANNResult<Self> {
+        let reader = BufReader::new(File::open(filename)?);
+        bincode::deserialize_from(reader).map_err(|err| {
+            ANNError::log_index_error(format!(
+                "Failed to load payloads from {}: {}",
+                filename, err
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod payload_store_test {
+    use super::*;
+
+    fn test_schema() -> PayloadSchema {
+        PayloadSchema::new()
+            .with_field("title", PayloadType::Text)
+            .with_field("year", PayloadType::Int)
+    }
+
+    #[test]
+    fn schema_validation_test() {
+        let mut store = PayloadStore::new(Some(test_schema()));
+        let record = Payload::Record(vec![
+            PayloadValue::Text("dune".to_string()),
+            PayloadValue::Int(1965),
+        ]);
+        store.insert(3, record.clone()).unwrap();
+        assert_eq!(store.get(3), Some(&record));
+        assert_eq!(test_schema().field_index("year"), Some(1));
+
+        assert!(store.insert(4, Payload::Bytes(vec![1, 2])).is_err());
+        assert!(store
+            .insert(
+                4,
+                Payload::Record(vec![PayloadValue::Text("x".to_string())])
+            )
+            .is_err());
+        assert!(store
+            .insert(
+                4,
+                Payload::Record(vec![PayloadValue::Int(1), PayloadValue::Int(2)])
+            )
+            .is_err());
+        assert_eq!(store.len(), 1);
+
+        let mut bytes_store = PayloadStore::new(None);
+        bytes_store.insert(0, Payload::Bytes(vec![7])).unwrap();
+        assert!(bytes_store.insert(1, record).is_err());
+    }
+
+    #[test]
+    fn save_and_load_test() {
+        let path = "tests/data/payload_store_test.payload";
+        let mut store = PayloadStore::new(Some(test_schema()));
+        for id in 0..10 {
+            store
+                .insert(
+                    id,
+                    Payload::Record(vec![
+                        PayloadValue::Text(format!("doc {}", id)),
+                        PayloadValue::Int(id as i64),
+                    ]),
+                )
+                .unwrap();
+        }
+        store.remove(5);
+        store.save(path).unwrap();
+
+        let loaded = PayloadStore::load(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded.schema(), Some(&test_schema()));
+        assert_eq!(loaded.len(), 9);
+        assert!(loaded.get(5).is_none());
+        assert_eq!(loaded.get(7), store.get(7));
+    }
+}
@@ -8,7 +8,7 @@ use hashbrown::HashMap;
 use rayon::prelude::{
     IndexedParallelIterator, IntoParallelRefMutIterator, ParallelIterator, ParallelSliceMut,
 };
-use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+use vector::prefetch_cache_line;
 
 use crate::{
     common::{ANNError, ANNResult},
@@ -219,21 +219,19 @@ pub fn pq_dist_lookup(
     pq_dists: &[f32],
 ) -> Vec<f32> {
     let mut dists_out: Vec<f32> = vec![0.0; n_pts];
-    unsafe {
-        _mm_prefetch(dists_out.as_ptr() as *const i8, _MM_HINT_T0);
-        _mm_prefetch(pq_ids.as_ptr() as *const i8, _MM_HINT_T0);
-        _mm_prefetch(pq_ids.as_ptr().add(64) as *const i8, _MM_HINT_T0);
-        _mm_prefetch(pq_ids.as_ptr().add(128) as *const i8, _MM_HINT_T0);
-    }
+    prefetch_cache_line(dists_out.as_ptr());
+    prefetch_cache_line(pq_ids.as_ptr());
+    prefetch_cache_line(pq_ids.as_ptr().wrapping_add(64));
+    prefetch_cache_line(pq_ids.as_ptr().wrapping_add(128));
     for chunk in 0..pq_nchunks {
         let chunk_dists = &pq_dists[256 * chunk..];
         if chunk < pq_nchunks - 1 {
-            unsafe {
-                _mm_prefetch(
-                    chunk_dists.as_ptr().offset(256 * chunk as isize).add(256) as *const i8,
-                    _MM_HINT_T0,
-                );
-            }
+            prefetch_cache_line(
+                chunk_dists
+                    .as_ptr()
+                    .wrapping_offset(256 * chunk as isize)
+                    .wrapping_add(256),
+            );
         }
         dists_out
             .par_iter_mut()
@@ -417,6 +415,7 @@ mod fixed_chunk_pq_table_test {
         );
     }
 
+    #[allow(clippy::type_complexity)]
     fn load_pq_pivots_bin(
         pq_pivots_path: &str,
         num_pq_chunks: &usize,
@@ -477,7 +476,7 @@ mod pq_index_prune_query_test {
 
         let dists_out = pq_dist_lookup(&pq_ids, 2, 2, &pq_dists);
         assert_eq!(dists_out.len(), 2);
-        assert_eq!(dists_out[0], pq_dists[0 + 1] + pq_dists[256 + 3]);
-        assert_eq!(dists_out[1], pq_dists[0 + 2] + pq_dists[256 + 2]);
+        assert_eq!(dists_out[0], pq_dists[1] + pq_dists[256 + 3]);
+        assert_eq!(dists_out[1], pq_dists[2] + pq_dists[256 + 2]);
     }
 }
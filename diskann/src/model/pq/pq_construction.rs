@@ -4,12 +4,13 @@
  */
 #![warn(missing_debug_implementations)]
 
+use rand::Rng;
 use rayon::prelude::{IndexedParallelIterator, ParallelIterator};
 use rayon::slice::ParallelSliceMut;
 
 use crate::common::{ANNError, ANNResult};
 use crate::storage::PQStorage;
-use crate::utils::{compute_closest_centers, file_exists, k_means_clustering};
+use crate::utils::{compute_closest_centers, create_rng, file_exists, k_means_clustering};
 
 /// Max size of PQ training set
 pub const MAX_PQ_TRAINING_SET_SIZE: f64 = 256_000f64;
@@ -28,6 +29,7 @@ const NUM_KMEANS_REPS_PQ: usize = 12;
 /// k-means in each chunk to compute the PQ pivots and stores in bin format in
 /// file pq_pivots_path as a s num_centers*dim floating point binary file
 /// PQ pivot table layout: {pivot offsets data: METADATA_SIZE}{pivot vector:[dim; num_centroid]}{centroid vector:[dim; 1]}{chunk offsets:[chunk_num+1; 1]}
+#[allow(clippy::too_many_arguments)]
 fn generate_pq_pivots(
     train_data: &mut [f32],
     num_train: usize,
@@ -36,6 +38,7 @@ fn generate_pq_pivots(
     num_pq_chunks: usize,
     max_k_means_reps: usize,
     pq_storage: &mut PQStorage,
+    rng: &mut impl Rng,
 ) -> ANNResult<()> {
     if num_pq_chunks > dim {
         return Err(ANNError::log_pq_error(
@@ -108,6 +111,7 @@ fn generate_pq_pivots(
             &mut cur_pivot_data,
             num_centers,
             max_k_means_reps,
+            rng,
         )?;
 
         // Copy centroids from this chunk table to full table
@@ -252,19 +256,23 @@ fn generate_pq_data_from_pivots<T: Copy + Into<f32>>(
 /// * `num_pq_chunks` - pq chunk number
 /// * `codebook_prefix` - predefined pivots file named
 /// * `pq_storage` - pq file access
+/// * `seed` - seed of the training sample and of the k-means pivots, None to seed from entropy
 pub fn generate_quantized_data<T: Default + Copy + Into<f32>>(
     p_val: f64,
     num_pq_chunks: usize,
     codebook_prefix: &str,
     pq_storage: &mut PQStorage,
+    seed: Option<u64>,
 ) -> ANNResult<()> {
     // If predefined pivots already exists, skip training.
     if !file_exists(codebook_prefix) {
+        let mut rng = create_rng(seed);
+
         // Instantiates train data with random sample updates train_data_vector
         // Training data with train_size samples loaded.
         // Each sampled file has train_dim.
         let (mut train_data_vector, train_size, train_dim) =
-            pq_storage.gen_random_slice::<T>(p_val)?;
+            pq_storage.gen_random_slice::<T>(p_val, &mut rng)?;
 
         generate_pq_pivots(
             &mut train_data_vector,
@@ -274,6 +282,7 @@ pub fn generate_quantized_data<T: Default + Copy + Into<f32>>(
             num_pq_chunks,
             NUM_KMEANS_REPS_PQ,
             pq_storage,
+            &mut rng,
         )?;
     }
     generate_pq_data_from_pivots::<T>(NUM_PQ_CENTROIDS, num_pq_chunks, pq_storage)?;
@@ -302,7 +311,17 @@ mod pq_test {
             2.1f32, 2.1f32, 2.2f32, 2.2f32, 2.2f32, 2.2f32, 2.2f32, 2.2f32, 2.2f32, 2.2f32,
             100.0f32, 100.0f32, 100.0f32, 100.0f32, 100.0f32, 100.0f32, 100.0f32, 100.0f32,
         ];
-        generate_pq_pivots(&mut train_data, 5, 8, 2, 2, 5, &mut pq_storage).unwrap();
+        generate_pq_pivots(
+            &mut train_data,
+            5,
+            8,
+            2,
+            2,
+            5,
+            &mut pq_storage,
+            &mut rand::thread_rng(),
+        )
+        .unwrap();
 
         let (data, nr, nc) = load_bin::<u64>(pivot_file_name, 0).unwrap();
         let file_offset_data = convert_types_u64_usize(&data, nr, nc);
@@ -363,7 +382,17 @@ mod pq_test {
         let pq_compressed_vectors_path = "generate_pq_data_from_pivots_test.bin";
         let mut pq_storage =
             PQStorage::new(pq_pivots_path, pq_compressed_vectors_path, data_file).unwrap();
-        generate_pq_pivots(&mut train_data, 5, 8, 2, 2, 5, &mut pq_storage).unwrap();
+        generate_pq_pivots(
+            &mut train_data,
+            5,
+            8,
+            2,
+            2,
+            5,
+            &mut pq_storage,
+            &mut rand::thread_rng(),
+        )
+        .unwrap();
         generate_pq_data_from_pivots::<f32>(2, 2, &mut pq_storage).unwrap();
         let (data, nr, nc) = load_bin::<u8>(pq_compressed_vectors_path, 0).unwrap();
         assert_eq!(nr, 5);
@@ -384,7 +413,7 @@ mod pq_test {
         let pq_compressed_vectors_path = "validation.bin";
         let mut pq_storage =
             PQStorage::new(pq_pivots_path, pq_compressed_vectors_path, data_file).unwrap();
-        generate_quantized_data::<f32>(0.5, 1, pq_pivots_path, &mut pq_storage).unwrap();
+        generate_quantized_data::<f32>(0.5, 1, pq_pivots_path, &mut pq_storage, None).unwrap();
 
         let (data, nr, nc) = load_bin::<u8>(pq_compressed_vectors_path, 0).unwrap();
         let (gt_data, gt_nr, gt_nc) = load_bin::<u8>(gound_truth_path, 0).unwrap();
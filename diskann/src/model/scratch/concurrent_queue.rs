@@ -114,7 +114,7 @@ impl<T> ConcurrentQueue<T> {
     }
 }
 
-fn lock<T>(mutex: &Mutex<T>) -> ANNResult<MutexGuard<T>> {
+fn lock<T>(mutex: &Mutex<T>) -> ANNResult<MutexGuard<'_, T>> {
     let guard = mutex.lock().map_err(|err| {
         ANNError::log_lock_poison_error(format!("ConcurrentQueue lock is poisoned, err={}", err))
     })?;
@@ -209,7 +209,7 @@ mod tests {
         let queue = ConcurrentQueue::new();
 
         let data = vec![1, 2, 3];
-        queue.insert(data.into_iter()).unwrap();
+        queue.insert(data).unwrap();
 
         assert_eq!(queue.pop().unwrap(), Some(1));
         assert_eq!(queue.pop().unwrap(), Some(2));
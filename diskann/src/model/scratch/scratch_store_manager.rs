@@ -3,10 +3,11 @@
  * Licensed under the MIT license.
  */
 use crate::common::ANNResult;
+use crate::instrumentation::metrics;
 
 use super::scratch_traits::Scratch;
 use super::ArcConcurrentBoxedQueue;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 pub struct ScratchStoreManager<T: Scratch> {
     scratch: Option<Box<T>>,
@@ -16,9 +17,18 @@ pub struct ScratchStoreManager<T: Scratch> {
 impl<T: Scratch> ScratchStoreManager<T> {
     pub fn new(scratch_pool: ArcConcurrentBoxedQueue<T>, wait_time: Duration) -> ANNResult<Self> {
         let mut scratch = scratch_pool.pop()?;
-        while scratch.is_none() {
-            scratch_pool.wait_for_push_notify(wait_time)?;
-            scratch = scratch_pool.pop()?;
+        if scratch.is_none() {
+            let start = Instant::now();
+            while scratch.is_none() {
+                scratch_pool.wait_for_push_notify(wait_time)?;
+                scratch = scratch_pool.pop()?;
+            }
+
+            let metrics = metrics();
+            metrics.scratch_waits.inc();
+            metrics
+                .scratch_wait_duration
+                .observe_duration(start.elapsed());
         }
 
         Ok(ScratchStoreManager {
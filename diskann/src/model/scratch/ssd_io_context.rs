@@ -3,23 +3,47 @@
  * Licensed under the MIT license.
  */
 #![allow(dead_code)] // Todo: Remove this when the disk index query code is complete.
+use cfg_if::cfg_if;
+
 use crate::common::ANNError;
 
-use platform::{FileHandle, IOCompletionPort};
+cfg_if! {
+    if #[cfg(target_os = "windows")] {
+        use platform::{FileHandle, IOCompletionPort};
 
-// The IOContext struct for disk I/O. One for each thread.
-pub struct IOContext {
-    pub status: Status,
-    pub file_handle: FileHandle,
-    pub io_completion_port: IOCompletionPort,
-}
+        // The IOContext struct for disk I/O. One for each thread.
+        pub struct IOContext {
+            pub status: Status,
+            pub file_handle: FileHandle,
+            pub io_completion_port: IOCompletionPort,
+        }
+
+        impl Default for IOContext {
+            fn default() -> Self {
+                IOContext {
+                    status: Status::ReadWait,
+                    file_handle: FileHandle::default(),
+                    io_completion_port: IOCompletionPort::default(),
+                }
+            }
+        }
+    } else {
+        use std::fs::File;
+
+        // The IOContext struct for disk I/O. One for each thread.
+        // The file is opened when the thread registers with the LinuxAlignedFileReader.
+        pub struct IOContext {
+            pub status: Status,
+            pub file_handle: Option<File>,
+        }
 
-impl Default for IOContext {
-    fn default() -> Self {
-        IOContext {
-            status: Status::ReadWait,
-            file_handle: FileHandle::default(),
-            io_completion_port: IOCompletionPort::default(),
+        impl Default for IOContext {
+            fn default() -> Self {
+                IOContext {
+                    status: Status::ReadWait,
+                    file_handle: None,
+                }
+            }
         }
     }
 }
@@ -2,7 +2,6 @@
  * Copyright (c) Microsoft Corporation. All rights reserved.
  * Licensed under the MIT license.
  */
-#![allow(dead_code)] // Todo: Remove this when the disk index query code is complete.
 use std::mem;
 use std::vec::Vec;
 
@@ -100,8 +100,8 @@ impl WindowsAlignedFileReader {
 
         let id = thread::current().id();
         if ctx_map.contains_key(&id) {
-            println!(
-                "Warning:: Duplicate registration for thread_id : {:?}. Directly call get_ctx to get the thread context data.", 
+            log::warn!(
+                "Duplicate registration for thread_id : {:?}. Directly call get_ctx to get the thread context data.", 
                 id);
 
             return Ok(());
@@ -10,14 +10,14 @@ use std::sync::Arc;
 
 use crate::{
     common::ANNResult,
-    model::{AlignedRead, IOContext, WindowsAlignedFileReader},
+    model::{AlignedFileReader, AlignedRead, IOContext},
 };
 
 /// Graph storage for disk index
 /// One thread has one storage instance
 pub struct DiskGraphStorage {
     /// Disk graph reader
-    disk_graph_reader: Arc<WindowsAlignedFileReader>,
+    disk_graph_reader: Arc<AlignedFileReader>,
 
     /// IOContext of current thread
     ctx: Arc<IOContext>,
@@ -25,7 +25,7 @@ pub struct DiskGraphStorage {
 
 impl DiskGraphStorage {
     /// Create a new DiskGraphStorage instance
-    pub fn new(disk_graph_reader: Arc<WindowsAlignedFileReader>) -> ANNResult<Self> {
+    pub fn new(disk_graph_reader: Arc<AlignedFileReader>) -> ANNResult<Self> {
         let ctx = disk_graph_reader.get_ctx()?;
         Ok(Self {
             disk_graph_reader,
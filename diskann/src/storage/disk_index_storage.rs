@@ -9,22 +9,59 @@ use std::marker::PhantomData;
 use std::{fs, mem};
 
 use crate::common::{ANNError, ANNResult};
-use crate::model::NUM_PQ_CENTROIDS;
+use crate::model::{IndexMetadata, NUM_PQ_CENTROIDS};
 use crate::storage::PQStorage;
 use crate::utils::{convert_types_u32_usize, convert_types_u64_usize, load_bin, save_bin_u64};
 use crate::utils::{
-    file_exists, gen_sample_data, get_file_size, round_up, CachedReader, CachedWriter,
+    create_rng, file_exists, gen_sample_data, get_file_size, round_up, CachedReader, CachedWriter,
 };
 
 const SECTOR_LEN: usize = 4096;
 
-/// Todo: Remove the allow(dead_code) when the disk search code is complete
-#[allow(dead_code)]
+/// PQ pivot table loaded from the pivot file
 pub struct PQPivotData {
-    dim: usize,
-    pq_table: Vec<f32>,
-    centroids: Vec<f32>,
-    chunk_offsets: Vec<usize>,
+    /// dimension of the full precision vectors
+    pub dim: usize,
+
+    /// pivot table: NUM_PQ_CENTROIDS * dim
+    pub pq_table: Vec<f32>,
+
+    /// centroid of each dimension
+    pub centroids: Vec<f32>,
+
+    /// offset of each chunk, num_pq_chunks + 1 entries
+    pub chunk_offsets: Vec<usize>,
+}
+
+/// Disk layout metadata stored in the first sector of the disk index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskLayoutMeta {
+    /// number of points
+    pub num_pts: u64,
+
+    /// dim of fp vector
+    pub dims: u64,
+
+    /// medoid (start point) of the graph
+    pub medoid: u64,
+
+    /// max node length in bytes
+    pub max_node_len: u64,
+
+    /// number of nodes per sector
+    pub num_nodes_per_sector: u64,
+
+    /// number of frozen points
+    pub num_frozen_pts: u64,
+
+    /// location of the frozen point
+    pub frozen_loc: u64,
+
+    /// whether reorder data is appended, always false for now
+    pub append_reorder_data: bool,
+
+    /// disk index file size in bytes
+    pub file_size: u64,
 }
 
 pub struct DiskIndexStorage<T> {
@@ -57,6 +94,22 @@ impl<T> DiskIndexStorage<T> {
         })
     }
 
+    /// Create DiskIndexStorage instance for the query path
+    /// The dataset file is not needed to search the disk index
+    pub fn new_for_search(index_path_prefix: String) -> ANNResult<Self> {
+        let pq_storage = PQStorage::new_for_search(
+            &(index_path_prefix.clone() + ".bin_pq_pivots.bin"),
+            &(index_path_prefix.clone() + ".bin_pq_compressed.bin"),
+        );
+
+        Ok(DiskIndexStorage {
+            dataset_file: String::new(),
+            index_path_prefix,
+            _marker: PhantomData,
+            pq_storage,
+        })
+    }
+
     pub fn get_pq_storage(&mut self) -> &mut PQStorage {
         &mut self.pq_storage
     }
@@ -91,14 +144,14 @@ impl<T> DiskIndexStorage<T> {
 
         // Create cached reader + writer
         let actual_file_size = get_file_size(mem_index_file.as_str())?;
-        println!("Vamana index file size={}", actual_file_size);
+        log::debug!("Vamana index file size={}", actual_file_size);
 
         let mut vamana_reader = File::open(mem_index_file)?;
         let mut diskann_writer = CachedWriter::new(disk_layout_file.as_str(), write_blk_size)?;
 
         let index_file_size = vamana_reader.read_u64::<LittleEndian>()?;
         if index_file_size != actual_file_size {
-            println!(
+            log::warn!(
                 "Vamana Index file size does not match expected size per meta-data. file size from file: {}, actual file size: {}",
                 index_file_size, actual_file_size
             );
@@ -117,9 +170,9 @@ impl<T> DiskIndexStorage<T> {
             + (dims * (mem::size_of::<T>() as u64));
         let num_nodes_per_sector = (SECTOR_LEN as u64) / max_node_len;
 
-        println!("medoid: {}B", medoid);
-        println!("max_node_len: {}B", max_node_len);
-        println!("num_nodes_per_sector: {}B", num_nodes_per_sector);
+        log::debug!("medoid: {}B", medoid);
+        log::debug!("max_node_len: {}B", max_node_len);
+        log::debug!("num_nodes_per_sector: {}B", num_nodes_per_sector);
 
         // SECTOR_LEN buffer for each sector
         let mut sector_buf = vec![0u8; SECTOR_LEN];
@@ -154,7 +207,7 @@ impl<T> DiskIndexStorage<T> {
 
         for sector in 0..num_sectors {
             if sector % 100_000 == 0 {
-                println!("Sector #{} written", sector);
+                log::trace!("Sector #{} written", sector);
             }
             sector_buf.fill(0);
 
@@ -212,16 +265,61 @@ impl<T> DiskIndexStorage<T> {
         Ok(())
     }
 
+    /// Load the disk layout metadata written by create_disk_layout
+    pub fn load_disk_layout_meta(&self) -> ANNResult<DiskLayoutMeta> {
+        let (meta, num_meta, _) = load_bin::<u64>(self.disk_index_file().as_str(), 0)?;
+        if num_meta < 9 {
+            return Err(ANNError::log_index_error(format!(
+                "Disk index metadata has {} entries, expecting at least 9",
+                num_meta
+            )));
+        }
+
+        Ok(DiskLayoutMeta {
+            num_pts: meta[0],
+            dims: meta[1],
+            medoid: meta[2],
+            max_node_len: meta[3],
+            num_nodes_per_sector: meta[4],
+            num_frozen_pts: meta[5],
+            frozen_loc: meta[6],
+            append_reorder_data: meta[7] != 0,
+            file_size: meta[8],
+        })
+    }
+
+    /// Load the compressed PQ table
+    /// Return (compressed data, number of points, number of pq chunks)
+    pub fn load_pq_compressed_data(&self) -> ANNResult<(Vec<u8>, usize, usize)> {
+        self.pq_storage.load_compressed_pivot_data()
+    }
+
+    /// Remove the intermediate in-memory index files written during build, if any
     pub fn index_build_cleanup(&self) -> ANNResult<()> {
-        fs::remove_file(self.mem_index_file())?;
+        if file_exists(&self.mem_index_file()) {
+            fs::remove_file(self.mem_index_file())?;
+        }
+
+        let mem_index_data_file = self.mem_index_file() + ".data";
+        if file_exists(&mem_index_data_file) {
+            fs::remove_file(mem_index_data_file)?;
+        }
+
+        let mem_index_metadata_file = IndexMetadata::file_name(&self.mem_index_file());
+        if file_exists(&mem_index_metadata_file) {
+            fs::remove_file(mem_index_metadata_file)?;
+        }
+
         Ok(())
     }
 
-    pub fn gen_query_warmup_data(&self, sampling_rate: f64) -> ANNResult<()> {
+    /// Sample the warmup queries from the dataset, seed None samples from entropy
+    pub fn gen_query_warmup_data(&self, sampling_rate: f64, seed: Option<u64>) -> ANNResult<()> {
         gen_sample_data::<T>(
             &self.dataset_file,
             &self.warmup_query_prefix(),
             sampling_rate,
+            &mut create_rng(seed),
         )?;
         Ok(())
     }
@@ -278,14 +376,23 @@ impl<T> DiskIndexStorage<T> {
         self.index_path_prefix.clone() + "_mem.index"
     }
 
-    fn disk_index_file(&self) -> String {
+    pub fn disk_index_file(&self) -> String {
         self.index_path_prefix.clone() + "_disk.index"
     }
 
+    /// Element type, configuration and tuned search parameters of the disk index
+    pub fn metadata_file(&self) -> String {
+        IndexMetadata::file_name(&self.disk_index_file())
+    }
+
     fn warmup_query_prefix(&self) -> String {
         self.index_path_prefix.clone() + "_sample"
     }
 
+    pub fn warmup_query_file(&self) -> String {
+        self.warmup_query_prefix() + "_data.bin"
+    }
+
     pub fn pq_pivot_file(&self) -> String {
         self.index_path_prefix.clone() + ".bin_pq_pivots.bin"
     }
@@ -305,16 +412,26 @@ mod disk_index_storage_test {
 
     const TEST_DATA_FILE: &str = "tests/data/siftsmall_learn_256pts.fbin";
     const DISK_INDEX_PATH_PREFIX: &str = "tests/data/disk_index_siftsmall_learn_256pts_R4_L50_A1.2";
+    const CREATE_DISK_LAYOUT_TEST_PREFIX: &str =
+        "tests/data/create_disk_layout_test_siftsmall_learn_256pts_R4_L50_A1.2";
     const TRUTH_DISK_LAYOUT: &str =
         "tests/data/truth_disk_index_siftsmall_learn_256pts_R4_L50_A1.2_disk.index";
+    const TRUTH_DISK_INDEX_PREFIX: &str =
+        "tests/data/truth_disk_index_siftsmall_learn_256pts_R4_L50_A1.2";
 
     #[test]
     fn create_disk_layout_test() {
-        let storage = DiskIndexStorage::<f32>::new(
-            get_test_file_path(TEST_DATA_FILE),
-            get_test_file_path(DISK_INDEX_PATH_PREFIX),
+        // Write the layout under its own prefix so the checked-in disk index next to the mem index is left untouched.
+        let index_path_prefix = get_test_file_path(CREATE_DISK_LAYOUT_TEST_PREFIX);
+        fs::copy(
+            get_test_file_path(DISK_INDEX_PATH_PREFIX) + "_mem.index",
+            index_path_prefix.clone() + "_mem.index",
         )
         .unwrap();
+
+        let storage =
+            DiskIndexStorage::<f32>::new(get_test_file_path(TEST_DATA_FILE), index_path_prefix)
+                .unwrap();
         storage.create_disk_layout().unwrap();
 
         let disk_layout_file = storage.disk_index_file();
@@ -324,6 +441,29 @@ mod disk_index_storage_test {
         assert!(rust_disk_layout == truth_disk_layout);
 
         fs::remove_file(disk_layout_file.as_str()).expect("Failed to delete file");
+        fs::remove_file(storage.mem_index_file()).expect("Failed to delete file");
+    }
+
+    #[test]
+    fn load_disk_layout_meta_test() {
+        let storage =
+            DiskIndexStorage::<f32>::new_for_search(get_test_file_path(TRUTH_DISK_INDEX_PREFIX))
+                .unwrap();
+        let meta = storage.load_disk_layout_meta().unwrap();
+
+        // R4 with 128 f32 dims: (4 + 1) * 4 + 128 * 4 bytes per node
+        assert_eq!(meta.num_pts, 256);
+        assert_eq!(meta.dims, 128);
+        assert_eq!(meta.max_node_len, 532);
+        assert_eq!(meta.num_nodes_per_sector, 7);
+        assert_eq!(meta.num_frozen_pts, 0);
+        assert!(!meta.append_reorder_data);
+        assert_eq!(
+            meta.file_size,
+            fs::metadata(get_test_file_path(TRUTH_DISK_LAYOUT))
+                .unwrap()
+                .len()
+        );
     }
 
     #[test]
@@ -4,6 +4,7 @@
  */
 use byteorder::{LittleEndian, ReadBytesExt};
 use rand::distributions::{Distribution, Uniform};
+use rand::Rng;
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::mem;
@@ -28,7 +29,8 @@ pub struct PQStorage {
     pq_data_file: String,
 
     /// PQ data reader
-    pq_data_file_reader: File,
+    /// None when opened for search, where only the pivot and compressed tables are read
+    pq_data_file_reader: Option<File>,
 }
 
 impl PQStorage {
@@ -42,10 +44,20 @@ impl PQStorage {
             pivot_file: pivot_file.to_string(),
             compressed_pivot_file: compressed_pivot_file.to_string(),
             pq_data_file: pq_data_file.to_string(),
-            pq_data_file_reader,
+            pq_data_file_reader: Some(pq_data_file_reader),
         })
     }
 
+    /// Create PQStorage for the query path, without the PQ data file
+    pub fn new_for_search(pivot_file: &str, compressed_pivot_file: &str) -> Self {
+        Self {
+            pivot_file: pivot_file.to_string(),
+            compressed_pivot_file: compressed_pivot_file.to_string(),
+            pq_data_file: String::new(),
+            pq_data_file_reader: None,
+        }
+    }
+
     pub fn write_compressed_pivot_metadata(&self, npts: i32, pq_chunk: i32) -> std::io::Result<()> {
         let mut writer = open_file_to_write(&self.compressed_pivot_file)?;
         writer.write_all(&npts.to_le_bytes())?;
@@ -119,7 +131,7 @@ impl PQStorage {
         file_exists(&self.pivot_file)
     }
 
-    pub fn read_pivot_metadata(&self) -> std::io::Result<(usize, usize)> {
+    pub fn read_pivot_metadata(&self) -> ANNResult<(usize, usize)> {
         let (_, file_num_centers, file_dim) = load_bin::<f32>(&self.pivot_file, METADATA_SIZE)?;
         Ok((file_num_centers, file_dim))
     }
@@ -164,8 +176,9 @@ impl PQStorage {
     }
 
     pub fn read_pq_data_metadata(&mut self) -> std::io::Result<(usize, usize)> {
-        let npts_i32 = self.pq_data_file_reader.read_i32::<LittleEndian>()?;
-        let dim_i32 = self.pq_data_file_reader.read_i32::<LittleEndian>()?;
+        let reader = self.pq_data_file_reader()?;
+        let npts_i32 = reader.read_i32::<LittleEndian>()?;
+        let dim_i32 = reader.read_i32::<LittleEndian>()?;
         let num_points = npts_i32 as usize;
         let dim = dim_i32 as usize;
         Ok((num_points, dim))
@@ -177,13 +190,28 @@ impl PQStorage {
         dim: usize,
     ) -> std::io::Result<Vec<T>> {
         let mut buf = vec![0u8; cur_block_size * dim * std::mem::size_of::<T>()];
-        self.pq_data_file_reader.read_exact(&mut buf)?;
+        self.pq_data_file_reader()?.read_exact(&mut buf)?;
 
         let ptr = buf.as_ptr() as *const T;
         let block_data = unsafe { std::slice::from_raw_parts(ptr, cur_block_size * dim) };
         Ok(block_data.to_vec())
     }
 
+    /// Load the compressed PQ table
+    /// Return (compressed data, number of points, number of pq chunks)
+    pub fn load_compressed_pivot_data(&self) -> ANNResult<(Vec<u8>, usize, usize)> {
+        load_bin::<u8>(&self.compressed_pivot_file, 0)
+    }
+
+    fn pq_data_file_reader(&mut self) -> std::io::Result<&mut File> {
+        self.pq_data_file_reader.as_mut().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "PQStorage was created without a PQ data file",
+            )
+        })
+    }
+
     /// streams data from the file, and samples each vector with probability p_val
     /// and returns a matrix of size slice_size* ndims as floating point type.
     /// the slice_size and ndims are set inside the function.
@@ -193,9 +221,11 @@ impl PQStorage {
     /// * `sampled_vectors` - sampled vector chose by p_val possibility
     /// * `slice_size` - how many sampled data return
     /// * `dim` - each sample data dimension
+    /// * `rng` - random generator of the sampling
     pub fn gen_random_slice<T: Default + Copy + Into<f32>>(
         &self,
         mut p_val: f64,
+        rng: &mut impl Rng,
     ) -> ANNResult<(Vec<f32>, usize, usize)> {
         let read_blk_size = 64 * 1024 * 1024;
         let mut reader = CachedReader::new(&self.pq_data_file, read_blk_size)?;
@@ -206,13 +236,12 @@ impl PQStorage {
         let mut slice_size = 0;
         p_val = if p_val < 1f64 { p_val } else { 1f64 };
 
-        let mut generator = rand::thread_rng();
         let distribution = Uniform::from(0.0..1.0);
 
         for _ in 0..npts {
             let mut cur_vector_bytes = vec![0u8; dim * mem::size_of::<T>()];
             reader.read(&mut cur_vector_bytes)?;
-            let random_value = distribution.sample(&mut generator);
+            let random_value = distribution.sample(rng);
             if random_value < p_val {
                 let ptr = cur_vector_bytes.as_ptr() as *const T;
                 let cur_vector_t = unsafe { std::slice::from_raw_parts(ptr, dim) };
@@ -227,14 +256,13 @@ impl PQStorage {
 
 #[cfg(test)]
 mod pq_storage_tests {
-    use rand::Rng;
-
     use super::*;
     use crate::utils::gen_random_slice;
 
     const DATA_FILE: &str = "tests/data/siftsmall_learn.bin";
     const PQ_PIVOT_PATH: &str = "tests/data/siftsmall_learn.bin_pq_pivots.bin";
     const PQ_COMPRESSED_PATH: &str = "tests/data/empty_pq_compressed.bin";
+    const PQ_COMPRESSED_PATH_25000PTS: &str = "tests/data/siftsmall_learn.bin_pq_compressed.bin";
 
     #[test]
     fn new_test() {
@@ -284,7 +312,7 @@ mod pq_storage_tests {
         let mut buf = vec![0u8; block_size * num_pq_chunks * std::mem::size_of::<u8>()];
         result_reader.read_exact(&mut buf).unwrap();
 
-        let ptr = buf.as_ptr() as *const u8;
+        let ptr = buf.as_ptr();
         let block_data = unsafe { std::slice::from_raw_parts(ptr, block_size * num_pq_chunks) };
 
         for index in 0..block_data.len() {
@@ -332,6 +360,17 @@ mod pq_storage_tests {
         assert_eq!(dim, 128);
     }
 
+    #[test]
+    fn new_for_search_test() {
+        let mut result = PQStorage::new_for_search(PQ_PIVOT_PATH, PQ_COMPRESSED_PATH_25000PTS);
+        assert!(result.read_pq_data_metadata().is_err());
+
+        let (compressed_data, npt, num_pq_chunks) = result.load_compressed_pivot_data().unwrap();
+        assert_eq!(npt, 25000);
+        assert_eq!(num_pq_chunks, 1);
+        assert_eq!(compressed_data.len(), 25000);
+    }
+
     #[test]
     fn gen_random_slice_test() {
         let file_name = "gen_random_slice_test.bin";
@@ -346,7 +385,7 @@ mod pq_storage_tests {
         std::fs::write(file_name, data).expect("Failed to write sample file");
 
         let (sampled_vectors, slice_size, ndims) =
-            gen_random_slice::<f32>(file_name, 1f64).unwrap();
+            gen_random_slice::<f32>(file_name, 1f64, &mut rand::thread_rng()).unwrap();
         let mut start = 8;
         (0..sampled_vectors.len()).for_each(|i| {
             assert_eq!(sampled_vectors[i].to_le_bytes(), data[start..start + 4]);
@@ -357,7 +396,7 @@ mod pq_storage_tests {
         assert_eq!(ndims, 8);
 
         let (sampled_vectors, slice_size, ndims) =
-            gen_random_slice::<f32>(file_name, 0f64).unwrap();
+            gen_random_slice::<f32>(file_name, 0f64, &mut rand::thread_rng()).unwrap();
         assert_eq!(sampled_vectors.len(), 0);
         assert_eq!(slice_size, 0);
         assert_eq!(ndims, 8);
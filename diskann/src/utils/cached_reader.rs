@@ -34,9 +34,11 @@ impl CachedReader {
         let cache_size = cache_size.min(fsize);
         let mut cache_buf = vec![0; cache_size as usize];
         reader.read_exact(&mut cache_buf)?;
-        println!(
+        log::debug!(
             "Opened: {}, size: {}, cache_size: {}",
-            filename, fsize, cache_size
+            filename,
+            fsize,
+            cache_size
         );
 
         Ok(Self {
@@ -28,16 +28,14 @@ impl CachedWriter {
         let writer = OpenOptions::new()
             .write(true)
             .create(true)
+            .truncate(true)
             .open(Path::new(filename))?;
 
         if cache_size == 0 {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Cache size must be greater than 0",
-            ));
+            return Err(std::io::Error::other("Cache size must be greater than 0"));
         }
 
-        println!("Opened: {}, cache_size: {}", filename, cache_size);
+        log::debug!("Opened: {}, cache_size: {}", filename, cache_size);
         Ok(Self {
             writer,
             cache_size,
@@ -54,7 +52,7 @@ impl CachedWriter {
         }
 
         self.writer.flush()?;
-        println!("Finished writing {}B", self.fsize);
+        log::debug!("Finished writing {}B", self.fsize);
         Ok(())
     }
 
@@ -0,0 +1,410 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+#![warn(missing_debug_implementations, missing_docs)]
+
+//! Loaders for embeddings that are not stored in the bin format
+//!
+//! NumPy .npy matrices (f32/f16/i8/u8, C-order) and JSON/JSONL embedding files are read into
+//! `Vec<Vec<T>>` batches ready for `build_vector` and `insert_vector`.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::str::FromStr;
+
+use half::f16;
+use serde_json::Value;
+
+use crate::common::{ANNError, ANNResult};
+use crate::utils::{VectorElement, VectorFileReader};
+
+/// Magic string at the start of a .npy file
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+/// Keys of a JSON object that may hold the embedding
+const JSON_EMBEDDING_KEYS: [&str; 2] = ["embedding", "vector"];
+
+/// Format of an input vector file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    /// DiskANN bin format, (npts, dim) header followed by the points
+    Bin,
+
+    /// NumPy .npy 2D matrix in C-order
+    Npy,
+
+    /// JSON array of embeddings
+    Json,
+
+    /// One embedding per line
+    Jsonl,
+}
+
+impl FromStr for InputFormat {
+    type Err = ANNError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "bin" => Ok(InputFormat::Bin),
+            "npy" => Ok(InputFormat::Npy),
+            "json" => Ok(InputFormat::Json),
+            "jsonl" => Ok(InputFormat::Jsonl),
+            _ => Err(ANNError::log_index_config_error(
+                "input_format".to_string(),
+                format!("{} is not one of bin, npy, json or jsonl", s),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for InputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            InputFormat::Bin => "bin",
+            InputFormat::Npy => "npy",
+            InputFormat::Json => "json",
+            InputFormat::Jsonl => "jsonl",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Load all the vectors of a file, converted to T
+pub fn load_vectors<T: VectorElement>(
+    file_name: &str,
+    format: InputFormat,
+) -> ANNResult<Vec<Vec<T>>> {
+    let vectors = match format {
+        InputFormat::Bin => VectorFileReader::<T>::open(file_name)?
+            .iter()
+            .collect::<ANNResult<Vec<Vec<T>>>>()?,
+        InputFormat::Npy => load_npy(file_name)?,
+        InputFormat::Json => load_json(file_name)?,
+        InputFormat::Jsonl => load_jsonl(file_name)?,
+    };
+
+    check_dimensions(file_name, &vectors)?;
+    Ok(vectors)
+}
+
+/// Number of points and dimension of loaded vectors
+pub fn vectors_metadata<T>(vectors: &[Vec<T>]) -> (usize, usize) {
+    (
+        vectors.len(),
+        vectors.first().map_or(0, |vector| vector.len()),
+    )
+}
+
+/// Load a NumPy .npy 2D matrix of f32, f16, i8 or u8 in C-order
+pub fn load_npy<T: VectorElement>(file_name: &str) -> ANNResult<Vec<Vec<T>>> {
+    let file = File::open(file_name)?;
+    let file_size = file.metadata()?.len() as usize;
+    let mut reader = BufReader::new(file);
+
+    let mut preamble = [0u8; 8];
+    reader.read_exact(&mut preamble)?;
+    if &preamble[..6] != NPY_MAGIC {
+        return Err(npy_error(file_name, "missing NUMPY magic string"));
+    }
+
+    // Version 1.0 stores the header length on 2 bytes, later versions on 4
+    let header_len = if preamble[6] == 1 {
+        let mut len = [0u8; 2];
+        reader.read_exact(&mut len)?;
+        u16::from_le_bytes(len) as usize
+    } else {
+        let mut len = [0u8; 4];
+        reader.read_exact(&mut len)?;
+        u32::from_le_bytes(len) as usize
+    };
+    let header_start = if preamble[6] == 1 { 10 } else { 12 };
+
+    let mut header = vec![0u8; header_len];
+    reader.read_exact(&mut header)?;
+    let header = String::from_utf8_lossy(&header);
+
+    let descr = npy_header_value(&header, "descr")
+        .and_then(|value| value.strip_prefix('\''))
+        .and_then(|value| value.split('\'').next())
+        .ok_or_else(|| npy_error(file_name, "missing descr"))?;
+    if npy_header_value(&header, "fortran_order").is_some_and(|value| value.starts_with("True")) {
+        return Err(npy_error(file_name, "only C-order arrays are supported"));
+    }
+    let shape = npy_header_value(&header, "shape")
+        .and_then(|value| value.strip_prefix('('))
+        .and_then(|value| value.split(')').next())
+        .ok_or_else(|| npy_error(file_name, "missing shape"))?
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        .map(|dim| dim.parse::<usize>())
+        .collect::<Result<Vec<usize>, _>>()
+        .map_err(|err| npy_error(file_name, &format!("invalid shape, {}", err)))?;
+
+    let (num_points, dim) = match shape.as_slice() {
+        [dim] => (1, *dim),
+        [num_points, dim] => (*num_points, *dim),
+        _ => return Err(npy_error(file_name, "only 1D and 2D arrays are supported")),
+    };
+
+    // The byte order character is '<', '|' or '=' for the little endian types we support
+    let (element_size, decode): (usize, fn(&[u8]) -> f32) =
+        match descr.trim_start_matches(['<', '|', '=']) {
+            "f4" => (4, |b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+            "f2" => (2, |b| f16::from_le_bytes([b[0], b[1]]).to_f32()),
+            "i1" => (1, |b| b[0] as i8 as f32),
+            "u1" => (1, |b| b[0] as f32),
+            _ => {
+                return Err(npy_error(
+                    file_name,
+                    &format!("unsupported dtype {}, use <f4, <f2, |i1 or |u1", descr),
+                ))
+            }
+        };
+
+    let data_size = num_points * dim * element_size;
+    let expected_size = header_start + header_len + data_size;
+    if file_size != expected_size {
+        return Err(ANNError::log_file_size_mismatch_error(
+            format!(
+                "npy file {} with {} points of dimension {} and dtype {}",
+                file_name, num_points, dim, descr
+            ),
+            file_size,
+            expected_size,
+        ));
+    }
+
+    let mut buf = vec![0u8; dim * element_size];
+    let mut vectors = Vec::with_capacity(num_points);
+    for _ in 0..num_points {
+        reader.read_exact(&mut buf)?;
+        vectors.push(
+            buf.chunks_exact(element_size)
+                .map(|bytes| T::from_f32(decode(bytes)))
+                .collect(),
+        );
+    }
+
+    Ok(vectors)
+}
+
+/// Load a JSON array whose items are arrays of numbers or objects with an "embedding" field
+pub fn load_json<T: VectorElement>(file_name: &str) -> ANNResult<Vec<Vec<T>>> {
+    let reader = BufReader::new(File::open(file_name)?);
+    let value: Value = serde_json::from_reader(reader)
+        .map_err(|err| json_error(file_name, 0, &err.to_string()))?;
+
+    match value {
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(idx, item)| json_embedding(file_name, idx, item))
+            .collect(),
+        _ => Err(json_error(file_name, 0, "expecting an array of embeddings")),
+    }
+}
+
+/// Load a JSON lines file with one array of numbers or object with an "embedding" field per line
+pub fn load_jsonl<T: VectorElement>(file_name: &str) -> ANNResult<Vec<Vec<T>>> {
+    let reader = BufReader::new(File::open(file_name)?);
+    let mut vectors = Vec::new();
+
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let item: Value = serde_json::from_str(&line)
+            .map_err(|err| json_error(file_name, idx, &err.to_string()))?;
+        vectors.push(json_embedding(file_name, idx, &item)?);
+    }
+
+    Ok(vectors)
+}
+
+/// Value of a key in the python dict literal of a .npy header
+fn npy_header_value<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    let key = format!("'{}':", key);
+    header
+        .find(&key)
+        .map(|pos| header[pos + key.len()..].trim_start())
+}
+
+/// Embedding of a JSON item, either an array of numbers or an object holding one
+fn json_embedding<T: VectorElement>(
+    file_name: &str,
+    idx: usize,
+    item: &Value,
+) -> ANNResult<Vec<T>> {
+    let values = match item {
+        Value::Array(values) => values,
+        Value::Object(object) => JSON_EMBEDDING_KEYS
+            .iter()
+            .find_map(|key| object.get(*key).and_then(Value::as_array))
+            .ok_or_else(|| json_error(file_name, idx, "object without an embedding array"))?,
+        _ => {
+            return Err(json_error(
+                file_name,
+                idx,
+                "expecting an array or an object",
+            ))
+        }
+    };
+
+    values
+        .iter()
+        .map(|value| {
+            value
+                .as_f64()
+                .map(|value| T::from_f32(value as f32))
+                .ok_or_else(|| json_error(file_name, idx, "embedding value is not a number"))
+        })
+        .collect()
+}
+
+/// All the vectors must have the same non zero dimension
+fn check_dimensions<T>(file_name: &str, vectors: &[Vec<T>]) -> ANNResult<()> {
+    let (_, dim) = vectors_metadata(vectors);
+    if let Some(idx) = vectors
+        .iter()
+        .position(|vector| vector.is_empty() || vector.len() != dim)
+    {
+        return Err(ANNError::log_index_error(format!(
+            "Vector {} of {} has dimension {}, expecting {}",
+            idx,
+            file_name,
+            vectors[idx].len(),
+            dim
+        )));
+    }
+
+    Ok(())
+}
+
+fn npy_error(file_name: &str, err: &str) -> ANNError {
+    ANNError::log_index_error(format!("Error reading npy file {}: {}", file_name, err))
+}
+
+fn json_error(file_name: &str, idx: usize, err: &str) -> ANNError {
+    ANNError::log_index_error(format!(
+        "Error reading JSON file {} at item {}: {}",
+        file_name, idx, err
+    ))
+}
+
+#[cfg(test)]
+mod embedding_loader_test {
+    use std::fs;
+
+    use vector::Half;
+
+    use super::*;
+
+    /// Build a version 1.0 .npy file
+    fn npy_bytes(descr: &str, shape: &str, data: &[u8]) -> Vec<u8> {
+        let mut header = format!(
+            "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+            descr, shape
+        );
+        // The header is padded with spaces and ends with a newline, aligned to 64 bytes
+        while (10 + header.len() + 1) % 64 != 0 {
+            header.push(' ');
+        }
+        header.push('\n');
+
+        let mut bytes = NPY_MAGIC.to_vec();
+        bytes.extend_from_slice(&[1, 0]);
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    #[test]
+    fn load_npy_test() {
+        let file_name = "embedding_loader_load_npy_test.npy";
+        let data: Vec<u8> = [1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        fs::write(file_name, npy_bytes("<f4", "(2, 3)", &data)).unwrap();
+
+        let vectors = load_vectors::<f32>(file_name, InputFormat::Npy).unwrap();
+        assert_eq!(vectors, vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]]);
+
+        let vectors = load_vectors::<Half>(file_name, InputFormat::Npy).unwrap();
+        assert_eq!(vectors_metadata(&vectors), (2, 3));
+        assert_eq!(vectors[1][2].to_f32(), 6.0);
+
+        fs::write(file_name, npy_bytes("|u1", "(2, 2)", &[1, 2, 255, 0])).unwrap();
+        let vectors = load_vectors::<f32>(file_name, InputFormat::Npy).unwrap();
+        assert_eq!(vectors, vec![vec![1.0, 2.0], vec![255.0, 0.0]]);
+
+        fs::write(file_name, npy_bytes("|i1", "(1, 2)", &[0xff, 3])).unwrap();
+        let vectors = load_vectors::<i8>(file_name, InputFormat::Npy).unwrap();
+        assert_eq!(vectors, vec![vec![-1, 3]]);
+
+        let data: Vec<u8> = [0.5f32, -2.0]
+            .iter()
+            .flat_map(|value| f16::from_f32(*value).to_le_bytes())
+            .collect();
+        fs::write(file_name, npy_bytes("<f2", "(2,)", &data)).unwrap();
+        let vectors = load_vectors::<f32>(file_name, InputFormat::Npy).unwrap();
+        assert_eq!(vectors, vec![vec![0.5, -2.0]]);
+
+        // Truncated data and unsupported dtype
+        fs::write(file_name, npy_bytes("<f4", "(2, 3)", &[0; 20])).unwrap();
+        assert!(load_vectors::<f32>(file_name, InputFormat::Npy).is_err());
+        fs::write(file_name, npy_bytes("<f8", "(1, 1)", &[0; 8])).unwrap();
+        assert!(load_vectors::<f32>(file_name, InputFormat::Npy).is_err());
+
+        fs::remove_file(file_name).expect("Failed to delete file");
+    }
+
+    #[test]
+    fn load_json_test() {
+        let file_name = "embedding_loader_load_json_test.json";
+        fs::write(
+            file_name,
+            r#"[{"filename": "a.jpg", "embedding": [1, 2.5]}, [3, 4], {"vector": [5, 6]}]"#,
+        )
+        .unwrap();
+
+        let vectors = load_vectors::<f32>(file_name, InputFormat::Json).unwrap();
+        assert_eq!(
+            vectors,
+            vec![vec![1.0, 2.5], vec![3.0, 4.0], vec![5.0, 6.0]]
+        );
+
+        fs::write(file_name, r#"[[1, 2], [3]]"#).unwrap();
+        assert!(load_vectors::<f32>(file_name, InputFormat::Json).is_err());
+
+        fs::remove_file(file_name).expect("Failed to delete file");
+    }
+
+    #[test]
+    fn load_jsonl_test() {
+        let file_name = "embedding_loader_load_jsonl_test.jsonl";
+        fs::write(file_name, "[1, 2]\n\n{\"embedding\": [3, 4]}\n").unwrap();
+
+        let vectors = load_vectors::<u8>(file_name, InputFormat::Jsonl).unwrap();
+        assert_eq!(vectors, vec![vec![1, 2], vec![3, 4]]);
+
+        fs::write(file_name, "[1, 2]\n{\"id\": 3}\n").unwrap();
+        assert!(load_vectors::<u8>(file_name, InputFormat::Jsonl).is_err());
+
+        fs::remove_file(file_name).expect("Failed to delete file");
+    }
+
+    #[test]
+    fn input_format_test() {
+        assert_eq!("NPY".parse::<InputFormat>().unwrap(), InputFormat::Npy);
+        assert_eq!("jsonl".parse::<InputFormat>().unwrap(), InputFormat::Jsonl);
+        assert!("csv".parse::<InputFormat>().is_err());
+        assert_eq!(InputFormat::Bin.to_string(), "bin");
+    }
+}
@@ -8,21 +8,20 @@
 
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use std::fs::{self, File, OpenOptions};
-use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::io::{BufReader, Seek, SeekFrom, Write};
 use std::path::Path;
 use std::{io, mem};
 
+use crate::common::ANNResult;
 use crate::model::data_store::DatasetDto;
+use crate::utils::{read_vector_file_header, VectorFileReader};
 
 /// Read metadata of data file.
 pub fn load_metadata_from_file(file_name: &str) -> std::io::Result<(usize, usize)> {
     let file = File::open(file_name)?;
     let mut reader = BufReader::new(file);
 
-    let npoints = reader.read_i32::<LittleEndian>()? as usize;
-    let ndims = reader.read_i32::<LittleEndian>()? as usize;
-
-    Ok((npoints, ndims))
+    read_vector_file_header(&mut reader)
 }
 
 /// Read the deleted vertex ids from file.
@@ -47,9 +46,6 @@ pub fn load_ids_to_delete_from_file(file_name: &str) -> std::io::Result<(usize,
 /// * `bin_file` - filename where the data is
 /// * `data` - destination dataset dto to which the data is copied
 /// * `pts_offset` - offset of points. data will be loaded after this point in dataset
-/// * `npts` - number of points read from bin_file
-/// * `dim` - point dimension read from bin_file
-/// * `rounded_dim` - rounded dimension (padding zero if it's > dim)
 /// # Return
 /// * `npts` - number of points read from bin_file
 /// * `dim` - point dimension read from bin_file
@@ -57,48 +53,11 @@ pub fn copy_aligned_data_from_file<T: Default + Copy>(
     bin_file: &str,
     dataset_dto: DatasetDto<T>,
     pts_offset: usize,
-) -> std::io::Result<(usize, usize)> {
-    let reader = File::open(bin_file)?;
-    copy_aligned_data_from_reader(reader, dataset_dto, pts_offset)
-}
+) -> ANNResult<(usize, usize)> {
+    let mut reader = VectorFileReader::<T>::open(bin_file)?;
+    let npts = reader.copy_aligned_into(dataset_dto.data, dataset_dto.rounded_dim, pts_offset)?;
 
-/// Copy data from a reader
-/// # Arguments
-/// * `reader` - the reader where the data is
-/// * `data` - destination dataset dto to which the data is copied
-/// * `pts_offset` - offset of points. data will be loaded after this point in dataset
-/// * `npts` - number of points read from bin_file
-/// * `dim` - point dimension read from bin_file
-/// * `rounded_dim` - rounded dimension (padding zero if it's > dim)
-/// # Return
-/// * `npts` - number of points read from bin_file
-/// * `dim` - point dimension read from bin_file
-pub fn copy_aligned_data_from_reader<T: Default + Copy>(
-    mut reader: impl Read,
-    dataset_dto: DatasetDto<T>,
-    pts_offset: usize,
-) -> std::io::Result<(usize, usize)> {
-    let npts = reader.read_i32::<LittleEndian>()? as usize;
-    let dim = reader.read_i32::<LittleEndian>()? as usize;
-    let rounded_dim = dataset_dto.rounded_dim;
-    let offset = pts_offset * rounded_dim;
-
-    for i in 0..npts {
-        let data_slice =
-            &mut dataset_dto.data[offset + i * rounded_dim..offset + i * rounded_dim + dim];
-        let mut buf = vec![0u8; dim * mem::size_of::<T>()];
-        reader.read_exact(&mut buf)?;
-
-        let ptr = buf.as_ptr() as *const T;
-        let temp_slice = unsafe { std::slice::from_raw_parts(ptr, dim) };
-        data_slice.copy_from_slice(temp_slice);
-
-        (i * rounded_dim + dim..i * rounded_dim + rounded_dim).for_each(|j| {
-            dataset_dto.data[j] = T::default();
-        });
-    }
-
-    Ok((npts, dim))
+    Ok((npts, reader.dim()))
 }
 
 /// Copy data from vector
@@ -113,7 +72,7 @@ pub fn copy_aligned_data_from_reader<T: Default + Copy>(
 /// * `npts` - number of points read from bin_file
 /// * `dim` - point dimension read from bin_file
 pub fn copy_aligned_data_from_vector<T: Default + Copy>(
-    vector: &Vec<Vec<T>>,
+    vector: &[Vec<T>],
     dataset_dto: DatasetDto<T>,
     pts_offset: usize,
     dim: usize,
@@ -122,12 +81,12 @@ pub fn copy_aligned_data_from_vector<T: Default + Copy>(
     let rounded_dim = dataset_dto.rounded_dim;
     let offset = pts_offset * rounded_dim;
 
-    for i in 0..npts {
+    for (i, point) in vector.iter().enumerate() {
         let data_slice =
             &mut dataset_dto.data[offset + i * rounded_dim..offset + i * rounded_dim + dim];
-        data_slice.copy_from_slice(&vector[i]);
+        data_slice.copy_from_slice(point);
 
-        (i * rounded_dim + dim..i * rounded_dim + rounded_dim).for_each(|j| {
+        (offset + i * rounded_dim + dim..offset + i * rounded_dim + rounded_dim).for_each(|j| {
             dataset_dto.data[j] = T::default();
         });
     }
@@ -144,6 +103,7 @@ pub fn open_file_to_write(file_name: &str) -> std::io::Result<File> {
     OpenOptions::new()
         .write(true)
         .create(true)
+        .truncate(false)
         .open(Path::new(file_name))
 }
 
@@ -206,26 +166,18 @@ pub fn save_data_in_base_dimensions<T: Default + Copy>(
 /// # Arguments
 /// * `bin_file` - filename where the data is
 /// * `file_offset` - data offset in file
+/// # Return
 /// * `data` - information data
 /// * `npts` - number of points
 /// * `ndims` - point dimension
-pub fn load_bin<T: Copy>(
+pub fn load_bin<T: Default + Copy>(
     bin_file: &str,
     file_offset: usize,
-) -> std::io::Result<(Vec<T>, usize, usize)> {
-    let mut reader = File::open(bin_file)?;
-    reader.seek(std::io::SeekFrom::Start(file_offset as u64))?;
-    let npts = reader.read_i32::<LittleEndian>()? as usize;
-    let dim = reader.read_i32::<LittleEndian>()? as usize;
-
-    let size = npts * dim * std::mem::size_of::<T>();
-    let mut buf = vec![0u8; size];
-    reader.read_exact(&mut buf)?;
-
-    let ptr = buf.as_ptr() as *const T;
-    let data = unsafe { std::slice::from_raw_parts(ptr, npts * dim) };
+) -> ANNResult<(Vec<T>, usize, usize)> {
+    let mut reader = VectorFileReader::<T>::open_at(bin_file, file_offset)?;
+    let data = reader.read_all()?;
 
-    Ok((data.to_vec(), npts, dim))
+    Ok((data, reader.num_points(), reader.dim()))
 }
 
 /// Get file size
@@ -247,7 +199,7 @@ macro_rules! save_bin {
         ) -> std::io::Result<usize> {
             let mut writer = open_file_to_write(filename)?;
 
-            println!("Writing bin: {}", filename);
+            log::debug!("Writing bin: {}", filename);
             writer.seek(SeekFrom::Start(offset as u64))?;
             let num_pts_i32 = num_pts as i32;
             let dims_i32 = dims as i32;
@@ -255,9 +207,11 @@ macro_rules! save_bin {
 
             writer.write_i32::<LittleEndian>(num_pts_i32)?;
             writer.write_i32::<LittleEndian>(dims_i32)?;
-            println!(
+            log::debug!(
                 "bin: #pts = {}, #dims = {}, size = {}B",
-                num_pts, dims, bytes_written
+                num_pts,
+                dims,
+                bytes_written
             );
 
             for item in data.iter() {
@@ -266,7 +220,7 @@ macro_rules! save_bin {
 
             writer.flush()?;
 
-            println!("Finished writing bin.");
+            log::debug!("Finished writing bin.");
             Ok(bytes_written)
         }
     };
@@ -281,6 +235,7 @@ mod file_util_test {
     use super::*;
     use crate::model::data_store::InmemDataset;
     use std::fs;
+    use std::io::Read;
 
     pub const DIM_8: usize = 8;
 
@@ -6,7 +6,7 @@
 
 //! Aligned allocator
 
-use rand::{distributions::Uniform, prelude::Distribution, thread_rng};
+use rand::{distributions::Uniform, prelude::Distribution, Rng};
 use rayon::prelude::*;
 use std::cmp::min;
 
@@ -70,8 +70,7 @@ fn lloyds_iter(
     if compute_residual {
         let buf_pad: usize = 32;
         let chunk_size: usize = 2 * 8192;
-        let nchunks =
-            num_points / chunk_size + (if num_points % chunk_size == 0 { 0 } else { 1 } as usize);
+        let nchunks = num_points.div_ceil(chunk_size);
 
         let mut residuals: Vec<f32> = vec![0.0; nchunks * buf_pad];
 
@@ -134,9 +133,10 @@ fn run_lloyds(
         )?;
 
         if (i != 0 && (old_residual - residual) / residual < 0.00001) || (residual < f32::EPSILON) {
-            println!(
+            log::debug!(
                 "Residuals unchanged: {} becomes {}. Early termination.",
-                old_residual, residual
+                old_residual,
+                residual
             );
             break;
         }
@@ -153,15 +153,15 @@ fn selecting_pivots(
     dim: usize,
     pivot_data: &mut [f32],
     num_centers: usize,
+    rng: &mut impl Rng,
 ) {
     let mut picked = Vec::new();
-    let mut rng = thread_rng();
     let distribution = Uniform::from(0..num_points);
 
     for j in 0..num_centers {
-        let mut tmp_pivot = distribution.sample(&mut rng);
+        let mut tmp_pivot = distribution.sample(rng);
         while picked.contains(&tmp_pivot) {
-            tmp_pivot = distribution.sample(&mut rng);
+            tmp_pivot = distribution.sample(rng);
         }
         picked.push(tmp_pivot);
         let data_offset = tmp_pivot * dim;
@@ -182,19 +182,19 @@ fn k_meanspp_selecting_pivots(
     dim: usize,
     pivot_data: &mut [f32],
     num_centers: usize,
+    rng: &mut impl Rng,
 ) {
     if num_points > (1 << 23) {
-        println!("ERROR: n_pts {} currently not supported for k-means++, maximum is 8388608. Falling back to random pivot selection.", num_points);
-        selecting_pivots(data, num_points, dim, pivot_data, num_centers);
+        log::warn!("n_pts {} currently not supported for k-means++, maximum is 8388608. Falling back to random pivot selection.", num_points);
+        selecting_pivots(data, num_points, dim, pivot_data, num_centers, rng);
         return;
     }
 
     let mut picked: Vec<usize> = Vec::new();
-    let mut rng = thread_rng();
     let real_distribution = Uniform::from(0.0..1.0);
     let int_distribution = Uniform::from(0..num_points);
 
-    let init_id = int_distribution.sample(&mut rng);
+    let init_id = int_distribution.sample(rng);
     let mut num_picked = 1;
 
     picked.push(init_id);
@@ -216,7 +216,7 @@ fn k_meanspp_selecting_pivots(
     let mut sum_flag = false;
 
     while num_picked < num_centers {
-        dart_val = real_distribution.sample(&mut rng);
+        dart_val = real_distribution.sample(rng);
 
         let mut sum: f64 = 0.0;
         for item in dist.iter().take(num_points) {
@@ -260,7 +260,7 @@ fn k_meanspp_selecting_pivots(
     }
 }
 
-/// k-means algorithm interface
+/// k-means algorithm interface, rng picks the initial pivots
 pub fn k_means_clustering(
     data: &[f32],
     num_points: usize,
@@ -268,8 +268,9 @@ pub fn k_means_clustering(
     centers: &mut [f32],
     num_centers: usize,
     max_reps: usize,
+    rng: &mut impl Rng,
 ) -> ANNResult<(Vec<Vec<usize>>, Vec<u32>, f32)> {
-    k_meanspp_selecting_pivots(data, num_points, dim, centers, num_centers);
+    k_meanspp_selecting_pivots(data, num_points, dim, centers, num_centers, rng);
     let (closest_docs, closest_center, residual) =
         run_lloyds(data, num_points, dim, centers, num_centers, max_reps)?;
     Ok((closest_docs, closest_center, residual))
@@ -372,7 +373,14 @@ mod kmeans_test {
 
         let mut pivot_data = vec![0.0; num_centers * dim];
 
-        selecting_pivots(&data, num_points, dim, &mut pivot_data, num_centers);
+        selecting_pivots(
+            &data,
+            num_points,
+            dim,
+            &mut pivot_data,
+            num_centers,
+            &mut rng,
+        );
 
         // Verify that each pivot point corresponds to a point in the data
         for i in 0..num_centers {
@@ -406,7 +414,14 @@ mod kmeans_test {
 
         let mut pivot_data = vec![0.0; num_centers * dim];
 
-        k_meanspp_selecting_pivots(&data, num_points, dim, &mut pivot_data, num_centers);
+        k_meanspp_selecting_pivots(
+            &data,
+            num_points,
+            dim,
+            &mut pivot_data,
+            num_centers,
+            &mut rng,
+        );
 
         // Verify that each pivot point corresponds to a point in the data
         for i in 0..num_centers {
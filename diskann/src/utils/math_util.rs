@@ -26,7 +26,7 @@ struct PivotContainer {
 
 impl PartialOrd for PivotContainer {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        other.piv_dist.partial_cmp(&self.piv_dist)
+        Some(self.cmp(other))
     }
 }
 
@@ -34,7 +34,10 @@ impl Ord for PivotContainer {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         // Treat NaN as less than all other values.
         // piv_dist should never be NaN.
-        self.partial_cmp(other).unwrap_or(Ordering::Less)
+        other
+            .piv_dist
+            .partial_cmp(&self.piv_dist)
+            .unwrap_or(Ordering::Less)
     }
 }
 
@@ -198,7 +201,7 @@ pub fn compute_closest_centers_in_block(
                         }
                     }
                 }
-                for (_j, center_idx) in center_chunk.iter_mut().enumerate() {
+                for center_idx in center_chunk.iter_mut() {
                     if let Some(this_piv) = top_k_queue.pop() {
                         *center_idx = this_piv.piv_id as u32;
                     } else {
@@ -253,7 +256,7 @@ pub fn compute_closest_centers(
     compute_vecs_l2sq(&mut pivs_norms_squared, pivot_data, num_centers, dim);
 
     let par_block_size = num_points;
-    let n_blocks = if num_points % par_block_size == 0 {
+    let n_blocks = if num_points.is_multiple_of(par_block_size) {
         num_points / par_block_size
     } else {
         num_points / par_block_size + 1
@@ -318,9 +321,11 @@ pub fn process_residuals(
     closest_centers: &[u32],
     to_subtract: bool,
 ) {
-    println!(
+    log::debug!(
         "Processing residuals of {} points in {} dimensions using {} centers",
-        num_points, dim, num_centers
+        num_points,
+        dim,
+        num_centers
     );
 
     data_load
@@ -365,7 +370,7 @@ mod math_util_test {
 
         compute_vecs_l2sq(&mut vecs_l2sq, &data, num_points, dim);
 
-        let expected = vec![14.0, 77.0];
+        let expected = [14.0, 77.0];
 
         assert_eq!(vecs_l2sq.len(), num_points);
         assert_abs_diff_eq!(vecs_l2sq[0], expected[0], epsilon = 1e-6);
@@ -426,7 +431,7 @@ mod math_util_test {
         let num_points = 4;
         let dim = 3;
         let num_centers = 2;
-        let mut data = vec![
+        let data = vec![
             1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 11.0, 12.0,
         ];
         let pivot_data = vec![1.0, 2.0, 3.0, 10.0, 11.0, 12.0];
@@ -13,6 +13,12 @@ pub use utils::*;
 pub mod bit_vec_extension;
 pub use bit_vec_extension::*;
 
+pub mod vector_file;
+pub use vector_file::*;
+
+pub mod embedding_loader;
+pub use embedding_loader::*;
+
 pub mod rayon_util;
 pub use rayon_util::*;
 
@@ -3,6 +3,7 @@
  * Licensed under the MIT license.
  */
 use rand::distributions::{Distribution, Uniform};
+use rand::Rng;
 use std::io::{Seek, SeekFrom, Write};
 use std::mem;
 use std::{fs::File, path::Path};
@@ -20,9 +21,11 @@ use super::CachedReader;
 /// * `sampled_vectors` - sampled vector chose by p_val possibility
 /// * `slice_size` - how many sampled data return
 /// * `dim` - each sample data dimension
+/// * `rng` - random generator of the sampling
 pub fn gen_random_slice<T: Default + Copy + Into<f32>>(
     data_file: &str,
     mut p_val: f64,
+    rng: &mut impl Rng,
 ) -> ANNResult<(Vec<f32>, usize, usize)> {
     let read_blk_size = 64 * 1024 * 1024;
     let mut reader = CachedReader::new(data_file, read_blk_size)?;
@@ -33,13 +36,12 @@ pub fn gen_random_slice<T: Default + Copy + Into<f32>>(
     let mut slice_size = 0;
     p_val = if p_val < 1f64 { p_val } else { 1f64 };
 
-    let mut generator = rand::thread_rng();
     let distribution = Uniform::from(0.0..1.0);
 
     for _ in 0..npts {
         let mut cur_vector_bytes = vec![0u8; dim * mem::size_of::<T>()];
         reader.read(&mut cur_vector_bytes)?;
-        let random_value = distribution.sample(&mut generator);
+        let random_value = distribution.sample(rng);
         if random_value < p_val {
             let ptr = cur_vector_bytes.as_ptr() as *const T;
             let cur_vector_t = unsafe { std::slice::from_raw_parts(ptr, dim) };
@@ -52,7 +54,12 @@ pub fn gen_random_slice<T: Default + Copy + Into<f32>>(
 }
 
 /// Generate random sample data and write into output_file
-pub fn gen_sample_data<T>(data_file: &str, output_file: &str, sampling_rate: f64) -> ANNResult<()> {
+pub fn gen_sample_data<T>(
+    data_file: &str,
+    output_file: &str,
+    sampling_rate: f64,
+    rng: &mut impl Rng,
+) -> ANNResult<()> {
     let read_blk_size = 64 * 1024 * 1024;
     let mut reader = CachedReader::new(data_file, read_blk_size)?;
 
@@ -63,7 +70,6 @@ pub fn gen_sample_data<T>(data_file: &str, output_file: &str, sampling_rate: f64
 
     let mut num_sampled_pts = 0u32;
     let one_const = 1u32;
-    let mut generator = rand::thread_rng();
     let distribution = Uniform::from(0.0..1.0);
 
     let npts_u32 = reader.read_u32()?;
@@ -77,7 +83,7 @@ pub fn gen_sample_data<T>(data_file: &str, output_file: &str, sampling_rate: f64
     for id in 0..npts_u32 {
         let mut cur_row_bytes = vec![0u8; dim * mem::size_of::<T>()];
         reader.read(&mut cur_row_bytes)?;
-        let random_value = distribution.sample(&mut generator);
+        let random_value = distribution.sample(rng);
         if random_value < sampling_rate {
             sample_data_writer.write_all(&cur_row_bytes)?;
             sample_id_writer.write_all(&id.to_le_bytes())?;
@@ -89,9 +95,10 @@ pub fn gen_sample_data<T>(data_file: &str, output_file: &str, sampling_rate: f64
     sample_data_writer.write_all(&num_sampled_pts.to_le_bytes())?;
     sample_id_writer.seek(SeekFrom::Start(0))?;
     sample_id_writer.write_all(&num_sampled_pts.to_le_bytes())?;
-    println!(
+    log::info!(
         "Wrote {} points to sample file: {}",
-        num_sampled_pts, sample_data_path
+        num_sampled_pts,
+        sample_data_path
     );
 
     Ok(())
@@ -120,7 +127,13 @@ mod partition_test {
         std::fs::write(file_name, data).expect("Failed to write sample file");
 
         let sample_file_prefix = file_name.to_string() + "_sample";
-        gen_sample_data::<f32>(file_name, sample_file_prefix.as_str(), 1f64).unwrap();
+        gen_sample_data::<f32>(
+            file_name,
+            sample_file_prefix.as_str(),
+            1f64,
+            &mut rand::thread_rng(),
+        )
+        .unwrap();
 
         let sample_data_path = format!("{}_data.bin", sample_file_prefix);
         let sample_ids_path = format!("{}_ids.bin", sample_file_prefix);
@@ -3,6 +3,8 @@
  * Licensed under the MIT license.
  */
 use num_traits::Num;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use std::sync::Mutex;
 
 /// Non recursive mutex
@@ -59,6 +61,14 @@ pub fn is_4096_aligned(x: u64) -> bool {
     is_aligned(x, 4096)
 }
 
+/// Random generator of the random choices of a build, seeded from entropy without a seed
+pub fn create_rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    }
+}
+
 /// all metadata of individual sub-component files is written in first 4KB for unified files
 pub const METADATA_SIZE: usize = 4096;
 
@@ -0,0 +1,559 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+#![warn(missing_debug_implementations, missing_docs)]
+
+//! Typed reader and writer for bin vector files
+//!
+//! A bin file (fbin, ibin, u8bin...) is a `(npts: i32, dim: i32)` little endian header followed
+//! by npts * dim elements stored row by row. Several of them can be packed in one file at
+//! different offsets, e.g. the PQ pivots file.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::mem;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use vector::{BFloat16, Half};
+
+use crate::common::{ANNError, ANNResult, AlignedBoxWithSlice};
+use crate::utils::{is_aligned, round_up};
+
+/// Size of the (npts, dim) header in bytes
+pub const VECTOR_FILE_HEADER_SIZE: usize = 2 * mem::size_of::<i32>();
+
+/// Element type of a vector file that can be converted from and to f32
+pub trait VectorElement: Copy + Default + Into<f32> {
+    /// Name of the type, as the drivers take it and index metadata saves it
+    const DATA_TYPE_NAME: &'static str;
+
+    /// Convert an f32 to this type, rounding and saturating for the integer types
+    fn from_f32(value: f32) -> Self;
+}
+
+impl VectorElement for f32 {
+    const DATA_TYPE_NAME: &'static str = "float";
+
+    fn from_f32(value: f32) -> Self {
+        value
+    }
+}
+
+impl VectorElement for Half {
+    const DATA_TYPE_NAME: &'static str = "f16";
+
+    fn from_f32(value: f32) -> Self {
+        Half::from_f32(value)
+    }
+}
+
+impl VectorElement for BFloat16 {
+    const DATA_TYPE_NAME: &'static str = "bf16";
+
+    fn from_f32(value: f32) -> Self {
+        BFloat16::from_f32(value)
+    }
+}
+
+impl VectorElement for i8 {
+    const DATA_TYPE_NAME: &'static str = "int8";
+
+    fn from_f32(value: f32) -> Self {
+        value.round() as i8
+    }
+}
+
+impl VectorElement for u8 {
+    const DATA_TYPE_NAME: &'static str = "uint8";
+
+    fn from_f32(value: f32) -> Self {
+        value.round() as u8
+    }
+}
+
+/// Read the (npts, dim) header at the current position of the reader
+pub fn read_vector_file_header(reader: &mut impl Read) -> io::Result<(usize, usize)> {
+    let npts = reader.read_i32::<LittleEndian>()?;
+    let dim = reader.read_i32::<LittleEndian>()?;
+
+    if npts < 0 || dim < 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid bin file header, npts = {}, dim = {}", npts, dim),
+        ));
+    }
+
+    Ok((npts as usize, dim as usize))
+}
+
+/// View a slice of plain elements as bytes
+fn as_bytes_mut<T: Copy>(data: &mut [T]) -> &mut [u8] {
+    unsafe { std::slice::from_raw_parts_mut(data.as_mut_ptr() as *mut u8, mem::size_of_val(data)) }
+}
+
+/// View a slice of plain elements as bytes
+fn as_bytes<T: Copy>(data: &[T]) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, mem::size_of_val(data)) }
+}
+
+/// Reader of the vectors of a bin file
+#[derive(Debug)]
+pub struct VectorFileReader<T> {
+    /// Buffered reader of the file
+    reader: BufReader<File>,
+
+    /// File name, for the error messages
+    file_name: String,
+
+    /// Number of points in the file
+    num_points: usize,
+
+    /// Dimension of the points
+    dim: usize,
+
+    /// Offset of the first point in the file
+    data_offset: u64,
+
+    /// Point the reader is positioned at, sequential reads don't need to seek
+    position: usize,
+
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Copy> VectorFileReader<T> {
+    /// Open a bin file, the file size must match the header exactly
+    pub fn open(file_name: &str) -> ANNResult<Self> {
+        Self::open_impl(file_name, 0, true)
+    }
+
+    /// Open the bin section that starts at `offset` of a file packing several of them,
+    /// the section must fit in the file
+    pub fn open_at(file_name: &str, offset: usize) -> ANNResult<Self> {
+        Self::open_impl(file_name, offset, false)
+    }
+
+    fn open_impl(file_name: &str, offset: usize, exact_size: bool) -> ANNResult<Self> {
+        let file = File::open(file_name)?;
+        let file_size = file.metadata()?.len() as usize;
+        let mut reader = BufReader::new(file);
+
+        reader.seek(SeekFrom::Start(offset as u64))?;
+        let (num_points, dim) = read_vector_file_header(&mut reader)?;
+
+        let expected_size = num_points
+            .checked_mul(dim)
+            .and_then(|len| len.checked_mul(mem::size_of::<T>()))
+            .and_then(|len| len.checked_add(offset + VECTOR_FILE_HEADER_SIZE))
+            .unwrap_or(usize::MAX);
+
+        if (exact_size && file_size != expected_size) || file_size < expected_size {
+            return Err(ANNError::log_file_size_mismatch_error(
+                format!(
+                    "Bin file {} with {} points of dimension {} and {} bytes per element at offset {}",
+                    file_name,
+                    num_points,
+                    dim,
+                    mem::size_of::<T>(),
+                    offset
+                ),
+                file_size,
+                expected_size,
+            ));
+        }
+
+        Ok(Self {
+            reader,
+            file_name: file_name.to_string(),
+            num_points,
+            dim,
+            data_offset: (offset + VECTOR_FILE_HEADER_SIZE) as u64,
+            position: 0,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Number of points in the file
+    pub fn num_points(&self) -> usize {
+        self.num_points
+    }
+
+    /// Dimension of the points
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Read the points [start, start + buf.len() / dim) into buf
+    pub fn read_range_into(&mut self, start: usize, buf: &mut [T]) -> ANNResult<()> {
+        let count = buf.len().checked_div(self.dim).unwrap_or(0);
+
+        if buf.len() != count * self.dim || start + count > self.num_points {
+            return Err(ANNError::log_index_error(format!(
+                "Cannot read {} elements from point {} of bin file {} with {} points of dimension {}",
+                buf.len(),
+                start,
+                self.file_name,
+                self.num_points,
+                self.dim
+            )));
+        }
+
+        if start != self.position {
+            let offset = self.data_offset + (start * self.dim * mem::size_of::<T>()) as u64;
+            self.reader.seek(SeekFrom::Start(offset))?;
+        }
+
+        self.reader.read_exact(as_bytes_mut(buf))?;
+        self.position = start + count;
+
+        Ok(())
+    }
+
+    /// Read the points [start, start + count) as a flat vector
+    pub fn read_range(&mut self, start: usize, count: usize) -> ANNResult<Vec<T>>
+    where
+        T: Default,
+    {
+        let mut data = vec![T::default(); count * self.dim];
+        self.read_range_into(start, &mut data)?;
+        Ok(data)
+    }
+
+    /// Read the point at index
+    pub fn read_vector(&mut self, index: usize) -> ANNResult<Vec<T>>
+    where
+        T: Default,
+    {
+        self.read_range(index, 1)
+    }
+
+    /// Read all the points as a flat vector
+    pub fn read_all(&mut self) -> ANNResult<Vec<T>>
+    where
+        T: Default,
+    {
+        self.read_range(0, self.num_points)
+    }
+
+    /// Copy all the points into data from point pts_offset on, each point padded with default
+    /// values up to rounded_dim. Return the number of points copied.
+    pub fn copy_aligned_into(
+        &mut self,
+        data: &mut [T],
+        rounded_dim: usize,
+        pts_offset: usize,
+    ) -> ANNResult<usize>
+    where
+        T: Default,
+    {
+        let end = (pts_offset + self.num_points) * rounded_dim;
+        if rounded_dim < self.dim || end > data.len() {
+            return Err(ANNError::log_index_error(format!(
+                "Cannot copy {} points of dimension {} from bin file {} at point {} of a buffer of {} elements with dimension {}",
+                self.num_points,
+                self.dim,
+                self.file_name,
+                pts_offset,
+                data.len(),
+                rounded_dim
+            )));
+        }
+
+        for i in 0..self.num_points {
+            let start = (pts_offset + i) * rounded_dim;
+            let point = &mut data[start..start + rounded_dim];
+            self.read_range_into(i, &mut point[..self.dim])?;
+            point[self.dim..].fill(T::default());
+        }
+
+        Ok(self.num_points)
+    }
+
+    /// Read all the points into a buffer aligned for the distance kernels, each point padded
+    /// to a multiple of 8 elements. Return the buffer and the padded dimension.
+    pub fn read_aligned(&mut self) -> ANNResult<(AlignedBoxWithSlice<T>, usize)>
+    where
+        T: Default,
+    {
+        let rounded_dim = round_up(self.dim, 8);
+        let alloc_size = self.num_points * rounded_dim;
+        let alignment = 8 * mem::size_of::<T>();
+
+        if !is_aligned(alloc_size * mem::size_of::<T>(), alignment) {
+            return Err(ANNError::log_index_error(format!(
+                "Requested memory size is not a multiple of {}. Can not be allocated.",
+                alignment
+            )));
+        }
+
+        let mut data = AlignedBoxWithSlice::<T>::new(alloc_size, alignment)?;
+        self.copy_aligned_into(&mut data, rounded_dim, 0)?;
+
+        Ok((data, rounded_dim))
+    }
+
+    /// Iterate over the points from the first one
+    pub fn iter(&mut self) -> VectorFileIter<'_, T> {
+        VectorFileIter {
+            reader: self,
+            next: 0,
+        }
+    }
+}
+
+impl<T: VectorElement> VectorFileReader<T> {
+    /// Read the points [start, start + count) converted to another element type
+    pub fn read_range_as<U: VectorElement>(
+        &mut self,
+        start: usize,
+        count: usize,
+    ) -> ANNResult<Vec<U>> {
+        Ok(self
+            .read_range(start, count)?
+            .into_iter()
+            .map(|value| U::from_f32(value.into()))
+            .collect())
+    }
+}
+
+/// Streaming iterator over the points of a bin file
+#[derive(Debug)]
+pub struct VectorFileIter<'a, T> {
+    reader: &'a mut VectorFileReader<T>,
+    next: usize,
+}
+
+impl<'a, T: Copy + Default> Iterator for VectorFileIter<'a, T> {
+    type Item = ANNResult<Vec<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.reader.num_points {
+            return None;
+        }
+
+        let vector = self.reader.read_vector(self.next);
+        self.next += 1;
+        Some(vector)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.reader.num_points - self.next;
+        (remaining, Some(remaining))
+    }
+}
+
+/// Writer of a bin file, points are appended and the header is updated on finish
+#[derive(Debug)]
+pub struct VectorFileWriter<T> {
+    /// Buffered writer of the file
+    writer: BufWriter<File>,
+
+    /// Dimension of the points
+    dim: usize,
+
+    /// Number of points written so far
+    num_points: usize,
+
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Copy> VectorFileWriter<T> {
+    /// Create or truncate a bin file for points of dimension dim
+    pub fn create(file_name: &str, dim: usize) -> ANNResult<Self> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(file_name)?;
+        let mut writer = BufWriter::new(file);
+
+        writer.write_i32::<LittleEndian>(0)?;
+        writer.write_i32::<LittleEndian>(i32::try_from(dim)?)?;
+
+        Ok(Self {
+            writer,
+            dim,
+            num_points: 0,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Dimension of the points
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Number of points written so far
+    pub fn num_points(&self) -> usize {
+        self.num_points
+    }
+
+    /// Append one point
+    pub fn write_vector(&mut self, vector: &[T]) -> ANNResult<()> {
+        if vector.len() != self.dim {
+            return Err(ANNError::log_index_error(format!(
+                "Cannot write a point of dimension {} to a bin file of dimension {}",
+                vector.len(),
+                self.dim
+            )));
+        }
+
+        self.write_vectors(vector)
+    }
+
+    /// Append points stored row by row in a flat slice
+    pub fn write_vectors(&mut self, vectors: &[T]) -> ANNResult<()> {
+        if self.dim == 0 || !vectors.len().is_multiple_of(self.dim) {
+            return Err(ANNError::log_index_error(format!(
+                "Cannot write {} elements to a bin file of dimension {}",
+                vectors.len(),
+                self.dim
+            )));
+        }
+
+        self.writer.write_all(as_bytes(vectors))?;
+        self.num_points += vectors.len() / self.dim;
+
+        Ok(())
+    }
+
+    /// Write the number of points in the header and flush the file, return the number of points
+    pub fn finish(mut self) -> ANNResult<usize> {
+        let num_points = i32::try_from(self.num_points)?;
+
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.writer.write_i32::<LittleEndian>(num_points)?;
+        self.writer.flush()?;
+
+        Ok(self.num_points)
+    }
+}
+
+/// Read a bin file into a buffer aligned for the distance kernels
+/// # Return
+/// * `data` - points padded to aligned_dim
+/// * `npts` - number of points
+/// * `dim` - point dimension
+/// * `aligned_dim` - dimension rounded up to a multiple of 8
+pub fn load_aligned_bin<T: Default + Copy>(
+    bin_file: &str,
+) -> ANNResult<(AlignedBoxWithSlice<T>, usize, usize, usize)> {
+    log::debug!("Reading (with alignment) bin file: {}", bin_file);
+    let mut reader = VectorFileReader::<T>::open(bin_file)?;
+    let (data, aligned_dim) = reader.read_aligned()?;
+
+    log::debug!(
+        "Metadata: #pts = {}, #dims = {}, aligned_dim = {}",
+        reader.num_points(),
+        reader.dim(),
+        aligned_dim
+    );
+
+    Ok((data, reader.num_points(), reader.dim(), aligned_dim))
+}
+
+/// Convert the elements of a bin file to another type, batch_size points at a time.
+/// Return the number of points converted.
+pub fn convert_vector_file<T: VectorElement, U: VectorElement>(
+    input_file: &str,
+    output_file: &str,
+    batch_size: usize,
+) -> ANNResult<usize> {
+    let mut reader = VectorFileReader::<T>::open(input_file)?;
+    let mut writer = VectorFileWriter::<U>::create(output_file, reader.dim())?;
+    let batch_size = batch_size.max(1);
+
+    for start in (0..reader.num_points()).step_by(batch_size) {
+        let count = batch_size.min(reader.num_points() - start);
+        writer.write_vectors(&reader.read_range_as::<U>(start, count)?)?;
+    }
+
+    writer.finish()
+}
+
+#[cfg(test)]
+mod vector_file_test {
+    use std::fs;
+
+    use super::*;
+
+    fn write_test_file(file_name: &str, data: &[f32], dim: usize) {
+        let mut writer = VectorFileWriter::<f32>::create(file_name, dim).unwrap();
+        writer.write_vectors(data).unwrap();
+        assert_eq!(writer.finish().unwrap(), data.len() / dim);
+    }
+
+    #[test]
+    fn write_and_read_test() {
+        let file_name = "vector_file_write_and_read_test.fbin";
+        let data: Vec<f32> = (0..12).map(|i| i as f32).collect();
+        write_test_file(file_name, &data, 3);
+
+        let mut reader = VectorFileReader::<f32>::open(file_name).unwrap();
+        assert_eq!(reader.num_points(), 4);
+        assert_eq!(reader.dim(), 3);
+        assert_eq!(reader.read_all().unwrap(), data);
+        assert_eq!(reader.read_vector(2).unwrap(), vec![6.0, 7.0, 8.0]);
+        assert_eq!(reader.read_range(1, 2).unwrap(), data[3..9].to_vec());
+        assert!(reader.read_range(3, 2).is_err());
+
+        let points: Vec<Vec<f32>> = reader.iter().map(|point| point.unwrap()).collect();
+        assert_eq!(points.len(), 4);
+        assert_eq!(points[3], vec![9.0, 10.0, 11.0]);
+
+        let mut aligned = vec![-1.0f32; 5 * 4];
+        assert_eq!(reader.copy_aligned_into(&mut aligned, 4, 1).unwrap(), 4);
+        assert_eq!(aligned[..4], [-1.0; 4]);
+        assert_eq!(aligned[4..8], [0.0, 1.0, 2.0, 0.0]);
+        assert_eq!(aligned[16..], [9.0, 10.0, 11.0, 0.0]);
+
+        fs::remove_file(file_name).expect("Failed to delete file");
+    }
+
+    #[test]
+    fn header_validation_test() {
+        let file_name = "vector_file_header_validation_test.fbin";
+        write_test_file(file_name, &[1.0; 8], 4);
+
+        // 2 points of 4 f32 don't match 2 points of 4 u8
+        assert!(VectorFileReader::<u8>::open(file_name).is_err());
+        // but the section fits in the file
+        assert!(VectorFileReader::<u8>::open_at(file_name, 0).is_ok());
+
+        let mut bytes = fs::read(file_name).unwrap();
+        bytes.truncate(bytes.len() - 1);
+        fs::write(file_name, &bytes).unwrap();
+        assert!(VectorFileReader::<f32>::open(file_name).is_err());
+        assert!(VectorFileReader::<f32>::open_at(file_name, 0).is_err());
+
+        fs::write(file_name, [0xff, 0xff, 0xff, 0xff, 1, 0, 0, 0]).unwrap();
+        assert!(VectorFileReader::<f32>::open(file_name).is_err());
+
+        fs::remove_file(file_name).expect("Failed to delete file");
+    }
+
+    #[test]
+    fn convert_vector_file_test() {
+        let input_file = "vector_file_convert_test.fbin";
+        let output_file = "vector_file_convert_test.bf16bin";
+        let data = [0.5f32, -1.25, 3.0, 300.0, -7.75, 2.0];
+        write_test_file(input_file, &data, 2);
+
+        assert_eq!(
+            convert_vector_file::<f32, BFloat16>(input_file, output_file, 2).unwrap(),
+            3
+        );
+
+        let mut reader = VectorFileReader::<BFloat16>::open(output_file).unwrap();
+        assert_eq!(reader.dim(), 2);
+        let converted: Vec<f32> = reader.read_range_as::<f32>(0, 3).unwrap();
+        assert_eq!(converted, data.to_vec());
+
+        let as_u8: Vec<u8> = reader.read_range_as::<u8>(0, 3).unwrap();
+        assert_eq!(as_u8, vec![1, 0, 3, 255, 0, 2]);
+
+        fs::remove_file(input_file).expect("Failed to delete file");
+        fs::remove_file(output_file).expect("Failed to delete file");
+    }
+}
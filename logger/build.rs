@@ -43,7 +43,7 @@ fn main() {
         env::set_var("PROTOC_INCLUDE", protobuf_inc_path);
         */
 
-        prost_build::compile_protos(&["src/indexlog.proto"], &["src/"]).unwrap();
+        compile_protos();
     } else {
         let protopkg = vcpkg::find_package("protobuf").unwrap();
         let protobuf_path = protopkg.link_paths[0].parent().unwrap();
@@ -66,6 +66,14 @@ fn main() {
             .to_string();
         env::set_var("PROTOC_INCLUDE", protobuf_inc_path);
 
-        prost_build::compile_protos(&["src/indexlog.proto"], &["src/"]).unwrap();
+        compile_protos();
     }
 }
+
+/// The messages derive Serialize for the JSON publisher
+fn compile_protos() {
+    prost_build::Config::new()
+        .type_attribute(".", "#[derive(serde::Serialize)]")
+        .compile_protos(&["src/indexlog.proto"], &["src/"])
+        .unwrap();
+}
@@ -3,6 +3,9 @@
  * Licensed under the MIT license.
  */
 use log::{debug, info, log_enabled, warn, Level};
+use logger::logger::indexlog::LogLevel;
+use logger::message_handler::{add_publisher, flush};
+use logger::publisher::JsonPublisher;
 use logger::trace_logger::TraceLogger;
 
 // cargo run --example trace_example
@@ -13,6 +16,12 @@ fn main() {
         .map(|()| log::set_max_level(log::LevelFilter::Trace))
         .unwrap();
 
+    // Besides stderr, write the messages up to Info as JSON lines
+    add_publisher(Box::new(
+        JsonPublisher::create("trace_example.jsonl", LogLevel::Info).unwrap(),
+    ))
+    .unwrap();
+
     info!("Rust logging n = {}", 42);
     warn!("This is too much fun!");
     debug!("Maybe we can make this code work");
@@ -26,4 +35,6 @@ fn main() {
         "is_enabled?  error: {:5?}, warn: {:5?}, info: {:5?}, debug: {:5?}, trace: {:5?}",
         error_is_enabled, warn_is_enabled, info_is_enabled, debug_is_enabled, trace_is_enabled,
     );
+
+    flush().unwrap();
 }
@@ -16,4 +16,5 @@ pub mod logger {
 pub mod error_logger;
 pub mod log_error;
 pub mod message_handler;
+pub mod publisher;
 pub mod trace_logger;
@@ -2,7 +2,8 @@
  * Copyright (c) Microsoft Corporation. All rights reserved.
  * Licensed under the MIT license.
  */
-use std::sync::mpsc::SendError;
+use std::io;
+use std::sync::mpsc::{RecvError, SendError};
 
 use crate::logger::indexlog::Log;
 
@@ -20,7 +21,39 @@ pub enum LogError {
     #[error("LockPoisonError: {err}")]
     LockPoisonError { err: String },
 
+    /// The processing thread exited before handling the message
+    #[error("RecvError: {err}")]
+    RecvError {
+        #[from]
+        err: RecvError,
+    },
+
+    /// A publisher failed to write a message
+    #[error("IOError: {err}")]
+    IOError { err: String },
+
+    /// A publisher failed to serialize a message
+    #[error("SerializationError: {err}")]
+    SerializationError { err: String },
+
     /// Failed to create EtwPublisher
+    #[cfg(all(feature = "etw", target_os = "windows"))]
     #[error("EtwProviderError: {err:?}")]
     ETWProviderError { err: win_etw_provider::Error },
 }
+
+impl From<io::Error> for LogError {
+    fn from(err: io::Error) -> Self {
+        LogError::IOError {
+            err: err.to_string(),
+        }
+    }
+}
+
+impl From<serde_json::Error> for LogError {
+    fn from(err: serde_json::Error) -> Self {
+        LogError::SerializationError {
+            err: err.to_string(),
+        }
+    }
+}
@@ -6,48 +6,38 @@ use crate::log_error::LogError;
 use crate::logger::indexlog::DiskIndexConstructionCheckpoint;
 use crate::logger::indexlog::Log;
 use crate::logger::indexlog::LogLevel;
+use crate::publisher::StderrPublisher;
 
-use std::sync::mpsc::{self, Sender};
-use std::sync::Mutex;
+use std::sync::mpsc::{self, SendError, Sender};
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 
-use win_etw_macros::trace_logging_provider;
+/// Message handed to the publishers
+#[derive(Debug, Clone, Copy)]
+pub struct LogRecord<'a> {
+    pub level: LogLevel,
 
-trait MessagePublisher {
-    fn publish(&self, log_level: LogLevel, message: &str);
-}
+    /// Text rendering of the message
+    pub message: &'a str,
 
-// ETW provider - the GUID specified here is that of the default provider for Geneva Metric Extensions
-// We are just using it as a placeholder until we have a version of OpenTelemetry exporter for Rust
-#[trace_logging_provider(guid = "edc24920-e004-40f6-a8e1-0e6e48f39d84")]
-trait EtwTraceProvider {
-    fn write(msg: &str);
+    /// Message holding only the part the text was rendered from
+    pub log: &'a Log,
 }
 
-struct EtwPublisher {
-    provider: EtwTraceProvider,
-    publish_to_stdout: bool,
-}
+/// Destination of the log messages
+pub trait MessagePublisher: Send + Sync {
+    /// Publish one message
+    fn publish(&self, record: &LogRecord) -> Result<(), LogError>;
 
-impl EtwPublisher {
-    pub fn new() -> Result<Self, win_etw_provider::Error> {
-        let provider = EtwTraceProvider::new();
-        Ok(EtwPublisher {
-            provider,
-            publish_to_stdout: true,
-        })
+    /// Write out the buffered messages
+    fn flush(&self) -> Result<(), LogError> {
+        Ok(())
     }
 }
 
-fn log_level_to_etw(level: LogLevel) -> win_etw_provider::Level {
-    match level {
-        LogLevel::Error => win_etw_provider::Level::ERROR,
-        LogLevel::Warn => win_etw_provider::Level::WARN,
-        LogLevel::Info => win_etw_provider::Level::INFO,
-        LogLevel::Debug => win_etw_provider::Level::VERBOSE,
-        LogLevel::Trace => win_etw_provider::Level(6),
-        LogLevel::Unspecified => win_etw_provider::Level(6),
-    }
+/// Whether a message of the given level passes a publisher publishing up to max_level
+pub fn level_enabled(level: LogLevel, max_level: LogLevel) -> bool {
+    level as i32 <= max_level as i32
 }
 
 fn i32_to_log_level(value: i32) -> LogLevel {
@@ -62,76 +52,223 @@ fn i32_to_log_level(value: i32) -> LogLevel {
     }
 }
 
-impl MessagePublisher for EtwPublisher {
-    fn publish(&self, log_level: LogLevel, message: &str) {
-        let options = win_etw_provider::EventOptions {
-            level: Some(log_level_to_etw(log_level)),
+/// Split the message into one message per part, with its level and text rendering
+fn render(message: Log) -> Vec<(LogLevel, String, Log)> {
+    let mut records = Vec::new();
+
+    if let Some(indexlog) = message.index_construction_log {
+        let str = format!(
+            "Time for {}% of index build completed: {:.3} seconds, {:.3}B cycles",
+            indexlog.percentage_complete, indexlog.time_spent_in_seconds, indexlog.g_cycles_spent
+        );
+        let level = i32_to_log_level(indexlog.log_level);
+        let log = Log {
+            index_construction_log: Some(indexlog),
             ..Default::default()
         };
-        self.provider.write(Some(&options), message);
+        records.push((level, str, log));
+    }
 
-        if self.publish_to_stdout {
-            println!("{}", message);
+    if let Some(disk_index_log) = message.disk_index_construction_log {
+        let str = format!(
+            "Time for disk index build [Checkpoint: {:?}] completed: {:.3} seconds, {:.3}B cycles",
+            DiskIndexConstructionCheckpoint::from_i32(disk_index_log.checkpoint)
+                .unwrap_or(DiskIndexConstructionCheckpoint::None),
+            disk_index_log.time_spent_in_seconds,
+            disk_index_log.g_cycles_spent
+        );
+        let level = i32_to_log_level(disk_index_log.log_level);
+        let log = Log {
+            disk_index_construction_log: Some(disk_index_log),
+            ..Default::default()
+        };
+        records.push((level, str, log));
+    }
+
+    if let Some(tracelog) = message.trace_log {
+        // The publishers print the level themselves
+        let str = tracelog.log_line.clone();
+        let level = i32_to_log_level(tracelog.log_level);
+        let log = Log {
+            trace_log: Some(tracelog),
+            ..Default::default()
+        };
+        records.push((level, str, log));
+    }
+
+    if let Some(err) = message.error_log {
+        let str = err.error_message.clone();
+        let level = i32_to_log_level(err.log_level);
+        let log = Log {
+            error_log: Some(err),
+            ..Default::default()
+        };
+        records.push((level, str, log));
+    }
+
+    records
+}
+
+/// Id of a registered publisher
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PublisherId(u64);
+
+/// Publishers the messages are sent to, in registration order
+struct PublisherRegistry {
+    next_id: u64,
+
+    publishers: Vec<(PublisherId, Arc<dyn MessagePublisher>)>,
+}
+
+impl PublisherRegistry {
+    fn with_default_publishers() -> Self {
+        let mut registry = PublisherRegistry {
+            next_id: 0,
+            publishers: Vec::new(),
+        };
+
+        registry.add(Box::new(StderrPublisher::new(LogLevel::Trace)));
+
+        #[cfg(all(feature = "etw", target_os = "windows"))]
+        match crate::publisher::EtwPublisher::new() {
+            Ok(etw_publisher) => {
+                registry.add(Box::new(etw_publisher));
+            }
+            Err(err) => eprintln!("Failed to create the ETW publisher: {:?}", err),
         }
+
+        registry
+    }
+
+    fn add(&mut self, publisher: Box<dyn MessagePublisher>) -> PublisherId {
+        let id = PublisherId(self.next_id);
+        self.next_id += 1;
+        self.publishers.push((id, Arc::from(publisher)));
+        id
+    }
+
+    /// Publishers to run a message through, so that the registry isn't locked while publishing
+    fn snapshot(&self) -> Vec<Arc<dyn MessagePublisher>> {
+        self.publishers
+            .iter()
+            .map(|(_, publisher)| publisher.clone())
+            .collect()
     }
 }
 
-struct MessageProcessor {
-    sender: Mutex<Sender<Log>>,
+lazy_static::lazy_static! {
+    /// Singleton registry, publishing to stderr until it is changed.
+    static ref PUBLISHERS: RwLock<PublisherRegistry> =
+        RwLock::new(PublisherRegistry::with_default_publishers());
 }
 
-impl MessageProcessor {
-    pub fn start_processing() -> Self {
-        let (sender, receiver) = mpsc::channel::<Log>();
-        thread::spawn(move || -> Result<(), LogError> {
-            for message in receiver {
-                // Process the received message
-                if let Some(indexlog) = message.index_construction_log {
-                    let str = format!(
-                        "Time for {}% of index build completed: {:.3} seconds, {:.3}B cycles",
-                        indexlog.percentage_complete,
-                        indexlog.time_spent_in_seconds,
-                        indexlog.g_cycles_spent
-                    );
-                    publish(i32_to_log_level(indexlog.log_level), &str)?;
-                }
+fn publishers() -> Result<Vec<Arc<dyn MessagePublisher>>, LogError> {
+    Ok(PUBLISHERS
+        .read()
+        .map_err(|err| LogError::LockPoisonError {
+            err: err.to_string(),
+        })?
+        .snapshot())
+}
 
-                if let Some(disk_index_log) = message.disk_index_construction_log {
-                    let str = format!(
-                        "Time for disk index build [Checkpoint: {:?}] completed: {:.3} seconds, {:.3}B cycles",
-                        DiskIndexConstructionCheckpoint::from_i32(disk_index_log.checkpoint).unwrap_or(DiskIndexConstructionCheckpoint::None),
-                        disk_index_log.time_spent_in_seconds,
-                        disk_index_log.g_cycles_spent
-                    );
-                    publish(i32_to_log_level(disk_index_log.log_level), &str)?;
-                }
+/// Publish the messages to another publisher as well.
+pub fn add_publisher(publisher: Box<dyn MessagePublisher>) -> Result<PublisherId, LogError> {
+    let mut registry = PUBLISHERS
+        .write()
+        .map_err(|err| LogError::LockPoisonError {
+            err: err.to_string(),
+        })?;
+    Ok(registry.add(publisher))
+}
 
-                if let Some(tracelog) = message.trace_log {
-                    let str = format!("{}:{}", tracelog.log_level, tracelog.log_line);
-                    publish(i32_to_log_level(tracelog.log_level), &str)?;
-                }
+/// Stop publishing to a publisher, returns whether it was registered.
+pub fn remove_publisher(id: PublisherId) -> Result<bool, LogError> {
+    let mut registry = PUBLISHERS
+        .write()
+        .map_err(|err| LogError::LockPoisonError {
+            err: err.to_string(),
+        })?;
+    let num_publishers = registry.publishers.len();
+    registry
+        .publishers
+        .retain(|(publisher_id, _)| *publisher_id != id);
+    Ok(registry.publishers.len() != num_publishers)
+}
+
+/// Replace all the publishers, the default stderr one included.
+pub fn set_publishers(
+    publishers: Vec<Box<dyn MessagePublisher>>,
+) -> Result<Vec<PublisherId>, LogError> {
+    let mut registry = PUBLISHERS
+        .write()
+        .map_err(|err| LogError::LockPoisonError {
+            err: err.to_string(),
+        })?;
+    registry.publishers.clear();
+    Ok(publishers
+        .into_iter()
+        .map(|publisher| registry.add(publisher))
+        .collect())
+}
+
+enum Command {
+    Publish(Log),
+
+    /// Flush the publishers and signal once the previous messages are published
+    Flush(Sender<()>),
+}
+
+struct MessageProcessor {
+    sender: Mutex<Sender<Command>>,
+}
 
-                if let Some(err) = message.error_log {
-                    publish(i32_to_log_level(err.log_level), &err.error_message)?;
+impl MessageProcessor {
+    pub fn start_processing() -> Self {
+        let (sender, receiver) = mpsc::channel::<Command>();
+        thread::spawn(move || {
+            for command in receiver {
+                match command {
+                    Command::Publish(message) => publish(message),
+                    Command::Flush(done) => {
+                        flush_publishers();
+                        let _ = done.send(());
+                    }
                 }
             }
-
-            Ok(())
         });
 
         let sender = Mutex::new(sender);
         MessageProcessor { sender }
     }
 
-    /// Log the message.
-    fn log(&self, message: Log) -> Result<(), LogError> {
-        Ok(self
-            .sender
+    fn send(&self, command: Command) -> Result<(), LogError> {
+        self.sender
             .lock()
             .map_err(|err| LogError::LockPoisonError {
                 err: err.to_string(),
             })?
-            .send(message)?)
+            .send(command)
+            .map_err(|SendError(command)| {
+                let message = match command {
+                    Command::Publish(message) => message,
+                    Command::Flush(_) => Log::default(),
+                };
+                LogError::SendError {
+                    err: SendError(message),
+                }
+            })
+    }
+
+    /// Log the message.
+    fn log(&self, message: Log) -> Result<(), LogError> {
+        self.send(Command::Publish(message))
+    }
+
+    /// Wait for the messages sent so far to be published.
+    fn flush(&self) -> Result<(), LogError> {
+        let (done_sender, done_receiver) = mpsc::channel();
+        self.send(Command::Flush(done_sender))?;
+        Ok(done_receiver.recv()?)
     }
 }
 
@@ -143,24 +280,128 @@ lazy_static::lazy_static! {
     };
 }
 
-lazy_static::lazy_static! {
-    /// Singleton publisher.
-    static ref PUBLISHER: Result<EtwPublisher, win_etw_provider::Error> = {
-        EtwPublisher::new()
-    };
-}
-
 /// Send a message to the logging system.
 pub fn send_log(message: Log) -> Result<(), LogError> {
     PROCESSOR.log(message)
 }
 
-fn publish(log_level: LogLevel, message: &str) -> Result<(), LogError> {
-    match *PUBLISHER {
-        Ok(ref etw_publisher) => {
-            etw_publisher.publish(log_level, message);
-            Ok(())
+/// Wait for the messages sent so far to be published, then flush the publishers.
+pub fn flush() -> Result<(), LogError> {
+    PROCESSOR.flush()
+}
+
+/// A failing publisher doesn't keep the message from the others
+fn publish(message: Log) {
+    let publishers = match publishers() {
+        Ok(publishers) => publishers,
+        Err(err) => {
+            eprintln!("Failed to publish log message: {}", err);
+            return;
+        }
+    };
+
+    for (level, str, log) in render(message) {
+        let record = LogRecord {
+            level,
+            message: &str,
+            log: &log,
+        };
+        for publisher in &publishers {
+            if let Err(err) = publisher.publish(&record) {
+                eprintln!("Failed to publish log message: {}", err);
+            }
         }
-        Err(ref err) => Err(LogError::ETWProviderError { err: err.clone() }),
+    }
+}
+
+fn flush_publishers() {
+    if let Ok(publishers) = publishers() {
+        for publisher in publishers {
+            if let Err(err) = publisher.flush() {
+                eprintln!("Failed to flush log publisher: {}", err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod message_handler_test {
+    use super::*;
+    use crate::logger::indexlog::{ErrorLog, TraceLog};
+    use crate::publisher::CallbackPublisher;
+
+    fn error_log(message: &str) -> Log {
+        Log {
+            error_log: Some(ErrorLog {
+                error_message: message.to_string(),
+                log_level: LogLevel::Error as i32,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn registered_publisher_receives_messages_test() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let callback_received = received.clone();
+        let id = add_publisher(Box::new(CallbackPublisher::new(
+            LogLevel::Warn,
+            move |record: &LogRecord| {
+                callback_received.lock().unwrap().push((
+                    record.level,
+                    record.message.to_string(),
+                    record.log.clone(),
+                ));
+            },
+        )))
+        .unwrap();
+
+        send_log(error_log("registry test error")).unwrap();
+        send_log(Log {
+            trace_log: Some(TraceLog {
+                log_line: "registry test trace".to_string(),
+                log_level: LogLevel::Trace as i32,
+            }),
+            ..Default::default()
+        })
+        .unwrap();
+        flush().unwrap();
+
+        assert!(remove_publisher(id).unwrap());
+        assert!(!remove_publisher(id).unwrap());
+        send_log(error_log("registry test removed")).unwrap();
+        flush().unwrap();
+
+        // Other tests log concurrently, only look at the messages of this one
+        let received: Vec<_> = received
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, message, _)| message.contains("registry test"))
+            .cloned()
+            .collect();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].0, LogLevel::Error);
+        assert_eq!(received[0].1, "registry test error");
+        assert_eq!(received[0].2, error_log("registry test error"));
+    }
+
+    #[test]
+    fn render_splits_message_parts_test() {
+        let mut message = error_log("render test");
+        message.trace_log = Some(TraceLog {
+            log_line: "line".to_string(),
+            log_level: LogLevel::Debug as i32,
+        });
+
+        let records = render(message);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].0, LogLevel::Debug);
+        assert_eq!(records[0].1, "line");
+        assert!(records[0].2.error_log.is_none());
+        assert_eq!(records[1].2, error_log("render test"));
+
+        assert!(level_enabled(LogLevel::Error, LogLevel::Warn));
+        assert!(!level_enabled(LogLevel::Info, LogLevel::Warn));
     }
 }
@@ -0,0 +1,38 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+use crate::log_error::LogError;
+use crate::logger::indexlog::LogLevel;
+use crate::message_handler::{level_enabled, LogRecord, MessagePublisher};
+
+type Callback = Box<dyn Fn(&LogRecord) + Send + Sync>;
+
+/// Hands the messages up to max_level to a callback, to route them into another logging system
+pub struct CallbackPublisher {
+    max_level: LogLevel,
+
+    callback: Callback,
+}
+
+impl CallbackPublisher {
+    pub fn new<F>(max_level: LogLevel, callback: F) -> Self
+    where
+        F: Fn(&LogRecord) + Send + Sync + 'static,
+    {
+        Self {
+            max_level,
+            callback: Box::new(callback),
+        }
+    }
+}
+
+impl MessagePublisher for CallbackPublisher {
+    fn publish(&self, record: &LogRecord) -> Result<(), LogError> {
+        if level_enabled(record.level, self.max_level) {
+            (self.callback)(record);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+use win_etw_macros::trace_logging_provider;
+
+use crate::log_error::LogError;
+use crate::logger::indexlog::LogLevel;
+use crate::message_handler::{LogRecord, MessagePublisher};
+
+// ETW provider of the DiskANN index logs
+#[trace_logging_provider(guid = "2e0588d8-ddd1-482a-ae43-55e28ef9ede1")]
+trait EtwTraceProvider {
+    fn write(msg: &str);
+}
+
+/// Writes the messages to ETW, built with the `etw` feature on Windows
+pub struct EtwPublisher {
+    provider: EtwTraceProvider,
+}
+
+impl EtwPublisher {
+    pub fn new() -> Result<Self, win_etw_provider::Error> {
+        let provider = EtwTraceProvider::new();
+        Ok(EtwPublisher { provider })
+    }
+}
+
+fn log_level_to_etw(level: LogLevel) -> win_etw_provider::Level {
+    match level {
+        LogLevel::Error => win_etw_provider::Level::ERROR,
+        LogLevel::Warn => win_etw_provider::Level::WARN,
+        LogLevel::Info => win_etw_provider::Level::INFO,
+        LogLevel::Debug => win_etw_provider::Level::VERBOSE,
+        LogLevel::Trace => win_etw_provider::Level(6),
+        LogLevel::Unspecified => win_etw_provider::Level(6),
+    }
+}
+
+impl MessagePublisher for EtwPublisher {
+    fn publish(&self, record: &LogRecord) -> Result<(), LogError> {
+        let options = win_etw_provider::EventOptions {
+            level: Some(log_level_to_etw(record.level)),
+            ..Default::default()
+        };
+        self.provider.write(Some(&options), record.message);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,131 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::log_error::LogError;
+use crate::logger::indexlog::LogLevel;
+use crate::message_handler::{level_enabled, LogRecord, MessagePublisher};
+
+/// Writes the `Log` messages of indexlog.proto up to max_level as newline-delimited JSON
+pub struct JsonPublisher {
+    max_level: LogLevel,
+
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl JsonPublisher {
+    pub fn new(writer: Box<dyn Write + Send>, max_level: LogLevel) -> Self {
+        Self {
+            max_level,
+            writer: Mutex::new(writer),
+        }
+    }
+
+    /// Append the messages to a file
+    pub fn create(path: impl AsRef<Path>, max_level: LogLevel) -> Result<Self, LogError> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::new(Box::new(BufWriter::new(file)), max_level))
+    }
+}
+
+impl MessagePublisher for JsonPublisher {
+    fn publish(&self, record: &LogRecord) -> Result<(), LogError> {
+        if !level_enabled(record.level, self.max_level) {
+            return Ok(());
+        }
+
+        let mut line = serde_json::to_vec(record.log)?;
+        line.push(b'\n');
+
+        let mut writer = self
+            .writer
+            .lock()
+            .map_err(|err| LogError::LockPoisonError {
+                err: err.to_string(),
+            })?;
+        writer.write_all(&line)?;
+
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<(), LogError> {
+        let mut writer = self
+            .writer
+            .lock()
+            .map_err(|err| LogError::LockPoisonError {
+                err: err.to_string(),
+            })?;
+        writer.flush()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod json_publisher_test {
+    use std::sync::Arc;
+
+    use serde_json::Value;
+
+    use super::*;
+    use crate::logger::indexlog::{IndexConstructionLog, Log};
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn writes_one_json_object_per_line_test() {
+        let buffer = SharedBuffer::default();
+        let publisher = JsonPublisher::new(Box::new(buffer.clone()), LogLevel::Info);
+
+        let log = Log {
+            index_construction_log: Some(IndexConstructionLog {
+                percentage_complete: 50.0,
+                time_spent_in_seconds: 1.5,
+                g_cycles_spent: 2.0,
+                log_level: LogLevel::Info as i32,
+            }),
+            ..Default::default()
+        };
+        for level in [LogLevel::Info, LogLevel::Trace, LogLevel::Error] {
+            publisher
+                .publish(&LogRecord {
+                    level,
+                    message: "",
+                    log: &log,
+                })
+                .unwrap();
+        }
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0]["index_construction_log"]["percentage_complete"],
+            50.0
+        );
+        assert_eq!(
+            lines[0]["index_construction_log"]["log_level"],
+            LogLevel::Info as i32
+        );
+        assert!(lines[0]["error_log"].is_null());
+    }
+}
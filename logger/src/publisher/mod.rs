@@ -0,0 +1,36 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+//! Publishers to register with `message_handler`
+
+use crate::logger::indexlog::LogLevel;
+
+mod stderr_publisher;
+pub use stderr_publisher::StderrPublisher;
+
+mod rotating_file_publisher;
+pub use rotating_file_publisher::RotatingFilePublisher;
+
+mod json_publisher;
+pub use json_publisher::JsonPublisher;
+
+mod callback_publisher;
+pub use callback_publisher::CallbackPublisher;
+
+#[cfg(all(feature = "etw", target_os = "windows"))]
+mod etw_publisher;
+#[cfg(all(feature = "etw", target_os = "windows"))]
+pub use etw_publisher::EtwPublisher;
+
+/// Name of a level in the text publishers
+fn level_name(level: LogLevel) -> &'static str {
+    match level {
+        LogLevel::Unspecified => "UNSPECIFIED",
+        LogLevel::Error => "ERROR",
+        LogLevel::Warn => "WARN",
+        LogLevel::Info => "INFO",
+        LogLevel::Debug => "DEBUG",
+        LogLevel::Trace => "TRACE",
+    }
+}
@@ -0,0 +1,184 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::log_error::LogError;
+use crate::logger::indexlog::LogLevel;
+use crate::message_handler::{level_enabled, LogRecord, MessagePublisher};
+
+use super::level_name;
+
+struct OpenFile {
+    file: File,
+
+    size: u64,
+}
+
+/// Appends the messages up to max_level to a file. Once the file reaches max_bytes it is
+/// renamed to `{path}.1`, the previous `{path}.1` to `{path}.2` and so on, keeping max_files
+/// of them.
+pub struct RotatingFilePublisher {
+    path: PathBuf,
+
+    max_bytes: u64,
+
+    max_files: usize,
+
+    max_level: LogLevel,
+
+    open_file: Mutex<OpenFile>,
+}
+
+impl RotatingFilePublisher {
+    pub fn new(
+        path: impl AsRef<Path>,
+        max_bytes: u64,
+        max_files: usize,
+        max_level: LogLevel,
+    ) -> Result<Self, LogError> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            path,
+            max_bytes,
+            max_files,
+            max_level,
+            open_file: Mutex::new(OpenFile { file, size }),
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        PathBuf::from(path)
+    }
+
+    fn rotate(&self, open_file: &mut OpenFile) -> Result<(), LogError> {
+        if self.max_files > 0 {
+            // Renaming onto an existing file fails on Windows
+            let oldest = self.rotated_path(self.max_files);
+            if oldest.exists() {
+                fs::remove_file(oldest)?;
+            }
+            for index in (1..self.max_files).rev() {
+                let rotated = self.rotated_path(index);
+                if rotated.exists() {
+                    fs::rename(rotated, self.rotated_path(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        open_file.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.path)?;
+        open_file.size = 0;
+
+        Ok(())
+    }
+}
+
+impl MessagePublisher for RotatingFilePublisher {
+    fn publish(&self, record: &LogRecord) -> Result<(), LogError> {
+        if !level_enabled(record.level, self.max_level) {
+            return Ok(());
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let line = format!(
+            "{}.{:03} [{}] {}\n",
+            timestamp.as_secs(),
+            timestamp.subsec_millis(),
+            level_name(record.level),
+            record.message
+        );
+
+        let mut open_file = self
+            .open_file
+            .lock()
+            .map_err(|err| LogError::LockPoisonError {
+                err: err.to_string(),
+            })?;
+        if open_file.size > 0 && open_file.size + line.len() as u64 > self.max_bytes {
+            self.rotate(&mut open_file)?;
+        }
+
+        open_file.file.write_all(line.as_bytes())?;
+        open_file.size += line.len() as u64;
+
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<(), LogError> {
+        let mut open_file = self
+            .open_file
+            .lock()
+            .map_err(|err| LogError::LockPoisonError {
+                err: err.to_string(),
+            })?;
+        open_file.file.flush()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod rotating_file_publisher_test {
+    use super::*;
+    use crate::logger::indexlog::Log;
+
+    #[test]
+    fn rotation_test() {
+        let dir = std::env::temp_dir().join(format!("logger_rotation_test_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("index.log");
+
+        let publisher = RotatingFilePublisher::new(&path, 120, 2, LogLevel::Info).unwrap();
+        let log = Log::default();
+        for i in 0..10 {
+            let message = format!("message {:02} of the rotation test", i);
+            publisher
+                .publish(&LogRecord {
+                    level: LogLevel::Info,
+                    message: &message,
+                    log: &log,
+                })
+                .unwrap();
+        }
+        publisher
+            .publish(&LogRecord {
+                level: LogLevel::Debug,
+                message: "filtered out",
+                log: &log,
+            })
+            .unwrap();
+        publisher.flush().unwrap();
+
+        // Every line is around 50 bytes, so that every file holds two of them
+        let current = fs::read_to_string(&path).unwrap();
+        let previous = fs::read_to_string(dir.join("index.log.1")).unwrap();
+        let oldest = fs::read_to_string(dir.join("index.log.2")).unwrap();
+        assert!(!dir.join("index.log.3").exists());
+
+        assert_eq!(current.lines().count(), 2);
+        assert!(current.contains("[INFO] message 09 of the rotation test"));
+        assert!(!current.contains("filtered out"));
+        assert!(previous.contains("message 07") && previous.contains("message 06"));
+        assert!(oldest.contains("message 05") && oldest.contains("message 04"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,38 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+use std::io::{self, Write};
+
+use crate::log_error::LogError;
+use crate::logger::indexlog::LogLevel;
+use crate::message_handler::{level_enabled, LogRecord, MessagePublisher};
+
+use super::level_name;
+
+/// Writes the messages up to max_level to stderr, prefixed with their level
+#[derive(Debug)]
+pub struct StderrPublisher {
+    max_level: LogLevel,
+}
+
+impl StderrPublisher {
+    pub fn new(max_level: LogLevel) -> Self {
+        Self { max_level }
+    }
+}
+
+impl MessagePublisher for StderrPublisher {
+    fn publish(&self, record: &LogRecord) -> Result<(), LogError> {
+        if level_enabled(record.level, self.max_level) {
+            writeln!(
+                io::stderr().lock(),
+                "[{}] {}",
+                level_name(record.level),
+                record.message
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -6,9 +6,28 @@ use crate::logger::indexlog::{Log, TraceLog};
 use crate::message_handler::send_log;
 
 use log;
+pub use log::LevelFilter;
+
+/// Environment variable overriding the level passed to init_trace_logger
+pub const LOG_LEVEL_ENV: &str = "DISKANN_LOG_LEVEL";
 
 pub struct TraceLogger {}
 
+/// Route the `log` records to the message publishers, up to the level set in
+/// DISKANN_LOG_LEVEL (off, error, warn, info, debug or trace) or else up to max_level.
+pub fn init_trace_logger(max_level: log::LevelFilter) -> Result<(), log::SetLoggerError> {
+    static LOGGER: TraceLogger = TraceLogger {};
+
+    let max_level = std::env::var(LOG_LEVEL_ENV)
+        .ok()
+        .and_then(|level| level.parse().ok())
+        .unwrap_or(max_level);
+    log::set_logger(&LOGGER)?;
+    log::set_max_level(max_level);
+
+    Ok(())
+}
+
 fn level_to_i32(value: log::Level) -> i32 {
     match value {
         log::Level::Error => 1,
@@ -0,0 +1,229 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+#![warn(missing_docs)]
+
+//! Python bindings for the in-memory index
+//!
+//! Run `maturin develop` in this folder to build the `diskannpy` module into the active
+//! virtualenv, then `pytest tests` to run the Python tests.
+
+use api_memory_insert_query::mem_ann_store::{MemANNStore, MISSING_ID};
+use diskann::common::{ANNError, ANNResult};
+use numpy::ndarray::{Array2, ArrayView2};
+use numpy::{IntoPyArray, PyArray2, PyReadonlyArray1, PyReadonlyArray2};
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use vector::Metric;
+
+fn to_py_err(err: ANNError) -> PyErr {
+    PyRuntimeError::new_err(err.to_string())
+}
+
+fn parse_metric(metric: &str) -> PyResult<Metric> {
+    metric
+        .parse::<Metric>()
+        .map_err(|err| PyValueError::new_err(err.to_string()))
+}
+
+/// In-memory Vamana index over float32 vectors
+///
+/// Ids are assigned in insertion order starting from 0.
+#[pyclass(module = "diskannpy")]
+pub struct MemIndex {
+    store: MemANNStore<f32>,
+
+    search_list_size: u32,
+}
+
+#[pymethods]
+impl MemIndex {
+    /// Create an empty index for vectors of the given dimension
+    #[new]
+    #[pyo3(signature = (dim, metric = "l2", max_degree = 64, search_list_size = 100, alpha = 1.2, num_threads = 0, max_points = 1024))]
+    fn new(
+        dim: usize,
+        metric: &str,
+        max_degree: u32,
+        search_list_size: u32,
+        alpha: f32,
+        num_threads: u32,
+        max_points: usize,
+    ) -> PyResult<Self> {
+        let metric = parse_metric(metric)?;
+        let store = MemANNStore::new(
+            metric,
+            dim,
+            max_degree,
+            search_list_size,
+            alpha,
+            num_threads,
+            max_points,
+        )
+        .map_err(to_py_err)?;
+
+        Ok(Self {
+            store,
+            search_list_size,
+        })
+    }
+
+    /// Build an index from the rows of a (n, dim) float32 array
+    #[staticmethod]
+    #[pyo3(signature = (data, metric = "l2", max_degree = 64, search_list_size = 100, alpha = 1.2, num_threads = 0))]
+    fn build(
+        py: Python<'_>,
+        data: PyReadonlyArray2<'_, f32>,
+        metric: &str,
+        max_degree: u32,
+        search_list_size: u32,
+        alpha: f32,
+        num_threads: u32,
+    ) -> PyResult<Self> {
+        let (num_points, dim) = data.as_array().dim();
+        let mut index = Self::new(
+            dim,
+            metric,
+            max_degree,
+            search_list_size,
+            alpha,
+            num_threads,
+            num_points,
+        )?;
+        index.insert(py, data)?;
+
+        Ok(index)
+    }
+
+    /// Dimension of the vectors
+    #[getter]
+    fn dim(&self) -> usize {
+        self.store.dimension()
+    }
+
+    /// Insert the rows of a (n, dim) float32 array, returns (first id, number of points)
+    fn insert(
+        &mut self,
+        py: Python<'_>,
+        data: PyReadonlyArray2<'_, f32>,
+    ) -> PyResult<(usize, usize)> {
+        let points = self.to_points(data.as_array())?;
+        let store = &mut self.store;
+
+        py.detach(|| store.insert_data(&points)).map_err(to_py_err)
+    }
+
+    /// Mark the points with the given ids as deleted, they are no longer returned by search
+    fn soft_delete(&mut self, ids: PyReadonlyArray1<'_, u32>) -> PyResult<()> {
+        self.store
+            .soft_delete(ids.as_array().to_vec())
+            .map_err(to_py_err)
+    }
+
+    /// Save the index to the given path
+    fn save(&mut self, py: Python<'_>, path: &str) -> PyResult<()> {
+        let store = &mut self.store;
+
+        py.detach(|| store.save_to_file(path)).map_err(to_py_err)
+    }
+
+    /// Load an index saved with save with its saved configuration
+    ///
+    /// dim and metric have to match the saved index, search_list_size is the default of the
+    /// searches.
+    #[staticmethod]
+    #[pyo3(signature = (path, dim, metric = "l2", search_list_size = 100))]
+    fn load(
+        py: Python<'_>,
+        path: &str,
+        dim: usize,
+        metric: &str,
+        search_list_size: u32,
+    ) -> PyResult<Self> {
+        let metric = parse_metric(metric)?;
+        let store = py
+            .detach(|| MemANNStore::load_checked(path, metric, dim))
+            .map_err(to_py_err)?;
+
+        Ok(Self {
+            store,
+            search_list_size,
+        })
+    }
+
+    /// Search the k nearest neighbors of every row of a (q, dim) float32 array
+    ///
+    /// Returns (ids, distances), both of shape (q, k). Slots the search could not fill hold
+    /// the id 2**32 - 1 and an infinite distance. The queries run in parallel with the GIL
+    /// released.
+    #[pyo3(signature = (queries, k, l = None))]
+    #[allow(clippy::type_complexity)]
+    fn search<'py>(
+        &self,
+        py: Python<'py>,
+        queries: PyReadonlyArray2<'py, f32>,
+        k: usize,
+        l: Option<u32>,
+    ) -> PyResult<(Bound<'py, PyArray2<u32>>, Bound<'py, PyArray2<f32>>)> {
+        if k == 0 {
+            return Err(PyValueError::new_err("k must be greater than 0"));
+        }
+
+        let queries = self.to_points(queries.as_array())?;
+        let l_value = l.unwrap_or(self.search_list_size).max(k as u32);
+        let num_queries = queries.len();
+
+        let (ids, distances) = py
+            .detach(|| -> ANNResult<(Vec<u32>, Vec<f32>)> {
+                let mut ids = vec![MISSING_ID; num_queries * k];
+                let mut distances = vec![f32::INFINITY; num_queries * k];
+
+                ids.par_chunks_mut(k)
+                    .zip(distances.par_chunks_mut(k))
+                    .zip(queries.par_iter())
+                    .try_for_each(|((ids, distances), query)| {
+                        let result = self.store.search(query, k, l_value)?;
+                        ids[..result.ids.len()].copy_from_slice(&result.ids);
+                        distances[..result.distances.len()].copy_from_slice(&result.distances);
+                        Ok::<_, ANNError>(())
+                    })?;
+
+                Ok((ids, distances))
+            })
+            .map_err(to_py_err)?;
+
+        let ids = Array2::from_shape_vec((num_queries, k), ids)
+            .map_err(|err| PyRuntimeError::new_err(err.to_string()))?;
+        let distances = Array2::from_shape_vec((num_queries, k), distances)
+            .map_err(|err| PyRuntimeError::new_err(err.to_string()))?;
+
+        Ok((ids.into_pyarray(py), distances.into_pyarray(py)))
+    }
+}
+
+impl MemIndex {
+    /// Copy the rows of a (n, dim) array to points
+    fn to_points(&self, data: ArrayView2<'_, f32>) -> PyResult<Vec<Vec<f32>>> {
+        let dim = self.store.dimension();
+        if data.ncols() != dim {
+            return Err(PyValueError::new_err(format!(
+                "expected vectors of dimension {}, got {}",
+                dim,
+                data.ncols()
+            )));
+        }
+
+        Ok(data.rows().into_iter().map(|row| row.to_vec()).collect())
+    }
+}
+
+/// In-memory DiskANN index
+#[pymodule]
+fn diskannpy(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<MemIndex>()?;
+    m.add("MISSING_ID", MISSING_ID)?;
+
+    Ok(())
+}
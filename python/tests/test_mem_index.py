@@ -0,0 +1,84 @@
+# Copyright (c) Microsoft Corporation. All rights reserved.
+# Licensed under the MIT license.
+import numpy as np
+import pytest
+
+import diskannpy
+
+DIM = 128
+
+
+def random_points(num_points, seed=0):
+    rng = np.random.default_rng(seed)
+    return rng.random((num_points, DIM), dtype=np.float32)
+
+
+def test_build_and_search_finds_itself():
+    data = random_points(500)
+    index = diskannpy.MemIndex.build(data, max_degree=32, search_list_size=50)
+
+    ids, distances = index.search(data[:20], k=5)
+
+    assert ids.shape == (20, 5)
+    assert ids.dtype == np.uint32
+    assert distances.shape == (20, 5)
+    assert distances.dtype == np.float32
+    np.testing.assert_array_equal(ids[:, 0], np.arange(20))
+    np.testing.assert_allclose(distances[:, 0], 0.0, atol=1e-4)
+
+
+def test_insert_returns_new_ids():
+    index = diskannpy.MemIndex(DIM, max_degree=32, search_list_size=50)
+
+    assert index.dim == DIM
+    assert index.insert(random_points(100, seed=1)) == (0, 100)
+    assert index.insert(random_points(50, seed=2)) == (100, 50)
+
+
+def test_soft_delete_hides_points():
+    data = random_points(300)
+    index = diskannpy.MemIndex.build(data, max_degree=32, search_list_size=50)
+
+    index.soft_delete(np.array([7, 8], dtype=np.uint32))
+    ids, _ = index.search(data[7:9], k=10, l=100)
+
+    assert 7 not in ids
+    assert 8 not in ids
+
+
+def test_save_and_load(tmp_path):
+    data = random_points(300)
+    index = diskannpy.MemIndex.build(data, max_degree=32, search_list_size=50)
+    expected_ids, expected_distances = index.search(data[:10], k=5)
+    path = str(tmp_path / "index")
+    index.save(path)
+
+    loaded = diskannpy.MemIndex.load(path, DIM, search_list_size=50)
+    ids, distances = loaded.search(data[:10], k=5)
+
+    np.testing.assert_array_equal(ids, expected_ids)
+    np.testing.assert_allclose(distances, expected_distances)
+    assert loaded.insert(random_points(10, seed=3)) == (300, 10)
+
+
+def test_load_rejects_other_metric(tmp_path):
+    index = diskannpy.MemIndex.build(random_points(100), max_degree=32, search_list_size=50)
+    path = str(tmp_path / "index")
+    index.save(path)
+
+    with pytest.raises(RuntimeError):
+        diskannpy.MemIndex.load(path, DIM, metric="cosine")
+
+
+def test_rejects_wrong_dimension():
+    index = diskannpy.MemIndex(DIM)
+
+    with pytest.raises(ValueError):
+        index.insert(np.zeros((2, DIM + 1), dtype=np.float32))
+    with pytest.raises(ValueError):
+        index.search(np.zeros((1, DIM - 1), dtype=np.float32), k=1)
+
+
+def test_rejects_unknown_metric():
+    with pytest.raises(ValueError):
+        diskannpy.MemIndex(DIM, metric="hamming")
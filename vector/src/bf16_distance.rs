@@ -0,0 +1,129 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+#![warn(missing_debug_implementations, missing_docs)]
+
+//! Distance calculation for bf16 vectors
+//!
+//! CPUs with AVX-512 BF16 multiply bf16 pairs natively with vdpbf16ps, AVX2 CPUs widen
+//! 8 lanes at a time to f32, the others use the scalar kernels.
+
+#[cfg(target_arch = "x86_64")]
+use crate::simd::has_avx512_bf16;
+#[cfg(target_arch = "x86_64")]
+use crate::x86_distance::*;
+#[cfg(target_arch = "x86_64")]
+use crate::SimdLevel;
+use crate::{scalar_distance, simd_level, BFloat16};
+
+/// Calculate the squared L2 distance of two bf16 vectors
+#[inline(never)]
+pub fn distance_l2_vector_bf16<const N: usize>(a: &[BFloat16; N], b: &[BFloat16; N]) -> f32 {
+    match simd_level() {
+        #[cfg(target_arch = "x86_64")]
+        _ if has_avx512_bf16() => unsafe { l2_bf16_avx512(a, b) },
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx512 | SimdLevel::Avx2 => unsafe { l2_bf16_avx2(a, b) },
+        _ => scalar_distance::l2(a, b),
+    }
+}
+
+/// Calculate the cosine similarity of two bf16 vectors, same as distance_cosine_vector_f32
+#[inline(never)]
+pub fn distance_cosine_vector_bf16<const N: usize>(a: &[BFloat16; N], b: &[BFloat16; N]) -> f32 {
+    let (dot, norm_a, norm_b) = match simd_level() {
+        #[cfg(target_arch = "x86_64")]
+        _ if has_avx512_bf16() => unsafe { dot_and_norms_bf16_avx512(a, b) },
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx512 | SimdLevel::Avx2 => unsafe { dot_and_norms_bf16_avx2(a, b) },
+        _ => scalar_distance::dot_and_norms(a, b),
+    };
+
+    let eps = 1e-12f32;
+    dot / (norm_a.sqrt().max(eps) * norm_b.sqrt().max(eps))
+}
+
+/// Calculate the negated inner product of two bf16 vectors, so that closer is smaller
+#[inline(never)]
+pub fn distance_ip_vector_bf16<const N: usize>(a: &[BFloat16; N], b: &[BFloat16; N]) -> f32 {
+    let dot = match simd_level() {
+        #[cfg(target_arch = "x86_64")]
+        _ if has_avx512_bf16() => unsafe { dot_bf16_avx512(a, b) },
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx512 | SimdLevel::Avx2 => unsafe { dot_bf16_avx2(a, b) },
+        _ => scalar_distance::dot(a, b),
+    };
+
+    -dot
+}
+
+#[cfg(test)]
+mod bf16_distance_test {
+    use approx::assert_relative_eq;
+    use rand::Rng;
+
+    use super::*;
+
+    fn random_vectors<const N: usize>() -> ([BFloat16; N], [BFloat16; N]) {
+        let mut rng = rand::thread_rng();
+        let a = [(); N].map(|_| BFloat16::from_f32(rng.gen_range(-10.0..10.0)));
+        let b = [(); N].map(|_| BFloat16::from_f32(rng.gen_range(-10.0..10.0)));
+        (a, b)
+    }
+
+    fn no_vector_dot(a: &[BFloat16], b: &[BFloat16]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x.to_f32() * y.to_f32()).sum()
+    }
+
+    fn no_vector_l2(a: &[BFloat16], b: &[BFloat16]) -> f32 {
+        a.iter()
+            .zip(b)
+            .map(|(x, y)| (x.to_f32() - y.to_f32()).powi(2))
+            .sum()
+    }
+
+    fn check_kernels<const N: usize>() {
+        let (a, b) = random_vectors::<N>();
+        let dot = no_vector_dot(&a, &b);
+        let cosine = dot / (no_vector_dot(&a, &a).sqrt() * no_vector_dot(&b, &b).sqrt());
+
+        assert_relative_eq!(
+            distance_l2_vector_bf16(&a, &b),
+            no_vector_l2(&a, &b),
+            max_relative = 1e-4
+        );
+        assert_relative_eq!(distance_ip_vector_bf16(&a, &b), -dot, epsilon = 1e-2);
+        assert_relative_eq!(distance_cosine_vector_bf16(&a, &b), cosine, epsilon = 1e-4);
+
+        #[cfg(target_arch = "x86_64")]
+        if matches!(simd_level(), SimdLevel::Avx512 | SimdLevel::Avx2) {
+            unsafe {
+                assert_relative_eq!(
+                    l2_bf16_avx2(&a, &b),
+                    no_vector_l2(&a, &b),
+                    max_relative = 1e-4
+                );
+                assert_relative_eq!(dot_bf16_avx2(&a, &b), dot, epsilon = 1e-2);
+            }
+        }
+    }
+
+    #[test]
+    fn kernels_match_novector() {
+        // 104 and 8 leave a tail shorter than one AVX-512 register
+        check_kernels::<8>();
+        check_kernels::<104>();
+        check_kernels::<128>();
+        check_kernels::<512>();
+    }
+
+    #[test]
+    fn l2_of_unaligned_vectors() {
+        let data: Vec<BFloat16> = (0..17).map(|i| BFloat16::from_f32(i as f32)).collect();
+        let a = <&[BFloat16; 8]>::try_from(&data[1..9]).unwrap();
+        let b = <&[BFloat16; 8]>::try_from(&data[9..17]).unwrap();
+
+        assert_eq!(distance_l2_vector_bf16(a, b), 8.0 * 64.0);
+    }
+}
@@ -0,0 +1,74 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+use bytemuck::{Pod, Zeroable};
+use half::bf16;
+use std::convert::AsRef;
+use std::fmt;
+
+// Define the BFloat16 type as a new type over bf16, the same way Half wraps f16.
+// bf16 keeps the 8 bit exponent of f32, so it covers the f32 range without the overflow of f16,
+// at the cost of a 7 bit mantissa. Converting a bf16 to f32 is a 16 bit left shift.
+#[repr(transparent)]
+pub struct BFloat16(bf16);
+
+unsafe impl Pod for BFloat16 {}
+unsafe impl Zeroable for BFloat16 {}
+
+// Implement From<BFloat16> for f32
+impl From<BFloat16> for f32 {
+    fn from(val: BFloat16) -> Self {
+        val.0.to_f32()
+    }
+}
+
+// Implement AsRef<bf16> for BFloat16
+impl AsRef<bf16> for BFloat16 {
+    fn as_ref(&self) -> &bf16 {
+        &self.0
+    }
+}
+
+impl BFloat16 {
+    pub fn from_f32(value: f32) -> Self {
+        Self(bf16::from_f32(value))
+    }
+
+    pub fn to_f32(&self) -> f32 {
+        self.0.to_f32()
+    }
+
+    pub fn from_bits(bits: u16) -> Self {
+        Self(bf16::from_bits(bits))
+    }
+
+    pub fn to_bits(&self) -> u16 {
+        self.0.to_bits()
+    }
+}
+
+// Implement Default for BFloat16.
+impl Default for BFloat16 {
+    fn default() -> Self {
+        Self(bf16::from_f32(Default::default()))
+    }
+}
+
+// Implement Clone for BFloat16.
+impl Clone for BFloat16 {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl fmt::Debug for BFloat16 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "BFloat16({:?})", self.0)
+    }
+}
+
+impl Copy for BFloat16 {}
+
+unsafe impl Send for BFloat16 {}
+unsafe impl Sync for BFloat16 {}
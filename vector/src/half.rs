@@ -22,6 +22,7 @@ use std::fmt;
 // Output:
 // Size of Half: 2 bytes
 // Alignment of Half: 2 bytes
+#[repr(transparent)]
 pub struct Half(f16);
 
 unsafe impl Pod for Half {}
@@ -58,7 +59,7 @@ impl Default for Half {
 // Implement Clone for Half.
 impl Clone for Half {
     fn clone(&self) -> Self {
-        Half(self.0)
+        *self
     }
 }
 
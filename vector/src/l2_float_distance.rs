@@ -4,117 +4,181 @@
  */
 #![warn(missing_debug_implementations, missing_docs)]
 
-//! Distance calculation for L2 Metric
+//! Distance calculation for f32 and f16 vectors
+//!
+//! Each function picks the kernel for the SimdLevel detected at runtime.
 
-#[cfg(not(target_feature = "avx2"))]
-compile_error!("Library must be compiled with -C target-feature=+avx2");
+#[cfg(target_arch = "aarch64")]
+use crate::neon_distance::*;
+#[cfg(target_arch = "x86_64")]
+use crate::x86_distance::*;
+use crate::{scalar_distance, simd_level, Half, SimdLevel};
 
-use std::arch::x86_64::*;
-
-use crate::Half;
-
-/// Calculate the distance by vector arithmetic
+/// Calculate the squared L2 distance of two f16 vectors
 #[inline(never)]
 pub fn distance_l2_vector_f16<const N: usize>(a: &[Half; N], b: &[Half; N]) -> f32 {
-    debug_assert_eq!(N % 8, 0);
-
-    // make sure the addresses are bytes aligned
-    debug_assert_eq!(a.as_ptr().align_offset(32), 0);
-    debug_assert_eq!(b.as_ptr().align_offset(32), 0);
-
-    unsafe {
-        let mut sum = _mm256_setzero_ps();
-        let a_ptr = a.as_ptr() as *const __m128i;
-        let b_ptr = b.as_ptr() as *const __m128i;
-
-        // Iterate over the elements in steps of 8
-        for i in (0..N).step_by(8) {
-            let a_vec = _mm256_cvtph_ps(_mm_load_si128(a_ptr.add(i / 8)));
-            let b_vec = _mm256_cvtph_ps(_mm_load_si128(b_ptr.add(i / 8)));
-
-            let diff = _mm256_sub_ps(a_vec, b_vec);
-            sum = _mm256_fmadd_ps(diff, diff, sum);
-        }
-
-        let x128: __m128 = _mm_add_ps(_mm256_extractf128_ps(sum, 1), _mm256_castps256_ps128(sum));
-        /* ( -, -, x1+x3+x5+x7, x0+x2+x4+x6 ) */
-        let x64: __m128 = _mm_add_ps(x128, _mm_movehl_ps(x128, x128));
-        /* ( -, -, -, x0+x1+x2+x3+x4+x5+x6+x7 ) */
-        let x32: __m128 = _mm_add_ss(x64, _mm_shuffle_ps(x64, x64, 0x55));
-        /* Conversion to float is a no-op on x86-64 */
-        _mm_cvtss_f32(x32)
+    match simd_level() {
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx512 => unsafe { l2_f16_avx512(a, b) },
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx2 => unsafe { l2_f16_avx2(a, b) },
+        _ => scalar_distance::l2(a, b),
     }
 }
 
-/// Calculate the distance by vector arithmetic
+/// Calculate the squared L2 distance of two f32 vectors
 #[inline(never)]
 pub fn distance_l2_vector_f32<const N: usize>(a: &[f32; N], b: &[f32; N]) -> f32 {
-    debug_assert_eq!(N % 8, 0);
-
-    // make sure the addresses are bytes aligned
-    debug_assert_eq!(a.as_ptr().align_offset(32), 0);
-    debug_assert_eq!(b.as_ptr().align_offset(32), 0);
-
-    unsafe {
-        let mut sum = _mm256_setzero_ps();
-
-        // Iterate over the elements in steps of 8
-        for i in (0..N).step_by(8) {
-            let a_vec = _mm256_load_ps(&a[i]);
-            let b_vec = _mm256_load_ps(&b[i]);
-            let diff = _mm256_sub_ps(a_vec, b_vec);
-            sum = _mm256_fmadd_ps(diff, diff, sum);
-        }
-
-        let x128: __m128 = _mm_add_ps(_mm256_extractf128_ps(sum, 1), _mm256_castps256_ps128(sum));
-        /* ( -, -, x1+x3+x5+x7, x0+x2+x4+x6 ) */
-        let x64: __m128 = _mm_add_ps(x128, _mm_movehl_ps(x128, x128));
-        /* ( -, -, -, x0+x1+x2+x3+x4+x5+x6+x7 ) */
-        let x32: __m128 = _mm_add_ss(x64, _mm_shuffle_ps(x64, x64, 0x55));
-        /* Conversion to float is a no-op on x86-64 */
-        _mm_cvtss_f32(x32)
+    match simd_level() {
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx512 => unsafe { l2_f32_avx512(a, b) },
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx2 => unsafe { l2_f32_avx2(a, b) },
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Sse => unsafe { l2_f32_sse(a, b) },
+        #[cfg(target_arch = "aarch64")]
+        SimdLevel::Neon => unsafe { l2_f32_neon(a, b) },
+        _ => scalar_distance::l2(a, b),
     }
 }
 
+/// Calculate the cosine similarity of two f32 vectors
 #[inline(never)]
 pub fn distance_cosine_vector_f32<const N: usize>(a: &[f32; N], b: &[f32; N]) -> f32 {
-    debug_assert_eq!(N % 8, 0);
-    debug_assert_eq!(a.as_ptr().align_offset(32), 0);
-    debug_assert_eq!(b.as_ptr().align_offset(32), 0);
+    let (dot, norm_a, norm_b) = match simd_level() {
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx512 => unsafe { dot_and_norms_f32_avx512(a, b) },
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx2 => unsafe { dot_and_norms_f32_avx2(a, b) },
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Sse => unsafe { dot_and_norms_f32_sse(a, b) },
+        #[cfg(target_arch = "aarch64")]
+        SimdLevel::Neon => unsafe { dot_and_norms_f32_neon(a, b) },
+        _ => scalar_distance::dot_and_norms(a, b),
+    };
+
+    // Compute cosine similarity (with zero-vector handling)
+    let eps = 1e-12f32;
+    dot / (norm_a.sqrt().max(eps) * norm_b.sqrt().max(eps))
+}
 
-    unsafe {
-        let mut dot = _mm256_setzero_ps();
-        let mut norm_a = _mm256_setzero_ps();
-        let mut norm_b = _mm256_setzero_ps();
+/// Calculate the negated inner product of two f32 vectors, so that closer is smaller
+#[inline(never)]
+pub fn distance_ip_vector_f32<const N: usize>(a: &[f32; N], b: &[f32; N]) -> f32 {
+    let dot = match simd_level() {
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx512 => unsafe { dot_f32_avx512(a, b) },
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx2 => unsafe { dot_f32_avx2(a, b) },
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Sse => unsafe { dot_f32_sse(a, b) },
+        #[cfg(target_arch = "aarch64")]
+        SimdLevel::Neon => unsafe { dot_f32_neon(a, b) },
+        _ => scalar_distance::dot(a, b),
+    };
+
+    -dot
+}
 
-        for i in (0..N).step_by(8) {
-            let a_vec = _mm256_load_ps(&a[i]);
-            let b_vec = _mm256_load_ps(&b[i]);
+#[cfg(test)]
+mod l2_float_distance_test {
+    use approx::assert_relative_eq;
+    use rand::Rng;
 
-            // calculate point product
-            dot = _mm256_fmadd_ps(a_vec, b_vec, dot);
+    use super::*;
 
-            // Compute squared norm
-            norm_a = _mm256_fmadd_ps(a_vec, a_vec, norm_a);
-            norm_b = _mm256_fmadd_ps(b_vec, b_vec, norm_b);
+    /// Levels this CPU can run, the dispatched one and all the levels below it
+    fn supported_levels() -> Vec<SimdLevel> {
+        match simd_level() {
+            SimdLevel::Avx512 => vec![SimdLevel::Avx512, SimdLevel::Avx2, SimdLevel::Sse],
+            SimdLevel::Avx2 => vec![SimdLevel::Avx2, SimdLevel::Sse],
+            level => vec![level],
         }
+    }
+
+    fn random_vector(len: usize) -> Vec<f32> {
+        let mut rng = rand::thread_rng();
+        (0..len).map(|_| rng.gen_range(-1.0..1.0)).collect()
+    }
 
-        // Horizontal summation
-        let sum_dot = hsum256_ps(dot);
-        let sum_norm_a = hsum256_ps(norm_a);
-        let sum_norm_b = hsum256_ps(norm_b);
+    #[allow(unused_variables)]
+    fn f32_kernels(level: SimdLevel, a: &[f32], b: &[f32]) -> (f32, f32, (f32, f32, f32)) {
+        unsafe {
+            match level {
+                #[cfg(target_arch = "x86_64")]
+                SimdLevel::Avx512 => (
+                    l2_f32_avx512(a, b),
+                    dot_f32_avx512(a, b),
+                    dot_and_norms_f32_avx512(a, b),
+                ),
+                #[cfg(target_arch = "x86_64")]
+                SimdLevel::Avx2 => (
+                    l2_f32_avx2(a, b),
+                    dot_f32_avx2(a, b),
+                    dot_and_norms_f32_avx2(a, b),
+                ),
+                #[cfg(target_arch = "x86_64")]
+                SimdLevel::Sse => (
+                    l2_f32_sse(a, b),
+                    dot_f32_sse(a, b),
+                    dot_and_norms_f32_sse(a, b),
+                ),
+                #[cfg(target_arch = "aarch64")]
+                SimdLevel::Neon => (
+                    l2_f32_neon(a, b),
+                    dot_f32_neon(a, b),
+                    dot_and_norms_f32_neon(a, b),
+                ),
+                _ => (
+                    scalar_distance::l2(a, b),
+                    scalar_distance::dot(a, b),
+                    scalar_distance::dot_and_norms(a, b),
+                ),
+            }
+        }
+    }
 
-        // Compute cosine similarity (with zero-vector handling)
-        let eps = 1e-12f32;
-        sum_dot / (sum_norm_a.sqrt().max(eps) * sum_norm_b.sqrt().max(eps))
+    #[test]
+    fn f32_kernels_match_scalar() {
+        // Lengths with and without a tail for every register width
+        for len in [3, 8, 20, 104, 128, 131] {
+            let a = random_vector(len);
+            let b = random_vector(len);
+            let (dot, norm_a, norm_b) = scalar_distance::dot_and_norms(&a, &b);
+
+            for level in supported_levels() {
+                let (l2, kernel_dot, (kernel_dot2, kernel_norm_a, kernel_norm_b)) =
+                    f32_kernels(level, &a, &b);
+
+                assert_relative_eq!(l2, scalar_distance::l2(&a, &b), max_relative = 1e-5);
+                assert_relative_eq!(kernel_dot, dot, epsilon = 1e-4);
+                assert_relative_eq!(kernel_dot2, dot, epsilon = 1e-4);
+                assert_relative_eq!(kernel_norm_a, norm_a, max_relative = 1e-5);
+                assert_relative_eq!(kernel_norm_b, norm_b, max_relative = 1e-5);
+            }
+        }
     }
-}
 
-// Helper function: AVX register horizontal sum
-#[inline(always)]
-unsafe fn hsum256_ps(v: __m256) -> f32 {
-    let x128: __m128 = _mm_add_ps(_mm256_extractf128_ps(v, 1), _mm256_castps256_ps128(v));
-    let x64 = _mm_add_ps(x128, _mm_movehl_ps(x128, x128));
-    let x32 = _mm_add_ss(x64, _mm_shuffle_ps(x64, x64, 0x55));
-    _mm_cvtss_f32(x32)
+    #[test]
+    fn f16_kernels_match_scalar() {
+        for len in [8, 24, 104, 128] {
+            let a: Vec<Half> = random_vector(len).into_iter().map(Half::from_f32).collect();
+            let b: Vec<Half> = random_vector(len).into_iter().map(Half::from_f32).collect();
+            let expected = scalar_distance::l2(&a, &b);
+
+            for level in supported_levels() {
+                let distance = unsafe {
+                    match level {
+                        #[cfg(target_arch = "x86_64")]
+                        SimdLevel::Avx512 => l2_f16_avx512(&a, &b),
+                        #[cfg(target_arch = "x86_64")]
+                        SimdLevel::Avx2 => l2_f16_avx2(&a, &b),
+                        _ => scalar_distance::l2(&a, &b),
+                    }
+                };
+
+                assert_relative_eq!(distance, expected, max_relative = 1e-5);
+            }
+        }
+    }
 }
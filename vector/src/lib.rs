@@ -10,16 +10,26 @@
 // #![feature(stdsimd)]
 // mod f32x16;
 // Uncomment above 2 to experiment with f32x16
+mod bf16_distance;
+mod bfloat16;
 mod distance;
 mod half;
 mod l2_float_distance;
 mod metric;
+#[cfg(target_arch = "aarch64")]
+mod neon_distance;
+mod scalar_distance;
+mod simd;
 mod utils;
+#[cfg(target_arch = "x86_64")]
+mod x86_distance;
 
+pub use crate::bfloat16::BFloat16;
 pub use crate::half::Half;
 pub use distance::FullPrecisionDistance;
 pub use metric::Metric;
-pub use utils::prefetch_vector;
+pub use simd::{simd_level, SimdLevel};
+pub use utils::{prefetch_cache_line, prefetch_vector};
 
 #[cfg(test)]
 mod distance_test;
@@ -14,6 +14,9 @@ pub enum Metric {
     /// Cosine similarity
     /// TODO: T should be float for Cosine distance
     Cosine,
+
+    /// Inner product, negated so that a smaller distance means a closer vector
+    InnerProduct,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -29,6 +32,7 @@ impl FromStr for Metric {
         match s.to_lowercase().as_str() {
             "l2" => Ok(Metric::L2),
             "cosine" => Ok(Metric::Cosine),
+            "ip" | "mips" => Ok(Metric::InnerProduct),
             _ => Err(ParseMetricError::InvalidFormat(String::from(s))),
         }
     }
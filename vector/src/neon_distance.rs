@@ -0,0 +1,67 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+#![warn(missing_debug_implementations, missing_docs)]
+
+//! aarch64 NEON distance kernels, the lanes that don't fill a register use the scalar kernels
+
+use std::arch::aarch64::*;
+
+use crate::scalar_distance;
+
+/// Squared L2 distance of f32 vectors with NEON
+#[target_feature(enable = "neon")]
+pub unsafe fn l2_f32_neon(a: &[f32], b: &[f32]) -> f32 {
+    let chunks = a.len() / 4 * 4;
+    let mut sum = vdupq_n_f32(0.0);
+
+    for i in (0..chunks).step_by(4) {
+        let diff = vsubq_f32(vld1q_f32(a.as_ptr().add(i)), vld1q_f32(b.as_ptr().add(i)));
+        sum = vfmaq_f32(sum, diff, diff);
+    }
+
+    vaddvq_f32(sum) + scalar_distance::l2(&a[chunks..], &b[chunks..])
+}
+
+/// Inner product of f32 vectors with NEON
+#[target_feature(enable = "neon")]
+pub unsafe fn dot_f32_neon(a: &[f32], b: &[f32]) -> f32 {
+    let chunks = a.len() / 4 * 4;
+    let mut dot = vdupq_n_f32(0.0);
+
+    for i in (0..chunks).step_by(4) {
+        dot = vfmaq_f32(
+            dot,
+            vld1q_f32(a.as_ptr().add(i)),
+            vld1q_f32(b.as_ptr().add(i)),
+        );
+    }
+
+    vaddvq_f32(dot) + scalar_distance::dot(&a[chunks..], &b[chunks..])
+}
+
+/// Inner product and squared norms of f32 vectors with NEON
+#[target_feature(enable = "neon")]
+pub unsafe fn dot_and_norms_f32_neon(a: &[f32], b: &[f32]) -> (f32, f32, f32) {
+    let chunks = a.len() / 4 * 4;
+    let mut dot = vdupq_n_f32(0.0);
+    let mut norm_a = vdupq_n_f32(0.0);
+    let mut norm_b = vdupq_n_f32(0.0);
+
+    for i in (0..chunks).step_by(4) {
+        let a_vec = vld1q_f32(a.as_ptr().add(i));
+        let b_vec = vld1q_f32(b.as_ptr().add(i));
+        dot = vfmaq_f32(dot, a_vec, b_vec);
+        norm_a = vfmaq_f32(norm_a, a_vec, a_vec);
+        norm_b = vfmaq_f32(norm_b, b_vec, b_vec);
+    }
+
+    let (tail_dot, tail_norm_a, tail_norm_b) =
+        scalar_distance::dot_and_norms(&a[chunks..], &b[chunks..]);
+    (
+        vaddvq_f32(dot) + tail_dot,
+        vaddvq_f32(norm_a) + tail_norm_a,
+        vaddvq_f32(norm_b) + tail_norm_b,
+    )
+}
@@ -0,0 +1,42 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+#![warn(missing_debug_implementations, missing_docs)]
+
+//! Portable distance kernels, used on CPUs without SIMD and for the tails of the SIMD kernels
+
+/// Squared L2 distance
+#[inline]
+pub fn l2<T: Copy + Into<f32>>(a: &[T], b: &[T]) -> f32 {
+    debug_assert_eq!(a.len(), b.len());
+
+    a.iter()
+        .zip(b)
+        .map(|(&x, &y)| {
+            let diff = x.into() - y.into();
+            diff * diff
+        })
+        .sum()
+}
+
+/// Inner product
+#[inline]
+pub fn dot<T: Copy + Into<f32>>(a: &[T], b: &[T]) -> f32 {
+    debug_assert_eq!(a.len(), b.len());
+
+    a.iter().zip(b).map(|(&x, &y)| x.into() * y.into()).sum()
+}
+
+/// Inner product and squared norms of both vectors
+#[inline]
+pub fn dot_and_norms<T: Copy + Into<f32>>(a: &[T], b: &[T]) -> (f32, f32, f32) {
+    debug_assert_eq!(a.len(), b.len());
+
+    a.iter()
+        .zip(b)
+        .fold((0.0, 0.0, 0.0), |(dot, norm_a, norm_b), (&x, &y)| {
+            let (x, y): (f32, f32) = (x.into(), y.into());
+            (dot + x * y, norm_a + x * x, norm_b + y * y)
+        })
+}
@@ -0,0 +1,105 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+#![warn(missing_debug_implementations, missing_docs)]
+
+//! Runtime detection of the SIMD instructions used by the distance kernels
+//!
+//! The crate is compiled for the baseline target, each kernel enables the instructions it
+//! needs with target_feature and the dispatchers pick one from the level detected here,
+//! so the same binary runs on any CPU of the target architecture.
+
+use std::fmt;
+use std::sync::OnceLock;
+
+/// Best SIMD instruction set available for the distance kernels
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SimdLevel {
+    /// Portable scalar code
+    Scalar,
+
+    /// x86_64 SSE, 4 f32 lanes without FMA
+    Sse,
+
+    /// x86_64 AVX2 with FMA and F16C, 8 f32 lanes
+    Avx2,
+
+    /// x86_64 AVX-512F on top of AVX2, 16 f32 lanes
+    Avx512,
+
+    /// aarch64 NEON, 4 f32 lanes with FMA
+    Neon,
+}
+
+impl fmt::Display for SimdLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SimdLevel::Scalar => "scalar",
+            SimdLevel::Sse => "sse",
+            SimdLevel::Avx2 => "avx2",
+            SimdLevel::Avx512 => "avx512",
+            SimdLevel::Neon => "neon",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+static SIMD_LEVEL: OnceLock<SimdLevel> = OnceLock::new();
+
+/// Get the SIMD level used by the distance kernels on this CPU, detected on first use
+#[inline]
+pub fn simd_level() -> SimdLevel {
+    *SIMD_LEVEL.get_or_init(detect_simd_level)
+}
+
+#[cfg(target_arch = "x86_64")]
+fn detect_simd_level() -> SimdLevel {
+    let avx2 = is_x86_feature_detected!("avx2")
+        && is_x86_feature_detected!("fma")
+        && is_x86_feature_detected!("f16c");
+
+    if avx2 && is_x86_feature_detected!("avx512f") {
+        SimdLevel::Avx512
+    } else if avx2 {
+        SimdLevel::Avx2
+    } else if is_x86_feature_detected!("sse") {
+        SimdLevel::Sse
+    } else {
+        SimdLevel::Scalar
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+fn detect_simd_level() -> SimdLevel {
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        SimdLevel::Neon
+    } else {
+        SimdLevel::Scalar
+    }
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn detect_simd_level() -> SimdLevel {
+    SimdLevel::Scalar
+}
+
+static HAS_AVX512_BF16: OnceLock<bool> = OnceLock::new();
+
+/// Whether the bf16 kernels can use AVX-512 BF16 dot products with BW/VL masked loads
+#[inline]
+pub(crate) fn has_avx512_bf16() -> bool {
+    *HAS_AVX512_BF16.get_or_init(|| {
+        #[cfg(target_arch = "x86_64")]
+        {
+            simd_level() == SimdLevel::Avx512
+                && is_x86_feature_detected!("avx512bw")
+                && is_x86_feature_detected!("avx512vl")
+                && is_x86_feature_detected!("avx512bf16")
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        {
+            false
+        }
+    })
+}
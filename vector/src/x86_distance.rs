@@ -0,0 +1,403 @@
+/*
+ * Copyright (c) Microsoft Corporation. All rights reserved.
+ * Licensed under the MIT license.
+ */
+#![warn(missing_debug_implementations, missing_docs)]
+
+//! x86_64 distance kernels
+//!
+//! Every kernel enables its instruction set with target_feature, so callers must check the
+//! detected SimdLevel first. Loads are unaligned and the lanes that don't fill a register
+//! are handled by masked loads or by the scalar kernels.
+
+use std::arch::x86_64::*;
+
+use crate::scalar_distance;
+use crate::{BFloat16, Half};
+
+/// Horizontal sum of 8 f32 lanes
+#[inline]
+#[target_feature(enable = "avx")]
+unsafe fn hsum256_ps(v: __m256) -> f32 {
+    let x128: __m128 = _mm_add_ps(_mm256_extractf128_ps(v, 1), _mm256_castps256_ps128(v));
+    hsum128_ps(x128)
+}
+
+/// Horizontal sum of 4 f32 lanes
+#[inline]
+unsafe fn hsum128_ps(v: __m128) -> f32 {
+    /* ( -, -, x1+x3, x0+x2 ) */
+    let x64: __m128 = _mm_add_ps(v, _mm_movehl_ps(v, v));
+    /* ( -, -, -, x0+x1+x2+x3 ) */
+    let x32: __m128 = _mm_add_ss(x64, _mm_shuffle_ps(x64, x64, 0x55));
+    _mm_cvtss_f32(x32)
+}
+
+/// Mask of the first len lanes of a 16 lane register
+#[inline(always)]
+fn tail_mask(len: usize) -> __mmask16 {
+    if len >= 16 {
+        u16::MAX
+    } else {
+        (1u16 << len) - 1
+    }
+}
+
+/// Squared L2 distance of f32 vectors with AVX-512F
+#[target_feature(enable = "avx512f")]
+pub unsafe fn l2_f32_avx512(a: &[f32], b: &[f32]) -> f32 {
+    let mut sum = _mm512_setzero_ps();
+
+    for i in (0..a.len()).step_by(16) {
+        let mask = tail_mask(a.len() - i);
+        let a_vec = _mm512_maskz_loadu_ps(mask, a.as_ptr().add(i));
+        let b_vec = _mm512_maskz_loadu_ps(mask, b.as_ptr().add(i));
+        let diff = _mm512_sub_ps(a_vec, b_vec);
+        sum = _mm512_fmadd_ps(diff, diff, sum);
+    }
+
+    _mm512_reduce_add_ps(sum)
+}
+
+/// Inner product of f32 vectors with AVX-512F
+#[target_feature(enable = "avx512f")]
+pub unsafe fn dot_f32_avx512(a: &[f32], b: &[f32]) -> f32 {
+    let mut dot = _mm512_setzero_ps();
+
+    for i in (0..a.len()).step_by(16) {
+        let mask = tail_mask(a.len() - i);
+        let a_vec = _mm512_maskz_loadu_ps(mask, a.as_ptr().add(i));
+        let b_vec = _mm512_maskz_loadu_ps(mask, b.as_ptr().add(i));
+        dot = _mm512_fmadd_ps(a_vec, b_vec, dot);
+    }
+
+    _mm512_reduce_add_ps(dot)
+}
+
+/// Inner product and squared norms of f32 vectors with AVX-512F
+#[target_feature(enable = "avx512f")]
+pub unsafe fn dot_and_norms_f32_avx512(a: &[f32], b: &[f32]) -> (f32, f32, f32) {
+    let mut dot = _mm512_setzero_ps();
+    let mut norm_a = _mm512_setzero_ps();
+    let mut norm_b = _mm512_setzero_ps();
+
+    for i in (0..a.len()).step_by(16) {
+        let mask = tail_mask(a.len() - i);
+        let a_vec = _mm512_maskz_loadu_ps(mask, a.as_ptr().add(i));
+        let b_vec = _mm512_maskz_loadu_ps(mask, b.as_ptr().add(i));
+        dot = _mm512_fmadd_ps(a_vec, b_vec, dot);
+        norm_a = _mm512_fmadd_ps(a_vec, a_vec, norm_a);
+        norm_b = _mm512_fmadd_ps(b_vec, b_vec, norm_b);
+    }
+
+    (
+        _mm512_reduce_add_ps(dot),
+        _mm512_reduce_add_ps(norm_a),
+        _mm512_reduce_add_ps(norm_b),
+    )
+}
+
+/// Squared L2 distance of f16 vectors with AVX-512F
+#[target_feature(enable = "avx512f")]
+pub unsafe fn l2_f16_avx512(a: &[Half], b: &[Half]) -> f32 {
+    let chunks = a.len() / 16 * 16;
+    let mut sum = _mm512_setzero_ps();
+
+    for i in (0..chunks).step_by(16) {
+        let a_vec = _mm512_cvtph_ps(_mm256_loadu_si256(a.as_ptr().add(i) as *const __m256i));
+        let b_vec = _mm512_cvtph_ps(_mm256_loadu_si256(b.as_ptr().add(i) as *const __m256i));
+        let diff = _mm512_sub_ps(a_vec, b_vec);
+        sum = _mm512_fmadd_ps(diff, diff, sum);
+    }
+
+    _mm512_reduce_add_ps(sum) + l2_f16_avx2(&a[chunks..], &b[chunks..])
+}
+
+/// Squared L2 distance of f32 vectors with AVX2 and FMA
+#[target_feature(enable = "avx2,fma")]
+pub unsafe fn l2_f32_avx2(a: &[f32], b: &[f32]) -> f32 {
+    let chunks = a.len() / 8 * 8;
+    let mut sum = _mm256_setzero_ps();
+
+    for i in (0..chunks).step_by(8) {
+        let a_vec = _mm256_loadu_ps(a.as_ptr().add(i));
+        let b_vec = _mm256_loadu_ps(b.as_ptr().add(i));
+        let diff = _mm256_sub_ps(a_vec, b_vec);
+        sum = _mm256_fmadd_ps(diff, diff, sum);
+    }
+
+    hsum256_ps(sum) + scalar_distance::l2(&a[chunks..], &b[chunks..])
+}
+
+/// Inner product of f32 vectors with AVX2 and FMA
+#[target_feature(enable = "avx2,fma")]
+pub unsafe fn dot_f32_avx2(a: &[f32], b: &[f32]) -> f32 {
+    let chunks = a.len() / 8 * 8;
+    let mut dot = _mm256_setzero_ps();
+
+    for i in (0..chunks).step_by(8) {
+        let a_vec = _mm256_loadu_ps(a.as_ptr().add(i));
+        let b_vec = _mm256_loadu_ps(b.as_ptr().add(i));
+        dot = _mm256_fmadd_ps(a_vec, b_vec, dot);
+    }
+
+    hsum256_ps(dot) + scalar_distance::dot(&a[chunks..], &b[chunks..])
+}
+
+/// Inner product and squared norms of f32 vectors with AVX2 and FMA
+#[target_feature(enable = "avx2,fma")]
+pub unsafe fn dot_and_norms_f32_avx2(a: &[f32], b: &[f32]) -> (f32, f32, f32) {
+    let chunks = a.len() / 8 * 8;
+    let mut dot = _mm256_setzero_ps();
+    let mut norm_a = _mm256_setzero_ps();
+    let mut norm_b = _mm256_setzero_ps();
+
+    for i in (0..chunks).step_by(8) {
+        let a_vec = _mm256_loadu_ps(a.as_ptr().add(i));
+        let b_vec = _mm256_loadu_ps(b.as_ptr().add(i));
+        dot = _mm256_fmadd_ps(a_vec, b_vec, dot);
+        norm_a = _mm256_fmadd_ps(a_vec, a_vec, norm_a);
+        norm_b = _mm256_fmadd_ps(b_vec, b_vec, norm_b);
+    }
+
+    let (tail_dot, tail_norm_a, tail_norm_b) =
+        scalar_distance::dot_and_norms(&a[chunks..], &b[chunks..]);
+    (
+        hsum256_ps(dot) + tail_dot,
+        hsum256_ps(norm_a) + tail_norm_a,
+        hsum256_ps(norm_b) + tail_norm_b,
+    )
+}
+
+/// Squared L2 distance of f16 vectors with AVX2, FMA and F16C
+#[target_feature(enable = "avx2,fma,f16c")]
+pub unsafe fn l2_f16_avx2(a: &[Half], b: &[Half]) -> f32 {
+    let chunks = a.len() / 8 * 8;
+    let mut sum = _mm256_setzero_ps();
+
+    for i in (0..chunks).step_by(8) {
+        let a_vec = _mm256_cvtph_ps(_mm_loadu_si128(a.as_ptr().add(i) as *const __m128i));
+        let b_vec = _mm256_cvtph_ps(_mm_loadu_si128(b.as_ptr().add(i) as *const __m128i));
+        let diff = _mm256_sub_ps(a_vec, b_vec);
+        sum = _mm256_fmadd_ps(diff, diff, sum);
+    }
+
+    hsum256_ps(sum) + scalar_distance::l2(&a[chunks..], &b[chunks..])
+}
+
+/// Squared L2 distance of f32 vectors with SSE
+#[target_feature(enable = "sse")]
+pub unsafe fn l2_f32_sse(a: &[f32], b: &[f32]) -> f32 {
+    let chunks = a.len() / 4 * 4;
+    let mut sum = _mm_setzero_ps();
+
+    for i in (0..chunks).step_by(4) {
+        let diff = _mm_sub_ps(
+            _mm_loadu_ps(a.as_ptr().add(i)),
+            _mm_loadu_ps(b.as_ptr().add(i)),
+        );
+        sum = _mm_add_ps(sum, _mm_mul_ps(diff, diff));
+    }
+
+    hsum128_ps(sum) + scalar_distance::l2(&a[chunks..], &b[chunks..])
+}
+
+/// Inner product of f32 vectors with SSE
+#[target_feature(enable = "sse")]
+pub unsafe fn dot_f32_sse(a: &[f32], b: &[f32]) -> f32 {
+    let chunks = a.len() / 4 * 4;
+    let mut dot = _mm_setzero_ps();
+
+    for i in (0..chunks).step_by(4) {
+        let a_vec = _mm_loadu_ps(a.as_ptr().add(i));
+        let b_vec = _mm_loadu_ps(b.as_ptr().add(i));
+        dot = _mm_add_ps(dot, _mm_mul_ps(a_vec, b_vec));
+    }
+
+    hsum128_ps(dot) + scalar_distance::dot(&a[chunks..], &b[chunks..])
+}
+
+/// Inner product and squared norms of f32 vectors with SSE
+#[target_feature(enable = "sse")]
+pub unsafe fn dot_and_norms_f32_sse(a: &[f32], b: &[f32]) -> (f32, f32, f32) {
+    let chunks = a.len() / 4 * 4;
+    let mut dot = _mm_setzero_ps();
+    let mut norm_a = _mm_setzero_ps();
+    let mut norm_b = _mm_setzero_ps();
+
+    for i in (0..chunks).step_by(4) {
+        let a_vec = _mm_loadu_ps(a.as_ptr().add(i));
+        let b_vec = _mm_loadu_ps(b.as_ptr().add(i));
+        dot = _mm_add_ps(dot, _mm_mul_ps(a_vec, b_vec));
+        norm_a = _mm_add_ps(norm_a, _mm_mul_ps(a_vec, a_vec));
+        norm_b = _mm_add_ps(norm_b, _mm_mul_ps(b_vec, b_vec));
+    }
+
+    let (tail_dot, tail_norm_a, tail_norm_b) =
+        scalar_distance::dot_and_norms(&a[chunks..], &b[chunks..]);
+    (
+        hsum128_ps(dot) + tail_dot,
+        hsum128_ps(norm_a) + tail_norm_a,
+        hsum128_ps(norm_b) + tail_norm_b,
+    )
+}
+
+/// Widen 8 bf16 to f32 by shifting them into the upper half of each 32 bit lane
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn load_bf16x8(ptr: *const BFloat16) -> __m256 {
+    let raw = _mm_loadu_si128(ptr as *const __m128i);
+    _mm256_castsi256_ps(_mm256_slli_epi32(_mm256_cvtepu16_epi32(raw), 16))
+}
+
+/// Squared L2 distance of bf16 vectors with AVX2 and FMA
+#[target_feature(enable = "avx2,fma")]
+pub unsafe fn l2_bf16_avx2(a: &[BFloat16], b: &[BFloat16]) -> f32 {
+    let chunks = a.len() / 8 * 8;
+    let mut sum = _mm256_setzero_ps();
+
+    for i in (0..chunks).step_by(8) {
+        let diff = _mm256_sub_ps(
+            load_bf16x8(a.as_ptr().add(i)),
+            load_bf16x8(b.as_ptr().add(i)),
+        );
+        sum = _mm256_fmadd_ps(diff, diff, sum);
+    }
+
+    hsum256_ps(sum) + scalar_distance::l2(&a[chunks..], &b[chunks..])
+}
+
+/// Inner product of bf16 vectors with AVX2 and FMA
+#[target_feature(enable = "avx2,fma")]
+pub unsafe fn dot_bf16_avx2(a: &[BFloat16], b: &[BFloat16]) -> f32 {
+    let chunks = a.len() / 8 * 8;
+    let mut dot = _mm256_setzero_ps();
+
+    for i in (0..chunks).step_by(8) {
+        dot = _mm256_fmadd_ps(
+            load_bf16x8(a.as_ptr().add(i)),
+            load_bf16x8(b.as_ptr().add(i)),
+            dot,
+        );
+    }
+
+    hsum256_ps(dot) + scalar_distance::dot(&a[chunks..], &b[chunks..])
+}
+
+/// Inner product and squared norms of bf16 vectors with AVX2 and FMA
+#[target_feature(enable = "avx2,fma")]
+pub unsafe fn dot_and_norms_bf16_avx2(a: &[BFloat16], b: &[BFloat16]) -> (f32, f32, f32) {
+    let chunks = a.len() / 8 * 8;
+    let mut dot = _mm256_setzero_ps();
+    let mut norm_a = _mm256_setzero_ps();
+    let mut norm_b = _mm256_setzero_ps();
+
+    for i in (0..chunks).step_by(8) {
+        let a_vec = load_bf16x8(a.as_ptr().add(i));
+        let b_vec = load_bf16x8(b.as_ptr().add(i));
+
+        dot = _mm256_fmadd_ps(a_vec, b_vec, dot);
+        norm_a = _mm256_fmadd_ps(a_vec, a_vec, norm_a);
+        norm_b = _mm256_fmadd_ps(b_vec, b_vec, norm_b);
+    }
+
+    let (tail_dot, tail_norm_a, tail_norm_b) =
+        scalar_distance::dot_and_norms(&a[chunks..], &b[chunks..]);
+    (
+        hsum256_ps(dot) + tail_dot,
+        hsum256_ps(norm_a) + tail_norm_a,
+        hsum256_ps(norm_b) + tail_norm_b,
+    )
+}
+
+/// Mask of the first len 16 bit lanes of a 32 lane register
+#[inline(always)]
+fn tail_mask32(len: usize) -> __mmask32 {
+    if len >= 32 {
+        u32::MAX
+    } else {
+        (1u32 << len) - 1
+    }
+}
+
+/// Load up to 32 bf16, the lanes past len are zero
+#[inline]
+#[target_feature(enable = "avx512f,avx512bw")]
+unsafe fn load_bf16x32(ptr: *const BFloat16, len: usize) -> __m512bh {
+    let raw = _mm512_maskz_loadu_epi16(tail_mask32(len), ptr as *const i16);
+    // __m512bh and __m512i are both 512 bit vectors
+    std::mem::transmute::<__m512i, __m512bh>(raw)
+}
+
+/// Load up to 16 bf16 widened to f32, the lanes past len are zero
+#[inline]
+#[target_feature(enable = "avx512f,avx512bw,avx512vl")]
+unsafe fn load_bf16x16(ptr: *const BFloat16, len: usize) -> __m512 {
+    let raw = _mm256_maskz_loadu_epi16(tail_mask(len), ptr as *const i16);
+    _mm512_castsi512_ps(_mm512_slli_epi32(_mm512_cvtepu16_epi32(raw), 16))
+}
+
+/// Squared L2 distance of bf16 vectors with AVX-512
+#[target_feature(enable = "avx512f,avx512bw,avx512vl")]
+pub unsafe fn l2_bf16_avx512(a: &[BFloat16], b: &[BFloat16]) -> f32 {
+    // The difference is taken in f32, subtracting in bf16 would lose the low bits
+    let mut sum = _mm512_setzero_ps();
+
+    for i in (0..a.len()).step_by(16) {
+        let len = a.len() - i;
+        let diff = _mm512_sub_ps(
+            load_bf16x16(a.as_ptr().add(i), len),
+            load_bf16x16(b.as_ptr().add(i), len),
+        );
+        sum = _mm512_fmadd_ps(diff, diff, sum);
+    }
+
+    _mm512_reduce_add_ps(sum)
+}
+
+/// Inner product of bf16 vectors with AVX-512 BF16
+#[target_feature(enable = "avx512f,avx512bw,avx512bf16")]
+pub unsafe fn dot_bf16_avx512(a: &[BFloat16], b: &[BFloat16]) -> f32 {
+    let mut dot = _mm512_setzero_ps();
+
+    for i in (0..a.len()).step_by(32) {
+        let len = a.len() - i;
+        dot = _mm512_dpbf16_ps(
+            dot,
+            load_bf16x32(a.as_ptr().add(i), len),
+            load_bf16x32(b.as_ptr().add(i), len),
+        );
+    }
+
+    _mm512_reduce_add_ps(dot)
+}
+
+/// Inner product and squared norms of bf16 vectors with AVX-512 BF16
+#[target_feature(enable = "avx512f,avx512bw,avx512bf16")]
+pub unsafe fn dot_and_norms_bf16_avx512(a: &[BFloat16], b: &[BFloat16]) -> (f32, f32, f32) {
+    let mut dot = _mm512_setzero_ps();
+    let mut norm_a = _mm512_setzero_ps();
+    let mut norm_b = _mm512_setzero_ps();
+
+    for i in (0..a.len()).step_by(32) {
+        let len = a.len() - i;
+        let a_vec = load_bf16x32(a.as_ptr().add(i), len);
+        let b_vec = load_bf16x32(b.as_ptr().add(i), len);
+
+        dot = _mm512_dpbf16_ps(dot, a_vec, b_vec);
+        norm_a = _mm512_dpbf16_ps(norm_a, a_vec, a_vec);
+        norm_b = _mm512_dpbf16_ps(norm_b, b_vec, b_vec);
+    }
+
+    (
+        _mm512_reduce_add_ps(dot),
+        _mm512_reduce_add_ps(norm_a),
+        _mm512_reduce_add_ps(norm_b),
+    )
+}
+
+/// Prefetch the cache line holding ptr
+#[inline]
+pub fn prefetch<T>(ptr: *const T) {
+    unsafe { _mm_prefetch(ptr as *const i8, _MM_HINT_T0) }
+}